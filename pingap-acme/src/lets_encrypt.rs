@@ -43,6 +43,7 @@ static WELL_KNOWN_PATH_PREFIX: &str = "/.well-known/acme-challenge/";
 /// 1. Generate a new certificate from Let's Encrypt
 /// 2. Update the configuration with the new certificate
 /// 3. Save the updated configuration
+/// 4. Reload the configuration with resolved secret references for running
 async fn update_certificate_lets_encrypt(
     storage: &'static (dyn ConfigStorage + Sync + Send),
     name: &str,
@@ -72,7 +73,17 @@ async fn update_certificate_lets_encrypt(
             message: e.to_string(),
         })?;
 
-    Ok(conf)
+    // the saved config is original, load the resolved config for running
+    storage
+        .load_config(LoadConfigOptions {
+            resolve_secret: true,
+            ..Default::default()
+        })
+        .await
+        .map_err(|e| Error::Fail {
+            category: "load_config".to_string(),
+            message: e.to_string(),
+        })
 }
 
 /// File cache parameters
@@ -13,23 +13,24 @@
 // limitations under the License.
 
 use super::http_cache::{CacheObject, HttpCacheStats, HttpCacheStorage};
+use super::index::CacheIndex;
 use super::{Error, Result, LOG_CATEGORY, PAGE_SIZE};
 #[cfg(feature = "tracing")]
 use super::{CACHE_READING_TIME, CACHE_WRITING_TIME};
 use async_trait::async_trait;
 use bytes::Bytes;
+use bytesize::ByteSize;
 use path_absolutize::*;
 use pingap_core::{convert_query_map, TinyUfo};
 #[cfg(feature = "tracing")]
 use prometheus::Histogram;
 use scopeguard::defer;
 use std::path::Path;
-use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
 use std::time::SystemTime;
 use substring::Substring;
 use tokio::fs;
 use tracing::{debug, error, info};
-use walkdir::WalkDir;
 
 /// A file-based cache implementation that combines disk storage with in-memory caching
 /// using TinyUfo for hot data.
@@ -55,6 +56,12 @@ pub struct FileCache {
     cache: Option<TinyUfo<String, CacheObject>>,
     /// Max tinyufo cache weight
     cache_file_max_weight: u16,
+    /// Persistent index of cache files
+    index: CacheIndex,
+    /// Max disk size of cache files, 0 means unlimited
+    max_size: u64,
+    /// Whether the least recently used files are being evicted
+    evicting: AtomicBool,
 }
 
 /// File cache parameters
@@ -70,6 +77,8 @@ struct FileCacheParams {
     cache_max: usize,
     /// Max tinyufo cache weight
     cache_file_max_weight: usize,
+    /// Max disk size of cache files
+    max_size: u64,
 }
 
 impl Default for FileCacheParams {
@@ -80,6 +89,7 @@ impl Default for FileCacheParams {
             writing_max: 1_000,
             cache_max: 0,
             cache_file_max_weight: 1024 * 1024 / PAGE_SIZE,
+            max_size: 0,
         }
     }
 }
@@ -110,10 +120,11 @@ fn resolve_path(path: &str) -> String {
     }
 }
 
-fn parse_params(dir: &str) -> FileCacheParams {
+fn parse_params(dir: &str, max_size: u64) -> FileCacheParams {
     let (dir, query) = dir.split_once('?').unwrap_or((dir, ""));
     let mut params = FileCacheParams {
         directory: resolve_path(dir),
+        max_size,
         ..Default::default()
     };
 
@@ -135,13 +146,20 @@ fn parse_params(dir: &str) -> FileCacheParams {
             .get("cache_file_max_size")
             .and_then(|v| v.parse::<usize>().map(|v| v / PAGE_SIZE).ok())
             .unwrap_or(params.cache_file_max_weight);
+        params.max_size = m
+            .get("max_size")
+            .and_then(|v| v.parse::<ByteSize>().ok())
+            .map(|v| v.as_u64())
+            .unwrap_or(params.max_size);
     }
     params
 }
 
-/// Create a file cache and use tinyufo for hotspot data caching
-pub fn new_file_cache(dir: &str) -> Result<FileCache> {
-    let params = parse_params(dir);
+/// Create a file cache and use tinyufo for hotspot data caching.
+/// The `max_size` is the default max disk size, it can be overridden
+/// by the `max_size` query param of directory.
+pub fn new_file_cache(dir: &str, max_size: u64) -> Result<FileCache> {
+    let params = parse_params(dir, max_size);
 
     let path = Path::new(&params.directory);
     // directory not exist, create it
@@ -155,6 +173,7 @@ pub fn new_file_cache(dir: &str) -> Result<FileCache> {
         writing_max = params.writing_max,
         cache_max = params.cache_max,
         cache_file_max_weight = params.cache_file_max_weight,
+        max_size = ByteSize::b(params.max_size).to_string(),
         "new file cache"
     );
     let mut cache = None;
@@ -163,7 +182,13 @@ pub fn new_file_cache(dir: &str) -> Result<FileCache> {
             Some(TinyUfo::new(params.cache_max, params.cache_max * PAGE_SIZE));
     }
 
+    // load the index of cache files, it's reconciled with the files
+    let index = CacheIndex::load(&params.directory);
+
     Ok(FileCache {
+        index,
+        max_size: params.max_size,
+        evicting: AtomicBool::new(false),
         directory: params.directory,
         cache_file_max_weight: params.cache_file_max_weight as u16,
         reading: AtomicU32::new(0),
@@ -180,13 +205,66 @@ pub fn new_file_cache(dir: &str) -> Result<FileCache> {
 
 impl FileCache {
     #[inline]
-    fn get_file_path(&self, key: &str, namespace: &str) -> std::path::PathBuf {
+    fn get_index_key(&self, key: &str, namespace: &str) -> String {
         if namespace.is_empty() {
-            Path::new(&self.directory).join(key)
+            key.to_string()
         } else {
-            Path::new(&self.directory).join(format!("{namespace}/{key}"))
+            format!("{namespace}/{key}")
+        }
+    }
+    #[inline]
+    fn get_file_path(&self, key: &str, namespace: &str) -> std::path::PathBuf {
+        Path::new(&self.directory).join(self.get_index_key(key, namespace))
+    }
+    /// Removes the cache file and its index entry of the index key,
+    /// returns true if the file is removed or not exists.
+    async fn remove_index_file(&self, index_key: &str) -> bool {
+        if let Some(c) = &self.cache {
+            let key = index_key.rsplit('/').next().unwrap_or(index_key);
+            c.remove(&key.to_string());
+        }
+        self.index.remove(index_key);
+        let file = Path::new(&self.directory).join(index_key);
+        match fs::remove_file(&file).await {
+            Ok(()) => true,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => true,
+            Err(e) => {
+                error!(
+                    category = LOG_CATEGORY,
+                    error = %e,
+                    file = file.to_string_lossy().to_string(),
+                    "remove cache file fail"
+                );
+                false
+            },
         }
     }
+    /// Evicts the least recently used files if the disk size is over limit,
+    /// the size will be reduced to 90% of max size to avoid evicting frequently.
+    async fn evict(&self) {
+        if self.max_size == 0 || self.index.size() <= self.max_size {
+            return;
+        }
+        // only one eviction at the same time
+        if self.evicting.swap(true, Ordering::Relaxed) {
+            return;
+        }
+        defer!(self.evicting.store(false, Ordering::Relaxed););
+        let keys = self.index.get_evictions(self.max_size / 10 * 9);
+        let mut success = 0;
+        for key in keys.iter() {
+            if self.remove_index_file(key).await {
+                success += 1;
+            }
+        }
+        info!(
+            category = LOG_CATEGORY,
+            count = keys.len(),
+            success,
+            size = ByteSize::b(self.index.size()).to_string(),
+            "evict file cache"
+        );
+    }
 }
 
 /// Returns the elapsed time in seconds (as f64) since the given SystemTime
@@ -217,9 +295,11 @@ impl HttpCacheStorage for FileCache {
         key: &str,
         namespace: &str,
     ) -> Result<Option<CacheObject>> {
+        let index_key = self.get_index_key(key, namespace);
         // Early return if found in cache
         if let Some(cache) = &self.cache {
             if let Some(obj) = cache.get(&key.to_string()) {
+                self.index.touch(&index_key);
                 debug!(
                     category = LOG_CATEGORY,
                     key, namespace, "get cache from tinyufo"
@@ -247,10 +327,15 @@ impl HttpCacheStorage for FileCache {
 
         let obj = match result {
             Ok(buf) if buf.len() >= 8 => {
+                self.index.touch(&index_key);
                 Ok(Some(CacheObject::from(Bytes::from(buf))))
             },
             Ok(_) => Ok(None),
-            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                // the file may be removed by others
+                self.index.remove(&index_key);
+                Ok(None)
+            },
             Err(e) => Err(Error::Io { source: e }),
         }?;
         // cache get from file, but not in tinyufo, put it to tinyufo
@@ -295,7 +380,9 @@ impl HttpCacheStorage for FileCache {
         }
         #[cfg(feature = "tracing")]
         let start = SystemTime::now();
+        let ttl = data.get_ttl();
         let buf: Bytes = data.into();
+        let size = buf.len() as u64;
         let file = self.get_file_path(key, namespace);
         // add writing count
         let count = self.writing.fetch_add(1, Ordering::Relaxed);
@@ -310,7 +397,10 @@ impl HttpCacheStorage for FileCache {
         #[cfg(feature = "tracing")]
         self.write_time.observe(elapsed_second(start));
         let _ = result.map_err(|e| Error::Io { source: e })?;
+        self.index
+            .insert(&self.get_index_key(key, namespace), size, ttl);
         debug!(category = LOG_CATEGORY, key, namespace, "put cache to file");
+        self.evict().await;
         Ok(())
     }
     /// Removes a cache entry from both TinyUfo and disk storage.
@@ -334,6 +424,7 @@ impl HttpCacheStorage for FileCache {
             );
             c.remove(&key.to_string());
         }
+        self.index.remove(&self.get_index_key(key, namespace));
         let file = self.get_file_path(key, namespace);
         fs::remove_file(file)
             .await
@@ -347,15 +438,18 @@ impl HttpCacheStorage for FileCache {
     /// Returns current cache statistics.
     ///
     /// # Returns
-    /// Statistics including current number of concurrent reads and writes
+    /// Statistics including current number of concurrent reads and writes,
+    /// and the disk usage of cache files
     #[inline]
     fn stats(&self) -> Option<HttpCacheStats> {
         Some(HttpCacheStats {
             reading: self.reading.load(Ordering::Relaxed),
             writing: self.writing.load(Ordering::Relaxed),
+            disk_usage: Some(self.index.size()),
         })
     }
-    /// Clears cache entries that were last accessed before the given timestamp.
+    /// Clears cache entries that are expired or were last accessed before
+    /// the given timestamp, the entries are got from the index.
     ///
     /// # Arguments
     /// * `access_before` - Remove entries last accessed before this time
@@ -365,43 +459,25 @@ impl HttpCacheStorage for FileCache {
     async fn clear(&self, access_before: SystemTime) -> Result<(i32, i32)> {
         let mut success = 0;
         let mut fail = 0;
-        for entry in WalkDir::new(&self.directory)
-            .into_iter()
-            .filter_map(|item| item.ok())
-            .filter(|item| !item.path().is_dir())
-        {
-            let Ok(metadata) = entry.metadata() else {
-                continue;
-            };
-            let Ok(accessed) = metadata.accessed() else {
-                continue;
-            };
-            if accessed > access_before {
-                continue;
+        for key in self.index.get_clearable(access_before) {
+            if self.remove_index_file(&key).await {
+                info!(
+                    category = LOG_CATEGORY,
+                    file = key,
+                    "remove cache file success"
+                );
+                success += 1;
+            } else {
+                fail += 1;
             }
-            let path = entry.path();
-            let file = path.to_string_lossy().to_string();
-            match fs::remove_file(path).await {
-                Ok(()) => {
-                    info!(
-                        category = LOG_CATEGORY,
-                        file, "remove cache file success"
-                    );
-                    success += 1;
-                },
-                Err(e) => {
-                    fail += 1;
-                    error!(
-                        category = LOG_CATEGORY,
-                        error = %e,
-                        file,
-                        "remove cache file fail"
-                    );
-                },
-            };
         }
+        self.index.save().await?;
         Ok((success, fail))
     }
+    /// Saves the index of cache files to disk if it has been changed.
+    async fn flush(&self) -> Result<bool> {
+        self.index.save().await
+    }
     fn support_clear(&self) -> bool {
         true
     }
@@ -419,10 +495,15 @@ mod tests {
     fn test_parse_params() {
         let params = parse_params(
             "~/pingap?reading_max=1000&writing_max=500&cache_max=100",
+            1024,
         );
         assert_eq!(1000, params.reading_max);
         assert_eq!(500, params.writing_max);
         assert_eq!(100, params.cache_max);
+        assert_eq!(1024, params.max_size);
+
+        let params = parse_params("~/pingap?max_size=1MB", 1024);
+        assert_eq!(1_000_000, params.max_size);
     }
 
     #[tokio::test]
@@ -431,7 +512,7 @@ mod tests {
         let namespace = "pingap";
         std::fs::create_dir(dir.path().join(namespace)).unwrap();
         let dir = format!("{}?cache_max=100", dir.path().to_string_lossy());
-        let cache = new_file_cache(&dir).unwrap();
+        let cache = new_file_cache(&dir, 0).unwrap();
 
         let key = "key";
         let obj = CacheObject {
@@ -456,7 +537,7 @@ mod tests {
         assert_eq!(obj, result);
 
         // empty tinyufo, get from file
-        let cache = new_file_cache(&dir).unwrap();
+        let cache = new_file_cache(&dir, 0).unwrap();
         let result = cache.get(key, namespace).await.unwrap().unwrap();
         assert_eq!(obj, result);
 
@@ -501,7 +582,7 @@ mod tests {
     fn test_get_file_path() {
         let dir = TempDir::new().unwrap();
         let cache =
-            new_file_cache(dir.path().to_string_lossy().as_ref()).unwrap();
+            new_file_cache(dir.path().to_string_lossy().as_ref(), 0).unwrap();
         assert_eq!(
             true,
             cache
@@ -523,9 +604,51 @@ mod tests {
     fn test_stats() {
         let dir = TempDir::new().unwrap();
         let dir = dir.into_path().to_string_lossy().to_string();
-        let cache = new_file_cache(&dir).unwrap();
+        let cache = new_file_cache(&dir, 0).unwrap();
         assert_eq!(0, cache.stats().unwrap().reading);
         assert_eq!(0, cache.stats().unwrap().writing);
+        assert_eq!(Some(0), cache.stats().unwrap().disk_usage);
+    }
+
+    #[tokio::test]
+    async fn test_file_cache_evict() {
+        let dir = TempDir::new().unwrap();
+        let namespace = "pingap";
+        std::fs::create_dir(dir.path().join(namespace)).unwrap();
+        let dir = dir.path().to_string_lossy().to_string();
+        // each object is 8 + 10 + 90 bytes
+        let cache = new_file_cache(&dir, 250).unwrap();
+        let obj = CacheObject {
+            meta: (b"Hello".to_vec(), b"World".to_vec()),
+            body: vec![0; 90].into(),
+        };
+        cache.put("a", namespace, obj.clone()).await.unwrap();
+        cache.put("b", namespace, obj.clone()).await.unwrap();
+        assert_eq!(Some(216), cache.stats().unwrap().disk_usage);
+
+        // over max size, the least recently used one is evicted,
+        // the order of keys is used if the access time is the same
+        cache.put("c", namespace, obj.clone()).await.unwrap();
+        assert_eq!(Some(216), cache.stats().unwrap().disk_usage);
+        assert_eq!(true, cache.get("a", namespace).await.unwrap().is_none());
+        assert_eq!(true, cache.get("c", namespace).await.unwrap().is_some());
+
+        // the index is persisted and loaded at startup
+        assert_eq!(true, cache.flush().await.unwrap());
+        let cache = new_file_cache(&dir, 250).unwrap();
+        assert_eq!(Some(216), cache.stats().unwrap().disk_usage);
+
+        // all entries are cleared
+        let (success, fail) = cache
+            .clear(
+                SystemTime::now()
+                    .checked_add(Duration::from_secs(3600))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!((2, 0), (success, fail));
+        assert_eq!(Some(0), cache.stats().unwrap().disk_usage);
     }
 
     #[test]
@@ -28,7 +28,7 @@ use pingora::cache::{
 use std::any::Any;
 use std::sync::Arc;
 use std::time::{Duration, SystemTime};
-use tracing::info;
+use tracing::{error, info};
 
 type BinaryMeta = (Vec<u8>, Vec<u8>);
 
@@ -55,6 +55,23 @@ impl CacheObject {
         }
         (size / PAGE_SIZE) as u16
     }
+    /// Returns the ttl of cache object, it's the fresh time of cache meta
+    /// plus the max stale time, so stale object can still be served.
+    /// Returns None if the meta can't be parsed.
+    pub fn get_ttl(&self) -> Option<Duration> {
+        let meta = CacheMeta::deserialize(&self.meta.0, &self.meta.1).ok()?;
+        let stale = meta
+            .stale_while_revalidate_sec()
+            .max(meta.stale_if_error_sec());
+        let fresh = meta
+            .fresh_until()
+            .duration_since(SystemTime::now())
+            .unwrap_or_default();
+        Some(
+            (fresh + Duration::from_secs(stale as u64))
+                .max(Duration::from_secs(1)),
+        )
+    }
 }
 
 const META_SIZE_LENGTH: usize = 8;
@@ -112,6 +129,8 @@ impl From<CacheObject> for Bytes {
 pub struct HttpCacheStats {
     pub reading: u32,
     pub writing: u32,
+    /// Disk usage of cache files, None if the storage is not disk based
+    pub disk_usage: Option<u64>,
 }
 
 /// Storage interface for HTTP caching operations
@@ -167,6 +186,14 @@ pub trait HttpCacheStorage: Sync + Send {
         Ok((-1, -1))
     }
 
+    /// Persists the in-memory state of storage, e.g. the index of file cache.
+    ///
+    /// # Returns
+    /// * `Result<bool>` - Whether the state has been persisted
+    async fn flush(&self) -> Result<bool> {
+        Ok(false)
+    }
+
     /// Returns current storage statistics.
     ///
     /// # Returns
@@ -190,6 +217,14 @@ async fn do_file_storage_clear(
     count: u32,
     cache: Arc<dyn HttpCacheStorage>,
 ) -> Result<bool, ServiceError> {
+    // persist the state of storage every loop
+    if let Err(e) = cache.flush().await {
+        error!(
+            category = LOG_CATEGORY,
+            error = %e,
+            "flush cache storage fail"
+        );
+    }
     // Add 1 every loop
     let offset = 60;
     if count % offset != 0 {
@@ -479,6 +514,7 @@ mod tests {
     use crate::tiny::new_tiny_ufo_cache;
     use bytes::{Bytes, BytesMut};
     use pingora::cache::storage::{HitHandler, MissHandler};
+    use pingora::http::ResponseHeader;
     use pretty_assertions::assert_eq;
     use std::sync::Arc;
 
@@ -549,4 +585,41 @@ mod tests {
         };
         assert_eq!(u16::MAX, obj.get_weight());
     }
+
+    #[test]
+    fn test_cache_object_get_ttl() {
+        let obj = CacheObject {
+            meta: (b"Hello".to_vec(), b"World".to_vec()),
+            body: Bytes::from_static(b"Hello World!"),
+        };
+        assert_eq!(None, obj.get_ttl());
+
+        let now = SystemTime::now();
+        let meta = CacheMeta::new(
+            now + Duration::from_secs(60),
+            now,
+            10,
+            120,
+            ResponseHeader::build(200, None).unwrap(),
+        );
+        let obj = CacheObject {
+            meta: meta.serialize().unwrap(),
+            body: Bytes::from_static(b"Hello World!"),
+        };
+        let ttl = obj.get_ttl().unwrap().as_secs();
+        assert_eq!(true, ttl > 170 && ttl <= 180);
+
+        let meta = CacheMeta::new(
+            now - Duration::from_secs(60),
+            now - Duration::from_secs(120),
+            0,
+            0,
+            ResponseHeader::build(200, None).unwrap(),
+        );
+        let obj = CacheObject {
+            meta: meta.serialize().unwrap(),
+            body: Bytes::new(),
+        };
+        assert_eq!(Some(Duration::from_secs(1)), obj.get_ttl());
+    }
 }
@@ -0,0 +1,375 @@
+// Copyright 2024-2025 Tree xie.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use super::{Error, Result, LOG_CATEGORY};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tracing::info;
+use walkdir::WalkDir;
+
+/// File name of the persistent index, it's stored in the cache directory
+pub static INDEX_FILE: &str = ".pingap_index";
+
+/// Index entry of a cache file
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct IndexEntry {
+    /// Size of the cache file in bytes
+    pub size: u64,
+    /// Last access time in seconds since unix epoch
+    pub accessed: u64,
+    /// Expiry time in seconds since unix epoch, 0 means never expire
+    pub expired: u64,
+}
+
+impl IndexEntry {
+    /// Returns true if the entry is expired or not accessed since the given time
+    fn should_clear(&self, access_before: u64, now: u64) -> bool {
+        self.accessed <= access_before
+            || (self.expired > 0 && self.expired <= now)
+    }
+}
+
+/// Returns the seconds since unix epoch of the given time
+#[inline]
+pub fn get_timestamp(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+/// Returns the seconds since unix epoch
+#[inline]
+pub fn now_timestamp() -> u64 {
+    get_timestamp(SystemTime::now())
+}
+
+/// In-memory index of file cache which is persisted to the cache directory,
+/// it's used to enforce max disk size and clear expired cache files
+/// without walking the whole directory.
+pub struct CacheIndex {
+    /// Path of the persistent index file
+    file: PathBuf,
+    /// Index entries, the key is the relative path of cache file
+    entries: Mutex<HashMap<String, IndexEntry>>,
+    /// Total size of all cache files
+    size: AtomicU64,
+    /// Whether the index has been changed since last saving
+    dirty: AtomicBool,
+}
+
+/// Parses one line of the index file: `key\tsize\taccessed\texpired`
+fn parse_line(line: &str) -> Option<(String, IndexEntry)> {
+    let mut arr = line.split('\t');
+    let key = arr.next()?;
+    let size = arr.next()?.parse().ok()?;
+    let accessed = arr.next()?.parse().ok()?;
+    let expired = arr.next()?.parse().ok()?;
+    if key.is_empty() {
+        return None;
+    }
+    Some((
+        key.to_string(),
+        IndexEntry {
+            size,
+            accessed,
+            expired,
+        },
+    ))
+}
+
+impl CacheIndex {
+    /// Loads the index of the cache directory.
+    /// The persistent index is reconciled with the cache files,
+    /// entries of missing files are dropped and files not in index are added,
+    /// so the index is still right after an unexpected exit.
+    pub fn load(directory: &str) -> Self {
+        let dir = Path::new(directory);
+        let file = dir.join(INDEX_FILE);
+        let mut saved = HashMap::new();
+        if let Ok(data) = std::fs::read_to_string(&file) {
+            for line in data.lines() {
+                if let Some((key, entry)) = parse_line(line) {
+                    saved.insert(key, entry);
+                }
+            }
+        }
+        let saved_count = saved.len();
+
+        let mut entries = HashMap::new();
+        let mut size = 0;
+        for item in WalkDir::new(dir)
+            .into_iter()
+            .filter_map(|item| item.ok())
+            .filter(|item| !item.path().is_dir())
+        {
+            let Ok(key) = item.path().strip_prefix(dir) else {
+                continue;
+            };
+            let key = key.to_string_lossy().to_string();
+            // skip the index file and its temporary file
+            if key.starts_with(INDEX_FILE) {
+                continue;
+            }
+            let entry = if let Some(entry) = saved.remove(&key) {
+                entry
+            } else {
+                let Ok(metadata) = item.metadata() else {
+                    continue;
+                };
+                let accessed = metadata
+                    .accessed()
+                    .or_else(|_| metadata.modified())
+                    .map(get_timestamp)
+                    .unwrap_or_else(|_| now_timestamp());
+                IndexEntry {
+                    size: metadata.len(),
+                    accessed,
+                    expired: 0,
+                }
+            };
+            size += entry.size;
+            entries.insert(key, entry);
+        }
+        info!(
+            category = LOG_CATEGORY,
+            dir = directory,
+            saved_count,
+            count = entries.len(),
+            size,
+            "load file cache index"
+        );
+        Self {
+            file,
+            entries: Mutex::new(entries),
+            size: AtomicU64::new(size),
+            dirty: AtomicBool::new(true),
+        }
+    }
+    /// Returns the total size of all cache files
+    #[inline]
+    pub fn size(&self) -> u64 {
+        self.size.load(Ordering::Relaxed)
+    }
+    /// Updates the last access time of the key
+    pub fn touch(&self, key: &str) {
+        if let Ok(mut entries) = self.entries.lock() {
+            if let Some(entry) = entries.get_mut(key) {
+                entry.accessed = now_timestamp();
+                self.dirty.store(true, Ordering::Relaxed);
+            }
+        }
+    }
+    /// Inserts or replaces the index entry of the key
+    pub fn insert(&self, key: &str, size: u64, ttl: Option<Duration>) {
+        let now = now_timestamp();
+        let entry = IndexEntry {
+            size,
+            accessed: now,
+            expired: ttl.map(|ttl| now + ttl.as_secs()).unwrap_or_default(),
+        };
+        if let Ok(mut entries) = self.entries.lock() {
+            if let Some(prev) = entries.insert(key.to_string(), entry) {
+                self.size.fetch_sub(prev.size, Ordering::Relaxed);
+            }
+            self.size.fetch_add(size, Ordering::Relaxed);
+            self.dirty.store(true, Ordering::Relaxed);
+        }
+    }
+    /// Removes the index entry of the key
+    pub fn remove(&self, key: &str) -> Option<IndexEntry> {
+        let entry = self.entries.lock().ok()?.remove(key)?;
+        self.size.fetch_sub(entry.size, Ordering::Relaxed);
+        self.dirty.store(true, Ordering::Relaxed);
+        Some(entry)
+    }
+    /// Returns the least recently used keys which should be evicted
+    /// to make the total size not greater than the target size.
+    /// The keys are not removed from index.
+    pub fn get_evictions(&self, target_size: u64) -> Vec<String> {
+        let size = self.size();
+        if size <= target_size {
+            return vec![];
+        }
+        let Ok(entries) = self.entries.lock() else {
+            return vec![];
+        };
+        let mut items: Vec<_> = entries
+            .iter()
+            .map(|(key, entry)| (entry.accessed, entry.size, key))
+            .collect();
+        items.sort_unstable();
+        let mut released = 0;
+        let mut keys = vec![];
+        for (_, entry_size, key) in items {
+            if size - released <= target_size {
+                break;
+            }
+            released += entry_size;
+            keys.push(key.clone());
+        }
+        keys
+    }
+    /// Returns the keys which are expired or not accessed since the given time
+    pub fn get_clearable(&self, access_before: SystemTime) -> Vec<String> {
+        let access_before = get_timestamp(access_before);
+        let now = now_timestamp();
+        let Ok(entries) = self.entries.lock() else {
+            return vec![];
+        };
+        entries
+            .iter()
+            .filter(|(_, entry)| entry.should_clear(access_before, now))
+            .map(|(key, _)| key.clone())
+            .collect()
+    }
+    /// Saves the index to the cache directory if it has been changed,
+    /// it's written to a temporary file first and then renamed.
+    pub async fn save(&self) -> Result<bool> {
+        if !self.dirty.swap(false, Ordering::Relaxed) {
+            return Ok(false);
+        }
+        let data = {
+            let Ok(entries) = self.entries.lock() else {
+                return Ok(false);
+            };
+            let mut data = String::with_capacity(entries.len() * 64);
+            for (key, entry) in entries.iter() {
+                data.push_str(&format!(
+                    "{key}\t{}\t{}\t{}\n",
+                    entry.size, entry.accessed, entry.expired
+                ));
+            }
+            data
+        };
+        let tmp = self.file.with_extension("tmp");
+        let result = async {
+            tokio::fs::write(&tmp, data).await?;
+            tokio::fs::rename(&tmp, &self.file).await
+        }
+        .await;
+        if let Err(e) = result {
+            self.dirty.store(true, Ordering::Relaxed);
+            return Err(Error::Io { source: e });
+        }
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use tempfile::TempDir;
+
+    impl CacheIndex {
+        fn len(&self) -> usize {
+            self.entries.lock().unwrap().len()
+        }
+        fn get(&self, key: &str) -> Option<IndexEntry> {
+            self.entries.lock().unwrap().get(key).cloned()
+        }
+    }
+
+    #[test]
+    fn test_parse_line() {
+        assert_eq!(
+            Some((
+                "pingap/key".to_string(),
+                IndexEntry {
+                    size: 100,
+                    accessed: 1,
+                    expired: 2,
+                }
+            )),
+            parse_line("pingap/key\t100\t1\t2")
+        );
+        assert_eq!(None, parse_line("pingap/key\t100\t1"));
+        assert_eq!(None, parse_line("\t100\t1\t2"));
+    }
+
+    #[tokio::test]
+    async fn test_cache_index() {
+        let dir = TempDir::new().unwrap();
+        std::fs::create_dir(dir.path().join("pingap")).unwrap();
+        std::fs::write(dir.path().join("pingap/a"), b"Hello").unwrap();
+        let directory = dir.path().to_string_lossy().to_string();
+
+        // rebuild index from files
+        let index = CacheIndex::load(&directory);
+        assert_eq!(1, index.len());
+        assert_eq!(5, index.size());
+        assert_eq!(5, index.get("pingap/a").unwrap().size);
+
+        index.insert("b", 10, Some(Duration::from_secs(60)));
+        index.insert("c", 20, None);
+        assert_eq!(35, index.size());
+        index.insert("c", 15, None);
+        assert_eq!(30, index.size());
+        assert_eq!(0, index.get("c").unwrap().expired);
+        assert_eq!(true, index.get("b").unwrap().expired > now_timestamp());
+
+        assert_eq!(true, index.save().await.unwrap());
+        // not changed
+        assert_eq!(false, index.save().await.unwrap());
+
+        // the entries of missing files are dropped
+        std::fs::write(dir.path().join("b"), b"0123456789").unwrap();
+        let index = CacheIndex::load(&directory);
+        assert_eq!(2, index.len());
+        assert_eq!(15, index.size());
+        assert_eq!(true, index.get("b").unwrap().expired > 0);
+
+        assert_eq!(Some(10), index.remove("b").map(|entry| entry.size));
+        assert_eq!(5, index.size());
+        assert_eq!(None, index.remove("b"));
+    }
+
+    #[test]
+    fn test_get_evictions() {
+        let dir = TempDir::new().unwrap();
+        let index = CacheIndex::load(&dir.path().to_string_lossy());
+        let now = now_timestamp();
+        {
+            let mut entries = index.entries.lock().unwrap();
+            for (i, key) in ["a", "b", "c"].iter().enumerate() {
+                entries.insert(
+                    key.to_string(),
+                    IndexEntry {
+                        size: 10,
+                        accessed: now - 100 + i as u64,
+                        expired: 0,
+                    },
+                );
+            }
+        }
+        index.size.store(30, Ordering::Relaxed);
+        index.touch("a");
+
+        assert_eq!(true, index.get_evictions(30).is_empty());
+        assert_eq!(vec!["b".to_string()], index.get_evictions(25));
+        assert_eq!(
+            vec!["b".to_string(), "c".to_string()],
+            index.get_evictions(10)
+        );
+
+        assert_eq!(
+            vec!["b".to_string()],
+            index
+                .get_clearable(UNIX_EPOCH + Duration::from_secs(now - 100 + 1))
+        );
+    }
+}
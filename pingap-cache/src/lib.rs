@@ -23,6 +23,8 @@ use tracing::info;
 
 mod file;
 mod http_cache;
+mod index;
+mod redis;
 mod tiny;
 
 pub static PAGE_SIZE: usize = 4096;
@@ -40,6 +42,8 @@ pub enum Error {
     OverQuota { max: u32, message: String },
     #[snafu(display("{message}"))]
     Prometheus { message: String },
+    #[snafu(display("Redis error: {source}"))]
+    Redis { source: ::redis::RedisError },
 }
 pub type Result<T, E = Error> = std::result::Result<T, E>;
 
@@ -55,14 +59,22 @@ fn new_tiny_ufo_cache(mode: &str, size: usize) -> HttpCache {
         cache: Arc::new(tiny::new_tiny_ufo_cache(mode, size / PAGE_SIZE, size)),
     }
 }
-fn new_file_cache(dir: &str) -> Result<HttpCache> {
-    let cache = file::new_file_cache(dir)?;
+fn new_file_cache(dir: &str, max_size: u64) -> Result<HttpCache> {
+    let cache = file::new_file_cache(dir, max_size)?;
     Ok(HttpCache {
         directory: Some(cache.directory.clone()),
         cache: Arc::new(cache),
     })
 }
 
+fn new_redis_cache(url: &str) -> Result<HttpCache> {
+    let cache = redis::new_redis_cache(url)?;
+    Ok(HttpCache {
+        directory: None,
+        cache: Arc::new(cache),
+    })
+}
+
 static CACHE_BACKEND: OnceCell<HttpCache> = OnceCell::new();
 const MAX_MEMORY_SIZE: usize = 100 * 1024 * 1024;
 static CACHED_INIT: AtomicBool = AtomicBool::new(false);
@@ -76,8 +88,20 @@ pub fn is_cache_backend_init() -> bool {
 pub struct CacheBackendOption {
     /// Directory to store cache files
     pub cache_directory: Option<String>,
-    /// Maximum size of cache storage
+    /// Maximum size of memory cache
     pub cache_max_size: Option<ByteSize>,
+    /// Maximum disk size of file cache, None means unlimited
+    pub cache_disk_max_size: Option<ByteSize>,
+}
+
+impl CacheBackendOption {
+    /// Returns the max disk size of file cache, 0 means unlimited.
+    /// The `cache_max_size` is only for memory cache, so it's not used.
+    fn get_disk_max_size(&self) -> u64 {
+        self.cache_disk_max_size
+            .map(|v| v.as_u64())
+            .unwrap_or_default()
+    }
 }
 
 /// Get the cache backend
@@ -110,13 +134,24 @@ pub fn get_cache_backend(
                 "".to_string()
             };
 
-        // Choose between file-based or memory-based cache
-        let cache = if !cache_directory.is_empty()
+        // Choose between redis, file-based or memory-based cache
+        let cache = if cache_directory.starts_with("redis://")
+            || cache_directory.starts_with("rediss://")
+        {
+            // Use redis cache which can be shared by multiple instances
+            cache_type = "redis";
+            new_redis_cache(cache_directory.as_str()).map_err(|e| {
+                Error::Invalid {
+                    message: e.to_string(),
+                }
+            })?
+        } else if !cache_directory.is_empty()
             && !cache_directory.starts_with("memory://")
         {
             // Use file-based cache if directory is specified
             cache_type = "file";
-            new_file_cache(cache_directory.as_str()).map_err(|e| {
+            let max_size = option.get_disk_max_size();
+            new_file_cache(cache_directory.as_str(), max_size).map_err(|e| {
                 Error::Invalid {
                     message: e.to_string(),
                 }
@@ -181,6 +216,24 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_cache_backend_option() {
+        // the max size of memory cache is not the disk limit of file cache
+        let option = CacheBackendOption {
+            cache_directory: Some("/opt/cache".to_string()),
+            cache_max_size: Some(ByteSize::mb(100)),
+            ..Default::default()
+        };
+        assert_eq!(0, option.get_disk_max_size());
+
+        let option = CacheBackendOption {
+            cache_directory: Some("/opt/cache".to_string()),
+            cache_max_size: Some(ByteSize::mb(100)),
+            cache_disk_max_size: Some(ByteSize::gb(10)),
+        };
+        assert_eq!(ByteSize::gb(10).as_u64(), option.get_disk_max_size());
+    }
+
     #[test]
     fn test_is_cache_backend_init() {
         assert_eq!(false, is_cache_backend_init());
@@ -190,7 +243,7 @@ mod tests {
         let _ = new_tiny_ufo_cache("compact", 1024);
 
         let dir = TempDir::new().unwrap();
-        let result = new_file_cache(&dir.into_path().to_string_lossy());
+        let result = new_file_cache(&dir.into_path().to_string_lossy(), 0);
         assert_eq!(true, result.is_ok());
     }
 }
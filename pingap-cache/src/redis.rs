@@ -0,0 +1,585 @@
+// Copyright 2024-2025 Tree xie.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use super::http_cache::{CacheObject, HttpCacheStats, HttpCacheStorage};
+use super::{Error, Result, LOG_CATEGORY};
+#[cfg(feature = "tracing")]
+use super::{CACHE_READING_TIME, CACHE_WRITING_TIME};
+use async_trait::async_trait;
+use bytes::Bytes;
+use pingap_core::convert_query_map;
+#[cfg(feature = "tracing")]
+use prometheus::Histogram;
+use redis::aio::{ConnectionManager, ConnectionManagerConfig};
+use scopeguard::defer;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::time::{Duration, SystemTime};
+use tokio::sync::OnceCell;
+use tracing::{debug, error, info};
+
+/// A redis based cache implementation, it can be shared by multiple
+/// pingap instances to reduce the load of upstreams.
+pub struct RedisCache {
+    /// Redis client used to create the connection manager
+    client: redis::Client,
+    /// Lazily created connection manager, it reconnects automatically
+    conn: OnceCell<ConnectionManager>,
+    /// Prefix of all cache keys
+    prefix: String,
+    /// Default ttl for cache object whose meta can't be parsed
+    ttl: Duration,
+    /// Response timeout of redis command
+    timeout: Duration,
+    /// Connection timeout of redis
+    connect_timeout: Duration,
+    /// Counter for current number of concurrent read operations
+    reading: AtomicU32,
+    /// Maximum allowed concurrent read operations
+    reading_max: u32,
+    #[cfg(feature = "tracing")]
+    /// Histogram metric for tracking cache read operation times
+    read_time: Box<Histogram>,
+    /// Counter for current number of concurrent write operations
+    writing: AtomicU32,
+    /// Maximum allowed concurrent write operations
+    writing_max: u32,
+    #[cfg(feature = "tracing")]
+    /// Histogram metric for tracking cache write operation times
+    write_time: Box<Histogram>,
+}
+
+/// Redis cache parameters
+#[derive(Debug, Clone)]
+struct RedisCacheParams {
+    /// Redis connection url without pingap cache params
+    url: String,
+    /// Prefix of cache keys
+    prefix: String,
+    /// Default ttl of cache object
+    ttl: Duration,
+    /// Response timeout of redis command
+    timeout: Duration,
+    /// Connection timeout of redis
+    connect_timeout: Duration,
+    /// Max reading count
+    reading_max: u32,
+    /// Max writing count
+    writing_max: u32,
+}
+
+impl Default for RedisCacheParams {
+    fn default() -> Self {
+        Self {
+            url: String::new(),
+            prefix: "pingap".to_string(),
+            ttl: Duration::from_secs(3600),
+            timeout: Duration::from_secs(3),
+            connect_timeout: Duration::from_secs(3),
+            reading_max: 10_000,
+            writing_max: 1_000,
+        }
+    }
+}
+
+/// Cache params which are handled by pingap,
+/// the others are passed to redis client.
+const CACHE_PARAM_KEYS: [&str; 6] = [
+    "prefix",
+    "ttl",
+    "timeout",
+    "connect_timeout",
+    "reading_max",
+    "writing_max",
+];
+
+fn parse_params(value: &str) -> RedisCacheParams {
+    let (url, query) = value.split_once('?').unwrap_or((value, ""));
+    let mut params = RedisCacheParams::default();
+    if query.is_empty() {
+        params.url = url.to_string();
+        return params;
+    }
+    let m = convert_query_map(query);
+    if let Some(prefix) = m.get("prefix") {
+        params.prefix = prefix.clone();
+    }
+    let parse_duration = |key: &str, default: Duration| {
+        m.get(key)
+            .and_then(|v| humantime::parse_duration(v).ok())
+            .unwrap_or(default)
+    };
+    params.ttl = parse_duration("ttl", params.ttl);
+    params.timeout = parse_duration("timeout", params.timeout);
+    params.connect_timeout =
+        parse_duration("connect_timeout", params.connect_timeout);
+    params.reading_max = m
+        .get("reading_max")
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(params.reading_max);
+    params.writing_max = m
+        .get("writing_max")
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(params.writing_max);
+
+    let rest: Vec<&str> = query
+        .split('&')
+        .filter(|item| {
+            let key = item.split_once('=').map(|(k, _)| k).unwrap_or(item);
+            !item.is_empty() && !CACHE_PARAM_KEYS.contains(&key)
+        })
+        .collect();
+    params.url = if rest.is_empty() {
+        url.to_string()
+    } else {
+        format!("{url}?{}", rest.join("&"))
+    };
+    params
+}
+
+/// Create a redis cache, the connection will be established
+/// when it's used for the first time.
+pub fn new_redis_cache(value: &str) -> Result<RedisCache> {
+    let params = parse_params(value);
+    let client = redis::Client::open(params.url.as_str())
+        .map_err(|e| Error::Redis { source: e })?;
+    info!(
+        category = LOG_CATEGORY,
+        prefix = params.prefix,
+        ttl = format!("{:?}", params.ttl),
+        timeout = format!("{:?}", params.timeout),
+        reading_max = params.reading_max,
+        writing_max = params.writing_max,
+        "new redis cache"
+    );
+    Ok(RedisCache {
+        client,
+        conn: OnceCell::new(),
+        prefix: params.prefix,
+        ttl: params.ttl,
+        timeout: params.timeout,
+        connect_timeout: params.connect_timeout,
+        reading: AtomicU32::new(0),
+        reading_max: params.reading_max,
+        #[cfg(feature = "tracing")]
+        read_time: CACHE_READING_TIME.clone(),
+        writing: AtomicU32::new(0),
+        writing_max: params.writing_max,
+        #[cfg(feature = "tracing")]
+        write_time: CACHE_WRITING_TIME.clone(),
+    })
+}
+
+/// Returns the elapsed time in seconds (as f64) since the given SystemTime
+#[cfg(feature = "tracing")]
+#[inline]
+fn elapsed_second(time: SystemTime) -> f64 {
+    time.elapsed().unwrap_or_default().as_millis() as f64 / 1000.0
+}
+
+impl RedisCache {
+    #[inline]
+    fn get_key(&self, key: &str, namespace: &str) -> String {
+        if namespace.is_empty() {
+            format!("{}:{key}", self.prefix)
+        } else {
+            format!("{}:{namespace}:{key}", self.prefix)
+        }
+    }
+    /// Get the connection manager, it will be created if not exists
+    async fn get_conn(&self) -> Result<ConnectionManager> {
+        let conn = self
+            .conn
+            .get_or_try_init(|| async {
+                let config = ConnectionManagerConfig::new()
+                    .set_response_timeout(self.timeout)
+                    .set_connection_timeout(self.connect_timeout);
+                ConnectionManager::new_with_config(self.client.clone(), config)
+                    .await
+            })
+            .await
+            .map_err(|e| Error::Redis { source: e })?;
+        Ok(conn.clone())
+    }
+}
+
+#[async_trait]
+impl HttpCacheStorage for RedisCache {
+    /// Retrieves a cache object from redis.
+    ///
+    /// # Returns
+    /// * `Ok(Some(CacheObject))` - If cache entry is found
+    /// * `Ok(None)` - If entry doesn't exist or is invalid
+    /// * `Err(Error::OverQuota)` - If max concurrent reads exceeded
+    /// * `Err(Error::Redis)` - On redis errors
+    async fn get(
+        &self,
+        key: &str,
+        namespace: &str,
+    ) -> Result<Option<CacheObject>> {
+        #[cfg(feature = "tracing")]
+        let start = SystemTime::now();
+        let count = self.reading.fetch_add(1, Ordering::Relaxed);
+        defer!(self.reading.fetch_sub(1, Ordering::Relaxed););
+        if self.reading_max > 0 && count >= self.reading_max {
+            return Err(Error::OverQuota {
+                max: self.reading_max,
+                message: "too many reading".to_string(),
+            });
+        }
+        let mut conn = self.get_conn().await?;
+        let result: Option<Vec<u8>> = redis::cmd("GET")
+            .arg(self.get_key(key, namespace))
+            .query_async(&mut conn)
+            .await
+            .map_err(|e| Error::Redis { source: e })?;
+        #[cfg(feature = "tracing")]
+        self.read_time.observe(elapsed_second(start));
+        debug!(
+            category = LOG_CATEGORY,
+            key, namespace, "get cache from redis"
+        );
+        Ok(match result {
+            Some(buf) if buf.len() >= 8 => {
+                Some(CacheObject::from(Bytes::from(buf)))
+            },
+            _ => None,
+        })
+    }
+    /// Stores a cache object to redis, the ttl is mapped from cache meta.
+    ///
+    /// # Returns
+    /// * `Ok(())` - On successful storage
+    /// * `Err(Error::OverQuota)` - If max concurrent writes exceeded
+    /// * `Err(Error::Redis)` - On redis errors
+    async fn put(
+        &self,
+        key: &str,
+        namespace: &str,
+        data: CacheObject,
+    ) -> Result<()> {
+        #[cfg(feature = "tracing")]
+        let start = SystemTime::now();
+        let count = self.writing.fetch_add(1, Ordering::Relaxed);
+        defer!(self.writing.fetch_sub(1, Ordering::Relaxed););
+        if self.writing_max > 0 && count >= self.writing_max {
+            return Err(Error::OverQuota {
+                max: self.writing_max,
+                message: "too many writing".to_string(),
+            });
+        }
+        let ttl = data.get_ttl().unwrap_or(self.ttl);
+        let buf: Bytes = data.into();
+        let mut conn = self.get_conn().await?;
+        let _: () = redis::cmd("SET")
+            .arg(self.get_key(key, namespace))
+            .arg(buf.as_ref())
+            .arg("PX")
+            .arg(ttl.as_millis() as u64)
+            .query_async(&mut conn)
+            .await
+            .map_err(|e| Error::Redis { source: e })?;
+        #[cfg(feature = "tracing")]
+        self.write_time.observe(elapsed_second(start));
+        debug!(
+            category = LOG_CATEGORY,
+            key,
+            namespace,
+            ttl = format!("{ttl:?}"),
+            "put cache to redis"
+        );
+        Ok(())
+    }
+    /// Removes a cache object from redis, the object is got and deleted
+    /// in one pipeline, so the caller knows whether it's removed.
+    ///
+    /// # Returns
+    /// * `Ok(Some(obj))` - The removed object if the key is deleted
+    /// * `Ok(None)` - If the key does not exist
+    /// * `Err(Error::Redis)` - On redis errors
+    async fn remove(
+        &self,
+        key: &str,
+        namespace: &str,
+    ) -> Result<Option<CacheObject>> {
+        let mut conn = self.get_conn().await?;
+        let key = self.get_key(key, namespace);
+        let (value, count): (Option<Vec<u8>>, i64) = redis::pipe()
+            .cmd("GET")
+            .arg(&key)
+            .cmd("DEL")
+            .arg(&key)
+            .query_async(&mut conn)
+            .await
+            .map_err(|e| Error::Redis { source: e })?;
+        debug!(
+            category = LOG_CATEGORY,
+            key, count, "remove cache from redis"
+        );
+        if count == 0 {
+            return Ok(None);
+        }
+        Ok(Some(match value {
+            Some(buf) if buf.len() >= 8 => CacheObject::from(Bytes::from(buf)),
+            // the key is deleted, but the value is invalid
+            _ => CacheObject::default(),
+        }))
+    }
+    /// Returns current cache statistics.
+    #[inline]
+    fn stats(&self) -> Option<HttpCacheStats> {
+        Some(HttpCacheStats {
+            reading: self.reading.load(Ordering::Relaxed),
+            writing: self.writing.load(Ordering::Relaxed),
+            disk_usage: None,
+        })
+    }
+    /// Clears cache objects which have been idle since the given timestamp.
+    /// The idle time is got by `OBJECT IDLETIME` of each key.
+    ///
+    /// # Returns
+    /// * `Ok((success, fail))` - Number of successfully and unsuccessfully removed entries
+    async fn clear(&self, access_before: SystemTime) -> Result<(i32, i32)> {
+        let idle = SystemTime::now()
+            .duration_since(access_before)
+            .map(|d| d.as_secs())
+            .unwrap_or_default();
+        let mut conn = self.get_conn().await?;
+        let pattern = format!("{}:*", self.prefix);
+        let mut success = 0;
+        let mut fail = 0;
+        let mut cursor = 0_u64;
+        loop {
+            let (next, keys): (u64, Vec<String>) = redis::cmd("SCAN")
+                .arg(cursor)
+                .arg("MATCH")
+                .arg(&pattern)
+                .arg("COUNT")
+                .arg(100)
+                .query_async(&mut conn)
+                .await
+                .map_err(|e| Error::Redis { source: e })?;
+            for key in keys {
+                let idle_time: Option<u64> = redis::cmd("OBJECT")
+                    .arg("IDLETIME")
+                    .arg(&key)
+                    .query_async(&mut conn)
+                    .await
+                    .unwrap_or_default();
+                // the key may be expired
+                let Some(idle_time) = idle_time else {
+                    continue;
+                };
+                if idle_time < idle {
+                    continue;
+                }
+                let result: redis::RedisResult<()> =
+                    redis::cmd("DEL").arg(&key).query_async(&mut conn).await;
+                match result {
+                    Ok(()) => {
+                        info!(
+                            category = LOG_CATEGORY,
+                            key, "remove redis cache success"
+                        );
+                        success += 1;
+                    },
+                    Err(e) => {
+                        fail += 1;
+                        error!(
+                            category = LOG_CATEGORY,
+                            error = %e,
+                            key,
+                            "remove redis cache fail"
+                        );
+                    },
+                }
+            }
+            if next == 0 {
+                break;
+            }
+            cursor = next;
+        }
+        Ok((success, fail))
+    }
+    fn support_clear(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bytes::Bytes;
+    use pretty_assertions::assert_eq;
+    use std::collections::HashMap;
+    use std::sync::{Arc, Mutex};
+    use std::time::{Duration, SystemTime};
+    use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+    use tokio::net::{TcpListener, TcpStream};
+
+    type Store = Arc<Mutex<HashMap<Vec<u8>, Vec<u8>>>>;
+
+    async fn read_command(
+        reader: &mut BufReader<TcpStream>,
+    ) -> Option<Vec<Vec<u8>>> {
+        let mut line = String::new();
+        reader.read_line(&mut line).await.ok()?;
+        let count: usize = line.trim().strip_prefix('*')?.parse().ok()?;
+        let mut args = vec![];
+        for _ in 0..count {
+            line.clear();
+            reader.read_line(&mut line).await.ok()?;
+            let size: usize = line.trim().strip_prefix('$')?.parse().ok()?;
+            let mut buf = vec![0; size + 2];
+            reader.read_exact(&mut buf).await.ok()?;
+            buf.truncate(size);
+            args.push(buf);
+        }
+        Some(args)
+    }
+
+    fn bulk(value: &[u8]) -> Vec<u8> {
+        let mut buf = format!("${}\r\n", value.len()).into_bytes();
+        buf.extend(value);
+        buf.extend(b"\r\n");
+        buf
+    }
+
+    fn handle_command(store: &Store, args: Vec<Vec<u8>>) -> Vec<u8> {
+        let mut store = store.lock().unwrap();
+        let cmd = String::from_utf8_lossy(&args[0]).to_uppercase();
+        match cmd.as_str() {
+            "GET" => match store.get(&args[1]) {
+                Some(value) => bulk(value),
+                None => b"$-1\r\n".to_vec(),
+            },
+            "SET" => {
+                store.insert(args[1].clone(), args[2].clone());
+                b"+OK\r\n".to_vec()
+            },
+            "DEL" => {
+                let count = store.remove(&args[1]).map(|_| 1).unwrap_or(0);
+                format!(":{count}\r\n").into_bytes()
+            },
+            "SCAN" => {
+                let prefix = args[3].strip_suffix(b"*").unwrap_or(&args[3]);
+                let keys: Vec<_> =
+                    store.keys().filter(|k| k.starts_with(prefix)).collect();
+                let mut buf = b"*2\r\n".to_vec();
+                buf.extend(bulk(b"0"));
+                buf.extend(format!("*{}\r\n", keys.len()).into_bytes());
+                for key in keys {
+                    buf.extend(bulk(key));
+                }
+                buf
+            },
+            "OBJECT" => {
+                if store.contains_key(&args[2]) {
+                    b":10\r\n".to_vec()
+                } else {
+                    b"$-1\r\n".to_vec()
+                }
+            },
+            _ => b"-ERR unknown command\r\n".to_vec(),
+        }
+    }
+
+    /// Start a tiny redis server stand-in which supports the commands of redis cache
+    async fn start_redis_server() -> (String, Store) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let store: Store = Arc::new(Mutex::new(HashMap::new()));
+        let server_store = store.clone();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let store = server_store.clone();
+                tokio::spawn(async move {
+                    let mut reader = BufReader::new(stream);
+                    while let Some(args) = read_command(&mut reader).await {
+                        let resp = handle_command(&store, args);
+                        if reader.get_mut().write_all(&resp).await.is_err() {
+                            break;
+                        }
+                    }
+                });
+            }
+        });
+        (format!("redis://{addr}"), store)
+    }
+
+    #[test]
+    fn test_parse_params() {
+        let params = parse_params(
+            "redis://127.0.0.1:6379/0?prefix=cache&ttl=10m&timeout=1s&reading_max=100&writing_max=10&protocol=resp3",
+        );
+        assert_eq!("redis://127.0.0.1:6379/0?protocol=resp3", params.url);
+        assert_eq!("cache", params.prefix);
+        assert_eq!(Duration::from_secs(600), params.ttl);
+        assert_eq!(Duration::from_secs(1), params.timeout);
+        assert_eq!(Duration::from_secs(3), params.connect_timeout);
+        assert_eq!(100, params.reading_max);
+        assert_eq!(10, params.writing_max);
+
+        let params = parse_params("redis://127.0.0.1:6379");
+        assert_eq!("redis://127.0.0.1:6379", params.url);
+        assert_eq!("pingap", params.prefix);
+    }
+
+    #[tokio::test]
+    async fn test_redis_cache() {
+        let (url, store) = start_redis_server().await;
+        let cache = new_redis_cache(&format!("{url}?prefix=test")).unwrap();
+        let key = "key";
+        let namespace = "pingap";
+        let obj = CacheObject {
+            meta: (b"Hello".to_vec(), b"World".to_vec()),
+            body: Bytes::from_static(b"Hello World!"),
+        };
+        let result = cache.get(key, namespace).await.unwrap();
+        assert_eq!(true, result.is_none());
+
+        cache.put(key, namespace, obj.clone()).await.unwrap();
+        assert_eq!(
+            true,
+            store
+                .lock()
+                .unwrap()
+                .contains_key(b"test:pingap:key".as_slice())
+        );
+        let result = cache.get(key, namespace).await.unwrap().unwrap();
+        assert_eq!(obj, result);
+
+        let result = cache.remove(key, namespace).await.unwrap();
+        assert_eq!(Some(obj.clone()), result);
+        let result = cache.get(key, namespace).await.unwrap();
+        assert_eq!(true, result.is_none());
+        // nothing is removed
+        let result = cache.remove(key, namespace).await.unwrap();
+        assert_eq!(None, result);
+
+        cache.put(key, namespace, obj.clone()).await.unwrap();
+        cache.put(key, "", obj.clone()).await.unwrap();
+        // idle time of stand-in is 10 seconds
+        let (success, fail) = cache
+            .clear(SystemTime::now() - Duration::from_secs(60))
+            .await
+            .unwrap();
+        assert_eq!((0, 0), (success, fail));
+        let (success, fail) = cache.clear(SystemTime::now()).await.unwrap();
+        assert_eq!((2, 0), (success, fail));
+        assert_eq!(true, store.lock().unwrap().is_empty());
+
+        assert_eq!(0, cache.stats().unwrap().reading);
+        assert_eq!(0, cache.stats().unwrap().writing);
+    }
+}
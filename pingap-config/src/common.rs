@@ -12,19 +12,26 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use super::graph::validate_graph;
+use super::secret::resolve_references;
+use super::template::{expand_template, TEMPLATE_CATEGORY};
 use super::{Error, Result};
 // use crate::plugin::parse_plugins;
 // use crate::proxy::Parser;
 use arc_swap::ArcSwap;
 use bytesize::ByteSize;
 use http::{HeaderName, HeaderValue};
+use ipnet::IpNet;
 use once_cell::sync::Lazy;
 use pingap_discovery::{is_static_discovery, DNS_DISCOVERY};
 use regex::Regex;
+use schemars::JsonSchema;
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize, Serializer};
+use std::collections::BTreeMap;
 use std::hash::{DefaultHasher, Hash, Hasher};
 use std::io::Cursor;
-use std::net::ToSocketAddrs;
+use std::net::{IpAddr, ToSocketAddrs};
 use std::sync::Arc;
 use std::time::Duration;
 use std::{collections::HashMap, str::FromStr};
@@ -90,6 +97,8 @@ pub enum PluginCategory {
     Cors,
     /// Accept-Encoding header processing
     AcceptEncoding,
+    /// Request coalescing for identical requests
+    Coalesce,
 }
 impl Serialize for PluginCategory {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
@@ -116,7 +125,7 @@ impl<'de> Deserialize<'de> for PluginCategory {
 }
 
 /// Configuration struct for TLS/SSL certificates
-#[derive(Debug, Default, Deserialize, Clone, Serialize, Hash)]
+#[derive(Debug, Default, Deserialize, Clone, Serialize, Hash, JsonSchema)]
 pub struct CertificateConf {
     /// Domain names this certificate is valid for (comma separated)
     pub domains: Option<String>,
@@ -209,7 +218,7 @@ impl CertificateConf {
 }
 
 /// Configuration for an upstream service that handles proxied requests
-#[derive(Debug, Default, Deserialize, Clone, Serialize, Hash)]
+#[derive(Debug, Default, Deserialize, Clone, Serialize, Hash, JsonSchema)]
 pub struct UpstreamConf {
     /// List of upstream server addresses in format "host:port" or "host:port weight"
     pub addrs: Vec<String>,
@@ -220,6 +229,7 @@ pub struct UpstreamConf {
     /// How frequently to update the upstream server list
     #[serde(default)]
     #[serde(with = "humantime_serde")]
+    #[schemars(with = "Option<String>")]
     pub update_frequency: Option<Duration>,
 
     /// Load balancing algorithm (e.g. "round_robin", "hash:cookie")
@@ -246,42 +256,51 @@ pub struct UpstreamConf {
     /// Timeout for establishing new connections
     #[serde(default)]
     #[serde(with = "humantime_serde")]
+    #[schemars(with = "Option<String>")]
     pub connection_timeout: Option<Duration>,
 
     /// Total timeout for the entire request/response cycle
     #[serde(default)]
     #[serde(with = "humantime_serde")]
+    #[schemars(with = "Option<String>")]
     pub total_connection_timeout: Option<Duration>,
 
     /// Timeout for reading response data
     #[serde(default)]
     #[serde(with = "humantime_serde")]
+    #[schemars(with = "Option<String>")]
     pub read_timeout: Option<Duration>,
 
     /// Timeout for idle connections in the pool
     #[serde(default)]
     #[serde(with = "humantime_serde")]
+    #[schemars(with = "Option<String>")]
     pub idle_timeout: Option<Duration>,
 
     /// Timeout for writing request data
     #[serde(default)]
     #[serde(with = "humantime_serde")]
+    #[schemars(with = "Option<String>")]
     pub write_timeout: Option<Duration>,
 
     /// TCP keepalive idle time
     #[serde(default)]
     #[serde(with = "humantime_serde")]
+    #[schemars(with = "Option<String>")]
     pub tcp_idle: Option<Duration>,
 
     /// TCP keepalive probe interval
     #[serde(default)]
     #[serde(with = "humantime_serde")]
+    #[schemars(with = "Option<String>")]
     pub tcp_interval: Option<Duration>,
 
     /// Number of TCP keepalive probes before connection is dropped
+    #[schemars(range(max = 16))]
     pub tcp_probe_count: Option<usize>,
 
     /// TCP receive buffer size
+    #[schemars(with = "Option<String>")]
     pub tcp_recv_buf: Option<ByteSize>,
 
     /// Enable TCP Fast Open
@@ -290,6 +309,12 @@ pub struct UpstreamConf {
     /// List of included configuration files
     pub includes: Option<Vec<String>>,
 
+    /// Name of the template(storage of template category) to expand
+    pub template: Option<String>,
+
+    /// Params of the template, they replace the `{{param}}` of template
+    pub params: Option<BTreeMap<String, String>>,
+
     /// Optional description/notes about this upstream
     pub remark: Option<String>,
 }
@@ -413,7 +438,7 @@ impl UpstreamConf {
 }
 
 /// Configuration for a location/route that handles incoming requests
-#[derive(Debug, Default, Deserialize, Clone, Serialize, Hash)]
+#[derive(Debug, Default, Deserialize, Clone, Serialize, Hash, JsonSchema)]
 pub struct LocationConf {
     /// Name of the upstream service to proxy requests to
     pub upstream: Option<String>,
@@ -445,6 +470,7 @@ pub struct LocationConf {
     pub plugins: Option<Vec<String>>,
 
     /// Maximum allowed size of request body
+    #[schemars(with = "Option<String>")]
     pub client_max_body_size: Option<ByteSize>,
 
     /// Maximum number of concurrent requests being processed
@@ -453,6 +479,12 @@ pub struct LocationConf {
     /// List of included configuration files
     pub includes: Option<Vec<String>>,
 
+    /// Name of the template(storage of template category) to expand
+    pub template: Option<String>,
+
+    /// Params of the template, they replace the `{{param}}` of template
+    pub params: Option<BTreeMap<String, String>>,
+
     /// Whether to enable gRPC-Web protocol support
     pub grpc_web: Option<bool>,
 
@@ -580,8 +612,62 @@ impl LocationConf {
     }
 }
 
+/// Path rule of location, it's used by the location selector to route
+/// requests and by the validation of the routes of server.
+#[derive(Debug, Clone, PartialEq)]
+pub enum LocationPath {
+    /// Empty path, it matches all paths
+    All,
+    /// Path starts with `~`, it's matched by regex
+    Regex(String),
+    /// Path starts with `=`, it matches the exact path
+    Equal(String),
+    /// Other path, it matches the paths with the prefix
+    Prefix(String),
+}
+
+impl LocationPath {
+    pub fn new(path: &str) -> Self {
+        let path = path.trim();
+        if let Some(value) = path.strip_prefix('~') {
+            return Self::Regex(value.trim().to_string());
+        }
+        if let Some(value) = path.strip_prefix('=') {
+            return Self::Equal(value.trim().to_string());
+        }
+        if path.is_empty() {
+            return Self::All;
+        }
+        Self::Prefix(path.to_string())
+    }
+    /// Whether the path is matched, the regex path is matched by the
+    /// compiled regex of location selector, so it's always false here.
+    pub fn is_match(&self, path: &str) -> bool {
+        match self {
+            Self::All => true,
+            Self::Equal(value) => value == path,
+            Self::Prefix(value) => path.starts_with(value),
+            Self::Regex(_) => false,
+        }
+    }
+    /// Whether all paths matched by the other rule are matched by this rule,
+    /// the regex paths are only compared as text.
+    pub fn covers(&self, other: &LocationPath) -> bool {
+        match (self, other) {
+            (Self::All, _) => true,
+            // the path of request always starts with "/"
+            (Self::Prefix(value), Self::All) => value == "/",
+            (Self::Regex(value), Self::Regex(other)) => value == other,
+            (Self::Regex(_), _) => false,
+            (_, Self::Equal(path)) => self.is_match(path),
+            (Self::Prefix(_), Self::Prefix(path)) => self.is_match(path),
+            _ => false,
+        }
+    }
+}
+
 /// Configuration for a server instance that handles incoming HTTP/HTTPS requests
-#[derive(Debug, Default, Deserialize, Clone, Serialize)]
+#[derive(Debug, Default, Deserialize, Clone, Serialize, JsonSchema)]
 pub struct ServerConf {
     /// Address to listen on in format "host:port" or multiple addresses separated by commas
     pub addr: String,
@@ -589,6 +675,26 @@ pub struct ServerConf {
     /// Access log format string for request logging
     pub access_log: Option<String>,
 
+    /// Dedicated output of access log: stdout, stderr, syslog://, http(s):// or file path,
+    /// access log is written to the application log if not set
+    pub access_log_output: Option<String>,
+
+    /// Only log the successful requests whose status >= the value
+    pub access_log_min_status: Option<u16>,
+
+    /// Only log the successful requests whose latency >= the value
+    #[serde(default)]
+    #[serde(with = "humantime_serde")]
+    #[schemars(with = "Option<String>")]
+    pub access_log_min_latency: Option<Duration>,
+
+    /// Do not log the successful requests whose path matches the regex
+    pub access_log_exclude_path: Option<String>,
+
+    /// Ratio of successful requests to log, between 0 and 1
+    #[schemars(range(min = 0.0, max = 1.0))]
+    pub access_log_sample_ratio: Option<f64>,
+
     /// List of location names that this server handles
     pub locations: Option<Vec<String>>,
 
@@ -602,9 +708,11 @@ pub struct ServerConf {
     pub tls_ciphersuites: Option<String>,
 
     /// Minimum TLS version to accept (e.g. "TLSv1.2")
+    #[schemars(extend("examples" = ["tlsv1.1", "tlsv1.2", "tlsv1.3"]))]
     pub tls_min_version: Option<String>,
 
     /// Maximum TLS version to use (e.g. "TLSv1.3")
+    #[schemars(extend("examples" = ["tlsv1.1", "tlsv1.2", "tlsv1.3"]))]
     pub tls_max_version: Option<String>,
 
     /// Whether to use global certificates instead of per-server certs
@@ -616,11 +724,13 @@ pub struct ServerConf {
     /// TCP keepalive idle timeout
     #[serde(default)]
     #[serde(with = "humantime_serde")]
+    #[schemars(with = "Option<String>")]
     pub tcp_idle: Option<Duration>,
 
     /// TCP keepalive probe interval
     #[serde(default)]
     #[serde(with = "humantime_serde")]
+    #[schemars(with = "Option<String>")]
     pub tcp_interval: Option<Duration>,
 
     /// Number of TCP keepalive probes before connection is dropped
@@ -635,15 +745,41 @@ pub struct ServerConf {
     /// OpenTelemetry exporter configuration
     pub otlp_exporter: Option<String>,
 
+    /// OpenTelemetry metrics exporter configuration
+    pub otlp_metrics: Option<String>,
+
     /// List of configuration files to include
     pub includes: Option<Vec<String>>,
 
+    /// Name of the template(storage of template category) to expand
+    pub template: Option<String>,
+
+    /// Params of the template, they replace the `{{param}}` of template
+    pub params: Option<BTreeMap<String, String>>,
+
     /// List of modules to enable for this server
     pub modules: Option<Vec<String>>,
 
     /// Whether to enable server-timing header
     pub enable_server_timing: Option<bool>,
 
+    /// Number of the slowest and the most recent failed requests to capture
+    pub request_capture_size: Option<usize>,
+
+    /// Names of request headers and query params whose values are redacted
+    /// in captured requests, they're added to the default list
+    pub request_capture_redacted: Option<Vec<String>>,
+
+    /// Metric groups of server-timing header(upstream, cache, plugin, total)
+    #[schemars(extend("items" = {
+        "type": "string",
+        "enum": ["upstream", "cache", "plugin", "total"]
+    }))]
+    pub server_timing_metrics: Option<Vec<String>>,
+
+    /// Client ip list(or cidr) which are allowed to receive server-timing header
+    pub server_timing_trusted_ips: Option<Vec<String>>,
+
     /// Optional description/notes about this server
     pub remark: Option<String>,
 }
@@ -653,6 +789,7 @@ impl ServerConf {
     /// 1. Parse listen addr to socket addr.
     /// 2. Check the locations are exists.
     /// 3. Parse access log layout success.
+    /// 4. Check the access log exclude path and sample ratio are valid.
     fn validate(&self, name: &str, location_names: &[String]) -> Result<()> {
         for addr in self.addr.split(',') {
             let _ = addr.to_socket_addrs().map_err(|e| Error::Io {
@@ -681,13 +818,50 @@ impl ServerConf {
             //     });
             // }
         }
+        if let Some(exclude_path) = &self.access_log_exclude_path {
+            Regex::new(exclude_path).map_err(|e| Error::Regex { source: e })?;
+        }
+        if let Some(metrics) = &self.server_timing_metrics {
+            for metric in metrics.iter() {
+                if !["upstream", "cache", "plugin", "total"]
+                    .contains(&metric.as_str())
+                {
+                    return Err(Error::Invalid {
+                        message: format!(
+                            "server timing metric({metric}) is invalid(server:{name})"
+                        ),
+                    });
+                }
+            }
+        }
+        if let Some(ips) = &self.server_timing_trusted_ips {
+            for ip in ips.iter() {
+                if IpAddr::from_str(ip).is_err() && IpNet::from_str(ip).is_err()
+                {
+                    return Err(Error::Invalid {
+                        message: format!(
+                            "server timing trusted ip({ip}) is invalid(server:{name})"
+                        ),
+                    });
+                }
+            }
+        }
+        if let Some(ratio) = self.access_log_sample_ratio {
+            if !(0.0..=1.0).contains(&ratio) {
+                return Err(Error::Invalid {
+                    message: format!(
+                        "access log sample ratio({ratio}) should be between 0 and 1(server:{name})"
+                    ),
+                });
+            }
+        }
 
         Ok(())
     }
 }
 
 /// Basic configuration options for the application
-#[derive(Debug, Default, Deserialize, Clone, Serialize)]
+#[derive(Debug, Default, Deserialize, Clone, Serialize, JsonSchema)]
 pub struct BasicConf {
     /// Application name
     pub name: Option<String>,
@@ -710,22 +884,27 @@ pub struct BasicConf {
     /// Grace period before forcefully terminating during shutdown(default: 5m)
     #[serde(default)]
     #[serde(with = "humantime_serde")]
+    #[schemars(with = "Option<String>")]
     pub grace_period: Option<Duration>,
     /// Maximum time to wait for graceful shutdown
     #[serde(default)]
     #[serde(with = "humantime_serde")]
+    #[schemars(with = "Option<String>")]
     pub graceful_shutdown_timeout: Option<Duration>,
     /// Maximum number of idle connections to keep in upstream connection pool
     pub upstream_keepalive_pool_size: Option<usize>,
     /// Webhook URL for notifications
     pub webhook: Option<String>,
     /// Type of webhook (e.g. "wecom", "dingtalk")
+    #[schemars(extend("examples" = ["normal", "wecom", "dingtalk"]))]
     pub webhook_type: Option<String>,
     /// List of events to send webhook notifications for
     pub webhook_notifications: Option<Vec<String>>,
     /// Log level (debug, info, warn, error)
+    #[schemars(extend("examples" = ["debug", "info", "warn", "error"]))]
     pub log_level: Option<String>,
     /// Size of log buffer before flushing
+    #[schemars(with = "Option<String>")]
     pub log_buffered_size: Option<ByteSize>,
     /// Whether to format logs as JSON
     pub log_format_json: Option<bool>,
@@ -736,11 +915,16 @@ pub struct BasicConf {
     /// How often to check for configuration changes that require restart
     #[serde(default)]
     #[serde(with = "humantime_serde")]
+    #[schemars(with = "Option<String>")]
     pub auto_restart_check_interval: Option<Duration>,
     /// Directory to store cache files
     pub cache_directory: Option<String>,
-    /// Maximum size of cache storage
+    /// Maximum size of memory cache
+    #[schemars(with = "Option<String>")]
     pub cache_max_size: Option<ByteSize>,
+    /// Maximum disk size of file cache, it's unlimited if not set
+    #[schemars(with = "Option<String>")]
+    pub cache_disk_max_size: Option<ByteSize>,
 }
 
 impl BasicConf {
@@ -761,7 +945,7 @@ impl BasicConf {
     }
 }
 
-#[derive(Debug, Default, Deserialize, Clone, Serialize)]
+#[derive(Debug, Default, Deserialize, Clone, Serialize, JsonSchema)]
 pub struct StorageConf {
     pub category: String,
     pub value: String,
@@ -769,6 +953,20 @@ pub struct StorageConf {
     pub remark: Option<String>,
 }
 
+impl StorageConf {
+    /// Returns the value of storage, it's decrypted if the secret is set
+    pub fn get_value(&self) -> Result<String> {
+        if let Some(key) = &self.secret {
+            return pingap_util::aes_decrypt(key, &self.value).map_err(|e| {
+                Error::Invalid {
+                    message: e.to_string(),
+                }
+            });
+        }
+        Ok(self.value.clone())
+    }
+}
+
 #[derive(Deserialize, Debug, Serialize)]
 struct TomlConfig {
     basic: Option<BasicConf>,
@@ -790,12 +988,15 @@ fn format_toml(value: &Value) -> String {
 
 pub type PluginConf = Map<String, Value>;
 
-#[derive(Debug, Default, Clone, Deserialize, Serialize)]
+#[derive(Debug, Default, Clone, Deserialize, Serialize, JsonSchema)]
 pub struct PingapConf {
     pub basic: BasicConf,
     pub upstreams: HashMap<String, UpstreamConf>,
     pub locations: HashMap<String, LocationConf>,
     pub servers: HashMap<String, ServerConf>,
+    #[schemars(
+        with = "HashMap<String, serde_json::Map<String, serde_json::Value>>"
+    )]
     pub plugins: HashMap<String, PluginConf>,
     pub certificates: HashMap<String, CertificateConf>,
     pub storages: HashMap<String, StorageConf>,
@@ -876,20 +1077,25 @@ impl PingapConf {
             toml::to_string_pretty(&m).map_err(|e| Error::Ser { source: e })?;
         Ok((path, value))
     }
+    /// Returns the names of config for each category except basic
+    pub(crate) fn get_category_names(
+        &self,
+    ) -> Vec<(&'static str, Vec<String>)> {
+        vec![
+            (CATEGORY_SERVER, self.servers.keys().cloned().collect()),
+            (CATEGORY_LOCATION, self.locations.keys().cloned().collect()),
+            (CATEGORY_UPSTREAM, self.upstreams.keys().cloned().collect()),
+            (CATEGORY_PLUGIN, self.plugins.keys().cloned().collect()),
+            (
+                CATEGORY_CERTIFICATE,
+                self.certificates.keys().cloned().collect(),
+            ),
+            (CATEGORY_STORAGE, self.storages.keys().cloned().collect()),
+        ]
+    }
     pub fn get_storage_value(&self, name: &str) -> Result<String> {
-        for (key, item) in self.storages.iter() {
-            if key != name {
-                continue;
-            }
-
-            if let Some(key) = &item.secret {
-                return pingap_util::aes_decrypt(key, &item.value).map_err(
-                    |e| Error::Invalid {
-                        message: e.to_string(),
-                    },
-                );
-            }
-            return Ok(item.value.clone());
+        if let Some(item) = self.storages.get(name) {
+            return item.get_value();
         }
         Ok("".to_string())
     }
@@ -897,14 +1103,17 @@ impl PingapConf {
 
 fn convert_include_toml(
     data: &HashMap<String, String>,
+    templates: &HashMap<String, String>,
     replace_includes: bool,
     mut value: Value,
-) -> String {
+    category: &str,
+    name: &str,
+) -> Result<String> {
     let Some(m) = value.as_table_mut() else {
-        return "".to_string();
+        return Ok("".to_string());
     };
     if !replace_includes {
-        return m.to_string();
+        return Ok(m.to_string());
     }
     if let Some(includes) = m.remove("includes") {
         if let Some(includes) = get_include_toml(data, includes) {
@@ -915,7 +1124,8 @@ fn convert_include_toml(
             }
         }
     }
-    m.to_string()
+    expand_template(templates, category, name, m)?;
+    Ok(m.to_string())
 }
 
 fn get_include_toml(
@@ -937,9 +1147,29 @@ fn get_include_toml(
     Some(arr.join("\n"))
 }
 
+/// Parses the toml of config, the references are resolved if storages are set
+fn parse_toml_conf<T: DeserializeOwned>(
+    toml: &str,
+    storages: Option<&HashMap<String, StorageConf>>,
+    category: &str,
+    name: &str,
+) -> Result<T> {
+    let Some(storages) = storages else {
+        return toml::from_str(toml).map_err(|e| Error::De { source: e });
+    };
+    let mut value = Value::Table(
+        toml::from_str(toml).map_err(|e| Error::De { source: e })?,
+    );
+    resolve_references(&mut value, storages).map_err(|e| Error::Invalid {
+        message: format!("{e} ({category}:{name})"),
+    })?;
+    value.try_into().map_err(|e| Error::De { source: e })
+}
+
 fn convert_pingap_config(
     data: &[u8],
     replace_includes: bool,
+    resolve_secrets: bool,
 ) -> Result<PingapConf, Error> {
     let data: TomlConfig = toml::from_str(
         std::string::String::from_utf8_lossy(data)
@@ -953,45 +1183,89 @@ fn convert_pingap_config(
         ..Default::default()
     };
     let mut includes = HashMap::new();
+    let mut templates = HashMap::new();
     for (name, value) in data.storages.unwrap_or_default() {
         let toml = format_toml(&value);
         let storage: StorageConf = toml::from_str(toml.as_str())
             .map_err(|e| Error::De { source: e })?;
-        includes.insert(name.clone(), storage.value.clone());
+        // the encrypted value is only decrypted for running
+        let value = if resolve_secrets {
+            storage.get_value()?
+        } else {
+            storage.value.clone()
+        };
+        if storage.category == TEMPLATE_CATEGORY {
+            templates.insert(name.clone(), value.clone());
+        }
+        includes.insert(name.clone(), value);
         conf.storages.insert(name, storage);
     }
+    let storages = conf.storages.clone();
+    let storages = if resolve_secrets {
+        Some(&storages)
+    } else {
+        None
+    };
 
     for (name, value) in data.upstreams.unwrap_or_default() {
-        let toml = convert_include_toml(&includes, replace_includes, value);
-
-        let upstream: UpstreamConf = toml::from_str(toml.as_str())
-            .map_err(|e| Error::De { source: e })?;
+        let toml = convert_include_toml(
+            &includes,
+            &templates,
+            replace_includes,
+            value,
+            CATEGORY_UPSTREAM,
+            &name,
+        )?;
+        let upstream: UpstreamConf =
+            parse_toml_conf(&toml, storages, CATEGORY_UPSTREAM, &name)?;
         conf.upstreams.insert(name, upstream);
     }
     for (name, value) in data.locations.unwrap_or_default() {
-        let toml = convert_include_toml(&includes, replace_includes, value);
-
-        let location: LocationConf = toml::from_str(toml.as_str())
-            .map_err(|e| Error::De { source: e })?;
+        let toml = convert_include_toml(
+            &includes,
+            &templates,
+            replace_includes,
+            value,
+            CATEGORY_LOCATION,
+            &name,
+        )?;
+        let location: LocationConf =
+            parse_toml_conf(&toml, storages, CATEGORY_LOCATION, &name)?;
         conf.locations.insert(name, location);
     }
     for (name, value) in data.servers.unwrap_or_default() {
-        let toml = convert_include_toml(&includes, replace_includes, value);
-
-        let server: ServerConf = toml::from_str(toml.as_str())
-            .map_err(|e| Error::De { source: e })?;
+        let toml = convert_include_toml(
+            &includes,
+            &templates,
+            replace_includes,
+            value,
+            CATEGORY_SERVER,
+            &name,
+        )?;
+        let server: ServerConf =
+            parse_toml_conf(&toml, storages, CATEGORY_SERVER, &name)?;
         conf.servers.insert(name, server);
     }
-    for (name, value) in data.plugins.unwrap_or_default() {
-        let plugin: PluginConf = toml::from_str(format_toml(&value).as_str())
-            .map_err(|e| Error::De { source: e })?;
+    for (name, mut value) in data.plugins.unwrap_or_default() {
+        if let Some(m) = value.as_table_mut().filter(|_| replace_includes) {
+            expand_template(&templates, CATEGORY_PLUGIN, &name, m)?;
+        }
+        let plugin: PluginConf = parse_toml_conf(
+            &format_toml(&value),
+            storages,
+            CATEGORY_PLUGIN,
+            &name,
+        )?;
         conf.plugins.insert(name, plugin);
     }
 
     for (name, value) in data.certificates.unwrap_or_default() {
-        let certificate: CertificateConf =
-            toml::from_str(format_toml(&value).as_str())
-                .map_err(|e| Error::De { source: e })?;
+        let certificate: CertificateConf = parse_toml_conf(
+            &format_toml(&value),
+            storages,
+            CATEGORY_CERTIFICATE,
+            &name,
+        )?;
         conf.certificates.insert(name, certificate);
     }
 
@@ -1007,10 +1281,24 @@ struct Description {
 
 impl PingapConf {
     pub fn new(data: &[u8], replace_includes: bool) -> Result<Self> {
-        convert_pingap_config(data, replace_includes)
+        convert_pingap_config(data, replace_includes, false)
+    }
+    /// Creates the config for running, the includes are replaced and
+    /// the references(`${secret:name}`, `${env:NAME}`, `${file:/path}`)
+    /// are resolved, so it should never be saved or shown.
+    pub fn new_resolved(data: &[u8]) -> Result<Self> {
+        convert_pingap_config(data, true, true)
     }
     /// Validate the options of pinggap config.
+    /// The includes and templates are expanded first, so the errors of
+    /// expansion are reported and the expanded entries are validated.
     pub fn validate(&self) -> Result<()> {
+        let ping_conf = toml::to_string_pretty(self)
+            .map_err(|e| Error::Ser { source: e })?;
+        convert_pingap_config(ping_conf.as_bytes(), true, false)?
+            .validate_entries()
+    }
+    fn validate_entries(&self) -> Result<()> {
         let mut upstream_names = vec![];
         for (name, upstream) in self.upstreams.iter() {
             upstream.validate(name)?;
@@ -1044,10 +1332,7 @@ impl PingapConf {
         for (_, certificate) in self.certificates.iter() {
             certificate.validate()?;
         }
-        let ping_conf = toml::to_string_pretty(self)
-            .map_err(|e| Error::Ser { source: e })?;
-        convert_pingap_config(ping_conf.as_bytes(), true)?;
-        Ok(())
+        validate_graph(self)
     }
     /// Generate the content hash of config.
     pub fn hash(&self) -> Result<String> {
@@ -1292,7 +1577,8 @@ mod tests {
         CertificateConf,
     };
     use super::{
-        LocationConf, PingapConf, PluginCategory, ServerConf, UpstreamConf,
+        LocationConf, LocationPath, PingapConf, PluginCategory, ServerConf,
+        UpstreamConf,
     };
     use pingap_core::PluginStep;
     use pingap_util::base64_encode;
@@ -1361,6 +1647,84 @@ EHjKf0Dweb4ppL4ddgeAKU5V0qn76K2fFaE=
         assert_eq!("B7B8046B", get_config_hash());
     }
 
+    #[test]
+    fn test_config_template() {
+        let toml = r#"
+[storages.apiLocation]
+category = "template"
+value = '''
+path = "{{path}}"
+upstream = "{{upstream}}"
+plugins = ["{{name}}Limit"]
+'''
+
+[storages.limitPlugin]
+category = "template"
+value = '''
+category = "limit"
+type = "ip"
+max = {{max}}
+'''
+
+[upstreams.users]
+addrs = ["127.0.0.1:5000"]
+
+[locations.users]
+template = "apiLocation"
+params = { path = "/users", upstream = "users" }
+
+[locations.orders]
+template = "apiLocation"
+params = { path = "/orders", upstream = "users" }
+weight = 1024
+
+[plugins.usersLimit]
+template = "limitPlugin"
+params = { max = "10" }
+
+[plugins.ordersLimit]
+template = "limitPlugin"
+params = { max = "20" }
+"#;
+        let conf = PingapConf::new(toml.as_bytes(), true).unwrap();
+        let location = &conf.locations["orders"];
+        assert_eq!(Some("/orders".to_string()), location.path);
+        assert_eq!(Some("users".to_string()), location.upstream);
+        assert_eq!(Some(vec!["ordersLimit".to_string()]), location.plugins);
+        assert_eq!(Some(1024), location.weight);
+        assert_eq!(None, location.template);
+        assert_eq!(Some(20), conf.plugins["ordersLimit"]["max"].as_integer());
+        assert_eq!(true, conf.validate().is_ok());
+
+        // the template is kept if includes are not replaced
+        let mut conf = PingapConf::new(toml.as_bytes(), false).unwrap();
+        assert_eq!(
+            Some("apiLocation".to_string()),
+            conf.locations["users"].template
+        );
+        assert_eq!(None, conf.locations["users"].path);
+        assert_eq!(true, conf.validate().is_ok());
+
+        // the errors of expansion are reported by validate
+        conf.locations.get_mut("users").unwrap().params = None;
+        assert_eq!(
+            "Invalid error template(apiLocation) param(path) is missing(location:users)",
+            conf.validate().unwrap_err().to_string()
+        );
+        // the expanded location is validated
+        conf.locations.get_mut("users").unwrap().params = Some(
+            [
+                ("path".to_string(), "/users".to_string()),
+                ("upstream".to_string(), "nope".to_string()),
+            ]
+            .into(),
+        );
+        assert_eq!(
+            "Invalid error upstream(nope) is not found(location:users)",
+            conf.validate().unwrap_err().to_string()
+        );
+    }
+
     #[test]
     fn test_plugin_category_serde() {
         #[derive(Deserialize, Serialize)]
@@ -1494,6 +1858,35 @@ EHjKf0Dweb4ppL4ddgeAKU5V0qn76K2fFaE=
         assert_eq!(0, conf.get_weight());
     }
 
+    #[test]
+    fn test_location_path() {
+        assert_eq!(LocationPath::All, LocationPath::new(" "));
+        assert_eq!(
+            LocationPath::Regex("/api".to_string()),
+            LocationPath::new("~ /api")
+        );
+        assert_eq!(
+            LocationPath::Equal("/api".to_string()),
+            LocationPath::new("=/api")
+        );
+        assert_eq!(
+            LocationPath::Prefix("/api".to_string()),
+            LocationPath::new("/api")
+        );
+
+        let root = LocationPath::new("/");
+        assert_eq!(true, root.is_match("/users"));
+        assert_eq!(true, root.covers(&LocationPath::All));
+        assert_eq!(true, LocationPath::All.covers(&root));
+        assert_eq!(false, root.covers(&LocationPath::new("~/users")));
+
+        let users = LocationPath::new("/users");
+        assert_eq!(true, users.covers(&LocationPath::new("=/users/1")));
+        assert_eq!(true, users.covers(&LocationPath::new("/users/1")));
+        assert_eq!(false, users.covers(&root));
+        assert_eq!(false, LocationPath::new("=/users").covers(&users));
+    }
+
     #[test]
     fn test_server_conf() {
         let mut conf = ServerConf::default();
@@ -1518,6 +1911,35 @@ EHjKf0Dweb4ppL4ddgeAKU5V0qn76K2fFaE=
         conf.locations = Some(vec!["lo".to_string()]);
         let result = conf.validate("test", &location_names);
         assert_eq!(true, result.is_ok());
+
+        conf.access_log_sample_ratio = Some(1.5);
+        let result = conf.validate("test", &location_names);
+        assert_eq!(
+            "Invalid error access log sample ratio(1.5) should be between 0 and 1(server:test)",
+            result.expect_err("").to_string()
+        );
+
+        conf.access_log_sample_ratio = None;
+        conf.server_timing_metrics = Some(vec!["tls".to_string()]);
+        let result = conf.validate("test", &location_names);
+        assert_eq!(
+            "Invalid error server timing metric(tls) is invalid(server:test)",
+            result.expect_err("").to_string()
+        );
+
+        conf.server_timing_metrics = None;
+        conf.server_timing_trusted_ips =
+            Some(vec!["127.0.0.1".to_string(), "192.168.0.0/16".to_string()]);
+        let result = conf.validate("test", &location_names);
+        assert_eq!(true, result.is_ok());
+
+        conf.server_timing_trusted_ips =
+            Some(vec!["192.168.0.0/33".to_string()]);
+        let result = conf.validate("test", &location_names);
+        assert_eq!(
+            "Invalid error server timing trusted ip(192.168.0.0/33) is invalid(server:test)",
+            result.expect_err("").to_string()
+        );
     }
 
     #[test]
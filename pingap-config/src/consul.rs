@@ -0,0 +1,602 @@
+// Copyright 2024-2025 Tree xie.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use super::history::get_config_files;
+use super::{ConfigStorage, Error, LoadConfigOptions, Result};
+use super::{Observer, PingapConf};
+use async_trait::async_trait;
+use humantime::parse_duration;
+use reqwest::{Client, Method, RequestBuilder, Response, StatusCode};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use substring::Substring;
+
+pub const CONSUL_PROTOCOL: &str = "consul://";
+/// Key of the history under the base path, it's skipped when loading config
+const HISTORY_KEY: &str = "/history.json";
+/// Header of consul acl token
+const CONSUL_TOKEN_HEADER: &str = "X-Consul-Token";
+/// Header of the index for blocking query
+const CONSUL_INDEX_HEADER: &str = "X-Consul-Index";
+/// Max count of operations in one transaction of consul
+const MAX_TXN_OPS: usize = 64;
+
+/// Key-value pair of consul kv api
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct ConsulKv {
+    key: String,
+    // base64 encoded value, it's null for folder
+    value: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "PascalCase")]
+struct ConsulTxnKv {
+    verb: String,
+    key: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    value: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct ConsulTxnOp {
+    #[serde(rename = "KV")]
+    kv: ConsulTxnKv,
+}
+
+fn new_consul_error(message: String) -> Error {
+    Error::Consul { message }
+}
+
+#[derive(Clone)]
+struct ConsulClient {
+    client: Client,
+    // Base url of consul agent, e.g. http://127.0.0.1:8500
+    base_url: String,
+    // Optional acl token
+    token: Option<String>,
+}
+
+impl ConsulClient {
+    fn request(&self, method: Method, path: &str) -> RequestBuilder {
+        let mut req = self
+            .client
+            .request(method, format!("{}/v1/{path}", self.base_url));
+        if let Some(token) = &self.token {
+            req = req.header(CONSUL_TOKEN_HEADER, token);
+        }
+        req
+    }
+    /// Returns the path of kv api, each segment of the key is encoded,
+    /// because the key contains the name of config, e.g. `servers/a b.toml`
+    fn kv_path(key: &str) -> String {
+        let key: Vec<_> = key.split('/').map(urlencoding::encode).collect();
+        format!("kv/{}", key.join("/"))
+    }
+    async fn send(&self, req: RequestBuilder) -> Result<Response> {
+        let resp = req
+            .send()
+            .await
+            .map_err(|e| new_consul_error(e.to_string()))?;
+        let status = resp.status();
+        // not found means the key does not exist
+        if status.is_success() || status == StatusCode::NOT_FOUND {
+            return Ok(resp);
+        }
+        let body = resp.text().await.unwrap_or_default();
+        Err(new_consul_error(format!("status: {status}, {body}")))
+    }
+    /// List all key-value pairs under the prefix, and return the consul index
+    async fn list(
+        &self,
+        prefix: &str,
+        index: Option<(u64, Duration)>,
+    ) -> Result<(Vec<ConsulKv>, u64)> {
+        let mut req = self
+            .request(Method::GET, &Self::kv_path(prefix))
+            .query(&[("recurse", "true")]);
+        if let Some((index, wait)) = index {
+            req = req
+                .query(&[
+                    ("index", index.to_string()),
+                    ("wait", format!("{}s", wait.as_secs())),
+                ])
+                // the blocking query should not be timeout
+                .timeout(wait + Duration::from_secs(30));
+        }
+        let resp = self.send(req).await?;
+        let index = resp
+            .headers()
+            .get(CONSUL_INDEX_HEADER)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.parse::<u64>().ok())
+            .unwrap_or_default();
+        if resp.status() == StatusCode::NOT_FOUND {
+            return Ok((vec![], index));
+        }
+        let kvs = resp
+            .json::<Vec<ConsulKv>>()
+            .await
+            .map_err(|e| new_consul_error(e.to_string()))?;
+        Ok((kvs, index))
+    }
+    async fn get(&self, key: &str) -> Result<Vec<u8>> {
+        let resp = self
+            .send(
+                self.request(Method::GET, &Self::kv_path(key))
+                    .query(&[("raw", "true")]),
+            )
+            .await?;
+        if resp.status() == StatusCode::NOT_FOUND {
+            return Ok(vec![]);
+        }
+        let buf = resp
+            .bytes()
+            .await
+            .map_err(|e| new_consul_error(e.to_string()))?;
+        Ok(buf.into())
+    }
+    async fn put(&self, key: &str, data: Vec<u8>) -> Result<()> {
+        self.send(self.request(Method::PUT, &Self::kv_path(key)).body(data))
+            .await?;
+        Ok(())
+    }
+    async fn delete(&self, key: &str) -> Result<()> {
+        self.send(self.request(Method::DELETE, &Self::kv_path(key)))
+            .await?;
+        Ok(())
+    }
+    async fn txn(&self, ops: &[ConsulTxnOp]) -> Result<()> {
+        self.send(self.request(Method::PUT, "txn").json(ops))
+            .await?;
+        Ok(())
+    }
+}
+
+pub struct ConsulStorage {
+    // Base path for all config entries in consul kv, without leading slash
+    path: String,
+    client: ConsulClient,
+    // Max wait time of blocking query
+    wait: Duration,
+    // Whether to separate config entries by category/name
+    separation: bool,
+}
+
+impl ConsulStorage {
+    /// Create a new consul storage for config.
+    /// Connection url format: consul://host:port/pingap?token=**&timeout=10s&wait=5m&tls
+    pub fn new(value: &str) -> Result<Self> {
+        let value = value.substring(CONSUL_PROTOCOL.len(), value.len());
+        let (value, query) = value.split_once('?').unwrap_or((value, ""));
+        let (host, path) = value.split_once('/').unwrap_or((value, ""));
+        let path = path.trim_matches('/').to_string();
+        if host.is_empty() || path.is_empty() {
+            return Err(Error::Invalid {
+                message: "consul host and path should not be empty".to_string(),
+            });
+        }
+
+        let mut token = None;
+        let mut timeout = Duration::from_secs(10);
+        let mut wait = Duration::from_secs(5 * 60);
+        let mut scheme = "http";
+        let mut separation = false;
+        for (key, value) in pingap_core::convert_query_map(query) {
+            match key.as_str() {
+                "token" => token = Some(value),
+                "timeout" => {
+                    if let Ok(d) = parse_duration(&value) {
+                        timeout = d;
+                    }
+                },
+                "wait" => {
+                    if let Ok(d) = parse_duration(&value) {
+                        wait = d;
+                    }
+                },
+                "tls" => scheme = "https",
+                "separation" => separation = true,
+                _ => {},
+            }
+        }
+        let client = Client::builder()
+            .timeout(timeout)
+            .build()
+            .map_err(|e| new_consul_error(e.to_string()))?;
+        Ok(Self {
+            path,
+            client: ConsulClient {
+                client,
+                base_url: format!("{scheme}://{host}"),
+                token,
+            },
+            wait,
+            separation,
+        })
+    }
+    fn get_key(&self, key: &str) -> String {
+        pingap_util::path_join(&self.path, key)
+    }
+    /// The prefix of config keys, the slash avoids matching other paths
+    fn get_prefix(&self) -> String {
+        format!("{}/", self.path)
+    }
+}
+
+/// Watcher of consul kv based on blocking query,
+/// it returns when the index of prefix is changed or the wait time is reached.
+pub struct ConsulWatcher {
+    client: ConsulClient,
+    prefix: String,
+    wait: Duration,
+    index: u64,
+}
+
+impl ConsulWatcher {
+    pub async fn watch(&mut self) -> Result<bool> {
+        let (_, index) = self
+            .client
+            .list(&self.prefix, Some((self.index, self.wait)))
+            .await?;
+        // the index may go backwards, e.g. the consul data is reset
+        let updated = index != self.index;
+        self.index = if index < self.index { 0 } else { index };
+        Ok(updated)
+    }
+}
+
+#[async_trait]
+impl ConfigStorage for ConsulStorage {
+    /// Load config from consul by fetching all keys under the base path
+    async fn load_config(&self, opts: LoadConfigOptions) -> Result<PingapConf> {
+        let (kvs, _) = self.client.list(&self.get_prefix(), None).await?;
+        let history_key = self.get_key(HISTORY_KEY);
+        let mut buffer = vec![];
+        for item in kvs {
+            if item.key == history_key {
+                continue;
+            }
+            let Some(value) = item.value else {
+                continue;
+            };
+            let value = pingap_util::base64_decode(value)
+                .map_err(|e| Error::Base64Decode { source: e })?;
+            buffer.extend(value);
+            buffer.push(0x0a);
+        }
+        if opts.resolve_secret {
+            return PingapConf::new_resolved(buffer.as_slice());
+        }
+        PingapConf::new(buffer.as_slice(), opts.replace_include)
+    }
+    /// Save config to consul, optionally separating by category/name
+    async fn save_config(
+        &self,
+        conf: &PingapConf,
+        category: &str,
+        name: Option<&str>,
+    ) -> Result<()> {
+        conf.validate()?;
+        let (path, toml_value) = if self.separation && name.is_some() {
+            conf.get_toml(category, name)?
+        } else {
+            conf.get_toml(category, None)?
+        };
+        let key = self.get_key(&path);
+        if toml_value.is_empty() {
+            self.client.delete(&key).await
+        } else {
+            self.client.put(&key, toml_value.into_bytes()).await
+        }
+    }
+    /// Indicates that this storage supports watching for changes
+    fn support_observer(&self) -> bool {
+        true
+    }
+    /// Sets up a blocking query watcher on the config path
+    async fn observe(&self) -> Result<Observer> {
+        let prefix = self.get_prefix();
+        // get the current index, so the first watch is blocked
+        let (_, index) = self.client.list(&prefix, None).await?;
+        Ok(Observer {
+            consul_watcher: Some(ConsulWatcher {
+                client: self.client.clone(),
+                prefix,
+                wait: self.wait,
+                index,
+            }),
+            ..Default::default()
+        })
+    }
+    /// Replace the whole config by transactions, the keys which are not
+    /// in the config are deleted. Consul limits the operations of one
+    /// transaction, so the operations are split into several transactions
+    /// and the deletions are executed last, the config may be partially
+    /// updated if one of the transactions fails.
+    async fn replace_config(&self, conf: &PingapConf) -> Result<()> {
+        conf.validate()?;
+        let files = get_config_files(conf, self.separation)?;
+        let keys: Vec<String> =
+            files.iter().map(|(path, _)| self.get_key(path)).collect();
+        let history_key = self.get_key(HISTORY_KEY);
+        let (kvs, _) = self.client.list(&self.get_prefix(), None).await?;
+        let mut delete_ops = vec![];
+        for item in kvs {
+            if item.key == history_key || keys.contains(&item.key) {
+                continue;
+            }
+            delete_ops.push(ConsulTxnOp {
+                kv: ConsulTxnKv {
+                    verb: "delete".to_string(),
+                    key: item.key,
+                    value: None,
+                },
+            });
+        }
+        let mut ops = vec![];
+        for (key, (_, value)) in keys.into_iter().zip(files) {
+            ops.push(ConsulTxnOp {
+                kv: ConsulTxnKv {
+                    verb: "set".to_string(),
+                    key,
+                    value: Some(pingap_util::base64_encode(value)),
+                },
+            });
+        }
+        ops.extend(delete_ops);
+        for chunk in ops.chunks(MAX_TXN_OPS) {
+            self.client.txn(chunk).await?;
+        }
+        Ok(())
+    }
+    async fn load_history(&self) -> Result<Vec<u8>> {
+        self.load(HISTORY_KEY).await
+    }
+    async fn save_history(&self, data: &[u8]) -> Result<()> {
+        self.save(HISTORY_KEY, data).await
+    }
+    /// Save key-value data under the base path
+    async fn save(&self, key: &str, data: &[u8]) -> Result<()> {
+        self.client.put(&self.get_key(key), data.to_vec()).await
+    }
+    /// Load key-value data from under the base path
+    async fn load(&self, key: &str) -> Result<Vec<u8>> {
+        self.client.get(&self.get_key(key)).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ServerConf, CATEGORY_SERVER};
+    use pretty_assertions::assert_eq;
+    use std::collections::BTreeMap;
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+    use std::sync::{Arc, Mutex};
+
+    /// Starts a http stand-in of consul kv api, it only supports
+    /// the apis used by consul storage.
+    fn start_consul_server() -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let store = Arc::new(Mutex::new(BTreeMap::<String, Vec<u8>>::new()));
+        let index = Arc::new(Mutex::new(1_u64));
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut request_line = String::new();
+                reader.read_line(&mut request_line).unwrap();
+                let mut content_length = 0;
+                let mut token = String::new();
+                loop {
+                    let mut line = String::new();
+                    reader.read_line(&mut line).unwrap();
+                    let line = line.trim();
+                    if line.is_empty() {
+                        break;
+                    }
+                    let (name, value) = line.split_once(':').unwrap();
+                    match name.to_lowercase().as_str() {
+                        "content-length" => {
+                            content_length = value.trim().parse().unwrap()
+                        },
+                        "x-consul-token" => token = value.trim().to_string(),
+                        _ => {},
+                    }
+                }
+                let mut body = vec![0; content_length];
+                reader.read_exact(&mut body).unwrap();
+
+                let arr: Vec<&str> = request_line.split(' ').collect();
+                let (path, query) =
+                    arr[1].split_once('?').unwrap_or((arr[1], ""));
+                let mut store = store.lock().unwrap();
+                let mut index = index.lock().unwrap();
+                let (status, resp) = if token != "pingap" {
+                    (403, b"ACL not found".to_vec())
+                } else if let Some(key) = path.strip_prefix("/v1/kv/") {
+                    let key = urlencoding::decode(key).unwrap().to_string();
+                    let key = key.as_str();
+                    match arr[0] {
+                        "GET" if query.contains("recurse") => {
+                            let kvs: Vec<String> = store
+                                .iter()
+                                .filter(|(k, _)| k.starts_with(key))
+                                .map(|(k, v)| {
+                                    format!(
+                                        r#"{{"Key":"{k}","Value":"{}"}}"#,
+                                        pingap_util::base64_encode(v)
+                                    )
+                                })
+                                .collect();
+                            if kvs.is_empty() {
+                                (404, vec![])
+                            } else {
+                                (200, format!("[{}]", kvs.join(",")).into())
+                            }
+                        },
+                        "GET" => match store.get(key) {
+                            Some(value) => (200, value.clone()),
+                            None => (404, vec![]),
+                        },
+                        "PUT" => {
+                            store.insert(key.to_string(), body);
+                            *index += 1;
+                            (200, b"true".to_vec())
+                        },
+                        _ => {
+                            store.remove(key);
+                            *index += 1;
+                            (200, b"true".to_vec())
+                        },
+                    }
+                } else {
+                    let ops: Vec<serde_json::Value> =
+                        serde_json::from_slice(&body).unwrap();
+                    // the limit of operations of consul transaction
+                    assert_eq!(true, ops.len() <= 64);
+                    for op in ops {
+                        let key = op["KV"]["Key"].as_str().unwrap().to_string();
+                        if op["KV"]["Verb"] == "set" {
+                            let value = pingap_util::base64_decode(
+                                op["KV"]["Value"].as_str().unwrap(),
+                            )
+                            .unwrap();
+                            store.insert(key, value);
+                        } else {
+                            store.remove(&key);
+                        }
+                    }
+                    *index += 1;
+                    (200, b"{}".to_vec())
+                };
+                let header = format!(
+                    "HTTP/1.1 {status} OK\r\ncontent-length: {}\r\nx-consul-index: {index}\r\nconnection: close\r\n\r\n",
+                    resp.len()
+                );
+                stream.write_all(header.as_bytes()).unwrap();
+                stream.write_all(&resp).unwrap();
+            }
+        });
+        addr.to_string()
+    }
+
+    #[tokio::test]
+    async fn test_consul_storage() {
+        let addr = start_consul_server();
+        let storage =
+            ConsulStorage::new(&format!("consul://{addr}/pingap?separation"))
+                .unwrap();
+        assert_eq!(
+            true,
+            storage
+                .load_config(LoadConfigOptions::default())
+                .await
+                .unwrap_err()
+                .to_string()
+                .contains("ACL not found")
+        );
+
+        let storage = ConsulStorage::new(&format!(
+            "consul://{addr}/pingap?separation&token=pingap&wait=1s"
+        ))
+        .unwrap();
+        let conf = storage
+            .load_config(LoadConfigOptions::default())
+            .await
+            .unwrap();
+        assert_eq!(true, conf.servers.is_empty());
+        let mut observer = storage.observe().await.unwrap();
+
+        let mut conf = PingapConf::default();
+        for addr in ["127.0.0.1:6188", "127.0.0.1:6189"] {
+            conf.servers.insert(
+                addr.to_string(),
+                ServerConf {
+                    addr: addr.to_string(),
+                    ..Default::default()
+                },
+            );
+            storage
+                .save_config(&conf, CATEGORY_SERVER, Some(addr))
+                .await
+                .unwrap();
+        }
+        assert_eq!(true, observer.watch().await.unwrap());
+        assert_eq!(
+            b"[servers.\"127.0.0.1:6188\"]\naddr = \"127.0.0.1:6188\"\n"
+                .to_vec(),
+            storage.load("/servers/127.0.0.1:6188.toml").await.unwrap()
+        );
+
+        storage.save_history(b"[]").await.unwrap();
+        let result = storage
+            .load_config(LoadConfigOptions::default())
+            .await
+            .unwrap();
+        assert_eq!(2, result.servers.len());
+
+        conf.servers.remove("127.0.0.1:6189");
+        storage.replace_config(&conf).await.unwrap();
+        let result = storage
+            .load_config(LoadConfigOptions::default())
+            .await
+            .unwrap();
+        assert_eq!(
+            vec!["127.0.0.1:6188"],
+            result.servers.keys().collect::<Vec<_>>()
+        );
+        assert_eq!(b"[]".to_vec(), storage.load_history().await.unwrap());
+
+        // the name of config is encoded in the path of kv api
+        let name = "server a#1?";
+        conf.servers.insert(
+            name.to_string(),
+            ServerConf {
+                addr: "127.0.0.1:6190".to_string(),
+                ..Default::default()
+            },
+        );
+        storage
+            .save_config(&conf, CATEGORY_SERVER, Some(name))
+            .await
+            .unwrap();
+        let data = storage.load("/servers/server a#1?.toml").await.unwrap();
+        assert_eq!(true, !data.is_empty());
+
+        // the operations are more than the limit of one transaction
+        conf.servers.clear();
+        for i in 0..100 {
+            let name = format!("server-{i}");
+            conf.servers.insert(
+                name.clone(),
+                ServerConf {
+                    addr: format!("127.0.0.1:{}", 7000 + i),
+                    ..Default::default()
+                },
+            );
+        }
+        storage.replace_config(&conf).await.unwrap();
+        let result = storage
+            .load_config(LoadConfigOptions::default())
+            .await
+            .unwrap();
+        assert_eq!(100, result.servers.len());
+        assert_eq!(false, result.servers.contains_key(name));
+    }
+}
@@ -12,10 +12,13 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use super::history::get_config_files;
 use super::{ConfigStorage, Error, LoadConfigOptions, Result};
 use super::{Observer, PingapConf};
 use async_trait::async_trait;
-use etcd_client::{Client, ConnectOptions, GetOptions, WatchOptions};
+use etcd_client::{
+    Client, ConnectOptions, GetOptions, Txn, TxnOp, WatchOptions,
+};
 use humantime::parse_duration;
 use substring::Substring;
 
@@ -31,6 +34,8 @@ pub struct EtcdStorage {
 }
 
 pub const ETCD_PROTOCOL: &str = "etcd://";
+/// Key of the history under the base path, it's skipped when loading config
+const HISTORY_KEY: &str = "/history.json";
 
 impl EtcdStorage {
     /// Create a new etcd storage for config.
@@ -88,6 +93,9 @@ impl EtcdStorage {
             separation,
         })
     }
+    fn get_history_key(&self) -> String {
+        pingap_util::path_join(&self.path, HISTORY_KEY)
+    }
     /// Connect to etcd server.
     async fn connect(&self) -> Result<Client> {
         Client::connect(&self.addrs, Some(self.options.clone()))
@@ -104,6 +112,7 @@ impl ConfigStorage for EtcdStorage {
     async fn load_config(&self, opts: LoadConfigOptions) -> Result<PingapConf> {
         let mut c = self.connect().await?;
         let replace_include = opts.replace_include;
+        let resolve_secret = opts.resolve_secret;
         let mut opts = GetOptions::new();
         opts = opts.with_prefix();
         let arr = c
@@ -113,11 +122,18 @@ impl ConfigStorage for EtcdStorage {
                 source: Box::new(e),
             })?
             .take_kvs();
+        let history_key = self.get_history_key();
         let mut buffer = vec![];
         for item in arr {
+            if item.key() == history_key.as_bytes() {
+                continue;
+            }
             buffer.extend(item.value());
             buffer.push(0x0a);
         }
+        if resolve_secret {
+            return PingapConf::new_resolved(buffer.as_slice());
+        }
         PingapConf::new(buffer.as_slice(), replace_include)
     }
     /// Save config to etcd, optionally separating by category/name
@@ -172,8 +188,53 @@ impl ConfigStorage for EtcdStorage {
             })?;
         Ok(Observer {
             etcd_watch_stream: Some(stream),
+            ..Default::default()
         })
     }
+    /// Replace the whole config in one transaction,
+    /// the keys which are not in the config are deleted.
+    async fn replace_config(&self, conf: &PingapConf) -> Result<()> {
+        conf.validate()?;
+        let mut c = self.connect().await?;
+        let files = get_config_files(conf, self.separation)?;
+        let keys: Vec<String> = files
+            .iter()
+            .map(|(path, _)| pingap_util::path_join(&self.path, path))
+            .collect();
+        let history_key = self.get_history_key();
+        let mut ops = vec![];
+        for item in c
+            .get(self.path.as_bytes(), Some(GetOptions::new().with_prefix()))
+            .await
+            .map_err(|e| Error::Etcd {
+                source: Box::new(e),
+            })?
+            .kvs()
+        {
+            let key = item.key();
+            if key == history_key.as_bytes()
+                || keys.iter().any(|k| k.as_bytes() == key)
+            {
+                continue;
+            }
+            ops.push(TxnOp::delete(key, None));
+        }
+        for (key, (_, value)) in keys.into_iter().zip(files) {
+            ops.push(TxnOp::put(key, value, None));
+        }
+        c.txn(Txn::new().and_then(ops))
+            .await
+            .map_err(|e| Error::Etcd {
+                source: Box::new(e),
+            })?;
+        Ok(())
+    }
+    async fn load_history(&self) -> Result<Vec<u8>> {
+        self.load(HISTORY_KEY).await
+    }
+    async fn save_history(&self, data: &[u8]) -> Result<()> {
+        self.save(HISTORY_KEY, data).await
+    }
     /// Save key-value data under the base path
     async fn save(&self, key: &str, data: &[u8]) -> Result<()> {
         let key = pingap_util::path_join(&self.path, key);
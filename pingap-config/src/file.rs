@@ -12,15 +12,58 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use super::history::get_config_files;
 use super::{
-    read_all_toml_files, ConfigStorage, Error, LoadConfigOptions, PingapConf,
-    Result,
+    convert_from_toml, convert_to_toml, read_all_config_files, ConfigFormat,
+    ConfigStorage, Error, LoadConfigOptions, PingapConf, Result,
+    CONFIG_FILE_EXTENSIONS,
 };
 use async_trait::async_trait;
 use futures_util::TryFutureExt;
+use glob::glob;
 use std::path::Path;
 use tokio::fs;
 
+/// Name of the history file in config directory
+pub(crate) const HISTORY_FILE: &str = "history.json";
+
+/// Writes the file atomically, the data is written to a temp file
+/// and then renamed to the target file.
+async fn write_file_atomic(file: &str, data: &[u8]) -> Result<()> {
+    let path = Path::new(file);
+    if let Some(p) = path.parent() {
+        fs::create_dir_all(p).await.map_err(|e| Error::Io {
+            source: e,
+            file: file.to_string(),
+        })?;
+    }
+    let tmp_file = format!("{file}.tmp");
+    fs::write(&tmp_file, data).await.map_err(|e| Error::Io {
+        source: e,
+        file: tmp_file.clone(),
+    })?;
+    fs::rename(&tmp_file, path).await.map_err(|e| Error::Io {
+        source: e,
+        file: file.to_string(),
+    })
+}
+
+/// Returns the file and format of the toml path(e.g. /servers.toml) in
+/// the config directory, the existing file of the same name is used,
+/// so the format of it is kept, otherwise the toml file is used.
+fn get_dir_config_file(dir: &str, path: &str) -> (String, ConfigFormat) {
+    let file = pingap_util::path_join(dir, path);
+    let name = file.trim_end_matches(".toml");
+    for extension in CONFIG_FILE_EXTENSIONS {
+        let current = format!("{name}.{extension}");
+        if Path::new(&current).is_file() {
+            let format = ConfigFormat::from_path(&current).unwrap_or_default();
+            return (current, format);
+        }
+    }
+    (file, ConfigFormat::Toml)
+}
+
 pub struct FileStorage {
     // Path to the configuration file or directory
     path: String,
@@ -50,6 +93,27 @@ impl FileStorage {
             separation,
         })
     }
+    /// Whether the config is stored in a single toml, yaml or json file
+    fn is_single_file(&self) -> bool {
+        ConfigFormat::from_path(&self.path).is_some()
+            || Path::new(&self.path).is_file()
+    }
+    /// Format of the single config file, it's toml if the extension
+    /// is not supported.
+    fn get_file_format(&self) -> ConfigFormat {
+        ConfigFormat::from_path(&self.path).unwrap_or_default()
+    }
+    /// The history is stored beside the single config file,
+    /// or in the config directory.
+    fn get_history_file(&self) -> String {
+        if self.is_single_file() {
+            return Path::new(&self.path)
+                .with_extension(HISTORY_FILE)
+                .to_string_lossy()
+                .to_string();
+        }
+        pingap_util::path_join(&self.path, HISTORY_FILE)
+    }
 }
 
 #[async_trait]
@@ -62,8 +126,8 @@ impl ConfigStorage for FileStorage {
         if opts.admin && !dir.exists() {
             return Ok(PingapConf::default());
         }
-        // Create directory if needed for non-TOML paths
-        if !filepath.ends_with(".toml") && !dir.exists() {
+        // Create directory if needed for non config file paths
+        if !self.is_single_file() && !dir.exists() {
             fs::create_dir_all(&filepath)
                 .map_err(|e| Error::Io {
                     source: e,
@@ -73,17 +137,23 @@ impl ConfigStorage for FileStorage {
         }
 
         let mut data = vec![];
-        // Handle directory of TOML files
+        // Handle directory of config files
         if dir.is_dir() {
-            let mut result = read_all_toml_files(&filepath).await?;
+            let mut result = read_all_config_files(&filepath).await?;
             data.append(&mut result);
         } else {
-            // Handle single TOML file
-            let mut buf = fs::read(&filepath).await.map_err(|e| Error::Io {
+            // Handle single config file
+            let buf = fs::read(&filepath).await.map_err(|e| Error::Io {
                 source: e,
                 file: filepath,
             })?;
-            data.append(&mut buf);
+            data.append(
+                &mut convert_to_toml(&buf, self.get_file_format())?
+                    .into_bytes(),
+            );
+        }
+        if opts.resolve_secret {
+            return PingapConf::new_resolved(data.as_slice());
         }
         PingapConf::new(data.as_slice(), opts.replace_include)
     }
@@ -97,7 +167,7 @@ impl ConfigStorage for FileStorage {
         let filepath = self.path.clone();
         conf.validate()?;
         let path = Path::new(&filepath);
-        if !path.exists() && path.extension().unwrap_or_default() == "toml" {
+        if !path.exists() && ConfigFormat::from_path(&filepath).is_some() {
             fs::File::create(&path).await.map_err(|e| Error::Io {
                 source: e,
                 file: filepath.clone(),
@@ -108,7 +178,7 @@ impl ConfigStorage for FileStorage {
             // For single file storage:
             // 1. Convert config to TOML
             // 2. Remove empty sections
-            // 3. Write back to file
+            // 3. Convert to the format of file and write back
             let ping_conf = toml::to_string_pretty(&conf)
                 .map_err(|e| Error::Ser { source: e })?;
             let mut values: toml::Table = toml::from_str(&ping_conf)
@@ -127,6 +197,8 @@ impl ConfigStorage for FileStorage {
             }
             let ping_conf = toml::to_string_pretty(&values)
                 .map_err(|e| Error::Ser { source: e })?;
+            let ping_conf =
+                convert_from_toml(&ping_conf, self.get_file_format())?;
             return fs::write(path, ping_conf).await.map_err(|e| Error::Io {
                 source: e,
                 file: filepath,
@@ -141,7 +213,7 @@ impl ConfigStorage for FileStorage {
             conf.get_toml(category, None)?
         };
 
-        let filepath = pingap_util::path_join(&filepath, &path);
+        let (filepath, format) = get_dir_config_file(&filepath, &path);
         let target_file = Path::new(&filepath);
         if let Some(p) = Path::new(&target_file).parent() {
             fs::create_dir_all(p).await.map_err(|e| Error::Io {
@@ -157,16 +229,81 @@ impl ConfigStorage for FileStorage {
                 })?;
             }
         } else {
-            fs::write(&filepath, toml_value)
-                .await
-                .map_err(|e| Error::Io {
-                    source: e,
-                    file: filepath,
-                })?;
+            let value = convert_from_toml(&toml_value, format)?;
+            fs::write(&filepath, value).await.map_err(|e| Error::Io {
+                source: e,
+                file: filepath,
+            })?;
         }
 
         Ok(())
     }
+    /// Replace the whole config, the files of config are written atomically
+    /// and the files which are not in the config are removed.
+    ///
+    /// For directory storage, only each file is replaced atomically, the
+    /// directory is not swapped because it may be a mount point and contains
+    /// other files(e.g. history). So a reload during the replacement may see
+    /// new and old files together, the removed files are always the last step.
+    async fn replace_config(&self, conf: &PingapConf) -> Result<()> {
+        conf.validate()?;
+        if self.is_single_file() {
+            let data = toml::to_string_pretty(conf)
+                .map_err(|e| Error::Ser { source: e })?;
+            let data =
+                pingap_util::toml_omit_empty_value(&data).map_err(|e| {
+                    Error::Invalid {
+                        message: e.to_string(),
+                    }
+                })?;
+            let data = convert_from_toml(&data, self.get_file_format())?;
+            return write_file_atomic(&self.path, data.as_bytes()).await;
+        }
+        let mut files = vec![];
+        for (path, value) in get_config_files(conf, self.separation)? {
+            let (file, format) = get_dir_config_file(&self.path, &path);
+            files.push((file, convert_from_toml(&value, format)?));
+        }
+        // write the new files before removing the others,
+        // so the config is never empty
+        for (file, value) in files.iter() {
+            write_file_atomic(file, value.as_bytes()).await?;
+        }
+        let pattern = format!("{}/**/*", self.path);
+        for entry in glob(&pattern).map_err(|e| Error::Pattern {
+            source: e,
+            path: pattern.clone(),
+        })? {
+            let file = entry.map_err(|e| Error::Glob { source: e })?;
+            if !file.is_file()
+                || file.file_name().unwrap_or_default() == HISTORY_FILE
+                || ConfigFormat::from_path(&file.to_string_lossy()).is_none()
+            {
+                continue;
+            }
+            if files.iter().any(|(item, _)| Path::new(item) == file) {
+                continue;
+            }
+            let file = file.to_string_lossy().to_string();
+            fs::remove_file(&file).await.map_err(|e| Error::Io {
+                source: e,
+                file: file.clone(),
+            })?;
+        }
+        Ok(())
+    }
+    async fn load_history(&self) -> Result<Vec<u8>> {
+        let file = self.get_history_file();
+        if !Path::new(&file).exists() {
+            return Ok(vec![]);
+        }
+        fs::read(&file)
+            .await
+            .map_err(|e| Error::Io { source: e, file })
+    }
+    async fn save_history(&self, data: &[u8]) -> Result<()> {
+        write_file_atomic(&self.get_history_file(), data).await
+    }
     async fn save(&self, key: &str, data: &[u8]) -> Result<()> {
         let key = pingap_util::path_join(&self.path, key);
         let path = Path::new(&key);
@@ -199,6 +336,7 @@ mod tests {
     use crate::*;
     use nanoid::nanoid;
     use pretty_assertions::assert_eq;
+    use std::path::Path;
 
     #[tokio::test]
     async fn test_file_storage() {
@@ -249,4 +387,110 @@ mod tests {
             .unwrap();
         assert_eq!(current_conf.hash().unwrap(), conf.hash().unwrap());
     }
+
+    #[tokio::test]
+    async fn test_file_storage_format() {
+        let conf = PingapConf::new(
+            r#"
+[upstreams.charts]
+addrs = ["127.0.0.1:5000"]
+
+[locations.lo]
+upstream = "charts"
+
+[servers.test]
+addr = "127.0.0.1:6188"
+locations = ["lo"]
+"#
+            .as_bytes(),
+            false,
+        )
+        .unwrap();
+
+        // single yaml and json file
+        let dir = tempfile::TempDir::new().unwrap();
+        for name in ["pingap.yaml", "pingap.yml", "pingap.json"] {
+            let file = dir.path().join(name).to_string_lossy().to_string();
+            let storage = FileStorage::new(&file).unwrap();
+            storage
+                .save_config(&conf, CATEGORY_SERVER, None)
+                .await
+                .unwrap();
+            let current_conf = storage
+                .load_config(LoadConfigOptions::default())
+                .await
+                .unwrap();
+            assert_eq!(conf.hash().unwrap(), current_conf.hash().unwrap());
+            let data = tokio::fs::read_to_string(&file).await.unwrap();
+            if name.ends_with(".json") {
+                assert_eq!(true, data.starts_with('{'));
+            } else {
+                assert_eq!(true, data.contains("servers:"));
+            }
+        }
+
+        // directory of mixed formats, the format of existing file is kept
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().to_string_lossy().to_string();
+        tokio::fs::write(
+            format!("{path}/upstreams.yml"),
+            b"upstreams:\n  charts:\n    addrs:\n      - 127.0.0.1:5000\n",
+        )
+        .await
+        .unwrap();
+        tokio::fs::write(
+            format!("{path}/locations.json"),
+            br#"{"locations":{"lo":{"upstream":"charts"}}}"#,
+        )
+        .await
+        .unwrap();
+        tokio::fs::write(format!("{path}/history.json"), b"[]")
+            .await
+            .unwrap();
+        let storage = FileStorage::new(&path).unwrap();
+        storage
+            .save_config(&conf, CATEGORY_SERVER, None)
+            .await
+            .unwrap();
+        storage
+            .save_config(&conf, CATEGORY_UPSTREAM, None)
+            .await
+            .unwrap();
+        let current_conf = storage
+            .load_config(LoadConfigOptions::default())
+            .await
+            .unwrap();
+        assert_eq!(conf.hash().unwrap(), current_conf.hash().unwrap());
+        let mut files: Vec<String> = std::fs::read_dir(&path)
+            .unwrap()
+            .map(|item| item.unwrap().file_name().to_string_lossy().to_string())
+            .collect();
+        files.sort();
+        assert_eq!(
+            vec![
+                "history.json",
+                "locations.json",
+                "servers.toml",
+                "upstreams.yml"
+            ],
+            files
+        );
+
+        // replace keeps the formats and removes the stale files
+        let mut new_conf = conf.clone();
+        new_conf.locations.clear();
+        new_conf.servers.get_mut("test").unwrap().locations = None;
+        storage.replace_config(&new_conf).await.unwrap();
+        let current_conf = storage
+            .load_config(LoadConfigOptions::default())
+            .await
+            .unwrap();
+        assert_eq!(new_conf.hash().unwrap(), current_conf.hash().unwrap());
+        assert_eq!(
+            false,
+            Path::new(&format!("{path}/locations.json")).exists()
+        );
+        assert_eq!(true, Path::new(&format!("{path}/history.json")).exists());
+        assert_eq!(true, Path::new(&format!("{path}/upstreams.yml")).exists());
+    }
 }
@@ -0,0 +1,192 @@
+// Copyright 2024-2025 Tree xie.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use super::{Error, Result};
+use std::path::Path;
+
+/// Extensions of the supported config files
+pub const CONFIG_FILE_EXTENSIONS: [&str; 4] = ["toml", "yaml", "yml", "json"];
+
+/// Format of config file, it's chosen by the extension of file.
+/// The yaml and json config are converted to toml when loading,
+/// so they have the same semantics as toml, e.g. includes.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum ConfigFormat {
+    #[default]
+    Toml,
+    Yaml,
+    Json,
+}
+
+impl ConfigFormat {
+    /// Returns the format of file by extension,
+    /// it's None if the extension is not supported.
+    pub fn from_path(path: &str) -> Option<Self> {
+        let extension = Path::new(path).extension()?.to_str()?;
+        match extension.to_lowercase().as_str() {
+            "toml" => Some(Self::Toml),
+            "yaml" | "yml" => Some(Self::Yaml),
+            "json" => Some(Self::Json),
+            _ => None,
+        }
+    }
+}
+
+/// Removes the null values, toml does not support null
+fn remove_null_values(value: &mut serde_json::Value) {
+    match value {
+        serde_json::Value::Object(values) => {
+            values.retain(|_, item| !item.is_null());
+            for item in values.values_mut() {
+                remove_null_values(item);
+            }
+        },
+        serde_json::Value::Array(values) => {
+            values.retain(|item| !item.is_null());
+            for item in values.iter_mut() {
+                remove_null_values(item);
+            }
+        },
+        _ => {},
+    }
+}
+
+/// Converts the config data of format to toml
+pub fn convert_to_toml(data: &[u8], format: ConfigFormat) -> Result<String> {
+    let mut value: serde_json::Value = match format {
+        ConfigFormat::Toml => {
+            return Ok(std::string::String::from_utf8_lossy(data).to_string())
+        },
+        ConfigFormat::Yaml => {
+            // empty yaml file is parsed as null
+            if data.iter().all(|item| item.is_ascii_whitespace()) {
+                return Ok("".to_string());
+            }
+            serde_yaml_ng::from_slice(data)
+                .map_err(|e| Error::Yaml { source: e })?
+        },
+        ConfigFormat::Json => serde_json::from_slice(data)
+            .map_err(|e| Error::Json { source: e })?,
+    };
+    remove_null_values(&mut value);
+    let value: toml::Table =
+        serde_json::from_value(value).map_err(|e| Error::Json { source: e })?;
+    toml::to_string_pretty(&value).map_err(|e| Error::Ser { source: e })
+}
+
+/// Converts the toml config to the data of format
+pub fn convert_from_toml(toml: &str, format: ConfigFormat) -> Result<String> {
+    if format == ConfigFormat::Toml {
+        return Ok(toml.to_string());
+    }
+    let value: toml::Table =
+        toml::from_str(toml).map_err(|e| Error::De { source: e })?;
+    match format {
+        ConfigFormat::Yaml => serde_yaml_ng::to_string(&value)
+            .map_err(|e| Error::Yaml { source: e }),
+        _ => serde_json::to_string_pretty(&value)
+            .map_err(|e| Error::Json { source: e }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::PingapConf;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_config_format() {
+        assert_eq!(
+            Some(ConfigFormat::Toml),
+            ConfigFormat::from_path("/opt/pingap.toml")
+        );
+        assert_eq!(
+            Some(ConfigFormat::Yaml),
+            ConfigFormat::from_path("/opt/pingap.yml")
+        );
+        assert_eq!(
+            Some(ConfigFormat::Yaml),
+            ConfigFormat::from_path("/opt/pingap.YAML")
+        );
+        assert_eq!(
+            Some(ConfigFormat::Json),
+            ConfigFormat::from_path("/opt/pingap.json")
+        );
+        assert_eq!(None, ConfigFormat::from_path("/opt/pingap"));
+    }
+
+    #[test]
+    fn test_convert_config() {
+        let yaml = r#"
+upstreams:
+  charts:
+    addrs:
+      - 127.0.0.1:5000
+    connection_timeout: 10s
+    remark: null
+locations:
+  lo:
+    upstream: charts
+    includes:
+      - proxyHeader
+servers:
+  test:
+    addr: 127.0.0.1:6188
+    locations:
+      - lo
+storages:
+  proxyHeader:
+    category: config
+    value: 'proxy_set_headers = ["name:value"]'
+"#;
+        let toml =
+            convert_to_toml(yaml.as_bytes(), ConfigFormat::Yaml).unwrap();
+        let conf = PingapConf::new(toml.as_bytes(), true).unwrap();
+        assert_eq!(
+            vec!["127.0.0.1:5000".to_string()],
+            conf.upstreams["charts"].addrs
+        );
+        assert_eq!(
+            Some(std::time::Duration::from_secs(10)),
+            conf.upstreams["charts"].connection_timeout
+        );
+        // includes are replaced as toml
+        assert_eq!(
+            Some(vec!["name:value".to_string()]),
+            conf.locations["lo"].proxy_set_headers
+        );
+
+        // round trip through yaml and json
+        let toml = toml::to_string_pretty(&conf).unwrap();
+        for format in [ConfigFormat::Yaml, ConfigFormat::Json] {
+            let data = convert_from_toml(&toml, format).unwrap();
+            let value = convert_to_toml(data.as_bytes(), format).unwrap();
+            let new_conf = PingapConf::new(value.as_bytes(), false).unwrap();
+            assert_eq!(conf.hash().unwrap(), new_conf.hash().unwrap());
+        }
+
+        assert_eq!(
+            "",
+            convert_to_toml(b"\n", ConfigFormat::Yaml).unwrap().trim()
+        );
+        assert_eq!(
+            true,
+            convert_to_toml(b"{", ConfigFormat::Json)
+                .unwrap_err()
+                .to_string()
+                .starts_with("Json error")
+        );
+    }
+}
@@ -0,0 +1,312 @@
+// Copyright 2024-2025 Tree xie.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use super::{Error, LocationConf, LocationPath, PingapConf, Result};
+
+/// Prefix of the plugins which are built in, they are not in the config
+const BUILTIN_PLUGIN_PREFIX: &str = "pingap:";
+
+/// The match rule of location, the path is matched by the same rule
+/// as the location selector which is used by the server to route requests.
+struct Route<'a> {
+    name: &'a str,
+    weight: u16,
+    /// Hosts of location, empty means all hosts
+    hosts: Vec<&'a str>,
+    path: &'a str,
+    path_rule: LocationPath,
+}
+
+impl<'a> Route<'a> {
+    fn new(name: &'a str, location: &'a LocationConf) -> Self {
+        let hosts = location
+            .host
+            .as_deref()
+            .unwrap_or_default()
+            .split(',')
+            .map(|item| item.trim())
+            .filter(|item| !item.is_empty())
+            .collect();
+        let path = location.path.as_deref().unwrap_or_default().trim();
+        Self {
+            name,
+            weight: location.get_weight(),
+            hosts,
+            path,
+            path_rule: LocationPath::new(path),
+        }
+    }
+    /// Returns the host which is matched by both routes with the same path,
+    /// e.g. the path `/` and the empty path match the same requests.
+    fn duplicate_host(&self, other: &Route) -> Option<&'a str> {
+        if !self.path_rule.covers(&other.path_rule)
+            || !other.path_rule.covers(&self.path_rule)
+        {
+            return None;
+        }
+        if self.hosts.is_empty() && other.hosts.is_empty() {
+            return Some("");
+        }
+        self.hosts
+            .iter()
+            .find(|host| other.hosts.contains(host))
+            .copied()
+    }
+    /// Whether all requests matched by the other route are matched by this route
+    fn covers(&self, other: &Route) -> bool {
+        // regex hosts are only compared as text
+        let host_covered = self.hosts.is_empty()
+            || (!other.hosts.is_empty()
+                && other.hosts.iter().all(|host| self.hosts.contains(host)));
+        host_covered && self.path_rule.covers(&other.path_rule)
+    }
+}
+
+fn validate_location_plugins(conf: &PingapConf) -> Result<()> {
+    let mut names: Vec<&String> = conf.locations.keys().collect();
+    names.sort();
+    for name in names {
+        let Some(plugins) = &conf.locations[name].plugins else {
+            continue;
+        };
+        for plugin in plugins {
+            if !plugin.starts_with(BUILTIN_PLUGIN_PREFIX)
+                && !conf.plugins.contains_key(plugin)
+            {
+                return Err(Error::Invalid {
+                    message: format!(
+                        "plugin({plugin}) is not found(location:{name})"
+                    ),
+                });
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Validates the routes of the locations of server, the locations
+/// should not be duplicated or shadowed by the location with higher weight.
+pub fn validate_server_locations(
+    conf: &PingapConf,
+    name: &str,
+    locations: &[String],
+) -> Result<()> {
+    let mut routes: Vec<Route> = vec![];
+    for location in locations {
+        if routes.iter().any(|item| item.name == location) {
+            return Err(Error::Invalid {
+                message: format!(
+                    "location({location}) is duplicated(server:{name})"
+                ),
+            });
+        }
+        // missing locations are checked by the server validation
+        if let Some(conf) = conf.locations.get(location) {
+            routes.push(Route::new(location, conf));
+        }
+    }
+    for (index, route) in routes.iter().enumerate() {
+        for other in routes.iter().skip(index + 1) {
+            if let Some(host) = route.duplicate_host(other) {
+                return Err(Error::Invalid {
+                    message: format!(
+                        "location({}) and location({}) have the same host({host}) and path({})(server:{name})",
+                        route.name, other.name, route.path
+                    ),
+                });
+            }
+            // the location with higher weight is matched first
+            let (high, low) = if route.weight >= other.weight {
+                (route, other)
+            } else {
+                (other, route)
+            };
+            if high.weight > low.weight && high.covers(low) {
+                return Err(Error::Invalid {
+                    message: format!(
+                        "location({}) is unreachable, it's shadowed by location({}) with higher weight(server:{name})",
+                        low.name, high.name
+                    ),
+                });
+            }
+        }
+    }
+    Ok(())
+}
+
+fn validate_server_routes(conf: &PingapConf) -> Result<()> {
+    let mut names: Vec<&String> = conf.servers.keys().collect();
+    names.sort();
+    for name in names {
+        if let Some(locations) = &conf.servers[name].locations {
+            validate_server_locations(conf, name, locations)?;
+        }
+    }
+    Ok(())
+}
+
+/// Validates the references between the entries of config, e.g. the
+/// plugins of location and the routes of the locations of server.
+pub(crate) fn validate_graph(conf: &PingapConf) -> Result<()> {
+    validate_location_plugins(conf)?;
+    validate_server_routes(conf)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{PluginConf, ServerConf};
+    use pretty_assertions::assert_eq;
+
+    fn new_location(
+        host: &str,
+        path: &str,
+        weight: Option<u16>,
+    ) -> LocationConf {
+        LocationConf {
+            host: Some(host.to_string()),
+            path: Some(path.to_string()),
+            weight,
+            ..Default::default()
+        }
+    }
+
+    fn new_conf(locations: Vec<(&str, LocationConf)>) -> PingapConf {
+        let mut conf = PingapConf::default();
+        let mut names = vec![];
+        for (name, location) in locations {
+            names.push(name.to_string());
+            conf.locations.insert(name.to_string(), location);
+        }
+        conf.servers.insert(
+            "web".to_string(),
+            ServerConf {
+                locations: Some(names),
+                ..Default::default()
+            },
+        );
+        conf
+    }
+
+    #[test]
+    fn test_validate_location_plugins() {
+        let mut conf = new_conf(vec![(
+            "users",
+            LocationConf {
+                plugins: Some(vec![
+                    "pingap:ping".to_string(),
+                    "auth".to_string(),
+                ]),
+                ..Default::default()
+            },
+        )]);
+        assert_eq!(
+            "Invalid error plugin(auth) is not found(location:users)",
+            validate_graph(&conf).unwrap_err().to_string()
+        );
+        conf.plugins
+            .insert("auth".to_string(), PluginConf::default());
+        assert_eq!(true, validate_graph(&conf).is_ok());
+    }
+
+    #[test]
+    fn test_validate_server_routes() {
+        let conf = new_conf(vec![
+            ("users", new_location("pingap.io", "/users", None)),
+            ("api", new_location("", "/api", None)),
+            ("static", new_location("", "", None)),
+            (
+                "regex",
+                new_location("~(?<name>.+).pingap.io", "~/v1", None),
+            ),
+        ]);
+        assert_eq!(true, validate_graph(&conf).is_ok());
+
+        let mut conf = new_conf(vec![
+            ("users", new_location("pingap.io", "/users", None)),
+            ("api", new_location("", "/api", None)),
+        ]);
+        conf.servers.get_mut("web").unwrap().locations = Some(vec![
+            "users".to_string(),
+            "api".to_string(),
+            "users".to_string(),
+        ]);
+        assert_eq!(
+            "Invalid error location(users) is duplicated(server:web)",
+            validate_graph(&conf).unwrap_err().to_string()
+        );
+
+        let conf = new_conf(vec![
+            (
+                "users",
+                new_location("pingap.io,pingap.com", "/users", None),
+            ),
+            ("accounts", new_location("pingap.com", "/users", Some(10))),
+        ]);
+        assert_eq!(
+            "Invalid error location(users) and location(accounts) have the same host(pingap.com) and path(/users)(server:web)",
+            validate_graph(&conf).unwrap_err().to_string()
+        );
+
+        let conf = new_conf(vec![
+            ("users", new_location("", "/users", None)),
+            ("profile", new_location("pingap.io", "=/users/me", Some(10))),
+        ]);
+        assert_eq!(
+            "Invalid error location(profile) is unreachable, it's shadowed by location(users) with higher weight(server:web)",
+            validate_graph(&conf).unwrap_err().to_string()
+        );
+
+        // the location of other host is not shadowed
+        let conf = new_conf(vec![
+            ("users", new_location("pingap.io", "/users", None)),
+            (
+                "profile",
+                new_location("pingap.com", "=/users/me", Some(10)),
+            ),
+        ]);
+        assert_eq!(true, validate_graph(&conf).is_ok());
+
+        // the regex path is not compared
+        let conf = new_conf(vec![
+            ("users", new_location("", "/users", None)),
+            ("profile", new_location("", "~/users/(.+)", Some(10))),
+        ]);
+        assert_eq!(true, validate_graph(&conf).is_ok());
+
+        // the weight of path `/` is 0, it matches the same requests
+        // as the empty path
+        let conf = new_conf(vec![
+            ("root", new_location("", "/", None)),
+            ("all", new_location("", "", None)),
+        ]);
+        assert_eq!(
+            "Invalid error location(root) and location(all) have the same host() and path(/)(server:web)",
+            validate_graph(&conf).unwrap_err().to_string()
+        );
+        let conf = new_conf(vec![
+            ("root", new_location("", "/", None)),
+            ("users", new_location("", "/users", None)),
+        ]);
+        assert_eq!(true, validate_graph(&conf).is_ok());
+        let conf = new_conf(vec![
+            ("root", new_location("pingap.io", "/", None)),
+            ("users", new_location("pingap.io", "/users", Some(0))),
+        ]);
+        assert_eq!(
+            "Invalid error location(users) is unreachable, it's shadowed by location(root) with higher weight(server:web)",
+            validate_graph(&conf).unwrap_err().to_string()
+        );
+    }
+}
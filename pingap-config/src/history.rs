@@ -0,0 +1,438 @@
+// Copyright 2024-2025 Tree xie.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use super::{
+    ConfigStorage, Error, LoadConfigOptions, PingapConf, Result, CATEGORY_BASIC,
+};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+use tracing::error;
+
+/// Max count of config revisions, the oldest revision is removed
+pub const MAX_CONFIG_REVISIONS: usize = 20;
+/// Category of the revision which is created by rollback
+pub const REVISION_CATEGORY_ROLLBACK: &str = "rollback";
+/// Category of the revision which records the config before the first change
+pub const REVISION_CATEGORY_INITIAL: &str = "initial";
+
+/// Lock of config updates, the config and its history are loaded and
+/// saved by one update at a time, so the concurrent revisions are not lost.
+static UPDATE_LOCK: Lazy<Mutex<()>> = Lazy::new(|| Mutex::new(()));
+
+/// Revision of config, it's created for each change of config
+#[derive(Debug, Default, Clone, Deserialize, Serialize)]
+pub struct ConfigRevision {
+    /// Version of revision, it's increased for each change
+    pub version: u64,
+    /// Created time of revision(unix timestamp in seconds)
+    pub created_at: u64,
+    /// The admin user who changes the config
+    pub author: String,
+    /// Category of the changed config
+    pub category: String,
+    /// Name of the changed config
+    pub name: Option<String>,
+    /// Diff from the previous revision
+    pub diff: Vec<String>,
+    /// The full original config(toml) of revision
+    pub data: String,
+}
+
+impl ConfigRevision {
+    /// Returns the config of revision
+    pub fn get_config(&self) -> Result<PingapConf> {
+        PingapConf::new(self.data.as_bytes(), false)
+    }
+}
+
+fn parse_revisions(data: &[u8]) -> Result<Vec<ConfigRevision>> {
+    if data.is_empty() {
+        return Ok(vec![]);
+    }
+    serde_json::from_slice(data).map_err(|e| Error::Invalid {
+        message: format!("parse config history fail, {e}"),
+    })
+}
+
+/// Adds the revision of current config, the previous config is added as
+/// the initial revision if the history is empty, so the first change can
+/// also be rolled back.
+fn add_revision(
+    revisions: &mut Vec<ConfigRevision>,
+    previous: &PingapConf,
+    current: &PingapConf,
+    category: &str,
+    name: Option<&str>,
+    author: &str,
+) -> Result<()> {
+    let now = pingap_util::now_sec();
+    let to_toml = |conf: &PingapConf| {
+        toml::to_string_pretty(conf).map_err(|e| Error::Ser { source: e })
+    };
+    if revisions.is_empty() {
+        revisions.push(ConfigRevision {
+            version: 1,
+            created_at: now,
+            category: REVISION_CATEGORY_INITIAL.to_string(),
+            data: to_toml(previous)?,
+            ..Default::default()
+        });
+    }
+    let version = revisions.last().map(|item| item.version).unwrap_or(0) + 1;
+    let (_, diff) = previous.diff(current);
+    revisions.push(ConfigRevision {
+        version,
+        created_at: now,
+        author: author.to_string(),
+        category: category.to_string(),
+        name: name.map(|name| name.to_string()),
+        diff,
+        data: to_toml(current)?,
+    });
+    if revisions.len() > MAX_CONFIG_REVISIONS {
+        revisions.drain(..revisions.len() - MAX_CONFIG_REVISIONS);
+    }
+    Ok(())
+}
+
+async fn load_original_config(
+    storage: &(dyn ConfigStorage + Send + Sync),
+) -> Result<PingapConf> {
+    storage
+        .load_config(LoadConfigOptions {
+            admin: true,
+            ..Default::default()
+        })
+        .await
+}
+
+/// Records the change of config to the history,
+/// the failure of history is logged and does not fail the change.
+async fn record_revision(
+    storage: &(dyn ConfigStorage + Send + Sync),
+    previous: &PingapConf,
+    current: &PingapConf,
+    category: &str,
+    name: Option<&str>,
+    author: &str,
+) {
+    let result = async {
+        let mut revisions = parse_revisions(&storage.load_history().await?)?;
+        add_revision(
+            &mut revisions,
+            previous,
+            current,
+            category,
+            name,
+            author,
+        )?;
+        let data =
+            serde_json::to_vec(&revisions).map_err(|e| Error::Invalid {
+                message: e.to_string(),
+            })?;
+        storage.save_history(&data).await
+    }
+    .await;
+    if let Err(e) = result {
+        error!(error = %e, category, "record config revision fail");
+    }
+}
+
+/// Saves the config of category and records the revision to the history
+pub async fn save_config_with_history(
+    storage: &(dyn ConfigStorage + Send + Sync),
+    conf: &PingapConf,
+    category: &str,
+    name: Option<&str>,
+    author: &str,
+) -> Result<()> {
+    let _guard = UPDATE_LOCK.lock().await;
+    let previous = load_original_config(storage).await?;
+    storage.save_config(conf, category, name).await?;
+    record_revision(storage, &previous, conf, category, name, author).await;
+    Ok(())
+}
+
+/// Returns the revisions of config, the newest is first
+pub async fn list_config_revisions(
+    storage: &(dyn ConfigStorage + Send + Sync),
+) -> Result<Vec<ConfigRevision>> {
+    let mut revisions = parse_revisions(&storage.load_history().await?)?;
+    revisions.reverse();
+    Ok(revisions)
+}
+
+/// Returns the revision of version
+pub async fn get_config_revision(
+    storage: &(dyn ConfigStorage + Send + Sync),
+    version: u64,
+) -> Result<ConfigRevision> {
+    list_config_revisions(storage)
+        .await?
+        .into_iter()
+        .find(|item| item.version == version)
+        .ok_or_else(|| Error::Invalid {
+            message: format!("config revision({version}) not found"),
+        })
+}
+
+/// Rolls back the config to the revision of version, the whole config is
+/// replaced at once and the rollback is recorded as a new revision.
+pub async fn rollback_config(
+    storage: &(dyn ConfigStorage + Send + Sync),
+    version: u64,
+    author: &str,
+) -> Result<PingapConf> {
+    let _guard = UPDATE_LOCK.lock().await;
+    let revision = get_config_revision(storage, version).await?;
+    let conf = revision.get_config()?;
+    let previous = load_original_config(storage).await?;
+    storage.replace_config(&conf).await?;
+    record_revision(
+        storage,
+        &previous,
+        &conf,
+        REVISION_CATEGORY_ROLLBACK,
+        Some(&version.to_string()),
+        author,
+    )
+    .await;
+    Ok(conf)
+}
+
+/// Returns the toml files of the whole config, the config is
+/// separated by category and name if separation is true.
+pub(crate) fn get_config_files(
+    conf: &PingapConf,
+    separation: bool,
+) -> Result<Vec<(String, String)>> {
+    let mut items = vec![(CATEGORY_BASIC.to_string(), None)];
+    for (category, names) in conf.get_category_names() {
+        if separation {
+            for name in names {
+                items.push((category.to_string(), Some(name)));
+            }
+        } else {
+            items.push((category.to_string(), None));
+        }
+    }
+    let mut files = vec![];
+    for (category, name) in items {
+        let (path, value) = conf.get_toml(&category, name.as_deref())?;
+        if !value.is_empty() {
+            files.push((path, value));
+        }
+    }
+    Ok(files)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{FileStorage, ServerConf, CATEGORY_SERVER};
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_add_revision() {
+        let previous = PingapConf::default();
+        let mut revisions = vec![];
+        let mut current = PingapConf::default();
+        current.servers.insert(
+            "test".to_string(),
+            ServerConf {
+                addr: "127.0.0.1:6188".to_string(),
+                ..Default::default()
+            },
+        );
+        add_revision(
+            &mut revisions,
+            &previous,
+            &current,
+            CATEGORY_SERVER,
+            Some("test"),
+            "pingap",
+        )
+        .unwrap();
+        assert_eq!(2, revisions.len());
+        assert_eq!(REVISION_CATEGORY_INITIAL, revisions[0].category);
+        assert_eq!(true, revisions[0].get_config().unwrap().servers.is_empty());
+        assert_eq!(2, revisions[1].version);
+        assert_eq!("pingap", revisions[1].author);
+        assert_eq!("++server:test", revisions[1].diff[0]);
+        assert_eq!(
+            "127.0.0.1:6188",
+            revisions[1].get_config().unwrap().servers["test"].addr
+        );
+
+        for _ in 0..MAX_CONFIG_REVISIONS {
+            add_revision(
+                &mut revisions,
+                &current,
+                &current,
+                CATEGORY_SERVER,
+                Some("test"),
+                "pingap",
+            )
+            .unwrap();
+        }
+        assert_eq!(MAX_CONFIG_REVISIONS, revisions.len());
+        assert_eq!(
+            MAX_CONFIG_REVISIONS as u64 + 2,
+            revisions.last().unwrap().version
+        );
+
+        let data = serde_json::to_vec(&revisions).unwrap();
+        assert_eq!(MAX_CONFIG_REVISIONS, parse_revisions(&data).unwrap().len());
+        assert_eq!(true, parse_revisions(b"").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_get_config_files() {
+        let mut conf = PingapConf::default();
+        conf.basic.name = Some("pingap".to_string());
+        for name in ["a", "b"] {
+            conf.servers.insert(
+                name.to_string(),
+                ServerConf {
+                    addr: "127.0.0.1:6188".to_string(),
+                    ..Default::default()
+                },
+            );
+        }
+        let files: Vec<String> = get_config_files(&conf, false)
+            .unwrap()
+            .into_iter()
+            .map(|(path, _)| path)
+            .collect();
+        assert_eq!(vec!["/basic.toml", "/servers.toml"], files);
+
+        let mut files: Vec<String> = get_config_files(&conf, true)
+            .unwrap()
+            .into_iter()
+            .map(|(path, _)| path)
+            .collect();
+        files.sort();
+        assert_eq!(
+            vec!["/basic.toml", "/servers/a.toml", "/servers/b.toml"],
+            files
+        );
+    }
+
+    #[tokio::test]
+    async fn test_rollback_config() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().to_string_lossy().to_string();
+        let storage = FileStorage::new(&format!("{path}?separation")).unwrap();
+
+        let mut conf = PingapConf::default();
+        for addr in ["127.0.0.1:6188", "127.0.0.1:6189"] {
+            conf.servers.insert(
+                addr.to_string(),
+                ServerConf {
+                    addr: addr.to_string(),
+                    ..Default::default()
+                },
+            );
+            save_config_with_history(
+                &storage,
+                &conf,
+                CATEGORY_SERVER,
+                Some(addr),
+                "pingap",
+            )
+            .await
+            .unwrap();
+        }
+        let revisions = list_config_revisions(&storage).await.unwrap();
+        assert_eq!(
+            vec![3, 2, 1],
+            revisions
+                .iter()
+                .map(|item| item.version)
+                .collect::<Vec<_>>()
+        );
+
+        // rollback to the revision which has only one server
+        let conf = rollback_config(&storage, 2, "admin").await.unwrap();
+        assert_eq!(1, conf.servers.len());
+        let current = load_original_config(&storage).await.unwrap();
+        assert_eq!(
+            vec!["127.0.0.1:6188"],
+            current.servers.keys().collect::<Vec<_>>()
+        );
+        assert_eq!(
+            false,
+            std::path::Path::new(&format!(
+                "{path}/servers/127.0.0.1:6189.toml"
+            ))
+            .exists()
+        );
+
+        let revision = get_config_revision(&storage, 4).await.unwrap();
+        assert_eq!(REVISION_CATEGORY_ROLLBACK, revision.category);
+        assert_eq!("admin", revision.author);
+        assert_eq!("--server:127.0.0.1:6189", revision.diff[0]);
+        assert_eq!(
+            "Invalid error config revision(10) not found",
+            get_config_revision(&storage, 10)
+                .await
+                .unwrap_err()
+                .to_string()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_save_config_with_history_concurrently() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().to_string_lossy().to_string();
+        let storage = FileStorage::new(&format!("{path}?separation")).unwrap();
+
+        let confs: Vec<(String, PingapConf)> = (0..5)
+            .map(|index| {
+                let addr = format!("127.0.0.1:{}", 6180 + index);
+                let mut conf = PingapConf::default();
+                conf.servers.insert(
+                    addr.clone(),
+                    ServerConf {
+                        addr: addr.clone(),
+                        ..Default::default()
+                    },
+                );
+                (addr, conf)
+            })
+            .collect();
+        let results =
+            futures_util::future::join_all(confs.iter().map(|(addr, conf)| {
+                save_config_with_history(
+                    &storage,
+                    conf,
+                    CATEGORY_SERVER,
+                    Some(addr),
+                    "pingap",
+                )
+            }))
+            .await;
+        assert_eq!(true, results.iter().all(|item| item.is_ok()));
+        // the initial revision and one revision for each update
+        let revisions = list_config_revisions(&storage).await.unwrap();
+        assert_eq!(
+            vec![6, 5, 4, 3, 2, 1],
+            revisions
+                .iter()
+                .map(|item| item.version)
+                .collect::<Vec<_>>()
+        );
+    }
+}
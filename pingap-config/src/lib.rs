@@ -23,8 +23,15 @@ use tokio::fs;
 use tracing::debug;
 
 mod common;
+mod consul;
 mod etcd;
 mod file;
+mod format;
+mod graph;
+mod history;
+mod schema;
+mod secret;
+mod template;
 
 // Error enum for all possible configuration-related errors
 #[derive(Debug, Snafu)]
@@ -47,6 +54,10 @@ pub enum Error {
     De { source: toml::de::Error },
     #[snafu(display("Toml ser error {source}"))]
     Ser { source: toml::ser::Error },
+    #[snafu(display("Yaml error {source}"))]
+    Yaml { source: serde_yaml_ng::Error },
+    #[snafu(display("Json error {source}"))]
+    Json { source: serde_json::Error },
     #[snafu(display("Url parse error {source}, {url}"))]
     UrlParse {
         source: url::ParseError,
@@ -63,18 +74,26 @@ pub enum Error {
     Regex { source: regex::Error },
     #[snafu(display("Etcd error {source}"))]
     Etcd { source: Box<etcd_client::Error> },
+    #[snafu(display("Consul error {message}"))]
+    Consul { message: String },
 }
 type Result<T, E = Error> = std::result::Result<T, E>;
 
 // Observer struct for watching configuration changes
+#[derive(Default)]
 pub struct Observer {
     // Optional watch stream for etcd-based configuration
     etcd_watch_stream: Option<WatchStream>,
+    // Optional blocking query watcher for consul-based configuration
+    consul_watcher: Option<ConsulWatcher>,
 }
 
 impl Observer {
     // Watches for configuration changes, returns true if changes detected
     pub async fn watch(&mut self) -> Result<bool> {
+        if let Some(watcher) = self.consul_watcher.as_mut() {
+            return watcher.watch().await;
+        }
         let sleep_time = Duration::from_secs(30);
         // no watch stream, just sleep a moment
         let Some(stream) = self.etcd_watch_stream.as_mut() else {
@@ -94,6 +113,9 @@ impl Observer {
 pub struct LoadConfigOptions {
     pub replace_include: bool, // Whether to replace include directives
     pub admin: bool,           // Whether this is an admin configuration
+    // Whether to resolve the secret references, it implies replace_include
+    // and the resolved config should only be used for running
+    pub resolve_secret: bool,
 }
 
 // Trait defining storage backend operations for configuration
@@ -117,11 +139,17 @@ pub trait ConfigStorage {
 
     // Create an observer for this storage
     async fn observe(&self) -> Result<Observer> {
-        Ok(Observer {
-            etcd_watch_stream: None,
-        })
+        Ok(Observer::default())
     }
 
+    // Replace the whole config at once, it's used for rollback.
+    // It's atomic for a single file, but not across the files of a directory.
+    async fn replace_config(&self, conf: &PingapConf) -> Result<()>;
+
+    // Load and save the history of config revisions(json)
+    async fn load_history(&self) -> Result<Vec<u8>>;
+    async fn save_history(&self, data: &[u8]) -> Result<()>;
+
     // Low-level storage operations
     async fn save(&self, key: &str, data: &[u8]) -> Result<()>;
     async fn load(&self, key: &str) -> Result<Vec<u8>>;
@@ -132,7 +160,7 @@ static CONFIG_STORAGE: OnceCell<Box<(dyn ConfigStorage + Sync + Send)>> =
     OnceCell::new();
 
 // Creates a new configuration storage based on the path
-// Supports both etcd:// and file:// protocols
+// Supports etcd://, consul:// and file:// protocols
 fn new_config_storage(
     path: &str,
 ) -> Result<Box<(dyn ConfigStorage + Sync + Send)>> {
@@ -140,6 +168,9 @@ fn new_config_storage(
         if path.starts_with(ETCD_PROTOCOL) {
             let storage = EtcdStorage::new(path)?;
             Box::new(storage)
+        } else if path.starts_with(CONSUL_PROTOCOL) {
+            let storage = ConsulStorage::new(path)?;
+            Box::new(storage)
         } else {
             let storage = FileStorage::new(path)?;
             Box::new(storage)
@@ -150,7 +181,7 @@ fn new_config_storage(
 /// Initializes the configuration storage system if it hasn't been initialized yet
 ///
 /// # Arguments
-/// * `path` - A string path that can be either a file path (file://) or etcd path (etcd://)
+/// * `path` - A string path that can be a file path (file://), etcd path (etcd://) or consul path (consul://)
 ///
 /// # Returns
 /// * `Result<&'static (dyn ConfigStorage + Sync + Send)>` - A reference to the initialized storage
@@ -174,6 +205,15 @@ pub async fn load_config(opts: LoadConfigOptions) -> Result<PingapConf> {
     storage.load_config(opts).await
 }
 
+/// Loads the config from the storage of path without initializing it,
+/// e.g. the candidate config of dry run.
+pub async fn load_config_from_path(
+    path: &str,
+    opts: LoadConfigOptions,
+) -> Result<PingapConf> {
+    new_config_storage(path)?.load_config(opts).await
+}
+
 pub async fn read_all_toml_files(dir: &str) -> Result<Vec<u8>> {
     let mut data = vec![];
     for entry in
@@ -195,6 +235,36 @@ pub async fn read_all_toml_files(dir: &str) -> Result<Vec<u8>> {
     Ok(data)
 }
 
+/// Reads all config files(toml, yaml and json) of the directory,
+/// the yaml and json files are converted to toml before appending.
+pub async fn read_all_config_files(dir: &str) -> Result<Vec<u8>> {
+    let mut data = vec![];
+    for entry in glob(&format!("{dir}/**/*")).map_err(|e| Error::Pattern {
+        source: e,
+        path: dir.to_string(),
+    })? {
+        let f = entry.map_err(|e| Error::Glob { source: e })?;
+        let filename = f.to_string_lossy().to_string();
+        let Some(format) = ConfigFormat::from_path(&filename) else {
+            continue;
+        };
+        // the history of config is not a part of config
+        if f.file_name().unwrap_or_default() == file::HISTORY_FILE
+            || !f.is_file()
+        {
+            continue;
+        }
+        let buf = fs::read(&f).await.map_err(|e| Error::Io {
+            source: e,
+            file: filename.clone(),
+        })?;
+        debug!(filename, "read config file");
+        data.append(&mut convert_to_toml(&buf, format)?.into_bytes());
+        data.push(0x0a);
+    }
+    Ok(data)
+}
+
 pub fn support_observer() -> bool {
     if let Some(storage) = CONFIG_STORAGE.get() {
         storage.support_observer()
@@ -212,20 +282,45 @@ pub fn get_config_storage() -> Option<&'static (dyn ConfigStorage + Sync + Send)
     }
 }
 
+fn get_inited_config_storage(
+) -> Result<&'static (dyn ConfigStorage + Sync + Send)> {
+    get_config_storage().ok_or_else(|| Error::Invalid {
+        message: "storage is not inited".to_string(),
+    })
+}
+
+/// Saves the config of category and records the revision,
+/// the author is the admin user who changes the config.
 pub async fn save_config(
     conf: &PingapConf,
     category: &str,
     name: Option<&str>,
+    author: &str,
 ) -> Result<()> {
-    let Some(storage) = CONFIG_STORAGE.get() else {
-        return Err(Error::Invalid {
-            message: "storage is not inited".to_string(),
-        });
-    };
-    storage.save_config(conf, category, name).await
+    let storage = get_inited_config_storage()?;
+    history::save_config_with_history(storage, conf, category, name, author)
+        .await
+}
+
+/// Returns the revisions of config, the newest is first
+pub async fn list_config_revisions() -> Result<Vec<ConfigRevision>> {
+    history::list_config_revisions(get_inited_config_storage()?).await
+}
+
+/// Returns the revision of config by version
+pub async fn get_config_revision(version: u64) -> Result<ConfigRevision> {
+    history::get_config_revision(get_inited_config_storage()?, version).await
+}
+
+/// Rolls back the config to the revision of version
+pub async fn rollback_config(version: u64, author: &str) -> Result<PingapConf> {
+    history::rollback_config(get_inited_config_storage()?, version, author)
+        .await
 }
 pub async fn sync_to_path(path: &str) -> Result<()> {
-    let conf = get_current_config();
+    // the current config is resolved, load the original config
+    // to avoid writing the secrets in plaintext
+    let conf = load_config(LoadConfigOptions::default()).await?;
     let storage = new_config_storage(path)?;
     sync_config(&conf, storage.as_ref()).await
 }
@@ -260,8 +355,18 @@ pub async fn sync_config(
 }
 
 pub use common::*;
+pub use consul::{ConsulStorage, ConsulWatcher, CONSUL_PROTOCOL};
 pub use etcd::{EtcdStorage, ETCD_PROTOCOL};
 pub use file::FileStorage;
+pub use format::{
+    convert_from_toml, convert_to_toml, ConfigFormat, CONFIG_FILE_EXTENSIONS,
+};
+pub use graph::validate_server_locations;
+pub use history::{
+    ConfigRevision, MAX_CONFIG_REVISIONS, REVISION_CATEGORY_INITIAL,
+    REVISION_CATEGORY_ROLLBACK,
+};
+pub use schema::{get_config_schema, get_plugin_schema};
 
 #[cfg(test)]
 mod tests {
@@ -0,0 +1,151 @@
+// Copyright 2024-2025 Tree xie.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use super::PingapConf;
+use serde_json::{json, Value};
+
+/// Returns the name of plugin schema in the definitions
+fn get_plugin_definition(category: &str) -> String {
+    format!("PluginConf_{category}")
+}
+
+/// Returns the json schema of the whole config.
+/// The plugin config is a free-form table whose keys depend on its
+/// category, so the schemas of plugins(category and schema) are added to
+/// the definitions and the plugin config is validated by its category.
+/// The category may be set by the template, so the plugin without
+/// category is allowed if its template is set.
+pub fn get_config_schema(plugin_schemas: &[(String, Value)]) -> Value {
+    let mut schema = schemars::schema_for!(PingapConf).to_value();
+    // all categories of config are optional
+    if let Some(value) = schema.as_object_mut() {
+        value.remove("required");
+    }
+    if plugin_schemas.is_empty() {
+        return schema;
+    }
+    let categories: Vec<&str> = plugin_schemas
+        .iter()
+        .map(|(name, _)| name.as_str())
+        .collect();
+    let rules: Vec<Value> = categories
+        .iter()
+        .map(|category| {
+            json!({
+                "if": {
+                    "properties": {
+                        "category": { "const": category }
+                    },
+                    "required": ["category"]
+                },
+                "then": {
+                    "$ref": format!("#/$defs/{}", get_plugin_definition(category))
+                }
+            })
+        })
+        .collect();
+    schema["properties"]["plugins"]["additionalProperties"] = json!({
+        "type": "object",
+        "properties": {
+            "category": {
+                "type": "string",
+                "enum": categories,
+                "description": "Category of plugin"
+            }
+        },
+        "anyOf": [
+            { "required": ["category"] },
+            { "required": ["template"] }
+        ],
+        "allOf": rules
+    });
+    for (category, value) in plugin_schemas {
+        schema["$defs"][get_plugin_definition(category)] = value.clone();
+    }
+    schema
+}
+
+/// Returns the json schema of the plugin config of category
+pub fn get_plugin_schema(category: &str, value: &Value) -> Value {
+    let mut schema = value.clone();
+    schema["$schema"] = json!("https://json-schema.org/draft/2020-12/schema");
+    schema["title"] = json!(get_plugin_definition(category));
+    schema
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_get_config_schema() {
+        let schema = get_config_schema(&[]);
+        assert_eq!("PingapConf", schema["title"]);
+        assert_eq!(true, schema["required"].is_null());
+        for name in [
+            "BasicConf",
+            "ServerConf",
+            "LocationConf",
+            "UpstreamConf",
+            "CertificateConf",
+            "StorageConf",
+        ] {
+            assert_eq!(true, schema["$defs"][name].is_object());
+        }
+        let server = &schema["$defs"]["ServerConf"]["properties"];
+        assert_eq!(
+            "Address to listen on in format \"host:port\" or multiple addresses separated by commas",
+            server["addr"]["description"]
+        );
+        assert_eq!(
+            json!(["string", "null"]),
+            server["access_log_min_latency"]["type"]
+        );
+        assert_eq!(1.0, server["access_log_sample_ratio"]["maximum"]);
+        assert_eq!(
+            json!(["upstream", "cache", "plugin", "total"]),
+            server["server_timing_metrics"]["items"]["enum"]
+        );
+
+        let ping = json!({
+            "type": "object",
+            "properties": {
+                "category": { "const": "ping" },
+                "path": { "type": "string" }
+            },
+            "additionalProperties": false
+        });
+        let schema = get_config_schema(&[("ping".to_string(), ping.clone())]);
+        assert_eq!(ping, schema["$defs"]["PluginConf_ping"]);
+        let plugins = &schema["properties"]["plugins"]["additionalProperties"];
+        assert_eq!(json!(["ping"]), plugins["properties"]["category"]["enum"]);
+        assert_eq!(
+            "#/$defs/PluginConf_ping",
+            plugins["allOf"][0]["then"]["$ref"]
+        );
+        assert_eq!(json!(["category"]), plugins["allOf"][0]["if"]["required"]);
+        assert_eq!(
+            json!([{ "required": ["category"] }, { "required": ["template"] }]),
+            plugins["anyOf"]
+        );
+
+        let schema = get_plugin_schema("ping", &ping);
+        assert_eq!("PluginConf_ping", schema["title"]);
+        assert_eq!(
+            "https://json-schema.org/draft/2020-12/schema",
+            schema["$schema"]
+        );
+    }
+}
@@ -0,0 +1,154 @@
+// Copyright 2024-2025 Tree xie.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use super::{Error, Result, StorageConf};
+use std::collections::HashMap;
+use toml::Value;
+
+const REFERENCE_START: &str = "${";
+const REFERENCE_END: &str = "}";
+const SECRET_PREFIX: &str = "secret:";
+const ENV_PREFIX: &str = "env:";
+const FILE_PREFIX: &str = "file:";
+
+/// Resolves the value of reference, it returns None if the
+/// reference is not supported, e.g. `${host}`.
+fn resolve_reference(
+    reference: &str,
+    storages: &HashMap<String, StorageConf>,
+) -> Option<Result<String>> {
+    let new_error = |message: String| Error::Invalid {
+        message: format!("resolve ${{{reference}}} fail, {message}"),
+    };
+    if let Some(name) = reference.strip_prefix(SECRET_PREFIX) {
+        let result = match storages.get(name) {
+            Some(storage) => storage.get_value(),
+            None => Err(new_error("storage not found".to_string())),
+        };
+        return Some(result);
+    }
+    if let Some(name) = reference.strip_prefix(ENV_PREFIX) {
+        return Some(std::env::var(name).map_err(|e| new_error(e.to_string())));
+    }
+    if let Some(path) = reference.strip_prefix(FILE_PREFIX) {
+        let path = pingap_util::resolve_path(path);
+        // the mounted secret file usually ends with a line break
+        return Some(
+            std::fs::read_to_string(&path)
+                .map(|value| value.trim_end_matches(['\r', '\n']).to_string())
+                .map_err(|e| new_error(format!("{e}, {path}"))),
+        );
+    }
+    None
+}
+
+/// Replaces all `${secret:name}`, `${env:NAME}` and `${file:/path}`
+/// references of the string, other text is kept as it is.
+fn resolve_str(
+    value: &str,
+    storages: &HashMap<String, StorageConf>,
+) -> Result<String> {
+    let mut result = String::with_capacity(value.len());
+    let mut rest = value;
+    while let Some(start) = rest.find(REFERENCE_START) {
+        let Some(end) = rest[start..].find(REFERENCE_END) else {
+            break;
+        };
+        let reference = &rest[start + REFERENCE_START.len()..start + end];
+        result.push_str(&rest[..start]);
+        match resolve_reference(reference, storages) {
+            Some(value) => result.push_str(&value?),
+            None => result.push_str(&rest[start..=start + end]),
+        }
+        rest = &rest[start + end + REFERENCE_END.len()..];
+    }
+    result.push_str(rest);
+    Ok(result)
+}
+
+/// Resolves the references of all string values in the toml value,
+/// the secret values only exist in memory and are never written back.
+pub(crate) fn resolve_references(
+    value: &mut Value,
+    storages: &HashMap<String, StorageConf>,
+) -> Result<()> {
+    match value {
+        Value::String(s) if s.contains(REFERENCE_START) => {
+            *s = resolve_str(s, storages)?;
+        },
+        Value::Array(values) => {
+            for item in values.iter_mut() {
+                resolve_references(item, storages)?;
+            }
+        },
+        Value::Table(values) => {
+            for (_, item) in values.iter_mut() {
+                resolve_references(item, storages)?;
+            }
+        },
+        _ => {},
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use std::io::Write;
+
+    #[test]
+    fn test_resolve_references() {
+        let key = "01234567890123456789012345678901";
+        let mut storages = HashMap::new();
+        storages.insert(
+            "token".to_string(),
+            StorageConf {
+                category: "secret".to_string(),
+                value: pingap_util::aes_encrypt(key, "pingap-token").unwrap(),
+                secret: Some(key.to_string()),
+                ..Default::default()
+            },
+        );
+        std::env::set_var("PINGAP_TEST_SECRET_USER", "pingap");
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        file.write_all(b"password\n").unwrap();
+        let path = file.path().to_string_lossy().to_string();
+
+        let mut value: Value = toml::from_str(&format!(
+            r#"
+headers = ["Authorization:Bearer ${{secret:token}}", "X-Host:${{host}}"]
+[auth]
+user = "${{env:PINGAP_TEST_SECRET_USER}}"
+password = "${{file:{path}}}"
+"#
+        ))
+        .unwrap();
+        resolve_references(&mut value, &storages).unwrap();
+        assert_eq!(
+            r#"["Authorization:Bearer pingap-token", "X-Host:${host}"]"#,
+            value["headers"].to_string()
+        );
+        assert_eq!(r#""pingap""#, value["auth"]["user"].to_string());
+        assert_eq!(r#""password""#, value["auth"]["password"].to_string());
+
+        let mut value = Value::String("${secret:not_found}".to_string());
+        assert_eq!(
+            "Invalid error resolve ${secret:not_found} fail, storage not found",
+            resolve_references(&mut value, &storages)
+                .unwrap_err()
+                .to_string()
+        );
+    }
+}
@@ -0,0 +1,380 @@
+// Copyright 2024-2025 Tree xie.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use super::{Error, Result};
+use std::collections::{BTreeMap, HashMap};
+use toml::{Table, Value};
+
+/// Category of storage whose value is a template
+pub const TEMPLATE_CATEGORY: &str = "template";
+const TEMPLATE_KEY: &str = "template";
+const PARAMS_KEY: &str = "params";
+/// The param of entry name, it's set by default
+const NAME_PARAM: &str = "name";
+const PARAM_START: &str = "{{";
+const PARAM_END: &str = "}}";
+
+/// Whether the param is an integer, float or boolean of toml, it can't
+/// change the structure of toml, so it's replaced before parsing and
+/// can be used as value, e.g. `max = {{max}}`.
+fn is_scalar_param(value: &str) -> bool {
+    if value.is_empty()
+        || !value
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "+-._".contains(c))
+    {
+        return false;
+    }
+    toml::from_str::<Table>(&format!("v = {value}"))
+        .map(|values| {
+            matches!(
+                values.get("v"),
+                Some(Value::Integer(_) | Value::Float(_) | Value::Boolean(_))
+            )
+        })
+        .unwrap_or_default()
+}
+
+/// Count of replacements of each param
+type ParamCounts = BTreeMap<String, usize>;
+
+/// Replaces all `{{param}}` of string by the values of params,
+/// only the scalar params are replaced if `scalar_only` is true,
+/// and the others are counted in `deferred`.
+fn render_str(
+    value: &str,
+    params: &HashMap<String, String>,
+    used: &mut ParamCounts,
+    deferred: &mut ParamCounts,
+    scalar_only: bool,
+) -> std::result::Result<String, String> {
+    let mut result = String::with_capacity(value.len());
+    let mut value = value;
+    while let Some(start) = value.find(PARAM_START) {
+        let Some(end) = value[start..].find(PARAM_END) else {
+            break;
+        };
+        let key = value[start + PARAM_START.len()..start + end].trim();
+        let next = start + end + PARAM_END.len();
+        match params.get(key) {
+            Some(param) if !scalar_only || is_scalar_param(param) => {
+                result.push_str(&value[..start]);
+                result.push_str(param);
+                *used.entry(key.to_string()).or_default() += 1;
+            },
+            None => {
+                return Err(format!("param({key}) is missing"));
+            },
+            // it's replaced after parsing
+            _ => {
+                result.push_str(&value[..next]);
+                *deferred.entry(key.to_string()).or_default() += 1;
+            },
+        }
+        value = &value[next..];
+    }
+    result.push_str(value);
+    Ok(result)
+}
+
+fn render_value(
+    value: &mut Value,
+    params: &HashMap<String, String>,
+    used: &mut ParamCounts,
+) -> std::result::Result<(), String> {
+    match value {
+        Value::String(s) if s.contains(PARAM_START) => {
+            *s = render_str(s, params, used, &mut ParamCounts::new(), false)?;
+        },
+        Value::Array(values) => {
+            for item in values.iter_mut() {
+                render_value(item, params, used)?;
+            }
+        },
+        Value::Table(values) => {
+            for (_, item) in values.iter_mut() {
+                render_value(item, params, used)?;
+            }
+        },
+        _ => {},
+    }
+    Ok(())
+}
+
+/// Parses the template and replaces all `{{param}}` of its string values
+/// by the values of params, so the params can't change the structure of
+/// toml and need no escaping. The scalar params are replaced before parsing.
+/// It fails if any param is missing or unused, or the non-scalar param
+/// is used outside string values, e.g. key or comment.
+fn render(
+    template: &str,
+    params: &HashMap<String, String>,
+) -> std::result::Result<Table, String> {
+    let mut used = ParamCounts::new();
+    let mut deferred = ParamCounts::new();
+    let template =
+        render_str(template, params, &mut used, &mut deferred, true)?;
+    let values: Table =
+        toml::from_str(&template).map_err(|e| format!("is invalid, {e}"))?;
+    let mut value = Value::Table(values);
+    let mut replaced = ParamCounts::new();
+    render_value(&mut value, params, &mut replaced)?;
+    for (key, count) in deferred {
+        if replaced.get(&key).copied().unwrap_or_default() < count {
+            return Err(format!(
+                "param({key}) is only allowed in string values"
+            ));
+        }
+    }
+    let mut unused: Vec<_> = params
+        .keys()
+        .filter(|key| {
+            *key != NAME_PARAM
+                && !used.contains_key(*key)
+                && !replaced.contains_key(*key)
+        })
+        .cloned()
+        .collect();
+    if !unused.is_empty() {
+        unused.sort();
+        return Err(format!("param({}) is not used", unused.join(",")));
+    }
+    let Value::Table(values) = value else {
+        return Ok(Table::new());
+    };
+    Ok(values)
+}
+
+fn expand(
+    templates: &HashMap<String, String>,
+    name: &str,
+    entry: &mut Table,
+) -> std::result::Result<(), String> {
+    let Some(template) = entry.remove(TEMPLATE_KEY) else {
+        return Ok(());
+    };
+    let template = template.as_str().unwrap_or_default().to_string();
+    let Some(value) = templates.get(&template) else {
+        return Err(format!("template({template}) is not found"));
+    };
+    let mut params = HashMap::new();
+    params.insert(NAME_PARAM.to_string(), name.to_string());
+    if let Some(values) = entry.remove(PARAMS_KEY) {
+        let Value::Table(values) = values else {
+            return Err("params should be a table".to_string());
+        };
+        for (key, value) in values {
+            // the name param is always the name of entry
+            if key == NAME_PARAM {
+                return Err(format!("param({key}) is reserved"));
+            }
+            let Value::String(value) = value else {
+                return Err(format!("param({key}) should be a string"));
+            };
+            params.insert(key, value);
+        }
+    }
+    let values = render(value, &params)
+        .map_err(|e| format!("template({template}) {e}"))?;
+    // the values of entry take precedence over the template
+    for (key, value) in values {
+        entry.entry(key).or_insert(value);
+    }
+    Ok(())
+}
+
+/// Expands the template of entry, the `template` and `params` are
+/// replaced by the rendered values of template.
+/// The errors of expansion contain the category and name of entry.
+pub(crate) fn expand_template(
+    templates: &HashMap<String, String>,
+    category: &str,
+    name: &str,
+    entry: &mut Table,
+) -> Result<()> {
+    expand(templates, name, entry).map_err(|e| Error::Invalid {
+        message: format!("{e}({category}:{name})"),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_render() {
+        let mut params = HashMap::new();
+        params.insert("name".to_string(), "users".to_string());
+        params.insert("path".to_string(), "/users".to_string());
+        assert_eq!(
+            r#"path = "/users"
+remark = "api of users"
+"#,
+            render(
+                r#"path = "{{ path }}"
+remark = "api of {{name}}""#,
+                &params
+            )
+            .unwrap()
+            .to_string()
+        );
+        assert_eq!(
+            "param(upstream) is missing",
+            render(r#"upstream = "{{upstream}}""#, &params).unwrap_err()
+        );
+        assert_eq!(
+            "param(path) is not used",
+            render("weight = 100", &params).unwrap_err()
+        );
+        // the scalar param can be used as value
+        params.insert("weight".to_string(), "1024".to_string());
+        assert_eq!(
+            Some(1024),
+            render("weight = {{weight}}\npath = '{{path}}'", &params).unwrap()
+                ["weight"]
+                .as_integer()
+        );
+        params.remove("weight");
+        // the string param can only be used in string values
+        assert_eq!(
+            "param(name) is only allowed in string values",
+            render("path = '{{path}}' # {{name}}", &params).unwrap_err()
+        );
+        assert_eq!(
+            "param(path) is only allowed in string values",
+            render("\"{{path}}\" = 1\nremark = '{{path}}'", &params)
+                .unwrap_err()
+        );
+        // the string param can't be used as value
+        assert_eq!(
+            true,
+            render("weight = {{path}}", &params)
+                .unwrap_err()
+                .starts_with("is invalid")
+        );
+
+        // the regex path is kept as it is
+        params.insert("path".to_string(), r#"~^/users/(\d+)$"#.to_string());
+        let values = render(r#"path = "{{path}}""#, &params).unwrap();
+        assert_eq!(
+            Some(r#"~^/users/(\d+)$"#),
+            values.get("path").and_then(|v| v.as_str())
+        );
+
+        // the param can't add other fields
+        params.insert(
+            "path".to_string(),
+            "/users\"\nupstream = \"evil".to_string(),
+        );
+        let values = render(r#"plugins = ["{{path}}"]"#, &params).unwrap();
+        assert_eq!(1, values.len());
+        assert_eq!(
+            Some("/users\"\nupstream = \"evil"),
+            values["plugins"][0].as_str()
+        );
+    }
+
+    #[test]
+    fn test_expand_template() {
+        let mut templates = HashMap::new();
+        templates.insert(
+            "api".to_string(),
+            r#"
+path = "{{path}}"
+upstream = "{{upstream}}"
+weight = {{weight}}
+remark = "api of {{name}}"
+"#
+            .to_string(),
+        );
+        let mut entry: Table = toml::from_str(
+            r#"
+template = "api"
+params = { path = "/users", upstream = "users", weight = "1024" }
+remark = "users api"
+"#,
+        )
+        .unwrap();
+        expand_template(&templates, "location", "users", &mut entry).unwrap();
+        assert_eq!(
+            r#"path = "/users"
+remark = "users api"
+upstream = "users"
+weight = 1024
+"#,
+            entry.to_string()
+        );
+
+        let mut entry: Table = toml::from_str(r#"template = "api""#).unwrap();
+        assert_eq!(
+            "Invalid error template(api) param(path) is missing(location:users)",
+            expand_template(&templates, "location", "users", &mut entry)
+                .unwrap_err()
+                .to_string()
+        );
+
+        let mut entry: Table = toml::from_str(r#"template = "web""#).unwrap();
+        assert_eq!(
+            "Invalid error template(web) is not found(upstream:users)",
+            expand_template(&templates, "upstream", "users", &mut entry)
+                .unwrap_err()
+                .to_string()
+        );
+
+        // the string param can't be used as value
+        let mut entry: Table = toml::from_str(
+            r#"
+template = "api"
+params = { path = "/users", upstream = "users", weight = "a" }
+"#,
+        )
+        .unwrap();
+        assert_eq!(
+            true,
+            expand_template(&templates, "location", "users", &mut entry)
+                .unwrap_err()
+                .to_string()
+                .starts_with("Invalid error template(api) is invalid")
+        );
+
+        let mut entry: Table = toml::from_str(
+            r#"
+template = "api"
+params = { path = "/users", upstream = "users", weight = 1 }
+"#,
+        )
+        .unwrap();
+        assert_eq!(
+            "Invalid error param(weight) should be a string(location:users)",
+            expand_template(&templates, "location", "users", &mut entry)
+                .unwrap_err()
+                .to_string()
+        );
+
+        let mut entry: Table = toml::from_str(
+            r#"
+template = "api"
+params = { path = "/users", upstream = "users", weight = "1", name = "a" }
+"#,
+        )
+        .unwrap();
+        assert_eq!(
+            "Invalid error param(name) is reserved(location:users)",
+            expand_template(&templates, "location", "users", &mut entry)
+                .unwrap_err()
+                .to_string()
+        );
+    }
+}
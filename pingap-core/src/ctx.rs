@@ -25,6 +25,8 @@ use opentelemetry::{
 };
 use pingora::cache::CacheKey;
 use pingora_limits::inflight::Guard;
+use std::any::Any;
+use std::sync::Arc;
 use std::time::Duration;
 
 const SECOND: u64 = 1_000;
@@ -106,6 +108,73 @@ impl OtelTracer {
                 ),
             )
     }
+    /// Creates a child span of http request span for internal processing,
+    /// e.g. plugin execution and cache lookup
+    #[inline]
+    pub fn new_internal_span(&self, name: &str) -> BoxedSpan {
+        self.tracer
+            .span_builder(name.to_string())
+            .with_kind(SpanKind::Internal)
+            .start_with_context(
+                &self.tracer,
+                &Context::current().with_remote_span_context(
+                    self.http_request_span.span_context().clone(),
+                ),
+            )
+    }
+    /// Creates a child span of the parent span
+    #[inline]
+    pub fn new_child_span(&self, name: &str, parent: &BoxedSpan) -> BoxedSpan {
+        self.tracer
+            .span_builder(name.to_string())
+            .with_kind(SpanKind::Internal)
+            .start_with_context(
+                &self.tracer,
+                &Context::current()
+                    .with_remote_span_context(parent.span_context().clone()),
+            )
+    }
+}
+
+/// Metric groups of the Server-Timing header
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ServerTimingMetrics {
+    /// Upstream connect, tcp, tls and time to first byte
+    pub upstream: bool,
+    /// Cache lookup and lock times
+    pub cache: bool,
+    /// Processing time of each plugin
+    pub plugin: bool,
+    /// Total service time
+    pub total: bool,
+}
+
+impl Default for ServerTimingMetrics {
+    fn default() -> Self {
+        Self {
+            upstream: true,
+            cache: true,
+            plugin: true,
+            total: true,
+        }
+    }
+}
+
+impl ServerTimingMetrics {
+    /// Creates the metric groups from names(upstream, cache, plugin, total),
+    /// all metrics are enabled if the names are empty.
+    pub fn new(names: &[String]) -> Self {
+        if names.is_empty() {
+            return Self::default();
+        }
+        let has = |name: &str| names.iter().any(|item| item == name);
+        Self {
+            upstream: has("upstream"),
+            cache: has("cache"),
+            plugin: has("plugin"),
+            total: has("total"),
+        }
+    }
 }
 
 /// Represents the state of a request/response cycle, tracking various metrics and properties
@@ -141,6 +210,8 @@ pub struct Ctx {
     pub location: String,
     /// Address of the upstream server
     pub upstream_address: String,
+    /// HTTP status code of the upstream response
+    pub upstream_status: Option<StatusCode>,
     /// Client's IP address
     pub client_ip: Option<String>,
     /// Remote connection port
@@ -153,6 +224,9 @@ pub struct Ctx {
     pub server_addr: Option<String>,
     /// Rate limiting guard
     pub guard: Option<Guard>,
+    /// In-flight call of request coalescing, the waiting requests
+    /// are released when it's dropped
+    pub coalesce_call: Option<Arc<dyn Any + Send + Sync>>,
     /// Unique identifier for the request
     pub request_id: Option<String>,
     /// Namespace for cache entries
@@ -161,6 +235,8 @@ pub struct Ctx {
     pub cache_keys: Option<Vec<String>>,
     /// Whether to check cache control headers
     pub check_cache_control: bool,
+    /// Cache status of the response (e.g., "hit", "miss", "stale")
+    pub cache_status: Option<&'static str>,
     /// Time spent looking up cache entries (in milliseconds)
     pub cache_lookup_time: Option<u64>,
     /// Time spent acquiring cache locks (in milliseconds)
@@ -207,10 +283,26 @@ pub struct Ctx {
     /// OpenTelemetry span for upstream requests (only with "full" feature)
     #[cfg(feature = "tracing")]
     pub upstream_span: Option<BoxedSpan>,
+    /// OpenTelemetry span for connecting to upstream (only with "full" feature)
+    #[cfg(feature = "tracing")]
+    pub upstream_connect_span: Option<BoxedSpan>,
+    /// OpenTelemetry span for waiting upstream response (only with "full" feature)
+    #[cfg(feature = "tracing")]
+    pub upstream_response_span: Option<BoxedSpan>,
+    /// OpenTelemetry span for cache lookup (only with "full" feature)
+    #[cfg(feature = "tracing")]
+    pub cache_lookup_span: Option<BoxedSpan>,
+    /// OpenTelemetry span for cache write (only with "full" feature)
+    #[cfg(feature = "tracing")]
+    pub cache_write_span: Option<BoxedSpan>,
     /// Custom variables map for request processing
     pub variables: Option<AHashMap<String, String>>,
     /// Plugin processing times
     pub plugin_processing_times: Option<Vec<(String, u32)>>,
+    /// Name of the plugin that responded to the request directly
+    pub responded_plugin: Option<String>,
+    /// Whether the request is rejected by the limit plugin
+    pub rate_limited: bool,
 }
 
 const ONE_HOUR_MS: u64 = 60 * 60 * 1000;
@@ -508,63 +600,82 @@ impl Ctx {
     /// about the request-response cycle to the client. This implementation includes
     /// various timing metrics like connection time, processing time, and cache operations.
     ///
+    /// Only the metric groups enabled in `metrics` are included.
+    ///
     /// Returns a String containing the formatted Server-Timing header value.
-    pub fn generate_server_timing(&self) -> String {
+    pub fn generate_server_timing(
+        &self,
+        metrics: &ServerTimingMetrics,
+    ) -> String {
         // the response header should be set before get body from upstream,
         // so upstream response time, compression time, etc. are not included
 
         let mut timings = Vec::new();
 
-        let mut upstream_time = 0;
-        let mut upstream_time_set = false;
+        if metrics.upstream {
+            let mut upstream_time = 0;
+            let mut upstream_time_set = false;
 
-        // Add upstream metrics
-        if let Some(time) = self.get_upstream_connect_time() {
-            upstream_time += time;
-            upstream_time_set = true;
-            timings.push(format!("upstream.connect;dur={}", time));
-        }
+            // Add upstream metrics
+            if let Some(time) = self.get_upstream_connect_time() {
+                upstream_time += time;
+                upstream_time_set = true;
+                timings.push(format!("upstream.connect;dur={}", time));
+            }
+            if let Some(time) = self.upstream_tcp_connect_time {
+                timings.push(format!("upstream.tcp;dur={}", time));
+            }
+            if let Some(time) = self.upstream_tls_handshake_time {
+                timings.push(format!("upstream.tls;dur={}", time));
+            }
 
-        if let Some(time) = self.get_upstream_processing_time() {
-            upstream_time += time;
-            upstream_time_set = true;
-            timings.push(format!("upstream.processing;dur={}", time));
-        }
+            if let Some(time) = self.get_upstream_processing_time() {
+                upstream_time += time;
+                upstream_time_set = true;
+                timings.push(format!("upstream.processing;dur={}", time));
+            }
 
-        if upstream_time_set {
-            timings.push(format!("upstream;dur={}", upstream_time));
+            if upstream_time_set {
+                timings.push(format!("upstream;dur={}", upstream_time));
+            }
         }
 
-        let mut cache_time = 0;
-        let mut cache_time_set = false;
-        // Add cache metrics
-        if let Some(time) = self.cache_lookup_time {
-            cache_time += time;
-            cache_time_set = true;
-            timings.push(format!("cache.lookup;dur={}", time));
-        }
+        if metrics.cache {
+            let mut cache_time = 0;
+            let mut cache_time_set = false;
+            // Add cache metrics
+            if let Some(time) = self.cache_lookup_time {
+                cache_time += time;
+                cache_time_set = true;
+                timings.push(format!("cache.lookup;dur={}", time));
+            }
 
-        if let Some(time) = self.cache_lock_time {
-            cache_time += time;
-            cache_time_set = true;
-            timings.push(format!("cache.lock;dur={}", time));
-        }
-        if cache_time_set {
-            timings.push(format!("cache;dur={}", cache_time));
+            if let Some(time) = self.cache_lock_time {
+                cache_time += time;
+                cache_time_set = true;
+                timings.push(format!("cache.lock;dur={}", time));
+            }
+            if cache_time_set {
+                timings.push(format!("cache;dur={}", cache_time));
+            }
         }
 
-        if let Some(times) = self.plugin_processing_times.as_ref() {
-            let mut plugin_time = 0;
-            for (name, time) in times {
-                plugin_time += time;
-                timings.push(format!("plugin.{name};dur={}", time));
+        if metrics.plugin {
+            if let Some(times) = self.plugin_processing_times.as_ref() {
+                let mut plugin_time = 0;
+                for (name, time) in times {
+                    plugin_time += time;
+                    timings.push(format!("plugin.{name};dur={}", time));
+                }
+                timings.push(format!("plugin;dur={}", plugin_time));
             }
-            timings.push(format!("plugin;dur={}", plugin_time));
         }
 
         // Add total service time
-        let service_time = now_ms() - self.created_at;
-        timings.push(format!("total;dur={}", service_time));
+        if metrics.total {
+            let service_time = now_ms() - self.created_at;
+            timings.push(format!("total;dur={}", service_time));
+        }
 
         timings.join(", ")
     }
@@ -927,6 +1038,23 @@ mod tests {
         ctx.add_plugin_processing_time("plugin2", 200);
 
         // total duration sometime changes(it may be 1 or 2), so we just check the prefix
-        assert_eq!(true, ctx.generate_server_timing().starts_with("upstream.connect;dur=1, upstream.processing;dur=2, upstream;dur=3, cache.lookup;dur=6, cache.lock;dur=7, cache;dur=13, plugin.plugin1;dur=100, plugin.plugin2;dur=200, plugin;dur=300, total;dur="));
+        assert_eq!(true, ctx.generate_server_timing(&ServerTimingMetrics::default()).starts_with("upstream.connect;dur=1, upstream.processing;dur=2, upstream;dur=3, cache.lookup;dur=6, cache.lock;dur=7, cache;dur=13, plugin.plugin1;dur=100, plugin.plugin2;dur=200, plugin;dur=300, total;dur="));
+
+        let metrics = ServerTimingMetrics::new(&[
+            "cache".to_string(),
+            "plugin".to_string(),
+        ]);
+        assert_eq!(
+            "cache.lookup;dur=6, cache.lock;dur=7, cache;dur=13, plugin.plugin1;dur=100, plugin.plugin2;dur=200, plugin;dur=300",
+            ctx.generate_server_timing(&metrics)
+        );
+
+        ctx.upstream_tcp_connect_time = Some(1);
+        ctx.upstream_tls_handshake_time = Some(1);
+        let metrics = ServerTimingMetrics::new(&["upstream".to_string()]);
+        assert_eq!(
+            "upstream.connect;dur=1, upstream.tcp;dur=1, upstream.tls;dur=1, upstream.processing;dur=2, upstream;dur=3",
+            ctx.generate_server_timing(&metrics)
+        );
     }
 }
@@ -25,9 +25,11 @@ use pingap_core::HTTP_HEADER_TRANSFER_CHUNKED;
 use pingap_core::{Ctx, Plugin, PluginStep};
 use pingap_plugin::{
     get_hash_key, get_int_conf, get_plugin_factory, get_str_conf, Error,
+    PluginSchema,
 };
 use pingora::http::ResponseHeader;
 use pingora::proxy::Session;
+use serde_json::json;
 use std::sync::Arc;
 use tracing::debug;
 
@@ -250,4 +252,41 @@ fn init() {
     get_plugin_factory().register("image_optim", |params| {
         Ok(Arc::new(ImageOptim::new(params)?))
     });
+    get_plugin_factory().register_schema(
+        "image_optim",
+        PluginSchema::new("Optimize the image of upstream response")
+            .steps(&[PluginStep::UpstreamResponse])
+            .property("output_types", json!({
+                "type": "string",
+                "description": "Output image types separated by comma(e.g. avif, webp)"
+            }))
+            .property("png_quality", json!({
+                "type": "integer",
+                "description": "Quality of png",
+                "default": 90,
+                "minimum": 1,
+                "maximum": 100
+            }))
+            .property("jpeg_quality", json!({
+                "type": "integer",
+                "description": "Quality of jpeg",
+                "default": 80,
+                "minimum": 1,
+                "maximum": 100
+            }))
+            .property("avif_quality", json!({
+                "type": "integer",
+                "description": "Quality of avif",
+                "default": 75,
+                "minimum": 1,
+                "maximum": 100
+            }))
+            .property("avif_speed", json!({
+                "type": "integer",
+                "description": "Speed of avif encoding",
+                "default": 3,
+                "minimum": 1,
+                "maximum": 10
+            })),
+    );
 }
@@ -0,0 +1,541 @@
+// Copyright 2024-2025 Tree xie.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use super::convert::Resources;
+use super::resource::List;
+use super::{Error, KubernetesConf, Result};
+use futures_util::future::select_all;
+use humantime::parse_duration;
+use reqwest::{Certificate, Client, RequestBuilder, Response};
+use serde::de::DeserializeOwned;
+use std::path::Path;
+use std::time::Duration;
+
+/// Directory of the service account which is mounted in the pod
+const SERVICE_ACCOUNT_DIR: &str =
+    "/var/run/secrets/kubernetes.io/serviceaccount";
+
+/// Kind of the watched resource, it's listed from the path of api
+struct ResourceKind {
+    api: &'static str,
+    plural: &'static str,
+    field_selector: Option<&'static str>,
+}
+
+const INGRESSES: ResourceKind = ResourceKind {
+    api: "apis/networking.k8s.io/v1",
+    plural: "ingresses",
+    field_selector: None,
+};
+const HTTP_ROUTES: ResourceKind = ResourceKind {
+    api: "apis/gateway.networking.k8s.io/v1",
+    plural: "httproutes",
+    field_selector: None,
+};
+const SERVICES: ResourceKind = ResourceKind {
+    api: "api/v1",
+    plural: "services",
+    field_selector: None,
+};
+const ENDPOINT_SLICES: ResourceKind = ResourceKind {
+    api: "apis/discovery.k8s.io/v1",
+    plural: "endpointslices",
+    field_selector: None,
+};
+// only the tls secrets are used
+const SECRETS: ResourceKind = ResourceKind {
+    api: "api/v1",
+    plural: "secrets",
+    field_selector: Some("type=kubernetes.io/tls"),
+};
+
+fn new_kubernetes_error(message: String) -> Error {
+    Error::Kubernetes { message }
+}
+
+struct KubernetesClient {
+    client: Client,
+    // Base url of api server, e.g. https://10.0.0.1:6443
+    base_url: String,
+    token: Option<String>,
+    // The token of service account is rotated,
+    // so the file is read for each request
+    token_file: Option<String>,
+}
+
+impl KubernetesClient {
+    async fn request(
+        &self,
+        path: &str,
+        field_selector: Option<&str>,
+    ) -> Result<RequestBuilder> {
+        let mut req = self.client.get(format!("{}{path}", self.base_url));
+        if let Some(field_selector) = field_selector {
+            req = req.query(&[("fieldSelector", field_selector)]);
+        }
+        let token = if let Some(file) = &self.token_file {
+            let token = tokio::fs::read_to_string(file).await.map_err(|e| {
+                Error::Io {
+                    source: e,
+                    file: file.to_string(),
+                }
+            })?;
+            Some(token)
+        } else {
+            self.token.clone()
+        };
+        if let Some(token) = token {
+            req = req.bearer_auth(token.trim());
+        }
+        Ok(req)
+    }
+    async fn send(&self, req: RequestBuilder) -> Result<Response> {
+        let resp = req
+            .send()
+            .await
+            .map_err(|e| new_kubernetes_error(e.to_string()))?;
+        let status = resp.status();
+        if status.is_success() {
+            return Ok(resp);
+        }
+        let body = resp.text().await.unwrap_or_default();
+        Err(new_kubernetes_error(format!("status: {status}, {body}")))
+    }
+    /// List the resources of path, and return the resource version
+    async fn list<T: DeserializeOwned>(
+        &self,
+        path: &str,
+        field_selector: Option<&str>,
+    ) -> Result<(Vec<T>, String)> {
+        let resp = self.send(self.request(path, field_selector).await?).await?;
+        let list = resp
+            .json::<List<T>>()
+            .await
+            .map_err(|e| new_kubernetes_error(e.to_string()))?;
+        Ok((
+            list.items,
+            list.metadata.resource_version.unwrap_or_default(),
+        ))
+    }
+    /// Watch the resources of path from the version, it returns true
+    /// when any event is received, and false if the wait time is reached.
+    async fn watch(
+        &self,
+        path: String,
+        field_selector: Option<&str>,
+        version: &str,
+        wait: Duration,
+    ) -> Result<bool> {
+        let timeout = wait.as_secs().to_string();
+        let req = self
+            .request(&path, field_selector)
+            .await?
+            .query(&[
+                ("watch", "true"),
+                ("allowWatchBookmarks", "false"),
+                ("resourceVersion", version),
+                ("timeoutSeconds", timeout.as_str()),
+            ])
+            // the watch should not be timeout
+            .timeout(wait + Duration::from_secs(30));
+        let mut resp = self.send(req).await?;
+        // each event is a line of json, the error event(e.g. the version
+        // is too old) is also treated as changed, so the resources are
+        // listed again.
+        while let Some(chunk) = resp
+            .chunk()
+            .await
+            .map_err(|e| new_kubernetes_error(e.to_string()))?
+        {
+            if chunk.iter().any(|item| !item.is_ascii_whitespace()) {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+}
+
+/// Controller of kubernetes, it lists the ingresses, httproutes, services,
+/// endpoint slices and tls secrets, then converts them to config.
+pub struct KubernetesController {
+    client: KubernetesClient,
+    // Only the resources of namespace are watched, all namespaces if none
+    namespace: Option<String>,
+    // Ingress class of the ingresses, all ingresses if it's empty
+    ingress_class: String,
+    // Name of gateway, the httproutes are watched only if it's set
+    gateway: Option<String>,
+    // Servers which the generated locations are added to
+    servers: Vec<String>,
+    // Max wait time of watch
+    wait: Duration,
+    // Resource versions of the last sync, the watch starts from them
+    resource_versions: Vec<(&'static ResourceKind, String)>,
+}
+
+impl KubernetesController {
+    /// Create a new kubernetes controller.
+    /// Url format: https://host:port?namespace=default&ingress_class=pingap&gateway=pingap&server=web&token=**&timeout=10s&wait=5m
+    /// The api server and service account of pod are used if the url
+    /// is empty(or only query).
+    pub fn new(value: &str) -> Result<Self> {
+        let (base_url, query) = value.split_once('?').unwrap_or((value, ""));
+        let mut base_url = base_url.trim_end_matches('/').to_string();
+        let mut token = None;
+        let mut token_file = None;
+        let mut ca_file = None;
+        if base_url.is_empty() {
+            let host =
+                std::env::var("KUBERNETES_SERVICE_HOST").unwrap_or_default();
+            if host.is_empty() {
+                return Err(Error::Invalid {
+                    message: "kubernetes api server should not be empty"
+                        .to_string(),
+                });
+            }
+            let port = std::env::var("KUBERNETES_SERVICE_PORT")
+                .unwrap_or("443".to_string());
+            base_url = if host.contains(':') {
+                format!("https://[{host}]:{port}")
+            } else {
+                format!("https://{host}:{port}")
+            };
+            for (name, file) in
+                [("token", &mut token_file), ("ca.crt", &mut ca_file)]
+            {
+                let path = format!("{SERVICE_ACCOUNT_DIR}/{name}");
+                if Path::new(&path).exists() {
+                    *file = Some(path);
+                }
+            }
+        }
+
+        let mut namespace = None;
+        let mut ingress_class = "pingap".to_string();
+        let mut gateway = None;
+        let mut servers = vec![];
+        let mut insecure = false;
+        let mut timeout = Duration::from_secs(10);
+        let mut wait = Duration::from_secs(5 * 60);
+        for (key, value) in pingap_core::convert_query_map(query) {
+            match key.as_str() {
+                "namespace" => namespace = Some(value),
+                "ingress_class" => ingress_class = value,
+                "gateway" => gateway = Some(value),
+                "server" => {
+                    servers = value
+                        .split(',')
+                        .map(|item| item.trim().to_string())
+                        .filter(|item| !item.is_empty())
+                        .collect();
+                },
+                "token" => token = Some(value),
+                "token_file" => token_file = Some(value),
+                "ca_file" => ca_file = Some(value),
+                "insecure" => insecure = true,
+                "timeout" => {
+                    if let Ok(d) = parse_duration(&value) {
+                        timeout = d;
+                    }
+                },
+                "wait" => {
+                    if let Ok(d) = parse_duration(&value) {
+                        wait = d;
+                    }
+                },
+                _ => {},
+            }
+        }
+
+        let mut builder = Client::builder().timeout(timeout);
+        if let Some(file) = &ca_file {
+            let data = std::fs::read(file).map_err(|e| Error::Io {
+                source: e,
+                file: file.to_string(),
+            })?;
+            let cert = Certificate::from_pem(&data)
+                .map_err(|e| new_kubernetes_error(e.to_string()))?;
+            builder = builder.add_root_certificate(cert);
+        }
+        if insecure {
+            builder = builder.danger_accept_invalid_certs(true);
+        }
+        let client = builder
+            .build()
+            .map_err(|e| new_kubernetes_error(e.to_string()))?;
+        Ok(Self {
+            client: KubernetesClient {
+                client,
+                base_url,
+                token,
+                token_file,
+            },
+            namespace,
+            ingress_class,
+            gateway,
+            servers,
+            wait,
+            resource_versions: vec![],
+        })
+    }
+    fn get_path(&self, kind: &ResourceKind) -> String {
+        if let Some(namespace) = &self.namespace {
+            format!("/{}/namespaces/{namespace}/{}", kind.api, kind.plural)
+        } else {
+            format!("/{}/{}", kind.api, kind.plural)
+        }
+    }
+    async fn list<T: DeserializeOwned>(
+        &self,
+        kind: &'static ResourceKind,
+        versions: &mut Vec<(&'static ResourceKind, String)>,
+    ) -> Result<Vec<T>> {
+        let (items, version) = self
+            .client
+            .list(&self.get_path(kind), kind.field_selector)
+            .await?;
+        versions.push((kind, version));
+        Ok(items)
+    }
+    /// Lists all the resources and converts them to config
+    pub async fn sync(&mut self) -> Result<KubernetesConf> {
+        let mut versions = vec![];
+        let mut resources = Resources {
+            ingresses: self.list(&INGRESSES, &mut versions).await?,
+            services: self.list(&SERVICES, &mut versions).await?,
+            endpoint_slices: self.list(&ENDPOINT_SLICES, &mut versions).await?,
+            secrets: self.list(&SECRETS, &mut versions).await?,
+            ..Default::default()
+        };
+        if self.gateway.is_some() {
+            resources.http_routes =
+                self.list(&HTTP_ROUTES, &mut versions).await?;
+        }
+        let mut conf =
+            resources.convert(&self.ingress_class, self.gateway.as_deref());
+        conf.servers.clone_from(&self.servers);
+        self.resource_versions = versions;
+        Ok(conf)
+    }
+    /// Watches the resources from the versions of last sync,
+    /// it returns true if any resource is changed(or not synced),
+    /// and false if the wait time is reached.
+    pub async fn watch(&self) -> Result<bool> {
+        if self.resource_versions.is_empty() {
+            return Ok(true);
+        }
+        let watches = self.resource_versions.iter().map(|(kind, version)| {
+            Box::pin(self.client.watch(
+                self.get_path(kind),
+                kind.field_selector,
+                version,
+                self.wait,
+            ))
+        });
+        let (result, _, _) = select_all(watches).await;
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use serde_json::json;
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
+
+    /// Starts a http stand-in of kubernetes api server, it lists the
+    /// resources of default namespace, and only the watch of ingresses
+    /// receives an event.
+    fn start_kubernetes_server() -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                std::thread::spawn(move || {
+                    let mut reader =
+                        BufReader::new(stream.try_clone().unwrap());
+                    let mut request_line = String::new();
+                    reader.read_line(&mut request_line).unwrap();
+                    let mut token = String::new();
+                    loop {
+                        let mut line = String::new();
+                        reader.read_line(&mut line).unwrap();
+                        let line = line.trim();
+                        if line.is_empty() {
+                            break;
+                        }
+                        let (name, value) = line.split_once(':').unwrap();
+                        if name.to_lowercase() == "authorization" {
+                            token = value.trim().to_string();
+                        }
+                    }
+                    let arr: Vec<&str> = request_line.split(' ').collect();
+                    let (path, query) =
+                        arr[1].split_once('?').unwrap_or((arr[1], ""));
+                    let plural = path
+                        .strip_prefix("/api/v1/namespaces/default/")
+                        .or(path.strip_prefix(
+                            "/apis/networking.k8s.io/v1/namespaces/default/",
+                        ))
+                        .or(path.strip_prefix(
+                            "/apis/discovery.k8s.io/v1/namespaces/default/",
+                        ))
+                        .or(path.strip_prefix(
+                            "/apis/gateway.networking.k8s.io/v1/namespaces/default/",
+                        ))
+                        .unwrap_or_default();
+                    let items = match plural {
+                        "ingresses" => json!([{
+                            "metadata": { "name": "web", "namespace": "default" },
+                            "spec": {
+                                "ingressClassName": "pingap",
+                                "rules": [{
+                                    "host": "web.example.com",
+                                    "http": {
+                                        "paths": [{
+                                            "path": "/",
+                                            "pathType": "Prefix",
+                                            "backend": {
+                                                "service": {
+                                                    "name": "web",
+                                                    "port": { "number": 80 }
+                                                }
+                                            }
+                                        }]
+                                    }
+                                }]
+                            }
+                        }]),
+                        "httproutes" => json!([{
+                            "metadata": { "name": "web", "namespace": "default" },
+                            "spec": {
+                                "parentRefs": [{ "name": "pingap" }],
+                                "rules": [{
+                                    "backendRefs": [{ "name": "web", "port": 80 }]
+                                }]
+                            }
+                        }]),
+                        "services" => json!([{
+                            "metadata": { "name": "web", "namespace": "default" },
+                            "spec": { "ports": [{ "port": 80 }] }
+                        }]),
+                        "endpointslices" => json!([{
+                            "metadata": {
+                                "name": "web-abc",
+                                "namespace": "default",
+                                "labels": {
+                                    "kubernetes.io/service-name": "web"
+                                }
+                            },
+                            "addressType": "IPv4",
+                            "ports": [{ "port": 8080 }],
+                            "endpoints": [{ "addresses": ["10.0.0.1"] }]
+                        }]),
+                        "secrets" if query.contains("fieldSelector") => {
+                            json!([])
+                        },
+                        _ => json!(null),
+                    };
+                    let (status, body) = if token != "Bearer pingap" {
+                        (401, "Unauthorized".to_string())
+                    } else if items.is_null() {
+                        (404, "Not Found".to_string())
+                    } else if query.contains("watch=true") {
+                        if plural != "ingresses" {
+                            std::thread::sleep(Duration::from_secs(3));
+                            (200, "".to_string())
+                        } else if query.contains("resourceVersion=10") {
+                            (
+                                200,
+                                r#"{"type":"MODIFIED","object":{}}"#
+                                    .to_string()
+                                    + "\n",
+                            )
+                        } else {
+                            (200, "".to_string())
+                        }
+                    } else {
+                        let list = json!({
+                            "metadata": { "resourceVersion": "10" },
+                            "items": items
+                        });
+                        (200, list.to_string())
+                    };
+                    let header = format!(
+                        "HTTP/1.1 {status} OK\r\ncontent-length: {}\r\nconnection: close\r\n\r\n",
+                        body.len()
+                    );
+                    let _ = stream.write_all(header.as_bytes());
+                    let _ = stream.write_all(body.as_bytes());
+                });
+            }
+        });
+        addr.to_string()
+    }
+
+    #[tokio::test]
+    async fn test_kubernetes_controller() {
+        let addr = start_kubernetes_server();
+        let mut controller = KubernetesController::new(&format!(
+            "http://{addr}?namespace=default&gateway=pingap&server=web&token=pingap"
+        ))
+        .unwrap();
+        // not synced
+        assert_eq!(true, controller.watch().await.unwrap());
+
+        let conf = controller.sync().await.unwrap();
+        assert_eq!(vec!["web".to_string()], conf.servers);
+        let mut upstreams: Vec<_> = conf.upstreams.keys().cloned().collect();
+        upstreams.sort();
+        assert_eq!(
+            vec![
+                "k8s.route.default.web.0".to_string(),
+                "k8s.svc.default.web.80".to_string()
+            ],
+            upstreams
+        );
+        assert_eq!(
+            vec!["10.0.0.1:8080".to_string()],
+            conf.upstreams["k8s.svc.default.web.80"].addrs
+        );
+        let mut locations: Vec<_> = conf.locations.keys().cloned().collect();
+        locations.sort();
+        assert_eq!(
+            vec![
+                "k8s.ingress.default.web.0.0".to_string(),
+                "k8s.route.default.web.0.0".to_string()
+            ],
+            locations
+        );
+        assert_eq!(5, controller.resource_versions.len());
+        // the event of ingresses is received
+        assert_eq!(true, controller.watch().await.unwrap());
+
+        let mut controller =
+            KubernetesController::new(&format!("http://{addr}?token=abc"))
+                .unwrap();
+        assert_eq!(
+            true,
+            controller
+                .sync()
+                .await
+                .unwrap_err()
+                .to_string()
+                .contains("401")
+        );
+    }
+}
@@ -0,0 +1,786 @@
+// Copyright 2024-2025 Tree xie.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use super::resource::{
+    EndpointSlice, HttpRoute, Ingress, IngressBackend, Secret, Service,
+    SERVICE_NAME_LABEL,
+};
+use super::{KubernetesConf, LOG_CATEGORY};
+use pingap_config::{CertificateConf, LocationConf, UpstreamConf};
+use std::collections::BTreeSet;
+use tracing::warn;
+
+/// Annotation of ingress class, it's used by the old ingress resources
+const INGRESS_CLASS_ANNOTATION: &str = "kubernetes.io/ingress.class";
+/// Annotation of ingress and httproute, the plugins(comma separated)
+/// are added to the generated locations
+pub const PLUGINS_ANNOTATION: &str = "pingap.io/plugins";
+
+/// Port of service, it's referenced by number or name
+enum BackendPort {
+    Number(i32),
+    Name(String),
+}
+
+/// The kubernetes resources which are converted to config
+#[derive(Debug, Default)]
+pub(crate) struct Resources {
+    pub ingresses: Vec<Ingress>,
+    pub http_routes: Vec<HttpRoute>,
+    pub services: Vec<Service>,
+    pub endpoint_slices: Vec<EndpointSlice>,
+    pub secrets: Vec<Secret>,
+}
+
+/// Converts the host of kubernetes to the host of location,
+/// the wildcard host is converted to regex host.
+fn convert_host(host: &str) -> String {
+    if let Some(domain) = host.strip_prefix("*.") {
+        return format!("~^[^.]+\\.{}$", regex::escape(domain));
+    }
+    host.to_string()
+}
+
+/// Sets the prefix path of kubernetes to the location. The prefix matches
+/// by path element, e.g. `/foo` matches `/foo` and `/foo/bar` but not
+/// `/foobar`, so it's converted to regex path. The weight of location is
+/// the same as the prefix path, then the longest prefix is matched first.
+fn set_prefix_path(location: &mut LocationConf, prefix: &str) {
+    let prefix = prefix.trim_end_matches('/');
+    if prefix.is_empty() {
+        location.path = Some("/".to_string());
+        return;
+    }
+    location.path = Some(prefix.to_string());
+    location.weight = Some(location.get_weight());
+    location.path = Some(format!("~^{}(?:/|$)", regex::escape(prefix)));
+}
+
+fn get_plugins(
+    annotations: &std::collections::HashMap<String, String>,
+) -> Option<Vec<String>> {
+    let plugins: Vec<String> = annotations
+        .get(PLUGINS_ANNOTATION)?
+        .split(',')
+        .map(|item| item.trim().to_string())
+        .filter(|item| !item.is_empty())
+        .collect();
+    if plugins.is_empty() {
+        return None;
+    }
+    Some(plugins)
+}
+
+impl Resources {
+    /// Returns the ready addresses of service port, they are fetched
+    /// from the endpoint slices of service.
+    /// The IPv6 endpoints are skipped, the address of upstream is
+    /// in "host:port" format.
+    fn get_service_addrs(
+        &self,
+        namespace: &str,
+        name: &str,
+        port: &BackendPort,
+    ) -> Vec<String> {
+        let Some(service) = self.services.iter().find(|item| {
+            item.metadata.namespace == namespace && item.metadata.name == name
+        }) else {
+            return vec![];
+        };
+        if service.spec.category.as_deref() == Some("ExternalName") {
+            return match (&service.spec.external_name, port) {
+                (Some(host), BackendPort::Number(port)) => {
+                    vec![format!("{host}:{port}")]
+                },
+                _ => vec![],
+            };
+        }
+        let Some(service_port) =
+            service.spec.ports.iter().find(|item| match port {
+                BackendPort::Number(port) => item.port == *port,
+                BackendPort::Name(port) => {
+                    item.name.as_deref() == Some(port.as_str())
+                },
+            })
+        else {
+            return vec![];
+        };
+        let port_name = service_port.name.clone().unwrap_or_default();
+        let mut addrs = vec![];
+        for slice in self.endpoint_slices.iter() {
+            if slice.metadata.namespace != namespace
+                || slice.metadata.labels.get(SERVICE_NAME_LABEL)
+                    != Some(&name.to_string())
+                || slice.address_type == "IPv6"
+            {
+                continue;
+            }
+            // the port name of endpoint slice is the same as service
+            let Some(port) = slice
+                .ports
+                .iter()
+                .find(|item| item.name.clone().unwrap_or_default() == port_name)
+                .and_then(|item| item.port)
+            else {
+                continue;
+            };
+            for endpoint in slice.endpoints.iter() {
+                if endpoint.conditions.ready == Some(false) {
+                    continue;
+                }
+                for addr in endpoint.addresses.iter() {
+                    addrs.push(format!("{addr}:{port}"));
+                }
+            }
+        }
+        addrs.sort();
+        addrs.dedup();
+        addrs
+    }
+    /// Adds the upstream of ingress backend and returns its name,
+    /// it's None if the backend has no ready address. The location of
+    /// backend is kept without upstream, so its requests get 503.
+    fn add_backend_upstream(
+        &self,
+        conf: &mut KubernetesConf,
+        namespace: &str,
+        backend: &IngressBackend,
+    ) -> Option<String> {
+        let service = backend.service.as_ref()?;
+        let (port, port_value) =
+            match (&service.port.number, &service.port.name) {
+                (Some(number), _) => {
+                    (BackendPort::Number(*number), number.to_string())
+                },
+                (_, Some(name)) => {
+                    (BackendPort::Name(name.clone()), name.clone())
+                },
+                _ => return None,
+            };
+        let name = format!("k8s.svc.{namespace}.{}.{port_value}", service.name);
+        if conf.upstreams.contains_key(&name) {
+            return Some(name);
+        }
+        let addrs = self.get_service_addrs(namespace, &service.name, &port);
+        if addrs.is_empty() {
+            warn!(
+                category = LOG_CATEGORY,
+                namespace,
+                service = service.name,
+                port = port_value,
+                "service has no ready endpoint"
+            );
+            return None;
+        }
+        let upstream = UpstreamConf {
+            addrs,
+            remark: Some(format!("service {namespace}/{}", service.name)),
+            ..Default::default()
+        };
+        if let Err(e) = upstream.validate(&name) {
+            warn!(category = LOG_CATEGORY, error = %e, "upstream is invalid");
+            return None;
+        }
+        conf.upstreams.insert(name.clone(), upstream);
+        Some(name)
+    }
+    /// Adds the certificate of tls secret, the domains are merged
+    /// if the secret is referenced by more than one ingress.
+    fn add_certificate(
+        &self,
+        conf: &mut KubernetesConf,
+        namespace: &str,
+        secret_name: &str,
+        domains: &[String],
+    ) {
+        let Some(secret) = self.secrets.iter().find(|item| {
+            item.metadata.namespace == namespace
+                && item.metadata.name == secret_name
+        }) else {
+            warn!(
+                category = LOG_CATEGORY,
+                namespace, secret_name, "tls secret is not found"
+            );
+            return;
+        };
+        let (Some(tls_cert), Some(tls_key)) =
+            (secret.data.get("tls.crt"), secret.data.get("tls.key"))
+        else {
+            return;
+        };
+        let name = format!("k8s.secret.{namespace}.{secret_name}");
+        let mut all_domains: BTreeSet<String> =
+            domains.iter().cloned().collect();
+        if let Some(domains) = conf
+            .certificates
+            .get(&name)
+            .and_then(|item| item.domains.clone())
+        {
+            all_domains.extend(domains.split(',').map(|item| item.to_string()));
+        }
+        let cert = CertificateConf {
+            domains: Some(
+                all_domains.into_iter().collect::<Vec<_>>().join(","),
+            ),
+            // the data of secret is base64 encoded
+            tls_cert: Some(tls_cert.clone()),
+            tls_key: Some(tls_key.clone()),
+            remark: Some(format!("secret {namespace}/{secret_name}")),
+            ..Default::default()
+        };
+        if let Err(e) = cert.validate() {
+            warn!(category = LOG_CATEGORY, error = %e, "certificate is invalid");
+            return;
+        }
+        conf.certificates.insert(name, cert);
+    }
+    /// Converts the ingresses of class to config,
+    /// the ingress class is not checked if it's empty.
+    fn convert_ingresses(&self, conf: &mut KubernetesConf, class: &str) {
+        for ingress in self.ingresses.iter() {
+            let meta = &ingress.metadata;
+            let ingress_class = ingress
+                .spec
+                .ingress_class_name
+                .as_ref()
+                .or(meta.annotations.get(INGRESS_CLASS_ANNOTATION));
+            if !class.is_empty()
+                && ingress_class.map(|v| v.as_str()) != Some(class)
+            {
+                continue;
+            }
+            let namespace = &meta.namespace;
+            let plugins = get_plugins(&meta.annotations);
+            let remark = format!("ingress {namespace}/{}", meta.name);
+            let mut hosts = vec![];
+            for (i, rule) in ingress.spec.rules.iter().enumerate() {
+                let host = rule.host.clone().unwrap_or_default();
+                if !host.is_empty() {
+                    hosts.push(host.clone());
+                }
+                let Some(http) = &rule.http else {
+                    continue;
+                };
+                for (j, item) in http.paths.iter().enumerate() {
+                    let upstream = self.add_backend_upstream(
+                        conf,
+                        namespace,
+                        &item.backend,
+                    );
+                    let path = item.path.clone().unwrap_or("/".to_string());
+                    let mut location = LocationConf {
+                        upstream,
+                        host: (!host.is_empty()).then(|| convert_host(&host)),
+                        plugins: plugins.clone(),
+                        remark: Some(remark.clone()),
+                        ..Default::default()
+                    };
+                    // ImplementationSpecific is matched as string prefix
+                    match item.path_type.as_deref() {
+                        Some("Exact") => {
+                            location.path = Some(format!("={path}"))
+                        },
+                        Some("Prefix") => set_prefix_path(&mut location, &path),
+                        _ => location.path = Some(path),
+                    }
+                    conf.locations.insert(
+                        format!(
+                            "k8s.ingress.{namespace}.{}.{i}.{j}",
+                            meta.name
+                        ),
+                        location,
+                    );
+                }
+            }
+            if let Some(backend) = &ingress.spec.default_backend {
+                let upstream =
+                    self.add_backend_upstream(conf, namespace, backend);
+                conf.locations.insert(
+                    format!("k8s.ingress.{namespace}.{}.default", meta.name),
+                    LocationConf {
+                        upstream,
+                        path: Some("/".to_string()),
+                        plugins: plugins.clone(),
+                        remark: Some(remark.clone()),
+                        ..Default::default()
+                    },
+                );
+            }
+            for tls in ingress.spec.tls.iter() {
+                let Some(secret_name) = &tls.secret_name else {
+                    continue;
+                };
+                // the hosts of rules are used if the hosts of tls are empty
+                let domains = if tls.hosts.is_empty() {
+                    &hosts
+                } else {
+                    &tls.hosts
+                };
+                self.add_certificate(conf, namespace, secret_name, domains);
+            }
+        }
+    }
+    /// Converts the httproutes attached to the gateway to config.
+    /// Only the path of match is supported, and the backends of each rule
+    /// are merged into one upstream with their weights.
+    fn convert_http_routes(&self, conf: &mut KubernetesConf, gateway: &str) {
+        for route in self.http_routes.iter() {
+            let meta = &route.metadata;
+            if !route.spec.parent_refs.iter().any(|item| {
+                item.name == gateway
+                    && item.kind.as_deref().unwrap_or("Gateway") == "Gateway"
+            }) {
+                continue;
+            }
+            let namespace = &meta.namespace;
+            let plugins = get_plugins(&meta.annotations);
+            let remark = format!("httproute {namespace}/{}", meta.name);
+            let host = route
+                .spec
+                .hostnames
+                .iter()
+                .map(|item| convert_host(item))
+                .collect::<Vec<_>>()
+                .join(",");
+            for (i, rule) in route.spec.rules.iter().enumerate() {
+                let mut addrs = vec![];
+                for backend in rule.backend_refs.iter() {
+                    let weight = backend.weight.unwrap_or(1);
+                    if weight <= 0
+                        || backend.kind.as_deref().unwrap_or("Service")
+                            != "Service"
+                    {
+                        continue;
+                    }
+                    let Some(port) = backend.port else {
+                        continue;
+                    };
+                    let backend_namespace =
+                        backend.namespace.as_ref().unwrap_or(namespace);
+                    for addr in self.get_service_addrs(
+                        backend_namespace,
+                        &backend.name,
+                        &BackendPort::Number(port),
+                    ) {
+                        if weight == 1 {
+                            addrs.push(addr);
+                        } else {
+                            addrs.push(format!("{addr} {weight}"));
+                        }
+                    }
+                }
+                // the locations of rule are kept without upstream, so
+                // their requests get 503
+                let upstream = if addrs.is_empty() {
+                    warn!(
+                        category = LOG_CATEGORY,
+                        route = remark,
+                        rule = i,
+                        "backends of rule have no ready endpoint"
+                    );
+                    None
+                } else {
+                    let upstream =
+                        format!("k8s.route.{namespace}.{}.{i}", meta.name);
+                    conf.upstreams.insert(
+                        upstream.clone(),
+                        UpstreamConf {
+                            addrs,
+                            remark: Some(remark.clone()),
+                            ..Default::default()
+                        },
+                    );
+                    Some(upstream)
+                };
+                let mut paths = vec![];
+                for item in rule.matches.iter() {
+                    let Some(path) = &item.path else {
+                        continue;
+                    };
+                    let value = path.value.clone().unwrap_or("/".to_string());
+                    let value = match path.category.as_deref() {
+                        Some("Exact") => (format!("={value}"), false),
+                        Some("RegularExpression") => {
+                            if let Err(e) = regex::Regex::new(&value) {
+                                warn!(
+                                    category = LOG_CATEGORY,
+                                    error = %e,
+                                    route = remark,
+                                    "path regex is invalid"
+                                );
+                                continue;
+                            }
+                            (format!("~{value}"), false)
+                        },
+                        // PathPrefix is the default type
+                        _ => (value, true),
+                    };
+                    paths.push(value);
+                }
+                if paths.is_empty() {
+                    paths.push(("/".to_string(), true));
+                }
+                for (j, (path, prefix)) in paths.into_iter().enumerate() {
+                    let mut location = LocationConf {
+                        upstream: upstream.clone(),
+                        host: (!host.is_empty()).then(|| host.clone()),
+                        plugins: plugins.clone(),
+                        remark: Some(remark.clone()),
+                        ..Default::default()
+                    };
+                    if prefix {
+                        set_prefix_path(&mut location, &path);
+                    } else {
+                        location.path = Some(path);
+                    }
+                    conf.locations.insert(
+                        format!("k8s.route.{namespace}.{}.{i}.{j}", meta.name),
+                        location,
+                    );
+                }
+            }
+        }
+    }
+    /// Converts the resources to the upstreams, locations and
+    /// certificates of config, the httproutes are converted only if
+    /// the gateway is set.
+    pub fn convert(
+        &self,
+        ingress_class: &str,
+        gateway: Option<&str>,
+    ) -> KubernetesConf {
+        let mut conf = KubernetesConf::default();
+        self.convert_ingresses(&mut conf, ingress_class);
+        if let Some(gateway) = gateway {
+            self.convert_http_routes(&mut conf, gateway);
+        }
+        conf
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use serde_json::json;
+
+    fn new_resources() -> Resources {
+        let services = json!([
+            {
+                "metadata": { "name": "web", "namespace": "default" },
+                "spec": {
+                    "ports": [
+                        { "name": "http", "port": 80 },
+                        { "name": "metrics", "port": 9000 }
+                    ]
+                }
+            },
+            {
+                "metadata": { "name": "api", "namespace": "default" },
+                "spec": { "ports": [{ "port": 8080 }] }
+            },
+            {
+                "metadata": { "name": "docs", "namespace": "default" },
+                "spec": {
+                    "type": "ExternalName",
+                    "externalName": "docs.example.com"
+                }
+            }
+        ]);
+        let endpoint_slices = json!([
+            {
+                "metadata": {
+                    "name": "web-abc",
+                    "namespace": "default",
+                    "labels": { "kubernetes.io/service-name": "web" }
+                },
+                "addressType": "IPv4",
+                "ports": [
+                    { "name": "http", "port": 8080 },
+                    { "name": "metrics", "port": 9090 }
+                ],
+                "endpoints": [
+                    {
+                        "addresses": ["10.0.0.2"],
+                        "conditions": { "ready": true }
+                    },
+                    {
+                        "addresses": ["10.0.0.1"],
+                        "conditions": { "ready": true }
+                    },
+                    {
+                        "addresses": ["10.0.0.3"],
+                        "conditions": { "ready": false }
+                    }
+                ]
+            },
+            {
+                "metadata": {
+                    "name": "web-v6",
+                    "namespace": "default",
+                    "labels": { "kubernetes.io/service-name": "web" }
+                },
+                "addressType": "IPv6",
+                "ports": [{ "name": "http", "port": 8080 }],
+                "endpoints": [{ "addresses": ["fd00::1"] }]
+            },
+            {
+                "metadata": {
+                    "name": "api-abc",
+                    "namespace": "default",
+                    "labels": { "kubernetes.io/service-name": "api" }
+                },
+                "addressType": "IPv4",
+                "ports": [{ "port": 3000 }],
+                "endpoints": [{ "addresses": ["10.0.1.1"] }]
+            }
+        ]);
+        let ingresses = json!([
+            {
+                "metadata": {
+                    "name": "web",
+                    "namespace": "default",
+                    "annotations": { "pingap.io/plugins": "pingap:ping, " }
+                },
+                "spec": {
+                    "ingressClassName": "pingap",
+                    "tls": [{ "secretName": "web-tls" }],
+                    "rules": [
+                        {
+                            "host": "web.example.com",
+                            "http": {
+                                "paths": [
+                                    {
+                                        "path": "/",
+                                        "pathType": "Prefix",
+                                        "backend": {
+                                            "service": {
+                                                "name": "web",
+                                                "port": { "name": "http" }
+                                            }
+                                        }
+                                    },
+                                    {
+                                        "path": "/api",
+                                        "pathType": "Exact",
+                                        "backend": {
+                                            "service": {
+                                                "name": "api",
+                                                "port": { "number": 8080 }
+                                            }
+                                        }
+                                    },
+                                    {
+                                        "path": "/stopped",
+                                        "pathType": "Prefix",
+                                        "backend": {
+                                            "service": {
+                                                "name": "stopped",
+                                                "port": { "number": 80 }
+                                            }
+                                        }
+                                    }
+                                ]
+                            }
+                        }
+                    ]
+                }
+            },
+            {
+                "metadata": {
+                    "name": "other",
+                    "namespace": "default",
+                    "annotations": { "kubernetes.io/ingress.class": "nginx" }
+                },
+                "spec": {
+                    "defaultBackend": {
+                        "service": { "name": "web", "port": { "number": 80 } }
+                    }
+                }
+            }
+        ]);
+        let http_routes = json!([
+            {
+                "metadata": { "name": "split", "namespace": "default" },
+                "spec": {
+                    "parentRefs": [{ "name": "pingap" }],
+                    "hostnames": ["*.example.com"],
+                    "rules": [
+                        {
+                            "matches": [
+                                { "path": { "type": "PathPrefix", "value": "/v1" } },
+                                { "path": { "type": "RegularExpression", "value": "^/v[2-3]/" } }
+                            ],
+                            "backendRefs": [
+                                { "name": "web", "port": 80, "weight": 3 },
+                                { "name": "api", "port": 8080 },
+                                { "name": "docs", "port": 443, "weight": 0 }
+                            ]
+                        }
+                    ]
+                }
+            },
+            {
+                "metadata": { "name": "docs", "namespace": "default" },
+                "spec": {
+                    "parentRefs": [{ "name": "other" }],
+                    "rules": [
+                        { "backendRefs": [{ "name": "docs", "port": 443 }] }
+                    ]
+                }
+            }
+        ]);
+
+        Resources {
+            ingresses: serde_json::from_value(ingresses).unwrap(),
+            http_routes: serde_json::from_value(http_routes).unwrap(),
+            services: serde_json::from_value(services).unwrap(),
+            endpoint_slices: serde_json::from_value(endpoint_slices).unwrap(),
+            secrets: vec![],
+        }
+    }
+
+    #[test]
+    fn test_convert_host() {
+        assert_eq!("example.com", convert_host("example.com"));
+        assert_eq!(r#"~^[^.]+\.example\.com$"#, convert_host("*.example.com"));
+    }
+
+    #[test]
+    fn test_get_service_addrs() {
+        let resources = new_resources();
+        assert_eq!(
+            vec!["10.0.0.1:8080".to_string(), "10.0.0.2:8080".to_string()],
+            resources.get_service_addrs(
+                "default",
+                "web",
+                &BackendPort::Number(80)
+            )
+        );
+        assert_eq!(
+            vec!["10.0.0.1:9090".to_string(), "10.0.0.2:9090".to_string()],
+            resources.get_service_addrs(
+                "default",
+                "web",
+                &BackendPort::Name("metrics".to_string())
+            )
+        );
+        assert_eq!(
+            vec!["10.0.1.1:3000".to_string()],
+            resources.get_service_addrs(
+                "default",
+                "api",
+                &BackendPort::Number(8080)
+            )
+        );
+        assert_eq!(
+            vec!["docs.example.com:443".to_string()],
+            resources.get_service_addrs(
+                "default",
+                "docs",
+                &BackendPort::Number(443)
+            )
+        );
+        assert_eq!(
+            true,
+            resources
+                .get_service_addrs("test", "web", &BackendPort::Number(80))
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_convert_ingress() {
+        let resources = new_resources();
+        let conf = resources.convert("pingap", None);
+
+        let mut upstreams: Vec<_> = conf.upstreams.keys().cloned().collect();
+        upstreams.sort();
+        assert_eq!(
+            vec![
+                "k8s.svc.default.api.8080".to_string(),
+                "k8s.svc.default.web.http".to_string()
+            ],
+            upstreams
+        );
+        let mut locations: Vec<_> = conf.locations.keys().cloned().collect();
+        locations.sort();
+        assert_eq!(
+            vec![
+                "k8s.ingress.default.web.0.0".to_string(),
+                "k8s.ingress.default.web.0.1".to_string(),
+                "k8s.ingress.default.web.0.2".to_string()
+            ],
+            locations
+        );
+        let location = &conf.locations["k8s.ingress.default.web.0.0"];
+        assert_eq!(Some("/".to_string()), location.path);
+        assert_eq!(None, location.weight);
+        let location = &conf.locations["k8s.ingress.default.web.0.1"];
+        assert_eq!(Some("=/api".to_string()), location.path);
+        assert_eq!(Some("web.example.com".to_string()), location.host);
+        assert_eq!(
+            Some("k8s.svc.default.api.8080".to_string()),
+            location.upstream
+        );
+        assert_eq!(Some(vec!["pingap:ping".to_string()]), location.plugins);
+        // the location of backend without endpoint has no upstream(503)
+        let location = &conf.locations["k8s.ingress.default.web.0.2"];
+        assert_eq!(None, location.upstream);
+        assert_eq!(Some("~^/stopped(?:/|$)".to_string()), location.path);
+        // the weight of prefix path with host
+        assert_eq!(Some(512 + 8 + 128), location.weight);
+        // the secret is not found
+        assert_eq!(true, conf.certificates.is_empty());
+
+        // all ingress classes
+        let conf = resources.convert("", None);
+        let location = &conf.locations["k8s.ingress.default.other.default"];
+        assert_eq!(Some("/".to_string()), location.path);
+        assert_eq!(None, location.host);
+        assert_eq!(
+            Some("k8s.svc.default.web.80".to_string()),
+            location.upstream
+        );
+    }
+
+    #[test]
+    fn test_convert_http_route() {
+        let resources = new_resources();
+        let conf = resources.convert("pingap", Some("pingap"));
+        let upstream = &conf.upstreams["k8s.route.default.split.0"];
+        assert_eq!(
+            vec![
+                "10.0.0.1:8080 3".to_string(),
+                "10.0.0.2:8080 3".to_string(),
+                "10.0.1.1:3000".to_string()
+            ],
+            upstream.addrs
+        );
+        let location = &conf.locations["k8s.route.default.split.0.0"];
+        assert_eq!(Some("~^/v1(?:/|$)".to_string()), location.path);
+        assert_eq!(Some(512 + 3 + 22), location.weight);
+        assert_eq!(
+            Some(r#"~^[^.]+\.example\.com$"#.to_string()),
+            location.host
+        );
+        let location = &conf.locations["k8s.route.default.split.0.1"];
+        assert_eq!(Some("~^/v[2-3]/".to_string()), location.path);
+        // the route of other gateway is skipped
+        assert_eq!(
+            false,
+            conf.upstreams.contains_key("k8s.route.default.docs.0")
+        );
+    }
+}
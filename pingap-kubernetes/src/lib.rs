@@ -0,0 +1,285 @@
+// Copyright 2024-2025 Tree xie.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use arc_swap::ArcSwap;
+use once_cell::sync::Lazy;
+use pingap_config::{
+    validate_server_locations, CertificateConf, LocationConf, PingapConf,
+    UpstreamConf,
+};
+use snafu::Snafu;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tracing::warn;
+
+mod controller;
+mod convert;
+mod resource;
+
+pub static LOG_CATEGORY: &str = "kubernetes";
+
+#[derive(Debug, Snafu)]
+pub enum Error {
+    #[snafu(display("{message}"))]
+    Invalid { message: String },
+    #[snafu(display("Io error {source}, {file}"))]
+    Io {
+        source: std::io::Error,
+        file: String,
+    },
+    #[snafu(display("Kubernetes error {message}"))]
+    Kubernetes { message: String },
+}
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// The config generated from kubernetes resources, it is merged into
+/// the loaded config, so it's applied by the hot reload of config.
+#[derive(Debug, Default, Clone)]
+pub struct KubernetesConf {
+    pub upstreams: HashMap<String, UpstreamConf>,
+    pub locations: HashMap<String, LocationConf>,
+    pub certificates: HashMap<String, CertificateConf>,
+    /// Servers which the locations are added to, all servers if empty
+    pub servers: Vec<String>,
+}
+
+impl KubernetesConf {
+    /// Merges the generated config into the config,
+    /// the entries of config are kept if they have the same names.
+    /// The generated location which is duplicated or shadowed by the other
+    /// locations of server is not added, so it can't fail the validation.
+    pub fn merge_into(&self, conf: &mut PingapConf) {
+        for (name, upstream) in self.upstreams.iter() {
+            conf.upstreams
+                .entry(name.to_string())
+                .or_insert_with(|| upstream.clone());
+        }
+        for (name, location) in self.locations.iter() {
+            conf.locations
+                .entry(name.to_string())
+                .or_insert_with(|| location.clone());
+        }
+        for (name, certificate) in self.certificates.iter() {
+            conf.certificates
+                .entry(name.to_string())
+                .or_insert_with(|| certificate.clone());
+        }
+        if self.locations.is_empty() {
+            return;
+        }
+        // sort the names, so the config is stable for diff
+        let mut names: Vec<&String> = self.locations.keys().collect();
+        names.sort();
+        let mut server_names: Vec<String> = conf
+            .servers
+            .keys()
+            .filter(|name| {
+                self.servers.is_empty() || self.servers.contains(name)
+            })
+            .cloned()
+            .collect();
+        server_names.sort();
+        for server_name in server_names {
+            let mut locations = conf.servers[&server_name]
+                .locations
+                .clone()
+                .unwrap_or_default();
+            // the conflicts of the config itself are reported by validation
+            let checked =
+                validate_server_locations(conf, &server_name, &locations)
+                    .is_ok();
+            for name in names.iter() {
+                if locations.contains(name) {
+                    continue;
+                }
+                locations.push(name.to_string());
+                if !checked {
+                    continue;
+                }
+                if let Err(e) =
+                    validate_server_locations(conf, &server_name, &locations)
+                {
+                    warn!(
+                        category = LOG_CATEGORY,
+                        server = server_name,
+                        location = name.as_str(),
+                        error = %e,
+                        "location is conflicted, it's not added"
+                    );
+                    locations.pop();
+                }
+            }
+            if let Some(server) = conf.servers.get_mut(&server_name) {
+                server.locations = Some(locations);
+            }
+        }
+    }
+}
+
+static KUBERNETES_CONF: Lazy<ArcSwap<KubernetesConf>> =
+    Lazy::new(|| ArcSwap::from_pointee(KubernetesConf::default()));
+
+/// Returns the config generated by the kubernetes controller
+pub fn get_kubernetes_conf() -> Arc<KubernetesConf> {
+    KUBERNETES_CONF.load().clone()
+}
+
+/// Sets the config generated by the kubernetes controller
+pub fn set_kubernetes_conf(conf: KubernetesConf) {
+    KUBERNETES_CONF.store(Arc::new(conf));
+}
+
+pub use controller::KubernetesController;
+pub use convert::PLUGINS_ANNOTATION;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pingap_config::ServerConf;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_merge_into() {
+        let mut conf = PingapConf::default();
+        conf.servers.insert(
+            "web".to_string(),
+            ServerConf {
+                locations: Some(vec!["static".to_string()]),
+                ..Default::default()
+            },
+        );
+        conf.servers
+            .insert("admin".to_string(), ServerConf::default());
+        conf.upstreams.insert(
+            "k8s.svc.default.web.80".to_string(),
+            UpstreamConf {
+                addrs: vec!["127.0.0.1:3000".to_string()],
+                ..Default::default()
+            },
+        );
+
+        let mut k8s_conf = KubernetesConf {
+            servers: vec!["web".to_string()],
+            ..Default::default()
+        };
+        k8s_conf.upstreams.insert(
+            "k8s.svc.default.web.80".to_string(),
+            UpstreamConf {
+                addrs: vec!["10.0.0.1:8080".to_string()],
+                ..Default::default()
+            },
+        );
+        for (name, path) in [
+            ("k8s.ingress.default.web.0.1", "/api"),
+            ("k8s.ingress.default.web.0.0", "/"),
+        ] {
+            k8s_conf.locations.insert(
+                name.to_string(),
+                LocationConf {
+                    upstream: Some("k8s.svc.default.web.80".to_string()),
+                    path: Some(path.to_string()),
+                    ..Default::default()
+                },
+            );
+        }
+        k8s_conf.merge_into(&mut conf);
+        // merge twice, the locations of server are not duplicated
+        k8s_conf.merge_into(&mut conf);
+
+        // the upstream of config is kept
+        assert_eq!(
+            vec!["127.0.0.1:3000".to_string()],
+            conf.upstreams["k8s.svc.default.web.80"].addrs
+        );
+        assert_eq!(2, conf.locations.len());
+        assert_eq!(
+            Some(vec![
+                "static".to_string(),
+                "k8s.ingress.default.web.0.0".to_string(),
+                "k8s.ingress.default.web.0.1".to_string()
+            ]),
+            conf.servers["web"].locations
+        );
+        assert_eq!(None, conf.servers["admin"].locations);
+    }
+
+    #[test]
+    fn test_merge_into_conflict() {
+        let new_location = |host: &str, path: &str| LocationConf {
+            upstream: Some("web".to_string()),
+            host: Some(host.to_string()),
+            path: Some(path.to_string()),
+            ..Default::default()
+        };
+        let mut conf = PingapConf::default();
+        conf.upstreams.insert(
+            "web".to_string(),
+            UpstreamConf {
+                addrs: vec!["127.0.0.1:3000".to_string()],
+                ..Default::default()
+            },
+        );
+        conf.locations
+            .insert("api".to_string(), new_location("pingap.io", "/api"));
+        conf.servers.insert(
+            "web".to_string(),
+            ServerConf {
+                addr: "127.0.0.1:6188".to_string(),
+                locations: Some(vec!["api".to_string()]),
+                ..Default::default()
+            },
+        );
+
+        let mut k8s_conf = KubernetesConf::default();
+        // the same host and path as the location of config
+        k8s_conf.locations.insert(
+            "k8s.ingress.default.api.0.0".to_string(),
+            new_location("pingap.io", "/api"),
+        );
+        // it's shadowed by the location of config
+        let mut location = new_location("pingap.io", "/api/users");
+        location.weight = Some(10);
+        k8s_conf
+            .locations
+            .insert("k8s.ingress.default.users.0.0".to_string(), location);
+        // the same path as the previous generated location
+        k8s_conf.locations.insert(
+            "k8s.ingress.default.web.0.0".to_string(),
+            new_location("", "/web"),
+        );
+        k8s_conf.locations.insert(
+            "k8s.ingress.default.web.0.1".to_string(),
+            new_location("", "/web"),
+        );
+        k8s_conf.merge_into(&mut conf);
+
+        assert_eq!(
+            Some(vec![
+                "api".to_string(),
+                "k8s.ingress.default.web.0.0".to_string(),
+            ]),
+            conf.servers["web"].locations
+        );
+        assert_eq!(true, conf.validate().is_ok());
+
+        // the conflicts of config itself are not hidden
+        conf.locations
+            .insert("api-v2".to_string(), new_location("pingap.io", "/api"));
+        conf.servers.get_mut("web").unwrap().locations =
+            Some(vec!["api".to_string(), "api-v2".to_string()]);
+        k8s_conf.merge_into(&mut conf);
+        assert_eq!(true, conf.validate().is_err());
+    }
+}
@@ -0,0 +1,240 @@
+// Copyright 2024-2025 Tree xie.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+// Only the fields of kubernetes resources used by the controller are
+// defined, the others are ignored when deserializing.
+
+use serde::Deserialize;
+use std::collections::HashMap;
+
+/// Label of endpoint slice, its value is the name of service
+pub(crate) const SERVICE_NAME_LABEL: &str = "kubernetes.io/service-name";
+
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct ObjectMeta {
+    #[serde(default)]
+    pub name: String,
+    #[serde(default)]
+    pub namespace: String,
+    #[serde(default)]
+    pub labels: HashMap<String, String>,
+    #[serde(default)]
+    pub annotations: HashMap<String, String>,
+}
+
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct ListMeta {
+    pub resource_version: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(bound(deserialize = "T: Deserialize<'de>"))]
+pub(crate) struct List<T> {
+    #[serde(default)]
+    pub metadata: ListMeta,
+    #[serde(default)]
+    pub items: Vec<T>,
+}
+
+#[derive(Debug, Default, Clone, Deserialize)]
+pub(crate) struct ServiceBackendPort {
+    pub name: Option<String>,
+    pub number: Option<i32>,
+}
+
+#[derive(Debug, Default, Clone, Deserialize)]
+pub(crate) struct IngressServiceBackend {
+    pub name: String,
+    #[serde(default)]
+    pub port: ServiceBackendPort,
+}
+
+#[derive(Debug, Default, Clone, Deserialize)]
+pub(crate) struct IngressBackend {
+    pub service: Option<IngressServiceBackend>,
+}
+
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct HttpIngressPath {
+    pub path: Option<String>,
+    pub path_type: Option<String>,
+    #[serde(default)]
+    pub backend: IngressBackend,
+}
+
+#[derive(Debug, Default, Clone, Deserialize)]
+pub(crate) struct HttpIngressRuleValue {
+    #[serde(default)]
+    pub paths: Vec<HttpIngressPath>,
+}
+
+#[derive(Debug, Default, Clone, Deserialize)]
+pub(crate) struct IngressRule {
+    pub host: Option<String>,
+    pub http: Option<HttpIngressRuleValue>,
+}
+
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct IngressTls {
+    #[serde(default)]
+    pub hosts: Vec<String>,
+    pub secret_name: Option<String>,
+}
+
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct IngressSpec {
+    pub ingress_class_name: Option<String>,
+    pub default_backend: Option<IngressBackend>,
+    #[serde(default)]
+    pub tls: Vec<IngressTls>,
+    #[serde(default)]
+    pub rules: Vec<IngressRule>,
+}
+
+/// Ingress of networking.k8s.io/v1
+#[derive(Debug, Default, Clone, Deserialize)]
+pub(crate) struct Ingress {
+    #[serde(default)]
+    pub metadata: ObjectMeta,
+    #[serde(default)]
+    pub spec: IngressSpec,
+}
+
+#[derive(Debug, Default, Clone, Deserialize)]
+pub(crate) struct ServicePort {
+    pub name: Option<String>,
+    pub port: i32,
+}
+
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct ServiceSpec {
+    #[serde(rename = "type")]
+    pub category: Option<String>,
+    pub external_name: Option<String>,
+    #[serde(default)]
+    pub ports: Vec<ServicePort>,
+}
+
+/// Service of core v1
+#[derive(Debug, Default, Clone, Deserialize)]
+pub(crate) struct Service {
+    #[serde(default)]
+    pub metadata: ObjectMeta,
+    #[serde(default)]
+    pub spec: ServiceSpec,
+}
+
+#[derive(Debug, Default, Clone, Deserialize)]
+pub(crate) struct EndpointConditions {
+    pub ready: Option<bool>,
+}
+
+#[derive(Debug, Default, Clone, Deserialize)]
+pub(crate) struct Endpoint {
+    #[serde(default)]
+    pub addresses: Vec<String>,
+    #[serde(default)]
+    pub conditions: EndpointConditions,
+}
+
+#[derive(Debug, Default, Clone, Deserialize)]
+pub(crate) struct EndpointPort {
+    pub name: Option<String>,
+    pub port: Option<i32>,
+}
+
+/// EndpointSlice of discovery.k8s.io/v1
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct EndpointSlice {
+    #[serde(default)]
+    pub metadata: ObjectMeta,
+    #[serde(default)]
+    pub address_type: String,
+    #[serde(default)]
+    pub endpoints: Vec<Endpoint>,
+    #[serde(default)]
+    pub ports: Vec<EndpointPort>,
+}
+
+/// Secret of core v1, the values of data are base64 encoded
+#[derive(Debug, Default, Clone, Deserialize)]
+pub(crate) struct Secret {
+    #[serde(default)]
+    pub metadata: ObjectMeta,
+    #[serde(default)]
+    pub data: HashMap<String, String>,
+}
+
+#[derive(Debug, Default, Clone, Deserialize)]
+pub(crate) struct ParentReference {
+    pub name: String,
+    pub kind: Option<String>,
+}
+
+#[derive(Debug, Default, Clone, Deserialize)]
+pub(crate) struct HttpPathMatch {
+    #[serde(rename = "type")]
+    pub category: Option<String>,
+    pub value: Option<String>,
+}
+
+#[derive(Debug, Default, Clone, Deserialize)]
+pub(crate) struct HttpRouteMatch {
+    pub path: Option<HttpPathMatch>,
+}
+
+#[derive(Debug, Default, Clone, Deserialize)]
+pub(crate) struct HttpBackendRef {
+    pub name: String,
+    pub namespace: Option<String>,
+    pub kind: Option<String>,
+    pub port: Option<i32>,
+    pub weight: Option<i32>,
+}
+
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct HttpRouteRule {
+    #[serde(default)]
+    pub matches: Vec<HttpRouteMatch>,
+    #[serde(default)]
+    pub backend_refs: Vec<HttpBackendRef>,
+}
+
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct HttpRouteSpec {
+    #[serde(default)]
+    pub parent_refs: Vec<ParentReference>,
+    #[serde(default)]
+    pub hostnames: Vec<String>,
+    #[serde(default)]
+    pub rules: Vec<HttpRouteRule>,
+}
+
+/// HTTPRoute of gateway.networking.k8s.io/v1
+#[derive(Debug, Default, Clone, Deserialize)]
+pub(crate) struct HttpRoute {
+    #[serde(default)]
+    pub metadata: ObjectMeta,
+    #[serde(default)]
+    pub spec: HttpRouteSpec,
+}
@@ -16,7 +16,7 @@ use super::regex::RegexCapture;
 use ahash::AHashMap;
 use arc_swap::ArcSwap;
 use once_cell::sync::Lazy;
-use pingap_config::LocationConf;
+use pingap_config::{LocationConf, LocationPath};
 use pingap_core::{convert_headers, HttpHeader};
 use pingora::http::RequestHeader;
 use regex::Regex;
@@ -48,27 +48,14 @@ struct RegexPath {
     value: RegexCapture,
 }
 
-#[derive(Debug)]
-struct PrefixPath {
-    value: String,
-}
-
-#[derive(Debug)]
-struct EqualPath {
-    value: String,
-}
-
 // PathSelector enum represents different ways to match request paths:
 // - RegexPath: Uses regex pattern matching
-// - PrefixPath: Matches if path starts with prefix
-// - EqualPath: Matches exact path
-// - Empty: Matches all paths
+// - Path: Matches all paths, exact path or prefix path by the path rule
+//   of config, which is also used to validate the routes of server
 #[derive(Debug)]
 enum PathSelector {
     RegexPath(RegexPath),
-    PrefixPath(PrefixPath),
-    EqualPath(EqualPath),
-    Empty,
+    Path(LocationPath),
 }
 /// Creates a new path selector based on the input path string.
 ///
@@ -84,28 +71,12 @@ enum PathSelector {
 /// - Starting with "=": Exact path matching  
 /// - Otherwise: Prefix path matching
 fn new_path_selector(path: &str) -> Result<PathSelector> {
-    let path = path.trim();
-    if path.is_empty() {
-        return Ok(PathSelector::Empty);
-    }
-    let first = path.chars().next().unwrap_or_default();
-    let last = path.substring(1, path.len()).trim();
-    let se = match first {
-        '~' => {
-            let re = RegexCapture::new(last).context(RegexSnafu {
-                value: last.to_string(),
-            })?;
+    let se = match LocationPath::new(path) {
+        LocationPath::Regex(value) => {
+            let re = RegexCapture::new(&value).context(RegexSnafu { value })?;
             PathSelector::RegexPath(RegexPath { value: re })
         },
-        '=' => PathSelector::EqualPath(EqualPath {
-            value: last.to_string(),
-        }),
-        _ => {
-            // trim
-            PathSelector::PrefixPath(PrefixPath {
-                value: path.to_string(),
-            })
-        },
+        rule => PathSelector::Path(rule),
     };
 
     Ok(se)
@@ -442,8 +413,6 @@ impl Location {
         // First check path matching if a path pattern is configured
         if !self.path.is_empty() {
             let matched = match &self.path_selector {
-                // For exact path matching, compare path strings directly
-                PathSelector::EqualPath(EqualPath { value }) => value == path,
                 // For regex path matching, use regex is_match
                 PathSelector::RegexPath(RegexPath { value }) => {
                     let (matched, value) = value.captures(path);
@@ -452,12 +421,8 @@ impl Location {
                     }
                     matched
                 },
-                // For prefix path matching, check if path starts with prefix
-                PathSelector::PrefixPath(PrefixPath { value }) => {
-                    path.starts_with(value)
-                },
-                // Empty path selector matches everything
-                PathSelector::Empty => true,
+                // For exact, prefix or empty path matching
+                PathSelector::Path(rule) => rule.is_match(path),
             };
             // If path doesn't match, return false early
             if !matched {
@@ -645,16 +610,25 @@ mod tests {
     #[test]
     fn test_new_path_selector() {
         let selector = new_path_selector("").unwrap();
-        assert_eq!(true, matches!(selector, PathSelector::Empty));
+        assert_eq!(
+            true,
+            matches!(selector, PathSelector::Path(LocationPath::All))
+        );
 
         let selector = new_path_selector("~/api").unwrap();
         assert_eq!(true, matches!(selector, PathSelector::RegexPath(_)));
 
         let selector = new_path_selector("=/api").unwrap();
-        assert_eq!(true, matches!(selector, PathSelector::EqualPath(_)));
+        assert_eq!(
+            true,
+            matches!(selector, PathSelector::Path(LocationPath::Equal(_)))
+        );
 
         let selector = new_path_selector("/api").unwrap();
-        assert_eq!(true, matches!(selector, PathSelector::PrefixPath(_)));
+        assert_eq!(
+            true,
+            matches!(selector, PathSelector::Path(LocationPath::Prefix(_)))
+        );
     }
     #[test]
     fn test_path_host_select_location() {
@@ -12,15 +12,21 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use super::Error;
 use bytes::BytesMut;
-use chrono::{Local, Utc};
+use chrono::{DateTime, Local, Utc};
 use pingap_core::{get_hostname, Ctx, HOST_NAME_TAG};
 use pingap_util::{format_byte_size, format_duration};
 use pingora::http::ResponseHeader;
 use pingora::proxy::Session;
 use regex::Regex;
+use serde_json::{Map, Value};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
 use substring::Substring;
 
+type Result<T, E = Error> = std::result::Result<T, E>;
+
 // Enum representing different types of log tags that can be used in the logging format
 #[derive(Debug, Clone, PartialEq)]
 pub enum TagCategory {
@@ -51,6 +57,19 @@ pub enum TagCategory {
     PayloadSize,
     PayloadSizeHuman,
     RequestId,
+    UpstreamAddr,
+    UpstreamStatus,
+    UpstreamConnectTime,
+    UpstreamTcpConnectTime,
+    UpstreamTlsHandshakeTime,
+    UpstreamProcessingTime,
+    UpstreamResponseTime,
+    CacheStatus,
+    CacheLookupTime,
+    TlsVersion,
+    TlsCipher,
+    CompressionRatio,
+    PluginTimes,
 }
 
 // Represents a single tag in the log format
@@ -65,6 +84,10 @@ pub struct Parser {
     pub needs_timestamp: bool,
     pub capacity: usize,
     pub tags: Vec<Tag>,
+    // Whether to format the log as a json object
+    pub json: bool,
+    // Field names of tags for json format, empty for static text
+    pub fields: Vec<String>,
 }
 
 // Parses special tags with prefixes like ~, >, <, :, $
@@ -119,9 +142,21 @@ static COMMON: &str =
     r###"{remote} "{method} {uri} {proto}" {status} {size_human}""###;
 static SHORT: &str = r###"{remote} {method} {uri} {proto} {status} {size_human} - {latency}ms"###;
 static TINY: &str = r###"{method} {uri} {status} {size_human} - {latency}ms"###;
+static JSON: &str = r###"{when} {client_ip} {method} {host} {uri} {proto} {status} {size} {latency} {referer} {user_agent} {request_id}"###;
+
+// Json format prefix, the tags of the format are converted to json fields,
+// e.g. `json:{method} {uri} {status} {>x-user} {~uid}`
+static JSON_PREFIX: &str = "json:";
 
 impl From<&str> for Parser {
     fn from(value: &str) -> Self {
+        let (json, value) = if value == "json" {
+            (true, JSON)
+        } else if let Some(value) = value.strip_prefix(JSON_PREFIX) {
+            (true, value)
+        } else {
+            (false, value)
+        };
         let value = match value {
             "combined" => COMBINED,
             "common" => COMMON,
@@ -133,6 +168,7 @@ impl From<&str> for Parser {
         let mut current = 0;
         let mut end = 0;
         let mut tags = vec![];
+        let mut fields = vec![];
 
         while let Some(result) = reg.find_at(value, current) {
             if end < result.start() {
@@ -142,6 +178,7 @@ impl From<&str> for Parser {
                         value.substring(end, result.start()).to_string(),
                     ),
                 });
+                fields.push(String::new());
             }
             let key = result.as_str();
 
@@ -234,12 +271,70 @@ impl From<&str> for Parser {
                     category: TagCategory::RequestId,
                     data: None,
                 }),
+                "{upstream_addr}" => tags.push(Tag {
+                    category: TagCategory::UpstreamAddr,
+                    data: None,
+                }),
+                "{upstream_status}" => tags.push(Tag {
+                    category: TagCategory::UpstreamStatus,
+                    data: None,
+                }),
+                "{upstream_connect_time}" => tags.push(Tag {
+                    category: TagCategory::UpstreamConnectTime,
+                    data: None,
+                }),
+                "{upstream_tcp_connect_time}" => tags.push(Tag {
+                    category: TagCategory::UpstreamTcpConnectTime,
+                    data: None,
+                }),
+                "{upstream_tls_handshake_time}" => tags.push(Tag {
+                    category: TagCategory::UpstreamTlsHandshakeTime,
+                    data: None,
+                }),
+                "{upstream_processing_time}" => tags.push(Tag {
+                    category: TagCategory::UpstreamProcessingTime,
+                    data: None,
+                }),
+                "{upstream_response_time}" => tags.push(Tag {
+                    category: TagCategory::UpstreamResponseTime,
+                    data: None,
+                }),
+                "{cache_status}" => tags.push(Tag {
+                    category: TagCategory::CacheStatus,
+                    data: None,
+                }),
+                "{cache_lookup_time}" => tags.push(Tag {
+                    category: TagCategory::CacheLookupTime,
+                    data: None,
+                }),
+                "{tls_version}" => tags.push(Tag {
+                    category: TagCategory::TlsVersion,
+                    data: None,
+                }),
+                "{tls_cipher}" => tags.push(Tag {
+                    category: TagCategory::TlsCipher,
+                    data: None,
+                }),
+                "{compression_ratio}" => tags.push(Tag {
+                    category: TagCategory::CompressionRatio,
+                    data: None,
+                }),
+                "{plugin_times}" => tags.push(Tag {
+                    category: TagCategory::PluginTimes,
+                    data: None,
+                }),
                 _ => {
                     if let Some(tag) = format_extra_tag(key) {
                         tags.push(tag);
                     }
                 },
             }
+            // the field name is the key without braces,
+            // it's only added when the tag is valid
+            fields.resize(
+                tags.len(),
+                key.substring(1, key.len() - 1).to_string(),
+            );
 
             end = result.end();
             current = result.start() + 1;
@@ -249,6 +344,7 @@ impl From<&str> for Parser {
                 category: TagCategory::Fill,
                 data: Some(value.substring(end, value.len()).to_string()),
             });
+            fields.push(String::new());
         }
         let needs_timestamp = tags.iter().any(|t| {
             matches!(
@@ -265,6 +361,8 @@ impl From<&str> for Parser {
             capacity,
             tags,
             needs_timestamp,
+            json,
+            fields,
         }
     }
 }
@@ -292,198 +390,486 @@ impl Parser {
         }
         size
     }
-    // Formats a log entry based on the session and context
-    pub fn format(&self, session: &Session, ctx: &Ctx) -> String {
-        // Better capacity estimation based on tag types and count
-        let mut buf = BytesMut::with_capacity(self.capacity);
-        let req_header = session.req_header();
-
-        // Then only calculate if needed
-        let (now, now_ms) = if self.needs_timestamp {
+    // Gets the current time and milliseconds timestamp,
+    // only calculated if needed
+    fn get_now(&self) -> (Option<DateTime<Utc>>, Option<u64>) {
+        if self.needs_timestamp {
             let n = Utc::now();
             (Some(n), Some(n.timestamp_millis() as u64))
         } else {
             (None, None)
-        };
+        }
+    }
+    // Formats a log entry based on the session and context
+    pub fn format(&self, session: &Session, ctx: &Ctx) -> String {
+        if self.json {
+            return self.format_json(session, ctx);
+        }
+        // Better capacity estimation based on tag types and count
+        let mut buf = BytesMut::with_capacity(self.capacity);
+        let (now, now_ms) = self.get_now();
 
         // Process each tag in the format string
         for tag in self.tags.iter() {
-            match tag.category {
-                TagCategory::Fill => {
-                    // Static text, just append it
-                    if let Some(data) = &tag.data {
-                        buf.extend_from_slice(data.as_bytes());
-                    }
-                },
-                TagCategory::Host => {
-                    // Add the host from request headers
-                    if let Some(host) = pingap_core::get_host(req_header) {
-                        buf.extend_from_slice(host.as_bytes());
-                    }
-                },
-                TagCategory::Method => {
-                    buf.extend_from_slice(
-                        req_header.method.as_str().as_bytes(),
-                    );
-                },
-                TagCategory::Path => {
-                    buf.extend_from_slice(req_header.uri.path().as_bytes());
-                },
-                TagCategory::Proto => {
-                    if session.is_http2() {
-                        buf.extend_from_slice(b"HTTP/2.0");
-                    } else {
-                        buf.extend_from_slice(b"HTTP/1.1");
-                    }
-                },
-                TagCategory::Query => {
-                    if let Some(query) = req_header.uri.query() {
-                        buf.extend_from_slice(query.as_bytes());
-                    }
-                },
-                TagCategory::Remote => {
-                    if let Some(addr) = &ctx.remote_addr {
-                        buf.extend_from_slice(addr.as_bytes());
-                    }
-                },
-                TagCategory::ClientIp => {
-                    if let Some(client_ip) = &ctx.client_ip {
-                        buf.extend_from_slice(client_ip.as_bytes());
-                    } else {
-                        buf.extend_from_slice(
-                            pingap_core::get_client_ip(session).as_bytes(),
-                        );
-                    }
-                },
-                TagCategory::Scheme => {
-                    if ctx.tls_version.is_some() {
-                        buf.extend_from_slice(b"https");
-                    } else {
-                        buf.extend_from_slice(b"http");
-                    }
-                },
-                TagCategory::Uri => {
-                    if let Some(value) = req_header.uri.path_and_query() {
-                        buf.extend_from_slice(value.as_str().as_bytes());
-                    }
-                },
-                TagCategory::Referrer => {
-                    let value = session.get_header_bytes("Referer");
-                    buf.extend_from_slice(value);
-                },
-                TagCategory::UserAgent => {
-                    let value = session.get_header_bytes("User-Agent");
-                    buf.extend_from_slice(value);
-                },
-                TagCategory::When => {
-                    if let Some(now) = &now {
-                        buf.extend_from_slice(
-                            now.with_timezone(&Local).to_rfc3339().as_bytes(),
-                        );
-                    }
-                },
-                TagCategory::WhenUtcIso => {
-                    if let Some(now) = &now {
-                        buf.extend_from_slice(now.to_rfc3339().as_bytes());
-                    }
+            buf =
+                append_tag_value(buf, tag, session, ctx, now.as_ref(), now_ms);
+        }
+
+        std::string::String::from_utf8(buf.into()).unwrap_or_default()
+    }
+    // Formats a log entry as a json object, the numeric tags are
+    // converted to numbers and the others are converted to strings.
+    // Cookies, headers and context values are grouped by category.
+    fn format_json(&self, session: &Session, ctx: &Ctx) -> String {
+        let (now, now_ms) = self.get_now();
+        let mut m = Map::new();
+        for (tag, field) in self.tags.iter().zip(self.fields.iter()) {
+            if field.is_empty() {
+                continue;
+            }
+            let value = match tag.category {
+                TagCategory::Size => Value::from(session.body_bytes_sent()),
+                TagCategory::Status => Value::from(
+                    ctx.status.map(|status| status.as_u16()).unwrap_or(0),
+                ),
+                TagCategory::Latency => {
+                    let Some(now_ms) = now_ms else {
+                        continue;
+                    };
+                    Value::from(now_ms - ctx.created_at)
                 },
                 TagCategory::WhenUnix => {
-                    if let Some(now_ms) = now_ms {
-                        buf.extend_from_slice(
-                            itoa::Buffer::new().format(now_ms).as_bytes(),
-                        );
-                    }
-                },
-                TagCategory::Size => {
-                    buf.extend_from_slice(
-                        itoa::Buffer::new()
-                            .format(session.body_bytes_sent())
-                            .as_bytes(),
-                    );
+                    let Some(now_ms) = now_ms else {
+                        continue;
+                    };
+                    Value::from(now_ms)
                 },
-                TagCategory::SizeHuman => {
-                    buf = format_byte_size(buf, session.body_bytes_sent());
+                TagCategory::PayloadSize => Value::from(ctx.payload_size),
+                TagCategory::UpstreamStatus => {
+                    let Some(status) = ctx.upstream_status else {
+                        continue;
+                    };
+                    Value::from(status.as_u16())
                 },
-                TagCategory::Status => {
-                    if let Some(status) = ctx.status {
-                        buf.extend_from_slice(status.as_str().as_bytes());
-                    } else {
-                        buf.extend_from_slice(b"0");
-                    }
+                TagCategory::UpstreamConnectTime
+                | TagCategory::UpstreamTcpConnectTime
+                | TagCategory::UpstreamTlsHandshakeTime
+                | TagCategory::UpstreamProcessingTime
+                | TagCategory::UpstreamResponseTime
+                | TagCategory::CacheLookupTime => {
+                    let Some(ms) = get_time_value(&tag.category, ctx) else {
+                        continue;
+                    };
+                    Value::from(ms)
                 },
-                TagCategory::Latency => {
-                    if let Some(now_ms) = now_ms {
-                        let ms = now_ms - ctx.created_at;
-                        buf.extend_from_slice(
-                            itoa::Buffer::new().format(ms).as_bytes(),
-                        );
-                    }
+                TagCategory::CompressionRatio => {
+                    let Some(stat) = &ctx.compression_stat else {
+                        continue;
+                    };
+                    Value::from((stat.ratio() * 10.0).round() / 10.0)
                 },
-                TagCategory::LatencyHuman => {
-                    if let Some(now_ms) = now_ms {
-                        let ms = now_ms - ctx.created_at;
-                        buf = format_duration(buf, ms);
+                TagCategory::PluginTimes => {
+                    let Some(times) = &ctx.plugin_processing_times else {
+                        continue;
+                    };
+                    let mut values = Map::new();
+                    for (name, time) in times.iter() {
+                        values.insert(name.to_string(), Value::from(*time));
                     }
+                    Value::Object(values)
                 },
-                TagCategory::Cookie => {
-                    if let Some(cookie) = &tag.data {
-                        if let Some(value) =
-                            pingap_core::get_cookie_value(req_header, cookie)
-                        {
-                            buf.extend_from_slice(value.as_bytes());
-                        }
+                _ => {
+                    let buf = append_tag_value(
+                        BytesMut::new(),
+                        tag,
+                        session,
+                        ctx,
+                        now.as_ref(),
+                        now_ms,
+                    );
+                    if buf.is_empty() {
+                        continue;
                     }
+                    Value::String(String::from_utf8_lossy(&buf).to_string())
                 },
-                TagCategory::RequestHeader => {
-                    if let Some(key) = &tag.data {
-                        if let Some(value) = req_header.headers.get(key) {
-                            buf.extend_from_slice(value.as_bytes());
-                        }
+            };
+            let group = match tag.category {
+                TagCategory::Cookie => Some("cookie"),
+                TagCategory::RequestHeader => Some("req_header"),
+                TagCategory::ResponseHeader => Some("resp_header"),
+                TagCategory::Context => Some("ctx"),
+                _ => None,
+            };
+            if let (Some(group), Some(name)) = (group, &tag.data) {
+                if let Value::Object(values) =
+                    m.entry(group).or_insert_with(|| Value::Object(Map::new()))
+                {
+                    values.insert(name.to_string(), value);
+                }
+            } else {
+                // remove the prefix of env tag, e.g. `$hostname`
+                let name = field.strip_prefix('$').unwrap_or(field);
+                m.insert(name.to_string(), value);
+            }
+        }
+        serde_json::to_string(&m).unwrap_or_default()
+    }
+}
+
+// Gets the milliseconds of the time tags
+fn get_time_value(category: &TagCategory, ctx: &Ctx) -> Option<u64> {
+    match category {
+        TagCategory::UpstreamConnectTime => ctx.get_upstream_connect_time(),
+        TagCategory::UpstreamTcpConnectTime => ctx.upstream_tcp_connect_time,
+        TagCategory::UpstreamTlsHandshakeTime => {
+            ctx.upstream_tls_handshake_time
+        },
+        TagCategory::UpstreamProcessingTime => {
+            ctx.get_upstream_processing_time()
+        },
+        TagCategory::UpstreamResponseTime => ctx.get_upstream_response_time(),
+        TagCategory::CacheLookupTime => ctx.cache_lookup_time,
+        _ => None,
+    }
+}
+
+// Appends the value of tag to the buffer
+fn append_tag_value(
+    mut buf: BytesMut,
+    tag: &Tag,
+    session: &Session,
+    ctx: &Ctx,
+    now: Option<&DateTime<Utc>>,
+    now_ms: Option<u64>,
+) -> BytesMut {
+    let req_header = session.req_header();
+    match tag.category {
+        TagCategory::Fill => {
+            // Static text, just append it
+            if let Some(data) = &tag.data {
+                buf.extend_from_slice(data.as_bytes());
+            }
+        },
+        TagCategory::Host => {
+            // Add the host from request headers
+            if let Some(host) = pingap_core::get_host(req_header) {
+                buf.extend_from_slice(host.as_bytes());
+            }
+        },
+        TagCategory::Method => {
+            buf.extend_from_slice(req_header.method.as_str().as_bytes());
+        },
+        TagCategory::Path => {
+            buf.extend_from_slice(req_header.uri.path().as_bytes());
+        },
+        TagCategory::Proto => {
+            if session.is_http2() {
+                buf.extend_from_slice(b"HTTP/2.0");
+            } else {
+                buf.extend_from_slice(b"HTTP/1.1");
+            }
+        },
+        TagCategory::Query => {
+            if let Some(query) = req_header.uri.query() {
+                buf.extend_from_slice(query.as_bytes());
+            }
+        },
+        TagCategory::Remote => {
+            if let Some(addr) = &ctx.remote_addr {
+                buf.extend_from_slice(addr.as_bytes());
+            }
+        },
+        TagCategory::ClientIp => {
+            if let Some(client_ip) = &ctx.client_ip {
+                buf.extend_from_slice(client_ip.as_bytes());
+            } else {
+                buf.extend_from_slice(
+                    pingap_core::get_client_ip(session).as_bytes(),
+                );
+            }
+        },
+        TagCategory::Scheme => {
+            if ctx.tls_version.is_some() {
+                buf.extend_from_slice(b"https");
+            } else {
+                buf.extend_from_slice(b"http");
+            }
+        },
+        TagCategory::Uri => {
+            if let Some(value) = req_header.uri.path_and_query() {
+                buf.extend_from_slice(value.as_str().as_bytes());
+            }
+        },
+        TagCategory::Referrer => {
+            let value = session.get_header_bytes("Referer");
+            buf.extend_from_slice(value);
+        },
+        TagCategory::UserAgent => {
+            let value = session.get_header_bytes("User-Agent");
+            buf.extend_from_slice(value);
+        },
+        TagCategory::When => {
+            if let Some(now) = now {
+                buf.extend_from_slice(
+                    now.with_timezone(&Local).to_rfc3339().as_bytes(),
+                );
+            }
+        },
+        TagCategory::WhenUtcIso => {
+            if let Some(now) = now {
+                buf.extend_from_slice(now.to_rfc3339().as_bytes());
+            }
+        },
+        TagCategory::WhenUnix => {
+            if let Some(now_ms) = now_ms {
+                buf.extend_from_slice(
+                    itoa::Buffer::new().format(now_ms).as_bytes(),
+                );
+            }
+        },
+        TagCategory::Size => {
+            buf.extend_from_slice(
+                itoa::Buffer::new()
+                    .format(session.body_bytes_sent())
+                    .as_bytes(),
+            );
+        },
+        TagCategory::SizeHuman => {
+            buf = format_byte_size(buf, session.body_bytes_sent());
+        },
+        TagCategory::Status => {
+            if let Some(status) = ctx.status {
+                buf.extend_from_slice(status.as_str().as_bytes());
+            } else {
+                buf.extend_from_slice(b"0");
+            }
+        },
+        TagCategory::Latency => {
+            if let Some(now_ms) = now_ms {
+                let ms = now_ms - ctx.created_at;
+                buf.extend_from_slice(
+                    itoa::Buffer::new().format(ms).as_bytes(),
+                );
+            }
+        },
+        TagCategory::LatencyHuman => {
+            if let Some(now_ms) = now_ms {
+                let ms = now_ms - ctx.created_at;
+                buf = format_duration(buf, ms);
+            }
+        },
+        TagCategory::Cookie => {
+            if let Some(cookie) = &tag.data {
+                if let Some(value) =
+                    pingap_core::get_cookie_value(req_header, cookie)
+                {
+                    buf.extend_from_slice(value.as_bytes());
+                }
+            }
+        },
+        TagCategory::RequestHeader => {
+            if let Some(key) = &tag.data {
+                if let Some(value) = req_header.headers.get(key) {
+                    buf.extend_from_slice(value.as_bytes());
+                }
+            }
+        },
+        TagCategory::ResponseHeader => {
+            if let Some(resp_header) = session.response_written() {
+                if let Some(key) = &tag.data {
+                    if let Some(value) = get_resp_header_value(resp_header, key)
+                    {
+                        buf.extend_from_slice(value);
                     }
-                },
-                TagCategory::ResponseHeader => {
-                    if let Some(resp_header) = session.response_written() {
-                        if let Some(key) = &tag.data {
-                            if let Some(value) =
-                                get_resp_header_value(resp_header, key)
-                            {
-                                buf.extend_from_slice(value);
-                            }
-                        }
+                }
+            }
+        },
+        TagCategory::PayloadSize => {
+            buf.extend_from_slice(
+                itoa::Buffer::new().format(ctx.payload_size).as_bytes(),
+            );
+        },
+        TagCategory::PayloadSizeHuman => {
+            buf = format_byte_size(buf, ctx.payload_size);
+        },
+        TagCategory::RequestId => {
+            if let Some(key) = &ctx.request_id {
+                buf.extend_from_slice(key.as_bytes());
+            }
+        },
+        TagCategory::Context => {
+            if let Some(key) = &tag.data {
+                buf = ctx.append_value(buf, key.as_str());
+            }
+        },
+        TagCategory::UpstreamAddr => {
+            buf.extend_from_slice(ctx.upstream_address.as_bytes());
+        },
+        TagCategory::UpstreamStatus => {
+            if let Some(status) = ctx.upstream_status {
+                buf.extend_from_slice(status.as_str().as_bytes());
+            }
+        },
+        TagCategory::UpstreamConnectTime
+        | TagCategory::UpstreamTcpConnectTime
+        | TagCategory::UpstreamTlsHandshakeTime
+        | TagCategory::UpstreamProcessingTime
+        | TagCategory::UpstreamResponseTime
+        | TagCategory::CacheLookupTime => {
+            if let Some(ms) = get_time_value(&tag.category, ctx) {
+                buf.extend_from_slice(
+                    itoa::Buffer::new().format(ms).as_bytes(),
+                );
+            }
+        },
+        TagCategory::CacheStatus => {
+            if let Some(status) = ctx.cache_status {
+                buf.extend(status.bytes().map(|b| b.to_ascii_uppercase()));
+            }
+        },
+        TagCategory::TlsVersion => {
+            if let Some(version) = &ctx.tls_version {
+                buf.extend_from_slice(version.as_bytes());
+            }
+        },
+        TagCategory::TlsCipher => {
+            if let Some(cipher) = &ctx.tls_cipher {
+                buf.extend_from_slice(cipher.as_bytes());
+            }
+        },
+        TagCategory::CompressionRatio => {
+            if let Some(stat) = &ctx.compression_stat {
+                buf.extend_from_slice(
+                    format!("{:.1}", stat.ratio()).as_bytes(),
+                );
+            }
+        },
+        TagCategory::PluginTimes => {
+            // e.g. `limit:1,cache:3`
+            if let Some(times) = &ctx.plugin_processing_times {
+                for (index, (name, time)) in times.iter().enumerate() {
+                    if index != 0 {
+                        buf.extend_from_slice(b",");
                     }
-                },
-                TagCategory::PayloadSize => {
+                    buf.extend_from_slice(name.as_bytes());
+                    buf.extend_from_slice(b":");
                     buf.extend_from_slice(
-                        itoa::Buffer::new().format(ctx.payload_size).as_bytes(),
+                        itoa::Buffer::new().format(*time).as_bytes(),
                     );
-                },
-                TagCategory::PayloadSizeHuman => {
-                    buf = format_byte_size(buf, ctx.payload_size);
-                },
-                TagCategory::RequestId => {
-                    if let Some(key) = &ctx.request_id {
-                        buf.extend_from_slice(key.as_bytes());
-                    }
-                },
-                TagCategory::Context => {
-                    if let Some(key) = &tag.data {
-                        buf = ctx.append_value(buf, key.as_str());
-                    }
-                },
-            };
-        }
+                }
+            }
+        },
+    };
+    buf
+}
 
-        std::string::String::from_utf8(buf.into()).unwrap_or_default()
+/// Filter of access log, it decides whether a request should be logged.
+/// The error requests (status >= 400 or no response) are always logged,
+/// the successful requests are filtered by path, status, latency
+/// and then sampled by ratio.
+#[derive(Debug, Default)]
+pub struct AccessLogFilter {
+    // Only log the requests whose status >= min status
+    min_status: Option<u16>,
+    // Only log the requests whose latency >= min latency
+    min_latency: Option<Duration>,
+    // Do not log the requests whose path matches the regex
+    exclude_path: Option<Regex>,
+    // Ratio of successful requests to log, 1.0 means all
+    sample_ratio: f64,
+    // Count of sampled requests
+    count: AtomicU64,
+}
+
+impl AccessLogFilter {
+    /// Creates a new access log filter, returns None if no condition is set
+    pub fn new(
+        min_status: Option<u16>,
+        min_latency: Option<Duration>,
+        exclude_path: Option<&str>,
+        sample_ratio: Option<f64>,
+    ) -> Result<Option<Self>> {
+        let exclude_path = exclude_path.unwrap_or_default();
+        if min_status.is_none()
+            && min_latency.is_none()
+            && exclude_path.is_empty()
+            && sample_ratio.is_none()
+        {
+            return Ok(None);
+        }
+        let sample_ratio = sample_ratio.unwrap_or(1.0);
+        if !(0.0..=1.0).contains(&sample_ratio) {
+            return Err(Error::Invalid {
+                message: format!(
+                    "sample ratio({sample_ratio}) should be between 0 and 1"
+                ),
+            });
+        }
+        let exclude_path = if exclude_path.is_empty() {
+            None
+        } else {
+            Some(Regex::new(exclude_path).map_err(|e| Error::Invalid {
+                message: e.to_string(),
+            })?)
+        };
+        Ok(Some(Self {
+            min_status,
+            min_latency,
+            exclude_path,
+            sample_ratio,
+            count: AtomicU64::new(0),
+        }))
+    }
+    /// Returns true if the request should be logged
+    ///
+    /// # Arguments
+    /// * `path` - Path of the request
+    /// * `status` - Response status, 0 means no response
+    /// * `latency` - Latency of the request in milliseconds
+    pub fn should_log(&self, path: &str, status: u16, latency: u64) -> bool {
+        // always log errors
+        if status == 0 || status >= 400 {
+            return true;
+        }
+        if let Some(exclude_path) = &self.exclude_path {
+            if exclude_path.is_match(path) {
+                return false;
+            }
+        }
+        // the request is logged if it matches any of status and latency
+        if self.min_status.is_some() || self.min_latency.is_some() {
+            let matched_status = self
+                .min_status
+                .map(|min_status| status >= min_status)
+                .unwrap_or_default();
+            let matched_latency = self
+                .min_latency
+                .map(|min_latency| latency >= min_latency.as_millis() as u64)
+                .unwrap_or_default();
+            if !matched_status && !matched_latency {
+                return false;
+            }
+        }
+        if self.sample_ratio >= 1.0 {
+            return true;
+        }
+        // sample evenly, the request is logged when the accumulated
+        // ratio crosses an integer
+        let count = self.count.fetch_add(1, Ordering::Relaxed);
+        let prev = (count as f64 * self.sample_ratio) as u64;
+        let current = ((count + 1) as f64 * self.sample_ratio) as u64;
+        current > prev
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{format_extra_tag, Parser, Tag, TagCategory};
+    use super::{format_extra_tag, AccessLogFilter, Parser, Tag, TagCategory};
     use http::Method;
-    use pingap_core::Ctx;
+    use pingap_core::{CompressionStat, Ctx};
     use pingora::proxy::Session;
     use pretty_assertions::assert_eq;
+    use std::time::Duration;
     use tokio_test::io::Builder;
 
     #[test]
@@ -667,6 +1053,97 @@ mod tests {
                     data: None,
                 },
             ),
+            (
+                "{upstream_addr}",
+                Tag {
+                    category: TagCategory::UpstreamAddr,
+                    data: None,
+                },
+            ),
+            (
+                "{upstream_status}",
+                Tag {
+                    category: TagCategory::UpstreamStatus,
+                    data: None,
+                },
+            ),
+            (
+                "{upstream_connect_time}",
+                Tag {
+                    category: TagCategory::UpstreamConnectTime,
+                    data: None,
+                },
+            ),
+            (
+                "{upstream_tcp_connect_time}",
+                Tag {
+                    category: TagCategory::UpstreamTcpConnectTime,
+                    data: None,
+                },
+            ),
+            (
+                "{upstream_tls_handshake_time}",
+                Tag {
+                    category: TagCategory::UpstreamTlsHandshakeTime,
+                    data: None,
+                },
+            ),
+            (
+                "{upstream_processing_time}",
+                Tag {
+                    category: TagCategory::UpstreamProcessingTime,
+                    data: None,
+                },
+            ),
+            (
+                "{upstream_response_time}",
+                Tag {
+                    category: TagCategory::UpstreamResponseTime,
+                    data: None,
+                },
+            ),
+            (
+                "{cache_status}",
+                Tag {
+                    category: TagCategory::CacheStatus,
+                    data: None,
+                },
+            ),
+            (
+                "{cache_lookup_time}",
+                Tag {
+                    category: TagCategory::CacheLookupTime,
+                    data: None,
+                },
+            ),
+            (
+                "{tls_version}",
+                Tag {
+                    category: TagCategory::TlsVersion,
+                    data: None,
+                },
+            ),
+            (
+                "{tls_cipher}",
+                Tag {
+                    category: TagCategory::TlsCipher,
+                    data: None,
+                },
+            ),
+            (
+                "{compression_ratio}",
+                Tag {
+                    category: TagCategory::CompressionRatio,
+                    data: None,
+                },
+            ),
+            (
+                "{plugin_times}",
+                Tag {
+                    category: TagCategory::PluginTimes,
+                    data: None,
+                },
+            ),
         ];
 
         for (value, tag) in tests {
@@ -732,4 +1209,149 @@ mod tests {
         let log = p.format(&session, &ctx);
         assert_eq!(true, log.len() == 13);
     }
+
+    #[tokio::test]
+    async fn test_json_logger() {
+        let p: Parser = "json:{method} {uri} {status} {size} {latency} \
+{payload_size} {~deviceId} {>accept} {:upstream_addr} {$hostname} {request_id}"
+            .into();
+        assert_eq!(true, p.json);
+        assert_eq!(p.tags.len(), p.fields.len());
+
+        let headers = [
+            "Host: github.com",
+            "Cookie: deviceId=abc",
+            "Accept: application/json",
+        ]
+        .join("\r\n");
+        let input_header =
+            format!("GET /vicanso/pingap?size=1 HTTP/1.1\r\n{headers}\r\n\r\n");
+        let mock_io = Builder::new().read(input_header.as_bytes()).build();
+        let mut session = Session::new_h1(Box::new(mock_io));
+        session.read_request().await.unwrap();
+
+        let ctx = Ctx {
+            upstream_address: "192.186.1.1:6188".to_string(),
+            status: Some(http::StatusCode::NOT_FOUND),
+            payload_size: 10,
+            created_at: pingap_util::now_ms() - 10,
+            ..Default::default()
+        };
+        let log = p.format(&session, &ctx);
+        let mut value: serde_json::Value = serde_json::from_str(&log).unwrap();
+        assert_eq!(true, value["latency"].as_u64().unwrap() >= 10);
+        assert_eq!(false, value["hostname"].as_str().unwrap().is_empty());
+        let m = value.as_object_mut().unwrap();
+        m.remove("latency");
+        m.remove("hostname");
+        assert_eq!(
+            r#"{"cookie":{"deviceId":"abc"},"ctx":{"upstream_addr":"192.186.1.1:6188"},"method":"GET","payload_size":10,"req_header":{"accept":"application/json"},"size":0,"status":404,"uri":"/vicanso/pingap?size=1"}"#,
+            value.to_string()
+        );
+
+        let p: Parser = "json".into();
+        let log = p.format(&session, &ctx);
+        let value: serde_json::Value = serde_json::from_str(&log).unwrap();
+        assert_eq!("github.com", value["host"].as_str().unwrap());
+        assert_eq!(404, value["status"].as_u64().unwrap());
+    }
+
+    #[test]
+    fn test_access_log_filter() {
+        assert_eq!(
+            true,
+            AccessLogFilter::new(None, None, None, None)
+                .unwrap()
+                .is_none()
+        );
+        assert_eq!(
+            true,
+            AccessLogFilter::new(None, None, None, Some(1.5)).is_err()
+        );
+        assert_eq!(
+            true,
+            AccessLogFilter::new(None, None, Some("(ping"), None).is_err()
+        );
+
+        let filter = AccessLogFilter::new(
+            Some(300),
+            Some(Duration::from_millis(500)),
+            Some("^/(ping|health)"),
+            None,
+        )
+        .unwrap()
+        .unwrap();
+        // errors are always logged
+        assert_eq!(true, filter.should_log("/ping", 502, 1));
+        assert_eq!(true, filter.should_log("/ping", 0, 1));
+        assert_eq!(false, filter.should_log("/ping", 200, 1000));
+        assert_eq!(false, filter.should_log("/users", 200, 100));
+        assert_eq!(true, filter.should_log("/users", 200, 500));
+        assert_eq!(true, filter.should_log("/users", 302, 100));
+
+        let filter = AccessLogFilter::new(None, None, None, Some(0.25))
+            .unwrap()
+            .unwrap();
+        let count = (0..100)
+            .filter(|_| filter.should_log("/users", 200, 1))
+            .count();
+        assert_eq!(25, count);
+        assert_eq!(true, filter.should_log("/users", 404, 1));
+
+        let filter = AccessLogFilter::new(None, None, None, Some(0.0))
+            .unwrap()
+            .unwrap();
+        assert_eq!(false, filter.should_log("/users", 200, 1));
+        assert_eq!(true, filter.should_log("/users", 500, 1));
+    }
+
+    #[tokio::test]
+    async fn test_upstream_tags() {
+        let input_header =
+            "GET /vicanso/pingap HTTP/1.1\r\nHost: github.com\r\n\r\n";
+        let mock_io = Builder::new().read(input_header.as_bytes()).build();
+        let mut session = Session::new_h1(Box::new(mock_io));
+        session.read_request().await.unwrap();
+
+        let ctx = Ctx {
+            upstream_address: "192.186.1.1:6188".to_string(),
+            upstream_status: Some(http::StatusCode::BAD_GATEWAY),
+            upstream_connect_time: Some(3),
+            upstream_tcp_connect_time: Some(1),
+            upstream_tls_handshake_time: Some(2),
+            upstream_processing_time: Some(10),
+            upstream_response_time: Some(12),
+            cache_status: Some("miss"),
+            cache_lookup_time: Some(1),
+            tls_version: Some("tlsv1.3".to_string()),
+            tls_cipher: Some("TLS_AES_256_GCM_SHA384".to_string()),
+            compression_stat: Some(CompressionStat {
+                in_bytes: 1000,
+                out_bytes: 300,
+                duration: Duration::from_millis(1),
+            }),
+            plugin_processing_times: Some(vec![
+                ("limit".to_string(), 1),
+                ("cache".to_string(), 3),
+            ]),
+            ..Default::default()
+        };
+        let format = "{upstream_addr} {upstream_status} \
+{upstream_connect_time} {upstream_tcp_connect_time} \
+{upstream_tls_handshake_time} {upstream_processing_time} \
+{upstream_response_time} {cache_status} {cache_lookup_time} \
+{tls_version} {tls_cipher} {compression_ratio} {plugin_times}";
+
+        let p: Parser = format.into();
+        assert_eq!(
+            "192.186.1.1:6188 502 3 1 2 10 12 MISS 1 tlsv1.3 TLS_AES_256_GCM_SHA384 3.3 limit:1,cache:3",
+            p.format(&session, &ctx)
+        );
+
+        let p: Parser = format!("json:{format}").as_str().into();
+        assert_eq!(
+            r#"{"cache_lookup_time":1,"cache_status":"MISS","compression_ratio":3.3,"plugin_times":{"cache":3,"limit":1},"tls_cipher":"TLS_AES_256_GCM_SHA384","tls_version":"tlsv1.3","upstream_addr":"192.186.1.1:6188","upstream_connect_time":3,"upstream_processing_time":10,"upstream_response_time":12,"upstream_status":502,"upstream_tcp_connect_time":1,"upstream_tls_handshake_time":2}"#,
+            p.format(&session, &ctx)
+        );
+    }
 }
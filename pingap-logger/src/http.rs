@@ -0,0 +1,649 @@
+// Copyright 2024-2025 Tree xie.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use super::Error;
+use humantime::parse_duration;
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::io;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{sync_channel, Receiver, RecvTimeoutError, SyncSender};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use url::Url;
+
+type Result<T, E = Error> = std::result::Result<T, E>;
+
+const DEFAULT_BATCH_SIZE: usize = 500;
+const DEFAULT_BUFFER_SIZE: usize = 10_000;
+const DEFAULT_FLUSH_INTERVAL: Duration = Duration::from_secs(1);
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(10);
+const DEFAULT_MAX_RETRIES: u32 = 3;
+const DEFAULT_INDEX: &str = "pingap";
+/// Initial delay of retry, it's doubled for each retry
+const RETRY_BASE_DELAY: Duration = Duration::from_millis(100);
+
+/// Format of the http log sink
+#[derive(Debug, Clone, PartialEq)]
+enum HttpSinkFormat {
+    /// Elasticsearch bulk api with ndjson body
+    Elasticsearch { index: String },
+    /// Loki push api with json body
+    Loki { labels: Vec<(String, String)> },
+}
+
+/// Parameters of http log sink, they are parsed from the query of url,
+/// e.g. `http://127.0.0.1:9200/_bulk?index=pingap&batch_size=100&flush_interval=2s`
+#[derive(Debug, Clone)]
+struct HttpSinkParams {
+    /// The endpoint without query
+    endpoint: String,
+    format: HttpSinkFormat,
+    /// Max count of lines of one request
+    batch_size: usize,
+    /// Max count of buffered lines, the new lines are dropped if the buffer is full
+    buffer_size: usize,
+    /// Max interval between two requests
+    flush_interval: Duration,
+    /// Timeout of request
+    timeout: Duration,
+    /// Max retries of failed request
+    max_retries: u32,
+}
+
+impl HttpSinkParams {
+    fn new(value: &str) -> Result<Self> {
+        let mut info = Url::parse(value).map_err(|e| Error::Invalid {
+            message: e.to_string(),
+        })?;
+        let mut params = Self {
+            endpoint: "".to_string(),
+            format: HttpSinkFormat::Elasticsearch {
+                index: DEFAULT_INDEX.to_string(),
+            },
+            batch_size: DEFAULT_BATCH_SIZE,
+            buffer_size: DEFAULT_BUFFER_SIZE,
+            flush_interval: DEFAULT_FLUSH_INTERVAL,
+            timeout: DEFAULT_TIMEOUT,
+            max_retries: DEFAULT_MAX_RETRIES,
+        };
+        let mut loki = info.path().ends_with("/loki/api/v1/push");
+        let mut index = DEFAULT_INDEX.to_string();
+        let mut labels = vec![];
+        for (key, value) in info.query_pairs() {
+            match key.as_ref() {
+                "format" => loki = value == "loki",
+                "index" => index = value.to_string(),
+                "labels" => {
+                    labels = value
+                        .split(',')
+                        .filter_map(|item| item.split_once(':'))
+                        .map(|(k, v)| {
+                            (k.trim().to_string(), v.trim().to_string())
+                        })
+                        .collect();
+                },
+                "batch_size" => {
+                    if let Ok(v) = value.parse::<usize>() {
+                        params.batch_size = v.max(1);
+                    }
+                },
+                "buffer_size" => {
+                    if let Ok(v) = value.parse::<usize>() {
+                        params.buffer_size = v.max(1);
+                    }
+                },
+                "flush_interval" => {
+                    if let Ok(v) = parse_duration(&value) {
+                        params.flush_interval = v;
+                    }
+                },
+                "timeout" => {
+                    if let Ok(v) = parse_duration(&value) {
+                        params.timeout = v;
+                    }
+                },
+                "max_retries" => {
+                    if let Ok(v) = value.parse::<u32>() {
+                        params.max_retries = v;
+                    }
+                },
+                _ => {},
+            }
+        }
+        params.format = if loki {
+            if labels.is_empty() {
+                labels.push(("job".to_string(), "pingap".to_string()));
+            }
+            HttpSinkFormat::Loki { labels }
+        } else {
+            HttpSinkFormat::Elasticsearch { index }
+        };
+        // the query is used for config, it should not be sent to server
+        info.set_query(None);
+        params.endpoint = info.to_string();
+        Ok(params)
+    }
+}
+
+/// Counters of http log sink
+#[derive(Debug, Default)]
+struct HttpSinkCounter {
+    sent: AtomicU64,
+    dropped: AtomicU64,
+    retries: AtomicU64,
+}
+
+/// Statistics of http log sink
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct HttpLogSinkStats {
+    /// The endpoint of sink
+    pub endpoint: String,
+    /// Count of lines sent successfully
+    pub sent: u64,
+    /// Count of lines dropped because the buffer is full or the request fails
+    pub dropped: u64,
+    /// Count of retried requests
+    pub retries: u64,
+}
+
+/// Counters of http log sinks keyed by endpoint, the value is the id of
+/// sink and its counter.
+type HttpSinkCounters = HashMap<String, (u64, Arc<HttpSinkCounter>)>;
+static HTTP_SINK_COUNTERS: Lazy<Mutex<HttpSinkCounters>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+static HTTP_SINK_ID: AtomicU64 = AtomicU64::new(0);
+
+/// Returns the statistics of all http log sinks
+pub fn get_http_log_sink_stats() -> Vec<HttpLogSinkStats> {
+    let Ok(counters) = HTTP_SINK_COUNTERS.lock() else {
+        return vec![];
+    };
+    let mut stats: Vec<_> = counters
+        .iter()
+        .map(|(endpoint, (_, counter))| HttpLogSinkStats {
+            endpoint: endpoint.clone(),
+            sent: counter.sent.load(Ordering::Relaxed),
+            dropped: counter.dropped.load(Ordering::Relaxed),
+            retries: counter.retries.load(Ordering::Relaxed),
+        })
+        .collect();
+    stats.sort_by(|a, b| a.endpoint.cmp(&b.endpoint));
+    stats
+}
+
+/// Removes the counter of sink when its worker exits
+fn remove_http_sink_counter(endpoint: &str, id: u64) {
+    if let Ok(mut counters) = HTTP_SINK_COUNTERS.lock() {
+        // the counter may be replaced by the new sink of the same endpoint
+        if counters.get(endpoint).map(|(value, _)| *value) == Some(id) {
+            counters.remove(endpoint);
+        }
+    }
+}
+
+/// Batching log sink which sends lines to an Elasticsearch bulk endpoint
+/// or Loki push api. The lines are buffered in a bounded channel and sent
+/// by a dedicated thread, so writing log never blocks.
+#[derive(Clone)]
+pub struct HttpLogSink {
+    sender: SyncSender<String>,
+    counter: Arc<HttpSinkCounter>,
+}
+
+impl io::Write for HttpLogSink {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let value = std::str::from_utf8(buf)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        for line in value.split('\n') {
+            let line = line.trim_end_matches('\r');
+            if line.is_empty() {
+                continue;
+            }
+            // drop the line if the buffer is full or the worker is stopped
+            if self.sender.try_send(line.to_string()).is_err() {
+                self.counter.dropped.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Creates a http log sink, the url should be `http://` or `https://`.
+/// The options are set by the query of url:
+/// - `format`: `loki` or `elasticsearch`(default), it's loki if the path is `/loki/api/v1/push`
+/// - `index`: index of elasticsearch, default is `pingap`
+/// - `labels`: labels of loki stream, e.g. `job:pingap,env:prod`
+/// - `batch_size`, `buffer_size`, `flush_interval`, `timeout`, `max_retries`
+pub fn new_http_log_sink(value: &str) -> Result<HttpLogSink> {
+    let params = HttpSinkParams::new(value)?;
+    let (sender, receiver) = sync_channel(params.buffer_size);
+    let counter = Arc::new(HttpSinkCounter::default());
+    let id = HTTP_SINK_ID.fetch_add(1, Ordering::Relaxed);
+    let endpoint = params.endpoint.clone();
+    if let Ok(mut counters) = HTTP_SINK_COUNTERS.lock() {
+        counters.insert(endpoint.clone(), (id, counter.clone()));
+    }
+    let worker_counter = counter.clone();
+    let worker_endpoint = endpoint.clone();
+    std::thread::Builder::new()
+        .name("pingap-http-log".to_string())
+        .spawn(move || {
+            // the logs of http client should not be sent to the sink again
+            tracing::dispatcher::with_default(
+                &tracing::Dispatch::none(),
+                || run_http_sink_worker(params, receiver, worker_counter),
+            );
+            // the worker exits after all senders of sink are dropped
+            remove_http_sink_counter(&worker_endpoint, id);
+        })
+        .map_err(|e| {
+            remove_http_sink_counter(&endpoint, id);
+            Error::Io { source: e }
+        })?;
+    Ok(HttpLogSink { sender, counter })
+}
+
+fn now_ns() -> u128 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos()
+}
+
+/// Converts the line to a json object, the plain text line is
+/// wrapped as `{"message": "...", "@timestamp": ...}`
+fn to_json_line(line: &str) -> String {
+    if line.starts_with('{') {
+        return line.to_string();
+    }
+    serde_json::json!({
+        "@timestamp": chrono::Utc::now().to_rfc3339(),
+        "message": line,
+    })
+    .to_string()
+}
+
+/// Creates the request body of lines
+fn new_body(
+    format: &HttpSinkFormat,
+    lines: &[String],
+) -> (Vec<u8>, &'static str) {
+    match format {
+        HttpSinkFormat::Elasticsearch { index } => {
+            let action = serde_json::json!({
+                "index": { "_index": index }
+            })
+            .to_string();
+            let mut buf = vec![];
+            for line in lines {
+                buf.extend_from_slice(action.as_bytes());
+                buf.push(b'\n');
+                buf.extend_from_slice(to_json_line(line).as_bytes());
+                buf.push(b'\n');
+            }
+            (buf, "application/x-ndjson")
+        },
+        HttpSinkFormat::Loki { labels } => {
+            let stream: serde_json::Map<String, serde_json::Value> = labels
+                .iter()
+                .map(|(k, v)| (k.clone(), serde_json::Value::from(v.as_str())))
+                .collect();
+            let now = now_ns();
+            let values: Vec<[String; 2]> = lines
+                .iter()
+                .enumerate()
+                // keep the order of lines in the same batch
+                .map(|(index, line)| {
+                    [(now + index as u128).to_string(), line.clone()]
+                })
+                .collect();
+            let body = serde_json::json!({
+                "streams": [{
+                    "stream": stream,
+                    "values": values,
+                }]
+            });
+            (body.to_string().into_bytes(), "application/json")
+        },
+    }
+}
+
+/// Sends the lines to server, the failed request is retried with backoff
+/// Returns the count of failed items of elasticsearch bulk response,
+/// the response status is 200 even if some items are failed,
+/// so the `errors` flag and the `error` of each item should be checked.
+fn get_bulk_failed_count(body: &[u8]) -> usize {
+    let Ok(value) = serde_json::from_slice::<serde_json::Value>(body) else {
+        return 0;
+    };
+    if !value
+        .get("errors")
+        .and_then(|v| v.as_bool())
+        .unwrap_or_default()
+    {
+        return 0;
+    }
+    let Some(items) = value.get("items").and_then(|v| v.as_array()) else {
+        return 0;
+    };
+    items
+        .iter()
+        // each item is an object with the action as key, e.g. {"index": {...}}
+        .filter(|item| {
+            item.as_object()
+                .map(|obj| obj.values().any(|v| v.get("error").is_some()))
+                .unwrap_or_default()
+        })
+        .count()
+}
+
+/// The request is not retried for client error except 429,
+/// because the same body will be rejected again.
+fn is_permanent_failure(status: http::StatusCode) -> bool {
+    status.is_client_error() && status != http::StatusCode::TOO_MANY_REQUESTS
+}
+
+fn drop_lines(
+    params: &HttpSinkParams,
+    counter: &HttpSinkCounter,
+    count: usize,
+    err: &str,
+) {
+    counter.dropped.fetch_add(count as u64, Ordering::Relaxed);
+    // use eprintln because the log of this thread is disabled
+    eprintln!(
+        "send logs to {} fail, {err}, {count} lines are dropped",
+        params.endpoint,
+    );
+}
+
+async fn send_lines(
+    client: &reqwest::Client,
+    params: &HttpSinkParams,
+    counter: &HttpSinkCounter,
+    lines: &[String],
+) {
+    let (body, content_type) = new_body(&params.format, lines);
+    let mut delay = RETRY_BASE_DELAY;
+    let mut attempt = 0;
+    loop {
+        let result = client
+            .post(&params.endpoint)
+            .header(http::header::CONTENT_TYPE, content_type)
+            .body(body.clone())
+            .send()
+            .await;
+        let err = match result {
+            Ok(resp) if resp.status().is_success() => {
+                let failed = match params.format {
+                    HttpSinkFormat::Elasticsearch { .. } => resp
+                        .bytes()
+                        .await
+                        .map(|body| get_bulk_failed_count(&body))
+                        .unwrap_or_default()
+                        .min(lines.len()),
+                    _ => 0,
+                };
+                counter.sent.fetch_add(
+                    (lines.len() - failed) as u64,
+                    Ordering::Relaxed,
+                );
+                // the failed items are not retried, otherwise the
+                // successful items of the same request are duplicated
+                if failed > 0 {
+                    drop_lines(params, counter, failed, "bulk items error");
+                }
+                return;
+            },
+            Ok(resp) if is_permanent_failure(resp.status()) => {
+                drop_lines(
+                    params,
+                    counter,
+                    lines.len(),
+                    &format!("status: {}", resp.status()),
+                );
+                return;
+            },
+            Ok(resp) => format!("status: {}", resp.status()),
+            Err(e) => e.to_string(),
+        };
+        if attempt >= params.max_retries {
+            drop_lines(params, counter, lines.len(), &err);
+            return;
+        }
+        attempt += 1;
+        counter.retries.fetch_add(1, Ordering::Relaxed);
+        tokio::time::sleep(delay).await;
+        delay *= 2;
+    }
+}
+
+fn run_http_sink_worker(
+    params: HttpSinkParams,
+    receiver: Receiver<String>,
+    counter: Arc<HttpSinkCounter>,
+) {
+    let runtime = match tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+    {
+        Ok(runtime) => runtime,
+        Err(e) => {
+            eprintln!("new runtime of http log sink fail, {e}");
+            return;
+        },
+    };
+    let client =
+        match reqwest::Client::builder().timeout(params.timeout).build() {
+            Ok(client) => client,
+            Err(e) => {
+                eprintln!("new client of http log sink fail, {e}");
+                return;
+            },
+        };
+    let mut lines = Vec::with_capacity(params.batch_size);
+    let mut deadline = Instant::now() + params.flush_interval;
+    loop {
+        let timeout = deadline.saturating_duration_since(Instant::now());
+        let closed = match receiver.recv_timeout(timeout) {
+            Ok(line) => {
+                lines.push(line);
+                false
+            },
+            Err(RecvTimeoutError::Timeout) => false,
+            Err(RecvTimeoutError::Disconnected) => true,
+        };
+        if !lines.is_empty()
+            && (closed
+                || lines.len() >= params.batch_size
+                || Instant::now() >= deadline)
+        {
+            runtime.block_on(send_lines(&client, &params, &counter, &lines));
+            lines.clear();
+        }
+        if closed {
+            return;
+        }
+        if Instant::now() >= deadline || lines.is_empty() {
+            deadline = Instant::now() + params.flush_interval;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+
+    /// Starts a local http server, the first request fails with 500
+    /// and the bodies of the successful requests are collected.
+    fn start_http_server() -> (String, Arc<Mutex<Vec<String>>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let bodies = Arc::new(Mutex::new(vec![]));
+        let result = bodies.clone();
+        std::thread::spawn(move || {
+            for (index, stream) in listener.incoming().enumerate() {
+                let mut stream = stream.unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut content_length = 0;
+                loop {
+                    let mut line = String::new();
+                    reader.read_line(&mut line).unwrap();
+                    let line = line.trim().to_lowercase();
+                    if line.is_empty() {
+                        break;
+                    }
+                    if let Some(value) = line.strip_prefix("content-length:") {
+                        content_length = value.trim().parse().unwrap();
+                    }
+                }
+                let mut body = vec![0; content_length];
+                reader.read_exact(&mut body).unwrap();
+                let status = if index == 0 {
+                    "500 Internal Server Error"
+                } else {
+                    bodies
+                        .lock()
+                        .unwrap()
+                        .push(String::from_utf8(body).unwrap());
+                    "200 OK"
+                };
+                stream
+                    .write_all(
+                        format!("HTTP/1.1 {status}\r\ncontent-length: 0\r\nconnection: close\r\n\r\n")
+                            .as_bytes(),
+                    )
+                    .unwrap();
+            }
+        });
+        (format!("http://{addr}"), result)
+    }
+
+    #[test]
+    fn test_http_sink_params() {
+        let params = HttpSinkParams::new(
+            "http://127.0.0.1:3100/loki/api/v1/push?labels=job:pingap,env:prod&batch_size=10&flush_interval=2s",
+        )
+        .unwrap();
+        assert_eq!("http://127.0.0.1:3100/loki/api/v1/push", params.endpoint);
+        assert_eq!(
+            HttpSinkFormat::Loki {
+                labels: vec![
+                    ("job".to_string(), "pingap".to_string()),
+                    ("env".to_string(), "prod".to_string())
+                ]
+            },
+            params.format
+        );
+        assert_eq!(10, params.batch_size);
+        assert_eq!(Duration::from_secs(2), params.flush_interval);
+
+        let params =
+            HttpSinkParams::new("http://127.0.0.1:9200/_bulk?index=logs")
+                .unwrap();
+        assert_eq!("http://127.0.0.1:9200/_bulk", params.endpoint);
+        assert_eq!(
+            HttpSinkFormat::Elasticsearch {
+                index: "logs".to_string()
+            },
+            params.format
+        );
+    }
+
+    #[test]
+    fn test_get_bulk_failed_count() {
+        assert_eq!(0, get_bulk_failed_count(b""));
+        assert_eq!(
+            0,
+            get_bulk_failed_count(
+                br#"{"errors":false,"items":[{"index":{"status":201}}]}"#
+            )
+        );
+        assert_eq!(
+            1,
+            get_bulk_failed_count(
+                br#"{"errors":true,"items":[{"index":{"status":201}},{"index":{"status":400,"error":{"type":"mapper_parsing_exception"}}}]}"#
+            )
+        );
+    }
+
+    #[test]
+    fn test_is_permanent_failure() {
+        assert_eq!(true, is_permanent_failure(http::StatusCode::BAD_REQUEST));
+        assert_eq!(true, is_permanent_failure(http::StatusCode::NOT_FOUND));
+        assert_eq!(
+            false,
+            is_permanent_failure(http::StatusCode::TOO_MANY_REQUESTS)
+        );
+        assert_eq!(
+            false,
+            is_permanent_failure(http::StatusCode::INTERNAL_SERVER_ERROR)
+        );
+    }
+
+    #[test]
+    fn test_http_log_sink() {
+        let (addr, bodies) = start_http_server();
+        let mut sink = new_http_log_sink(&format!(
+            "{addr}/_bulk?index=access&flush_interval=100ms"
+        ))
+        .unwrap();
+        sink.write_all(b"GET /ping 200\n").unwrap();
+        sink.write_all(b"{\"status\":404}\n").unwrap();
+        let endpoint = format!("{addr}/_bulk");
+        let get_stats = || {
+            get_http_log_sink_stats()
+                .into_iter()
+                .find(|item| item.endpoint == endpoint)
+        };
+
+        for _ in 0..50 {
+            if get_stats().map(|item| item.sent).unwrap_or_default() > 0 {
+                break;
+            }
+            std::thread::sleep(Duration::from_millis(100));
+        }
+        let stats = get_stats().unwrap();
+        assert_eq!(2, stats.sent);
+        assert_eq!(0, stats.dropped);
+        assert_eq!(1, stats.retries);
+
+        let bodies = bodies.lock().unwrap();
+        assert_eq!(1, bodies.len());
+        let lines: Vec<&str> = bodies[0].lines().collect();
+        assert_eq!(4, lines.len());
+        assert_eq!(r#"{"index":{"_index":"access"}}"#, lines[0]);
+        assert_eq!(true, lines[1].contains(r#""message":"GET /ping 200""#));
+        assert_eq!(r#"{"status":404}"#, lines[3]);
+
+        // the counter is removed after the worker exits
+        drop(sink);
+        for _ in 0..50 {
+            if get_stats().is_none() {
+                break;
+            }
+            std::thread::sleep(Duration::from_millis(100));
+        }
+        assert_eq!(true, get_stats().is_none());
+    }
+}
@@ -15,6 +15,7 @@
 use snafu::Snafu;
 
 mod access;
+mod http;
 #[cfg(unix)]
 mod syslog;
 mod writer;
@@ -30,4 +31,7 @@ pub enum Error {
 }
 
 pub use access::*;
+pub use http::{
+    get_http_log_sink_stats, new_http_log_sink, HttpLogSink, HttpLogSinkStats,
+};
 pub use writer::*;
@@ -12,6 +12,7 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use super::http::new_http_log_sink;
 #[cfg(unix)]
 use super::syslog::new_syslog_writer;
 use super::{Error, LOG_CATEGORY};
@@ -19,20 +20,29 @@ use bytesize::ByteSize;
 use chrono::Timelike;
 use flate2::write::GzEncoder;
 use flate2::Compression;
+use once_cell::sync::Lazy;
 use pingap_core::convert_query_map;
 use pingap_core::Error as ServiceError;
 use pingap_core::SimpleServiceTaskFuture;
+use serde::Serialize;
+use std::collections::HashMap;
 use std::fs;
 use std::io;
+use std::io::Write;
 #[cfg(unix)]
 use std::os::unix::fs::MetadataExt;
 #[cfg(windows)]
 use std::os::windows::fs::MetadataExt;
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Mutex;
 use std::time::{Duration, SystemTime};
 use tracing::{error, info, Level};
-use tracing_subscriber::fmt::writer::BoxMakeWriter;
+use tracing_appender::non_blocking::{
+    ErrorCounter, NonBlocking, NonBlockingBuilder, WorkerGuard,
+};
+use tracing_appender::rolling::RollingFileAppender;
+use tracing_subscriber::fmt::writer::{BoxMakeWriter, MakeWriter};
 use walkdir::WalkDir;
 
 const DEFAULT_COMPRESSION_LEVEL: u8 = 9;
@@ -261,17 +271,23 @@ pub struct LoggerParams {
     pub json: bool,
 }
 
-fn new_file_writer(
-    params: &LoggerParams,
-) -> Result<(BoxMakeWriter, Option<(String, SimpleServiceTaskFuture)>)> {
-    let mut file = pingap_util::resolve_path(&params.log);
+/// Creates a rolling file appender of the log path,
+/// the rolling and compression options are set by the query of path,
+/// e.g. `/var/log/pingap.log?rolling=hourly&compression=zstd`
+fn new_rolling_file_appender(
+    log: &str,
+) -> Result<(
+    RollingFileAppender,
+    Option<(String, SimpleServiceTaskFuture)>,
+)> {
+    let mut file = pingap_util::resolve_path(log);
     let mut rolling_type = "".to_string();
     let mut compression = "".to_string();
     let mut level = 0;
     let mut days_ago = 0;
     let mut time_point_hour = 0;
     let mut task = None;
-    if let Some((_, query)) = params.log.split_once('?') {
+    if let Some((_, query)) = log.split_once('?') {
         file = file.replace(&format!("?{query}"), "");
         let m = convert_query_map(query);
         if let Some(value) = m.get("rolling") {
@@ -327,7 +343,13 @@ fn new_file_writer(
         "never" => tracing_appender::rolling::never(dir, filename),
         _ => tracing_appender::rolling::daily(dir, filename),
     };
+    Ok((file_appender, task))
+}
 
+fn new_file_writer(
+    params: &LoggerParams,
+) -> Result<(BoxMakeWriter, Option<(String, SimpleServiceTaskFuture)>)> {
+    let (file_appender, task) = new_rolling_file_appender(&params.log)?;
     let writer = if params.capacity < MIN_BUFFER_CAPACITY {
         BoxMakeWriter::new(file_appender)
     } else {
@@ -382,6 +404,10 @@ pub fn logger_try_init(
     let mut log_type = "stdio";
     let writer = if params.log.is_empty() {
         BoxMakeWriter::new(std::io::stderr)
+    } else if is_http_output(&params.log) {
+        log_type = "http";
+        let sink = new_http_log_sink(&params.log)?;
+        BoxMakeWriter::new(move || sink.clone())
     } else if params.log.starts_with("syslog://") {
         #[cfg(unix)]
         {
@@ -420,3 +446,199 @@ pub fn logger_try_init(
 
     Ok(task)
 }
+
+/// Whether the log is sent to a http sink(elasticsearch or loki)
+fn is_http_output(output: &str) -> bool {
+    output.starts_with("http://") || output.starts_with("https://")
+}
+
+/// Buffer capacity of the access log file writer
+const ACCESS_LOG_BUFFER_CAPACITY: usize = 64 * 1024;
+
+/// Adapter which makes a `BoxMakeWriter` usable as `io::Write`
+#[cfg(unix)]
+struct MakeWriterAdapter(BoxMakeWriter);
+
+#[cfg(unix)]
+impl io::Write for MakeWriterAdapter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.make_writer().write(buf)
+    }
+    fn flush(&mut self) -> io::Result<()> {
+        self.0.make_writer().flush()
+    }
+}
+
+/// Statistics of access log writer
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct AccessLogWriterStats {
+    /// The output of access log
+    pub output: String,
+    /// Count of lines dropped because the channel of worker is full
+    pub dropped: u64,
+}
+
+/// Dropped line counters of access log writers, the key is the output,
+/// the value is the id of writer and its counter.
+static ACCESS_LOG_COUNTERS: Lazy<Mutex<HashMap<String, (u64, ErrorCounter)>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+static ACCESS_LOG_WRITER_ID: AtomicU64 = AtomicU64::new(0);
+
+/// Returns the statistics of all access log writers
+pub fn get_access_log_writer_stats() -> Vec<AccessLogWriterStats> {
+    let Ok(counters) = ACCESS_LOG_COUNTERS.lock() else {
+        return vec![];
+    };
+    let mut stats: Vec<_> = counters
+        .iter()
+        .map(|(output, (_, counter))| AccessLogWriterStats {
+            output: output.clone(),
+            dropped: counter.dropped_lines() as u64,
+        })
+        .collect();
+    stats.sort_by(|a, b| a.output.cmp(&b.output));
+    stats
+}
+
+/// Dedicated writer of access log.
+/// The log lines are sent to a worker thread through a channel,
+/// so writing access log does not block the request
+/// and does not contend with the application log.
+/// The lines are dropped if the channel is full, the count of
+/// dropped lines is got by `get_access_log_writer_stats`.
+pub struct AccessLogWriter {
+    id: u64,
+    output: String,
+    writer: NonBlocking,
+    // the worker thread is stopped when the guard is dropped
+    _guard: WorkerGuard,
+}
+
+impl Drop for AccessLogWriter {
+    fn drop(&mut self) {
+        if let Ok(mut counters) = ACCESS_LOG_COUNTERS.lock() {
+            // the counter may be replaced by the new writer of the same output
+            if counters.get(&self.output).map(|(id, _)| *id) == Some(self.id) {
+                counters.remove(&self.output);
+            }
+        }
+    }
+}
+
+impl AccessLogWriter {
+    /// Writes a line of access log, the line break is appended
+    pub fn write(&self, line: &str) {
+        let mut buf = Vec::with_capacity(line.len() + 1);
+        buf.extend_from_slice(line.as_bytes());
+        buf.push(b'\n');
+        let mut writer = self.writer.clone();
+        if let Err(e) = writer.write_all(&buf) {
+            error!(
+                category = LOG_CATEGORY,
+                error = %e,
+                "write access log fail"
+            );
+        }
+    }
+}
+
+/// Creates a dedicated writer of access log, the output supports:
+/// - `stdout` or `stderr`
+/// - `syslog://...`, it's only supported on unix systems
+/// - `http://...` or `https://...`, elasticsearch bulk api or loki push api
+/// - file path with the same query options of application log,
+///   e.g. `/var/log/access.log?rolling=hourly&compression=zstd`
+///
+/// # Returns
+/// The access log writer and the optional compression service task
+pub fn new_access_log_writer(
+    output: &str,
+) -> Result<(AccessLogWriter, Option<(String, SimpleServiceTaskFuture)>)> {
+    let mut task = None;
+    let writer: Box<dyn io::Write + Send> = match output {
+        "stdout" => Box::new(io::stdout()),
+        "stderr" => Box::new(io::stderr()),
+        _ if is_http_output(output) => Box::new(new_http_log_sink(output)?),
+        _ if output.starts_with("syslog://") => {
+            #[cfg(unix)]
+            {
+                Box::new(MakeWriterAdapter(new_syslog_writer(output)?))
+            }
+            #[cfg(not(unix))]
+            {
+                return Err(Error::Invalid {
+                    message: "syslog is only supported on Unix systems"
+                        .to_string(),
+                });
+            }
+        },
+        _ => {
+            let (file_appender, t) = new_rolling_file_appender(output)?;
+            task = t.map(|(_, t)| (format!("access_log_compress:{output}"), t));
+            // the worker flushes the buffer after the pending lines are written
+            Box::new(io::BufWriter::with_capacity(
+                ACCESS_LOG_BUFFER_CAPACITY,
+                file_appender,
+            ))
+        },
+    };
+    let (writer, guard) = NonBlockingBuilder::default()
+        .lossy(true)
+        .thread_name("pingap-access-log")
+        .finish(writer);
+    let id = ACCESS_LOG_WRITER_ID.fetch_add(1, Ordering::Relaxed);
+    if let Ok(mut counters) = ACCESS_LOG_COUNTERS.lock() {
+        counters.insert(output.to_string(), (id, writer.error_counter()));
+    }
+    Ok((
+        AccessLogWriter {
+            id,
+            output: output.to_string(),
+            writer,
+            _guard: guard,
+        },
+        task,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_new_access_log_writer() {
+        let dir = TempDir::new().unwrap();
+        let file = dir.path().join("access.log");
+        let output = format!(
+            "{}?rolling=never&compression=zstd",
+            file.to_string_lossy()
+        );
+        let (writer, task) = new_access_log_writer(&output).unwrap();
+        assert_eq!(format!("access_log_compress:{output}"), task.unwrap().0);
+        writer.write("GET /ping 200");
+        writer.write("GET /users 404");
+        assert_eq!(
+            true,
+            get_access_log_writer_stats().contains(&AccessLogWriterStats {
+                output: output.clone(),
+                dropped: 0,
+            })
+        );
+        // the pending lines are flushed when the worker is stopped
+        drop(writer);
+        assert_eq!(
+            "GET /ping 200\nGET /users 404\n",
+            fs::read_to_string(&file).unwrap()
+        );
+        assert_eq!(
+            false,
+            get_access_log_writer_stats()
+                .iter()
+                .any(|item| item.output == output)
+        );
+
+        assert_eq!(true, new_access_log_writer("stdout").unwrap().1.is_none());
+    }
+}
@@ -12,9 +12,11 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+mod metrics;
 mod provider;
 mod tracer;
 
+pub use metrics::{get_metrics, MetricsService, OtelMetrics};
 pub use opentelemetry::{global, trace, KeyValue};
 pub use opentelemetry_http::HeaderExtractor;
 pub use tracer::*;
@@ -0,0 +1,845 @@
+// Copyright 2024-2025 Tree xie.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use ahash::AHashMap;
+use arc_swap::ArcSwap;
+use async_trait::async_trait;
+use humantime::parse_duration;
+use once_cell::sync::Lazy;
+use opentelemetry::metrics::{
+    Counter, Gauge, Histogram, Meter, MeterProvider, UpDownCounter,
+};
+use opentelemetry::KeyValue;
+use opentelemetry_otlp::{
+    Compression, Protocol, WithExportConfig, WithTonicConfig,
+};
+use opentelemetry_sdk::{
+    metrics::{PeriodicReader, SdkMeterProvider},
+    Resource,
+};
+use pingap_core::Ctx;
+use pingap_performance::{get_code_label, get_process_system_info};
+use pingap_util::now_ms;
+use pingora::proxy::Session;
+use pingora::{server::ShutdownWatch, services::background::BackgroundService};
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{error, info, warn};
+use url::Url;
+
+const LOG_CATEGORY: &str = "otel";
+/// Default timeout for exporting metrics
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(3);
+/// Default interval between two exports
+const DEFAULT_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Milliseconds to seconds conversion factor
+const SECOND: f64 = 1000.0;
+
+/// Configuration for the metrics service
+#[derive(Debug, Clone)]
+pub struct MetricsConfig {
+    /// Timeout duration for exporting metrics
+    timeout: Duration,
+    /// Interval between two exports of metrics
+    interval: Duration,
+    /// Compression of grpc export, it's not supported by http protocol
+    compression: Option<Compression>,
+    /// Export protocol, grpc or http with binary protobuf
+    protocol: Protocol,
+}
+
+impl Default for MetricsConfig {
+    fn default() -> Self {
+        Self {
+            timeout: DEFAULT_TIMEOUT,
+            interval: DEFAULT_INTERVAL,
+            compression: None,
+            protocol: Protocol::Grpc,
+        }
+    }
+}
+
+impl MetricsConfig {
+    /// Parses configuration options from URL query parameters
+    ///
+    /// # Arguments
+    /// * `url` - The parsed URL containing query parameters
+    fn parse_query_params(&mut self, url: &Url) {
+        for (key, value) in url.query_pairs() {
+            match key.as_ref() {
+                "timeout" => {
+                    if let Ok(v) = parse_duration(&value) {
+                        self.timeout = v;
+                    }
+                },
+                "interval" => {
+                    if let Ok(v) = parse_duration(&value) {
+                        self.interval = v;
+                    }
+                },
+                "compression" => {
+                    if value.to_lowercase() == "zstd" {
+                        self.compression = Some(Compression::Zstd);
+                    } else {
+                        self.compression = Some(Compression::Gzip);
+                    }
+                },
+                "protocol" => {
+                    if value.to_lowercase() == "http" {
+                        self.protocol = Protocol::HttpBinary;
+                    } else {
+                        self.protocol = Protocol::Grpc;
+                    }
+                },
+                _ => {},
+            }
+        }
+        // the http exporter does not support compression
+        if self.protocol != Protocol::Grpc && self.compression.is_some() {
+            warn!(
+                category = LOG_CATEGORY,
+                "compression is not supported by http protocol, it is ignored"
+            );
+            self.compression = None;
+        }
+    }
+}
+
+/// Service for exporting metrics to an OpenTelemetry collector
+///
+/// The metrics are the same as the prometheus metrics of pingap-performance,
+/// they are recorded by the server and exported periodically.
+#[derive(Debug)]
+pub struct MetricsService {
+    name: String,
+    endpoint: String,
+    config: MetricsConfig,
+}
+
+impl MetricsService {
+    /// Creates a new MetricsService, the configuration is parsed from
+    /// the query of endpoint, e.g. http://localhost:4317?interval=30s
+    pub fn new(name: &str, endpoint: &str) -> Self {
+        let mut config = MetricsConfig::default();
+        if let Ok(info) = Url::parse(endpoint) {
+            config.parse_query_params(&info);
+        }
+        Self {
+            name: name.to_string(),
+            endpoint: endpoint.to_string(),
+            config,
+        }
+    }
+}
+
+/// Global storage for otel metrics, mapping server names to metrics instances.
+type OtelMetricsMap = AHashMap<String, Arc<OtelMetrics>>;
+
+static OTEL_METRICS_MAP: Lazy<ArcSwap<OtelMetricsMap>> =
+    Lazy::new(|| ArcSwap::from_pointee(AHashMap::new()));
+
+/// Adds or updates the otel metrics of server
+fn add_metrics(name: &str, metrics: OtelMetrics) {
+    let mut m: OtelMetricsMap = AHashMap::new();
+    for (name, metrics) in OTEL_METRICS_MAP.load().iter() {
+        m.insert(name.to_string(), metrics.clone());
+    }
+    m.insert(name.to_string(), Arc::new(metrics));
+    OTEL_METRICS_MAP.store(Arc::new(m));
+}
+
+/// Removes the otel metrics of server
+fn remove_metrics(name: &str) {
+    let mut m: OtelMetricsMap = AHashMap::new();
+    for (key, metrics) in OTEL_METRICS_MAP.load().iter() {
+        if key != name {
+            m.insert(key.to_string(), metrics.clone());
+        }
+    }
+    OTEL_METRICS_MAP.store(Arc::new(m));
+}
+
+/// Gets the otel metrics of server, it will be None
+/// if the metrics service is not enabled or not started.
+#[inline]
+pub fn get_metrics(name: &str) -> Option<Arc<OtelMetrics>> {
+    OTEL_METRICS_MAP.load().get(name).cloned()
+}
+
+/// OpenTelemetry instruments of http server,
+/// they mirror the prometheus metrics of pingap-performance.
+pub struct OtelMetrics {
+    http_requests_total: Counter<u64>,
+    http_requests_current: UpDownCounter<i64>,
+    http_received: Histogram<f64>,
+    http_received_bytes: Counter<u64>,
+    http_responses_codes: Counter<u64>,
+    http_response_time: Histogram<f64>,
+    http_sent: Histogram<f64>,
+    http_sent_bytes: Counter<u64>,
+    connection_reuses: Counter<u64>,
+    tls_handshake_time: Histogram<f64>,
+    upstream_connections: Gauge<i64>,
+    upstream_connections_current: Gauge<i64>,
+    upstream_tcp_connect_time: Histogram<f64>,
+    upstream_tls_handshake_time: Histogram<f64>,
+    upstream_reuses: Counter<u64>,
+    upstream_processing_time: Histogram<f64>,
+    upstream_response_time: Histogram<f64>,
+    upstream_responses_codes: Counter<u64>,
+    upstream_latency: Histogram<f64>,
+    cache_status: Counter<u64>,
+    cache_lookup_time: Histogram<f64>,
+    cache_lock_time: Histogram<f64>,
+    cache_reading: Gauge<i64>,
+    cache_writing: Gauge<i64>,
+    compression_ratio: Histogram<f64>,
+    rate_limit_rejections: Counter<u64>,
+    plugin_processing_time: Histogram<f64>,
+}
+
+fn new_histogram(
+    meter: &Meter,
+    name: &'static str,
+    help: &'static str,
+    unit: &'static str,
+    buckets: &[f64],
+) -> Histogram<f64> {
+    meter
+        .f64_histogram(name)
+        .with_description(help)
+        .with_unit(unit)
+        .with_boundaries(buckets.to_vec())
+        .build()
+}
+
+fn new_counter(
+    meter: &Meter,
+    name: &'static str,
+    help: &'static str,
+) -> Counter<u64> {
+    meter.u64_counter(name).with_description(help).build()
+}
+
+fn new_gauge(
+    meter: &Meter,
+    name: &'static str,
+    help: &'static str,
+) -> Gauge<i64> {
+    meter.i64_gauge(name).with_description(help).build()
+}
+
+/// Registers the observable gauges of process, they are collected on every export.
+fn register_process_gauges(meter: &Meter) {
+    meter
+        .i64_observable_gauge("pingap_memory")
+        .with_description("pingap memory size(mb)")
+        .with_callback(|observer| {
+            let info = get_process_system_info();
+            observer.observe(info.memory_mb as i64, &[]);
+        })
+        .build();
+    meter
+        .i64_observable_gauge("pingap_fd_count")
+        .with_description("pingap open file count")
+        .with_callback(|observer| {
+            let info = get_process_system_info();
+            observer.observe(info.fd_count as i64, &[]);
+        })
+        .build();
+    meter
+        .i64_observable_gauge("pingap_tcp_count")
+        .with_description("pingap tcp connections")
+        .with_callback(|observer| {
+            let info = get_process_system_info();
+            observer.observe(info.tcp_count as i64, &[]);
+        })
+        .build();
+    meter
+        .i64_observable_gauge("pingap_tcp6_count")
+        .with_description("pingap tcp6 connections")
+        .with_callback(|observer| {
+            let info = get_process_system_info();
+            observer.observe(info.tcp6_count as i64, &[]);
+        })
+        .build();
+}
+
+impl OtelMetrics {
+    /// Creates the instruments of http server from the meter
+    fn new(meter: &Meter) -> Self {
+        register_process_gauges(meter);
+        Self {
+            http_requests_total: new_counter(
+                meter,
+                "pingap_http_requests_total",
+                "pingap total http requests",
+            ),
+            http_requests_current: meter
+                .i64_up_down_counter("pingap_http_requests_current")
+                .with_description("pingap current http requests")
+                .build(),
+            http_received: new_histogram(
+                meter,
+                "pingap_http_received",
+                "pingap http received from clients(KB)",
+                "KiBy",
+                &[1.0, 5.0, 10.0, 50.0, 100.0, 500.0, 1000.0],
+            ),
+            http_received_bytes: new_counter(
+                meter,
+                "pingap_http_received_bytes",
+                "pingap http received from clients(bytes)",
+            ),
+            http_responses_codes: new_counter(
+                meter,
+                "pingap_http_responses_codes",
+                "pingap total responses of http codes",
+            ),
+            http_response_time: new_histogram(
+                meter,
+                "pingap_http_response_time",
+                "pingap http response time(second)",
+                "s",
+                &[
+                    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0,
+                    10.0,
+                ],
+            ),
+            http_sent: new_histogram(
+                meter,
+                "pingap_http_sent",
+                "pingap http sent to clients(KB)",
+                "KiBy",
+                &[1.0, 5.0, 10.0, 50.0, 100.0, 500.0, 1000.0, 5000.0],
+            ),
+            http_sent_bytes: new_counter(
+                meter,
+                "pingap_http_sent_bytes",
+                "pingap http sent to clients(bytes)",
+            ),
+            connection_reuses: new_counter(
+                meter,
+                "pingap_connection_reuses",
+                "pingap connection reuses during tcp connect",
+            ),
+            tls_handshake_time: new_histogram(
+                meter,
+                "pingap_tls_handshake_time",
+                "pingap tls handshake time(second)",
+                "s",
+                &[0.01, 0.05, 0.1, 0.5, 1.0],
+            ),
+            upstream_connections: new_gauge(
+                meter,
+                "pingap_upstream_connections",
+                "pingap connected connections of upstream",
+            ),
+            upstream_connections_current: new_gauge(
+                meter,
+                "pingap_upstream_connections_current",
+                "pingap current connections of upstream",
+            ),
+            upstream_tcp_connect_time: new_histogram(
+                meter,
+                "pingap_upstream_tcp_connect_time",
+                "pingap upstream tcp connect time(second)",
+                "s",
+                &[0.005, 0.01, 0.05, 0.1, 0.5, 1.0],
+            ),
+            upstream_tls_handshake_time: new_histogram(
+                meter,
+                "pingap_upstream_tls_handshake_time",
+                "pingap upstream tsl handshake time(second)",
+                "s",
+                &[0.01, 0.05, 0.1, 0.5, 1.0],
+            ),
+            upstream_reuses: new_counter(
+                meter,
+                "pingap_upstream_reuses",
+                "pingap connection reuse during connect to upstream",
+            ),
+            upstream_processing_time: new_histogram(
+                meter,
+                "pingap_upstream_processing_time",
+                "pingap upstream processing time(second)",
+                "s",
+                &[0.01, 0.02, 0.1, 0.5, 1.0, 5.0, 10.0],
+            ),
+            upstream_response_time: new_histogram(
+                meter,
+                "pingap_upstream_response_time",
+                "pingap upstream response time(second)",
+                "s",
+                &[0.005, 0.01, 0.05, 0.1, 0.5, 1.0],
+            ),
+            upstream_responses_codes: new_counter(
+                meter,
+                "pingap_upstream_responses_codes",
+                "pingap upstream response codes",
+            ),
+            upstream_latency: new_histogram(
+                meter,
+                "pingap_upstream_latency",
+                "pingap upstream latency of backend(second)",
+                "s",
+                &[0.005, 0.01, 0.05, 0.1, 0.5, 1.0, 5.0],
+            ),
+            cache_status: new_counter(
+                meter,
+                "pingap_cache_status",
+                "pingap cache status(hit, miss, stale, etc.)",
+            ),
+            cache_lookup_time: new_histogram(
+                meter,
+                "pingap_cache_lookup_time",
+                "pingap cache lookup time(second)",
+                "s",
+                &[0.001, 0.005, 0.01, 0.05, 0.1, 0.25, 0.5, 1.0],
+            ),
+            cache_lock_time: new_histogram(
+                meter,
+                "pingap_cache_lock_time",
+                "pingap cache lock time(second)",
+                "s",
+                &[0.01, 0.05, 0.1, 1.0, 3.0],
+            ),
+            cache_reading: new_gauge(
+                meter,
+                "pingap_cache_reading",
+                "pingap cache reading count",
+            ),
+            cache_writing: new_gauge(
+                meter,
+                "pingap_cache_writing",
+                "pingap cache writing count",
+            ),
+            compression_ratio: new_histogram(
+                meter,
+                "pingap_compression_ratio",
+                "pingap response compression ratio",
+                "1",
+                &[1.0, 2.0, 3.0, 5.0, 10.0],
+            ),
+            rate_limit_rejections: new_counter(
+                meter,
+                "pingap_rate_limit_rejections",
+                "pingap requests rejected by rate limit plugin",
+            ),
+            plugin_processing_time: new_histogram(
+                meter,
+                "pingap_plugin_processing_time",
+                "pingap plugin processing time(second)",
+                "s",
+                &[0.001, 0.005, 0.01, 0.05, 0.1, 0.5],
+            ),
+        }
+    }
+
+    /// Records the start of request processing for the location.
+    pub fn before(&self, location: &str) {
+        let attrs = [KeyValue::new("location", location.to_string())];
+        self.http_requests_total.add(1, &attrs);
+        self.http_requests_current.add(1, &attrs);
+    }
+
+    /// Records the metrics of request after it has been processed.
+    pub fn after(&self, session: &Session, ctx: &Ctx) {
+        let location = &ctx.location;
+        let upstream = &ctx.upstream;
+        let attrs = [KeyValue::new("location", location.to_string())];
+        let response_time =
+            now_ms().saturating_sub(ctx.created_at) as f64 / SECOND;
+        let code = ctx.status.map(|status| status.as_u16()).unwrap_or(0);
+        let sent_bytes = session.body_bytes_sent() as u64;
+
+        self.http_requests_current.add(-1, &attrs);
+        self.http_received
+            .record(ctx.payload_size as f64 / 1024.0, &attrs);
+        self.http_received_bytes
+            .add(ctx.payload_size as u64, &attrs);
+        self.http_response_time.record(response_time, &attrs);
+        self.http_sent.record(sent_bytes as f64 / 1024.0, &attrs);
+        if sent_bytes > 0 {
+            self.http_sent_bytes.add(sent_bytes, &attrs);
+        }
+        self.http_responses_codes.add(
+            1,
+            &[
+                KeyValue::new("location", location.to_string()),
+                KeyValue::new("code", get_code_label(code)),
+            ],
+        );
+
+        if ctx.connection_reused {
+            self.connection_reuses.add(1, &[]);
+        }
+        if let Some(tls_handshake_time) = ctx.tls_handshake_time {
+            self.tls_handshake_time
+                .record(tls_handshake_time as f64 / SECOND, &[]);
+        }
+
+        // upstream
+        if !upstream.is_empty() {
+            let upstream_attrs =
+                [KeyValue::new("upstream", upstream.to_string())];
+            if let Some(count) = ctx.upstream_connected {
+                self.upstream_connections
+                    .record(count as i64, &upstream_attrs);
+            }
+            if let Some(count) = ctx.upstream_processing {
+                self.upstream_connections_current
+                    .record(count as i64, &upstream_attrs);
+            }
+            if let Some(value) = ctx.upstream_tcp_connect_time {
+                self.upstream_tcp_connect_time
+                    .record(value as f64 / SECOND, &upstream_attrs);
+            }
+            if let Some(value) = ctx.upstream_tls_handshake_time {
+                self.upstream_tls_handshake_time
+                    .record(value as f64 / SECOND, &upstream_attrs);
+            }
+            if ctx.upstream_reused {
+                self.upstream_reuses.add(1, &upstream_attrs);
+            }
+            if let Some(value) = ctx.upstream_processing_time {
+                self.upstream_processing_time
+                    .record(value as f64 / SECOND, &upstream_attrs);
+            }
+            if let Some(value) = ctx.upstream_response_time {
+                self.upstream_response_time
+                    .record(value as f64 / SECOND, &upstream_attrs);
+            }
+            // upstream outcome by backend address
+            let addr_attrs = [
+                KeyValue::new("upstream", upstream.to_string()),
+                KeyValue::new("addr", ctx.upstream_address.clone()),
+            ];
+            let upstream_code_label = ctx
+                .upstream_status
+                .map(|status| get_code_label(status.as_u16()))
+                .unwrap_or("unknown");
+            self.upstream_responses_codes.add(
+                1,
+                &[
+                    addr_attrs[0].clone(),
+                    addr_attrs[1].clone(),
+                    KeyValue::new("code", upstream_code_label),
+                ],
+            );
+            if let Some(latency) =
+                ctx.upstream_response_time.or(ctx.upstream_processing_time)
+            {
+                self.upstream_latency
+                    .record(latency as f64 / SECOND, &addr_attrs);
+            }
+        }
+
+        // cache stats
+        if let Some(cache_status) = ctx.cache_status {
+            self.cache_status.add(
+                1,
+                &[
+                    KeyValue::new("location", location.to_string()),
+                    KeyValue::new("status", cache_status),
+                ],
+            );
+        }
+        if let Some(value) = ctx.cache_lookup_time {
+            self.cache_lookup_time.record(value as f64 / SECOND, &[]);
+        }
+        if let Some(value) = ctx.cache_lock_time {
+            self.cache_lock_time.record(value as f64 / SECOND, &[]);
+        }
+        if let Some(cache_reading) = ctx.cache_reading {
+            self.cache_reading.record(cache_reading as i64, &[]);
+        }
+        if let Some(cache_writing) = ctx.cache_writing {
+            self.cache_writing.record(cache_writing as i64, &[]);
+        }
+
+        // compression stats
+        if let Some(compression_stat) = &ctx.compression_stat {
+            self.compression_ratio.record(compression_stat.ratio(), &[]);
+        }
+
+        // plugin stats
+        if ctx.rate_limited {
+            let plugin = ctx.responded_plugin.clone().unwrap_or_default();
+            self.rate_limit_rejections
+                .add(1, &[KeyValue::new("plugin", plugin)]);
+        }
+        if let Some(times) = &ctx.plugin_processing_times {
+            for (plugin, time) in times.iter() {
+                self.plugin_processing_time.record(
+                    *time as f64 / SECOND,
+                    &[KeyValue::new("plugin", plugin.clone())],
+                );
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl BackgroundService for MetricsService {
+    /// Open telemetry metrics background service, it will export metrics periodically.
+    async fn start(&self, mut shutdown: ShutdownWatch) {
+        let result = if self.config.protocol == Protocol::Grpc {
+            let mut builder = opentelemetry_otlp::MetricExporter::builder()
+                .with_tonic()
+                .with_endpoint(&self.endpoint)
+                .with_timeout(self.config.timeout);
+            if let Some(compression) = self.config.compression {
+                builder = builder.with_compression(compression);
+            }
+            builder.build()
+        } else {
+            // the query is used for config, it should not be sent to collector,
+            // e.g. http://127.0.0.1:4318/v1/metrics?protocol=http
+            let endpoint = self
+                .endpoint
+                .split_once('?')
+                .map(|(endpoint, _)| endpoint)
+                .unwrap_or(&self.endpoint);
+            opentelemetry_otlp::MetricExporter::builder()
+                .with_http()
+                .with_protocol(self.config.protocol)
+                .with_endpoint(endpoint)
+                .with_timeout(self.config.timeout)
+                .build()
+        };
+
+        let result = result.map(|exporter| {
+            let reader = PeriodicReader::builder(exporter)
+                .with_interval(self.config.interval)
+                .build();
+            SdkMeterProvider::builder()
+                .with_reader(reader)
+                .with_resource(
+                    Resource::builder()
+                        .with_service_name(format!("pingap:{}", self.name))
+                        .build(),
+                )
+                .build()
+        });
+
+        match result {
+            Ok(meter_provider) => {
+                let meter = meter_provider.meter("http_proxy");
+                add_metrics(&self.name, OtelMetrics::new(&meter));
+                info!(
+                    category = LOG_CATEGORY,
+                    name = self.name,
+                    endpoint = self.endpoint,
+                    protocol = format!("{:?}", self.config.protocol),
+                    interval = format!("{:?}", self.config.interval),
+                    "opentelemetry metrics init success"
+                );
+
+                let _ = shutdown.changed().await;
+                remove_metrics(&self.name);
+                if let Err(e) = meter_provider.shutdown() {
+                    error!(
+                        category = LOG_CATEGORY,
+                        name = self.name,
+                        error = %e,
+                        "opentelemetry metrics shutdown fail"
+                    );
+                } else {
+                    info!(
+                        category = LOG_CATEGORY,
+                        name = self.name,
+                        "opentelemetry metrics shutdown success"
+                    );
+                }
+            },
+            Err(e) => {
+                error!(
+                    category = LOG_CATEGORY,
+                    name = self.name,
+                    error = %e,
+                    "opentelemetry metrics init fail"
+                );
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use opentelemetry_sdk::error::OTelSdkResult;
+    use opentelemetry_sdk::metrics::data::{
+        Histogram as HistogramData, ResourceMetrics, Sum,
+    };
+    use opentelemetry_sdk::metrics::reader::MetricReader;
+    use opentelemetry_sdk::metrics::{
+        InstrumentKind, ManualReader, MetricResult, Pipeline, Temporality,
+    };
+    use pingap_core::CompressionStat;
+    use pretty_assertions::assert_eq;
+    use std::sync::Weak;
+    use tokio_test::io::Builder;
+
+    /// Manual reader which can be collected after it's added to provider
+    #[derive(Debug, Clone)]
+    struct TestReader(Arc<ManualReader>);
+
+    impl MetricReader for TestReader {
+        fn register_pipeline(&self, pipeline: Weak<Pipeline>) {
+            self.0.register_pipeline(pipeline)
+        }
+        fn collect(&self, rm: &mut ResourceMetrics) -> MetricResult<()> {
+            self.0.collect(rm)
+        }
+        fn force_flush(&self) -> OTelSdkResult {
+            self.0.force_flush()
+        }
+        fn shutdown(&self) -> OTelSdkResult {
+            self.0.shutdown()
+        }
+        fn temporality(&self, kind: InstrumentKind) -> Temporality {
+            self.0.temporality(kind)
+        }
+    }
+
+    #[test]
+    fn test_metrics_config() {
+        let service = MetricsService::new(
+            "web",
+            "http://127.0.0.1:4317?interval=30s&timeout=5s&compression=zstd",
+        );
+        assert_eq!("web", service.name);
+        assert_eq!(Duration::from_secs(30), service.config.interval);
+        assert_eq!(Duration::from_secs(5), service.config.timeout);
+        assert_eq!(Some(Compression::Zstd), service.config.compression);
+        assert_eq!(Protocol::Grpc, service.config.protocol);
+
+        let service = MetricsService::new(
+            "web",
+            "http://127.0.0.1:4318/v1/metrics?protocol=http&compression=gzip&interval=a",
+        );
+        assert_eq!(DEFAULT_INTERVAL, service.config.interval);
+        assert_eq!(Protocol::HttpBinary, service.config.protocol);
+        // compression is ignored by http protocol
+        assert_eq!(None, service.config.compression);
+    }
+
+    #[tokio::test]
+    async fn test_otel_metrics() {
+        let reader = TestReader(Arc::new(ManualReader::builder().build()));
+        let provider = SdkMeterProvider::builder()
+            .with_reader(reader.clone())
+            .build();
+        let metrics = OtelMetrics::new(&provider.meter("http_proxy"));
+
+        let input_header =
+            "GET /vicanso/pingap?size=1 HTTP/1.1\r\nHost: github.com\r\n\r\n";
+        let mock_io = Builder::new().read(input_header.as_bytes()).build();
+        let mut session = Session::new_h1(Box::new(mock_io));
+        session.read_request().await.unwrap();
+
+        metrics.before("lo");
+        metrics.after(
+            &session,
+            &Ctx {
+                created_at: now_ms() - 10,
+                status: Some(pingora::http::StatusCode::TOO_MANY_REQUESTS),
+                upstream_status: Some(pingora::http::StatusCode::OK),
+                upstream_address: "127.0.0.1:3000".to_string(),
+                cache_status: Some("miss"),
+                responded_plugin: Some("limit".to_string()),
+                rate_limited: true,
+                plugin_processing_times: Some(vec![("limit".to_string(), 1)]),
+                payload_size: 1024,
+                upstream_response_time: Some(5),
+                compression_stat: Some(CompressionStat {
+                    in_bytes: 1024,
+                    out_bytes: 512,
+                    duration: Duration::from_millis(20),
+                }),
+                location: "lo".to_string(),
+                upstream: "upstream".to_string(),
+                ..Default::default()
+            },
+        );
+
+        let mut rm = ResourceMetrics {
+            resource: Resource::builder_empty().build(),
+            scope_metrics: vec![],
+        };
+        reader.collect(&mut rm).unwrap();
+        let get_sum = |name: &str| {
+            let metric = rm.scope_metrics[0]
+                .metrics
+                .iter()
+                .find(|item| item.name == name)
+                .unwrap();
+            let sum = metric.data.as_any().downcast_ref::<Sum<u64>>().unwrap();
+            sum.data_points
+                .iter()
+                .map(|point| {
+                    let mut attrs: Vec<String> = point
+                        .attributes
+                        .iter()
+                        .map(|kv| format!("{}={}", kv.key, kv.value))
+                        .collect();
+                    attrs.sort();
+                    (attrs.join(","), point.value)
+                })
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            vec![("location=lo".to_string(), 1)],
+            get_sum("pingap_http_requests_total")
+        );
+        assert_eq!(
+            vec![("code=4xx,location=lo".to_string(), 1)],
+            get_sum("pingap_http_responses_codes")
+        );
+        assert_eq!(
+            vec![(
+                "addr=127.0.0.1:3000,code=2xx,upstream=upstream".to_string(),
+                1
+            )],
+            get_sum("pingap_upstream_responses_codes")
+        );
+        assert_eq!(
+            vec![("location=lo,status=miss".to_string(), 1)],
+            get_sum("pingap_cache_status")
+        );
+        assert_eq!(
+            vec![("plugin=limit".to_string(), 1)],
+            get_sum("pingap_rate_limit_rejections")
+        );
+        assert_eq!(
+            vec![("location=lo".to_string(), 1024)],
+            get_sum("pingap_http_received_bytes")
+        );
+
+        let metric = rm.scope_metrics[0]
+            .metrics
+            .iter()
+            .find(|item| item.name == "pingap_upstream_latency")
+            .unwrap();
+        let histogram = metric
+            .data
+            .as_any()
+            .downcast_ref::<HistogramData<f64>>()
+            .unwrap();
+        assert_eq!(1, histogram.data_points[0].count);
+        assert_eq!(0.005, histogram.data_points[0].sum);
+    }
+}
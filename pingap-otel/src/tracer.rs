@@ -20,7 +20,9 @@ use opentelemetry::{
     propagation::{TextMapCompositePropagator, TextMapPropagator},
     trace::TracerProvider,
 };
-use opentelemetry_otlp::{Compression, WithExportConfig, WithTonicConfig};
+use opentelemetry_otlp::{
+    Compression, Protocol, WithExportConfig, WithTonicConfig,
+};
 use opentelemetry_sdk::{
     propagation::{BaggagePropagator, TraceContextPropagator},
     trace::{BatchConfigBuilder, RandomIdGenerator, Sampler},
@@ -29,7 +31,7 @@ use opentelemetry_sdk::{
 
 use pingora::{server::ShutdownWatch, services::background::BackgroundService};
 use std::time::Duration;
-use tracing::{error, info};
+use tracing::{error, info, warn};
 use url::Url;
 
 const LOG_CATEGORY: &str = "otel";
@@ -63,7 +65,14 @@ pub struct TracerConfig {
     support_jaeger_propagator: bool,
     /// Enable W3C Baggage propagation format support
     support_baggage_propagator: bool,
+    /// Compression of grpc export, it's not supported by http protocol
     compression: Option<Compression>,
+    /// Export protocol, grpc or http with binary protobuf
+    protocol: Protocol,
+    /// Ratio of traces to sample, None means sampling all traces
+    sample_ratio: Option<f64>,
+    /// Follow the sampling decision of the parent span if it exists
+    parent_based: bool,
 }
 
 impl Default for TracerConfig {
@@ -79,6 +88,9 @@ impl Default for TracerConfig {
             support_jaeger_propagator: false,
             support_baggage_propagator: false,
             compression: None,
+            protocol: Protocol::Grpc,
+            sample_ratio: None,
+            parent_based: false,
         }
     }
 }
@@ -196,13 +208,39 @@ impl TracerServiceBuilder {
                         self.config.compression = Some(Compression::Gzip);
                     }
                 },
+                "protocol" => {
+                    if value.to_lowercase() == "http" {
+                        self.config.protocol = Protocol::HttpBinary;
+                    } else {
+                        self.config.protocol = Protocol::Grpc;
+                    }
+                },
+                "sample_ratio" => {
+                    if let Ok(v) = value.parse::<f64>() {
+                        self.config.sample_ratio = Some(v.clamp(0.0, 1.0));
+                    }
+                },
+                "parent_based" => {
+                    self.config.parent_based = true;
+                },
                 _ => {},
             }
         }
     }
 
     /// Builds and returns a new TracerService with the configured options
-    pub fn build(self) -> TracerService {
+    pub fn build(mut self) -> TracerService {
+        // the http exporter does not support compression
+        if self.config.protocol != Protocol::Grpc
+            && self.config.compression.is_some()
+        {
+            warn!(
+                category = LOG_CATEGORY,
+                endpoint = self.endpoint,
+                "compression is not supported by http protocol, it is ignored"
+            );
+            self.config.compression = None;
+        }
         TracerService {
             name: self.name.unwrap_or_else(|| "default".to_string()),
             endpoint: self
@@ -213,6 +251,23 @@ impl TracerServiceBuilder {
     }
 }
 
+/// Creates the sampler of tracer provider
+///
+/// # Arguments
+/// * `config` - The tracer config with sample ratio and parent based options
+fn new_sampler(config: &TracerConfig) -> Sampler {
+    let sampler = if let Some(ratio) = config.sample_ratio {
+        Sampler::TraceIdRatioBased(ratio)
+    } else {
+        Sampler::AlwaysOn
+    };
+    if config.parent_based {
+        Sampler::ParentBased(Box::new(sampler))
+    } else {
+        sampler
+    }
+}
+
 /// Gets the full service name by adding the 'pingap:' prefix
 ///
 /// # Arguments
@@ -241,15 +296,32 @@ pub fn new_http_proxy_tracer(name: &str) -> Option<BoxedTracer> {
 impl BackgroundService for TracerService {
     /// Open telemetry background service, it will schedule export data to server.
     async fn start(&self, mut shutdown: ShutdownWatch) {
-        let mut builder = opentelemetry_otlp::SpanExporter::builder()
-            .with_tonic()
-            .with_endpoint(&self.endpoint)
-            .with_timeout(self.config.timeout);
-        if let Some(compression) = self.config.compression {
-            builder = builder.with_compression(compression);
-        }
+        let result = if self.config.protocol == Protocol::Grpc {
+            let mut builder = opentelemetry_otlp::SpanExporter::builder()
+                .with_tonic()
+                .with_endpoint(&self.endpoint)
+                .with_timeout(self.config.timeout);
+            if let Some(compression) = self.config.compression {
+                builder = builder.with_compression(compression);
+            }
+            builder.build()
+        } else {
+            // the query is used for config, it should not be sent to collector,
+            // e.g. http://127.0.0.1:4318/v1/traces?protocol=http
+            let endpoint = self
+                .endpoint
+                .split_once('?')
+                .map(|(endpoint, _)| endpoint)
+                .unwrap_or(&self.endpoint);
+            opentelemetry_otlp::SpanExporter::builder()
+                .with_http()
+                .with_protocol(self.config.protocol)
+                .with_endpoint(endpoint)
+                .with_timeout(self.config.timeout)
+                .build()
+        };
 
-        let result = builder.build().map(|exporter| {
+        let result = result.map(|exporter| {
             let batch =
                 opentelemetry_sdk::trace::BatchSpanProcessor::builder(exporter)
                     .with_batch_config(
@@ -267,7 +339,7 @@ impl BackgroundService for TracerService {
                     .build();
             opentelemetry_sdk::trace::SdkTracerProvider::builder()
                 .with_span_processor(batch)
-                .with_sampler(Sampler::AlwaysOn)
+                .with_sampler(new_sampler(&self.config))
                 .with_id_generator(RandomIdGenerator::default())
                 .with_max_attributes_per_span(self.config.max_attributes)
                 .with_max_events_per_span(self.config.max_events)
@@ -302,6 +374,9 @@ impl BackgroundService for TracerService {
                     category = LOG_CATEGORY,
                     name = self.name,
                     endpoint = self.endpoint,
+                    protocol = format!("{:?}", self.config.protocol),
+                    sample_ratio = self.config.sample_ratio,
+                    parent_based = self.config.parent_based,
                     support_jaeger_propagator =
                         self.config.support_jaeger_propagator,
                     support_baggage_propagator =
@@ -336,3 +411,60 @@ impl BackgroundService for TracerService {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_tracer_config() {
+        let service = TracerService::new(
+            "web",
+            "http://127.0.0.1:4317?compression=zstd&sample_ratio=0.1&parent_based&timeout=5s&max_queue_size=100",
+        );
+        assert_eq!("web", service.name);
+        assert_eq!(Protocol::Grpc, service.config.protocol);
+        assert_eq!(Some(Compression::Zstd), service.config.compression);
+        assert_eq!(Some(0.1), service.config.sample_ratio);
+        assert_eq!(true, service.config.parent_based);
+        assert_eq!(Duration::from_secs(5), service.config.timeout);
+        assert_eq!(100, service.config.max_queue_size);
+
+        // the ratio is clamped to [0, 1]
+        let service = TracerService::new(
+            "web",
+            "http://127.0.0.1:4318/v1/traces?protocol=http&compression=gzip&sample_ratio=1.5",
+        );
+        assert_eq!(Protocol::HttpBinary, service.config.protocol);
+        // compression is ignored by http protocol
+        assert_eq!(None, service.config.compression);
+        assert_eq!(Some(1.0), service.config.sample_ratio);
+        assert_eq!(false, service.config.parent_based);
+
+        let service = TracerService::new(
+            "web",
+            "http://127.0.0.1:4317?protocol=grpc&sample_ratio=a",
+        );
+        assert_eq!(Protocol::Grpc, service.config.protocol);
+        assert_eq!(None, service.config.sample_ratio);
+    }
+
+    #[test]
+    fn test_new_sampler() {
+        let mut config = TracerConfig::default();
+        assert_eq!("AlwaysOn", format!("{:?}", new_sampler(&config)));
+
+        config.sample_ratio = Some(0.5);
+        assert_eq!(
+            "TraceIdRatioBased(0.5)",
+            format!("{:?}", new_sampler(&config))
+        );
+
+        config.parent_based = true;
+        assert_eq!(
+            "ParentBased(TraceIdRatioBased(0.5))",
+            format!("{:?}", new_sampler(&config))
+        );
+    }
+}
@@ -0,0 +1,42 @@
+// Copyright 2024-2025 Tree xie.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+/// Returns the category label (1xx, 2xx, etc.) of the http status code,
+/// it's shared by the prometheus and opentelemetry metrics.
+pub fn get_code_label(code: u16) -> &'static str {
+    match code {
+        100..=199 => "1xx",
+        200..=299 => "2xx",
+        300..=399 => "3xx",
+        400..=499 => "4xx",
+        500..=599 => "5xx",
+        _ => "unknown",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_get_code_label() {
+        assert_eq!("1xx", get_code_label(101));
+        assert_eq!("2xx", get_code_label(200));
+        assert_eq!("3xx", get_code_label(304));
+        assert_eq!("4xx", get_code_label(429));
+        assert_eq!("5xx", get_code_label(502));
+        assert_eq!("unknown", get_code_label(0));
+    }
+}
@@ -12,9 +12,11 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+mod label;
 mod metrics;
 mod process;
 
+pub use label::get_code_label;
 pub use metrics::*;
 pub use process::*;
 #[cfg(feature = "tracing")]
@@ -13,6 +13,7 @@
 // limitations under the License.
 
 use super::{get_process_system_info, get_processing_accepted, LOG_CATEGORY};
+use bytesize::ByteSize;
 use pingap_cache::{get_cache_backend, is_cache_backend_init};
 use pingap_core::SimpleServiceTaskFuture;
 use pingap_location::get_locations_stats;
@@ -34,6 +35,7 @@ pub fn new_performance_metrics_log_service() -> (String, SimpleServiceTaskFuture
                 // Get cache statistics (reading/writing counts)
                 let mut cache_reading: i64 = -1;
                 let mut cache_writing: i64 = -1;
+                let mut cache_disk_usage = None;
                 // if cache backend not initialized, do not get cache statistics
                 if is_cache_backend_init() {
                     // the cache backend is initialized once,
@@ -42,6 +44,9 @@ pub fn new_performance_metrics_log_service() -> (String, SimpleServiceTaskFuture
                         if let Some(stats) = cache.stats() {
                             cache_reading = stats.reading as i64;
                             cache_writing = stats.writing as i64;
+                            cache_disk_usage = stats
+                                .disk_usage
+                                .map(|v| ByteSize::b(v).to_string());
                         }
                     }
                 }
@@ -119,6 +124,7 @@ pub fn new_performance_metrics_log_service() -> (String, SimpleServiceTaskFuture
                     tcp6_count = system_info.tcp6_count, // IPv6 TCP connection count
                     cache_reading,                       // Active cache reads
                     cache_writing,                       // Active cache writes
+                    cache_disk_usage, // Disk usage of cache files
                 );
                 Ok(true)
             }
@@ -12,12 +12,15 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use super::{get_process_system_info, Error, Result, LOG_CATEGORY};
+use super::{
+    get_code_label, get_process_system_info, Error, Result, LOG_CATEGORY,
+};
 use humantime::parse_duration;
 use pingap_cache::{CACHE_READING_TIME, CACHE_WRITING_TIME};
 use pingap_core::Error as ServiceError;
 use pingap_core::SimpleServiceTaskFuture;
 use pingap_core::{get_hostname, Ctx};
+use pingap_util::now_ms;
 use pingora::proxy::Session;
 use prometheus::core::Collector;
 use prometheus::{
@@ -28,7 +31,6 @@ use prometheus::{
 };
 use std::sync::Arc;
 use std::time::Duration;
-use std::time::{SystemTime, UNIX_EPOCH};
 use tracing::error;
 use url::Url;
 
@@ -36,14 +38,6 @@ use url::Url;
 /// This allows for dynamic host identification in distributed deployments.
 static HOST_NAME_TAG: &str = "$HOSTNAME";
 
-#[inline]
-fn now_ms() -> u64 {
-    SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .unwrap_or_default()
-        .as_millis() as u64
-}
-
 /// Comprehensive metrics collector for HTTP server monitoring.
 ///
 /// This struct maintains various Prometheus metrics types to track:
@@ -113,6 +107,15 @@ pub struct Prometheus {
     /// Histogram of upstream response times in seconds, labeled by upstream
     upstream_response_time: Box<HistogramVec>,
 
+    /// Count of upstream response codes grouped by category, labeled by upstream, backend address and code
+    upstream_responses_codes: Box<IntCounterVec>,
+
+    /// Histogram of upstream response latency in seconds, labeled by upstream and backend address
+    upstream_latency: Box<HistogramVec>,
+
+    /// Count of cache statuses (hit, miss, stale, etc.), labeled by location and status
+    cache_status: Box<IntCounterVec>,
+
     /// Histogram of cache lookup times in seconds
     cache_lookup_time: Box<Histogram>,
 
@@ -128,6 +131,12 @@ pub struct Prometheus {
     /// Histogram of response compression ratios
     compression_ratio: Box<Histogram>,
 
+    /// Count of requests rejected by rate limit plugins, labeled by plugin
+    rate_limit_rejections: Box<IntCounterVec>,
+
+    /// Histogram of plugin execution times in seconds, labeled by plugin
+    plugin_processing_time: Box<HistogramVec>,
+
     /// Current memory usage in megabytes
     memory: Box<IntGauge>,
 
@@ -199,14 +208,7 @@ impl Prometheus {
         let sent = sent_bytes as f64 / 1024.0;
 
         // http response code
-        let code_label = match code {
-            100..=199 => "1xx",
-            200..=299 => "2xx",
-            300..=399 => "3xx",
-            400..=499 => "4xx",
-            500..=599 => "5xx",
-            _ => "unknown",
-        };
+        let code_label = get_code_label(code);
         let mut labels_list = Vec::with_capacity(2);
         labels_list.push([""]);
         if !location.is_empty() {
@@ -299,9 +301,30 @@ impl Prometheus {
                     .with_label_values(upstream_labels)
                     .observe(upstream_response_time as f64 / SECOND);
             }
+            // upstream outcome by backend address
+            let addr = ctx.upstream_address.as_str();
+            let upstream_code_label = ctx
+                .upstream_status
+                .map(|status| get_code_label(status.as_u16()))
+                .unwrap_or("unknown");
+            self.upstream_responses_codes
+                .with_label_values(&[upstream, addr, upstream_code_label])
+                .inc();
+            if let Some(latency) =
+                ctx.upstream_response_time.or(ctx.upstream_processing_time)
+            {
+                self.upstream_latency
+                    .with_label_values(&[upstream, addr])
+                    .observe(latency as f64 / SECOND);
+            }
         }
 
         // cache stats
+        if let Some(cache_status) = ctx.cache_status {
+            self.cache_status
+                .with_label_values(&[location, cache_status])
+                .inc();
+        }
         if let Some(cache_lookup_time) = ctx.cache_lookup_time {
             self.cache_lookup_time
                 .observe(cache_lookup_time as f64 / SECOND);
@@ -321,6 +344,21 @@ impl Prometheus {
         if let Some(compression_stat) = &ctx.compression_stat {
             self.compression_ratio.observe(compression_stat.ratio());
         }
+
+        // plugin stats
+        if ctx.rate_limited {
+            let plugin = ctx.responded_plugin.as_deref().unwrap_or_default();
+            self.rate_limit_rejections
+                .with_label_values(&[plugin])
+                .inc();
+        }
+        if let Some(times) = &ctx.plugin_processing_times {
+            for (plugin, time) in times.iter() {
+                self.plugin_processing_time
+                    .with_label_values(&[plugin])
+                    .observe(*time as f64 / SECOND);
+            }
+        }
     }
 
     /// Collects all registered metrics and updates system resource gauges.
@@ -682,6 +720,25 @@ pub fn new_prometheus(server: &str) -> Result<Prometheus> {
         &["upstream"],
         &[0.005, 0.01, 0.05, 0.1, 0.5, 1.0],
     )?);
+    let upstream_responses_codes = Box::new(new_int_counter_vec(
+        server,
+        "pingap_upstream_responses_codes",
+        "pingap upstream response codes",
+        &["upstream", "addr", "code"],
+    )?);
+    let upstream_latency = Box::new(new_histogram_vec(
+        server,
+        "pingap_upstream_latency",
+        "pingap upstream latency of backend(second)",
+        &["upstream", "addr"],
+        &[0.005, 0.01, 0.05, 0.1, 0.5, 1.0, 5.0],
+    )?);
+    let cache_status = Box::new(new_int_counter_vec(
+        server,
+        "pingap_cache_status",
+        "pingap cache status(hit, miss, stale, etc.)",
+        &["location", "status"],
+    )?);
     let cache_lookup_time = Box::new(new_histogram(
         server,
         "pingap_cache_lookup_time",
@@ -710,6 +767,19 @@ pub fn new_prometheus(server: &str) -> Result<Prometheus> {
         "pingap response compression ratio",
         &[1.0, 2.0, 3.0, 5.0, 10.0],
     )?);
+    let rate_limit_rejections = Box::new(new_int_counter_vec(
+        server,
+        "pingap_rate_limit_rejections",
+        "pingap requests rejected by rate limit plugin",
+        &["plugin"],
+    )?);
+    let plugin_processing_time = Box::new(new_histogram_vec(
+        server,
+        "pingap_plugin_processing_time",
+        "pingap plugin processing time(second)",
+        &["plugin"],
+        &[0.001, 0.005, 0.01, 0.05, 0.1, 0.5],
+    )?);
 
     let memory = Box::new(new_int_gauge(
         server,
@@ -750,6 +820,9 @@ pub fn new_prometheus(server: &str) -> Result<Prometheus> {
         upstream_reuses.clone(),
         upstream_processing_time.clone(),
         upstream_response_time.clone(),
+        upstream_responses_codes.clone(),
+        upstream_latency.clone(),
+        cache_status.clone(),
         cache_lookup_time.clone(),
         cache_lock_time.clone(),
         cache_reading.clone(),
@@ -757,6 +830,8 @@ pub fn new_prometheus(server: &str) -> Result<Prometheus> {
         CACHE_READING_TIME.clone(),
         CACHE_WRITING_TIME.clone(),
         compression_ratio.clone(),
+        rate_limit_rejections.clone(),
+        plugin_processing_time.clone(),
         memory.clone(),
         fd_count.clone(),
         tcp_count.clone(),
@@ -787,11 +862,16 @@ pub fn new_prometheus(server: &str) -> Result<Prometheus> {
         upstream_reuses,
         upstream_processing_time,
         upstream_response_time,
+        upstream_responses_codes,
+        upstream_latency,
+        cache_status,
         cache_lookup_time,
         cache_lock_time,
         cache_reading,
         cache_writing,
         compression_ratio,
+        rate_limit_rejections,
+        plugin_processing_time,
         memory,
         fd_count,
         tcp_count,
@@ -834,7 +914,13 @@ mod tests {
             &session,
             &Ctx {
                 created_at: now_ms() - 10,
-                status: Some(StatusCode::from_u16(200).unwrap()),
+                status: Some(StatusCode::from_u16(429).unwrap()),
+                upstream_status: Some(StatusCode::from_u16(200).unwrap()),
+                upstream_address: "127.0.0.1:3000".to_string(),
+                cache_status: Some("miss"),
+                responded_plugin: Some("limit".to_string()),
+                rate_limited: true,
+                plugin_processing_times: Some(vec![("limit".to_string(), 1)]),
                 connection_reused: true,
                 tls_handshake_time: Some(1),
                 payload_size: 1024,
@@ -856,6 +942,6 @@ mod tests {
             },
         );
         let buf = p.metrics().unwrap();
-        assert_eq!(225, std::str::from_utf8(&buf).unwrap().split('\n').count());
+        assert_eq!(257, std::str::from_utf8(&buf).unwrap().split('\n').count());
     }
 }
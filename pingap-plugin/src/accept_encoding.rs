@@ -14,12 +14,14 @@
 
 use super::{
     get_bool_conf, get_hash_key, get_plugin_factory, get_str_conf, Error,
+    PluginSchema,
 };
 use async_trait::async_trait;
 use ctor::ctor;
 use pingap_config::PluginConf;
 use pingap_core::{Ctx, HttpResponse, Plugin, PluginStep};
 use pingora::proxy::Session;
+use serde_json::json;
 use smallvec::SmallVec;
 use std::sync::Arc;
 use tracing::debug;
@@ -161,6 +163,20 @@ fn init() {
     factory.register("accept_encoding", |params| {
         Ok(Arc::new(AcceptEncoding::new(params)?))
     });
+    get_plugin_factory().register_schema(
+        "accept_encoding",
+        PluginSchema::new("Adjust the accept-encoding header of request")
+            .steps(&[PluginStep::EarlyRequest])
+            .property("encodings", json!({
+                "type": "string",
+                "description": "Supported encodings separated by comma(e.g. zstd, br, gzip)"
+            }))
+            .property("only_one_encoding", json!({
+                "type": "boolean",
+                "default": false,
+                "description": "Only keep the first matched encoding"
+            })),
+    );
 }
 
 #[cfg(test)]
@@ -14,7 +14,7 @@
 
 use super::{
     get_bool_conf, get_hash_key, get_plugin_factory, get_str_conf,
-    get_str_slice_conf, Error,
+    get_str_slice_conf, Error, PluginSchema,
 };
 use async_trait::async_trait;
 use bytes::Bytes;
@@ -26,6 +26,7 @@ use pingap_config::{PluginCategory, PluginConf};
 use pingap_core::{Ctx, HttpResponse, Plugin, PluginStep};
 use pingap_util::base64_decode;
 use pingora::proxy::Session;
+use serde_json::json;
 use std::sync::Arc;
 use std::time::Duration;
 use tokio::time::sleep;
@@ -227,6 +228,28 @@ fn init() {
     let factory = get_plugin_factory();
     factory
         .register("basic_auth", |params| Ok(Arc::new(BasicAuth::new(params)?)));
+    get_plugin_factory().register_schema(
+        "basic_auth",
+        PluginSchema::new("Http basic authentication")
+            .steps(&[PluginStep::Request])
+            .property("authorizations", json!({
+                "type": "array",
+                "items": {
+                    "type": "string"
+                },
+                "description": "Base64 encoded credentials of user:password"
+            }))
+            .property("hide_credentials", json!({
+                "type": "boolean",
+                "default": false,
+                "description": "Remove the authorization header before proxying"
+            }))
+            .property("delay", json!({
+                "type": "string",
+                "description": "Delay of the response when authentication fails(e.g. 1s)"
+            }))
+            .required(&["authorizations"]),
+    );
 }
 
 #[cfg(test)]
@@ -14,7 +14,7 @@
 
 use super::{
     get_bool_conf, get_hash_key, get_plugin_factory, get_str_conf,
-    get_str_slice_conf, Error,
+    get_str_slice_conf, Error, PluginSchema,
 };
 use async_trait::async_trait;
 use bstr::ByteSlice;
@@ -34,6 +34,7 @@ use pingora::cache::key::CacheHashKey;
 use pingora::cache::lock::{CacheKeyLock, CacheLock};
 use pingora::cache::predictor::{CacheablePredictor, Predictor};
 use pingora::proxy::Session;
+use serde_json::json;
 use std::str::FromStr;
 use std::sync::Arc;
 use std::time::Duration;
@@ -191,6 +192,7 @@ impl TryFrom<&PluginConf> for Cache {
         let cache = get_cache_backend(Some(CacheBackendOption {
             cache_directory: basic_conf.cache_directory.clone(),
             cache_max_size: basic_conf.cache_max_size,
+            cache_disk_max_size: basic_conf.cache_disk_max_size,
         }))
         .map_err(|e| Error::Invalid {
             category: "cache_backend".to_string(),
@@ -461,6 +463,62 @@ impl Plugin for Cache {
 fn init() {
     get_plugin_factory()
         .register("cache", |params| Ok(Arc::new(Cache::new(params)?)));
+    get_plugin_factory().register_schema(
+        "cache",
+        PluginSchema::new("Http response cache")
+            .steps(&[PluginStep::Request])
+            .property("lock", json!({
+                "type": "string",
+                "description": "Timeout of cache lock which prevents cache stampede",
+                "default": "1s"
+            }))
+            .property("max_ttl", json!({
+                "type": "string",
+                "description": "Max ttl of cached response"
+            }))
+            .property("max_file_size", json!({
+                "type": "string",
+                "description": "Max size of cacheable response(e.g. 1mb)",
+                "default": "1mb"
+            }))
+            .property("namespace", json!({
+                "type": "string",
+                "description": "Namespace of cache, it's a sub directory of file cache"
+            }))
+            .property("headers", json!({
+                "type": "array",
+                "items": {
+                    "type": "string"
+                },
+                "description": "Request headers which are appended to the cache key"
+            }))
+            .property("eviction", json!({
+                "type": "boolean",
+                "default": false,
+                "description": "Enable the eviction manager of cache"
+            }))
+            .property("predictor", json!({
+                "type": "boolean",
+                "default": false,
+                "description": "Enable the cacheable predictor"
+            }))
+            .property("purge_ip_list", json!({
+                "type": "array",
+                "items": {
+                    "type": "string"
+                },
+                "description": "Ip list(or cidr) which are allowed to purge cache"
+            }))
+            .property("skip", json!({
+                "type": "string",
+                "description": "Skip the cache if the request uri matches the regex"
+            }))
+            .property("check_cache_control", json!({
+                "type": "boolean",
+                "default": false,
+                "description": "Only cache the response whose cache-control is cacheable"
+            })),
+    );
 }
 
 #[cfg(test)]
@@ -0,0 +1,675 @@
+// Copyright 2024-2025 Tree xie.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use super::{
+    get_hash_key, get_plugin_factory, get_str_conf, get_str_slice_conf, Error,
+    PluginSchema,
+};
+use ahash::AHashMap;
+use async_trait::async_trait;
+use bstr::ByteSlice;
+use bytes::Bytes;
+use ctor::ctor;
+use http::{header, HeaderName, HeaderValue, Method};
+use pingap_config::{PluginCategory, PluginConf};
+use pingap_core::{
+    Ctx, HttpHeader, HttpResponse, ModifyResponseBody, Plugin, PluginStep,
+};
+use pingora::http::{RequestHeader, ResponseHeader};
+use pingora::proxy::Session;
+use serde_json::json;
+use std::sync::{Arc, Mutex, Weak};
+use std::time::Duration;
+use tokio::sync::watch;
+use tracing::debug;
+
+type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// The response of leader request, it's shared by all waiting requests
+struct CoalescedResponse {
+    header: ResponseHeader,
+    body: Bytes,
+    /// The request header values of leader for the vary headers of response
+    vary: Vec<(HeaderName, Option<HeaderValue>)>,
+}
+
+impl CoalescedResponse {
+    /// Whether the request has the same values of vary headers as the leader
+    fn is_vary_matched(&self, session: &Session) -> bool {
+        self.vary.iter().all(|(name, value)| {
+            session.req_header().headers.get(name) == value.as_ref()
+        })
+    }
+}
+
+/// The result of leader request
+enum CoalescedResult {
+    /// The response can be shared by the waiting requests
+    Shared(Box<CoalescedResponse>),
+    /// The response is private, the waiting requests are forwarded to upstream
+    Private,
+}
+
+type CoalescedReceiver = watch::Receiver<Option<Arc<CoalescedResult>>>;
+
+/// In-flight calls, the key is the coalesce key of request
+type Calls = Mutex<AHashMap<String, (Weak<Call>, CoalescedReceiver)>>;
+
+/// An in-flight upstream call of the leader request.
+/// It's removed from the in-flight calls when dropped,
+/// and the waiting requests will be released.
+struct Call {
+    key: String,
+    calls: Arc<Calls>,
+    sender: watch::Sender<Option<Arc<CoalescedResult>>>,
+    header: Mutex<Option<(ResponseHeader, VaryHeaders)>>,
+}
+
+impl Call {
+    /// Removes the call from in-flight calls if it's still the current one
+    fn remove(&self) {
+        if let Ok(mut calls) = self.calls.lock() {
+            let is_current = calls
+                .get(&self.key)
+                .map(|(call, _)| std::ptr::eq(call.as_ptr(), self))
+                .unwrap_or_default();
+            if is_current {
+                calls.remove(&self.key);
+            }
+        }
+    }
+}
+
+impl Drop for Call {
+    fn drop(&mut self) {
+        self.remove();
+    }
+}
+
+/// Collects the response body of leader request and fans it out
+/// to all waiting requests.
+struct CoalesceBody {
+    call: Arc<Call>,
+}
+
+impl ModifyResponseBody for CoalesceBody {
+    fn handle(&self, data: Bytes) -> Bytes {
+        let header = self.call.header.lock().ok().and_then(|mut h| h.take());
+        if let Some((header, vary)) = header {
+            self.call.sender.send_replace(Some(Arc::new(
+                CoalescedResult::Shared(Box::new(CoalescedResponse {
+                    header,
+                    body: data.clone(),
+                    vary,
+                })),
+            )));
+        }
+        // the response is done, new requests should not wait for it
+        self.call.remove();
+        data
+    }
+}
+
+type VaryHeaders = Vec<(HeaderName, Option<HeaderValue>)>;
+
+/// Headers which should not be fanned out to waiting requests
+static SKIP_HEADERS: [HeaderName; 3] = [
+    header::CONTENT_LENGTH,
+    header::TRANSFER_ENCODING,
+    header::CONNECTION,
+];
+
+/// Returns the request header values of the vary headers of response,
+/// `None` means the response can't be shared, e.g. it's private,
+/// no-store, sets cookie or varies by `*`.
+fn get_shared_vary(
+    req_header: &RequestHeader,
+    resp_header: &ResponseHeader,
+) -> Option<VaryHeaders> {
+    if resp_header.headers.contains_key(header::SET_COOKIE) {
+        return None;
+    }
+    let is_private = resp_header
+        .headers
+        .get_all(header::CACHE_CONTROL)
+        .iter()
+        .flat_map(|value| value.to_str().unwrap_or_default().split(','))
+        .any(|item| {
+            let item = item.trim();
+            item.eq_ignore_ascii_case("private")
+                || item.eq_ignore_ascii_case("no-store")
+        });
+    if is_private {
+        return None;
+    }
+    let mut vary = vec![];
+    for value in resp_header.headers.get_all(header::VARY).iter() {
+        for item in value.to_str().unwrap_or_default().split(',') {
+            let item = item.trim();
+            if item.is_empty() {
+                continue;
+            }
+            if item == "*" {
+                return None;
+            }
+            let Ok(name) = HeaderName::from_bytes(item.as_bytes()) else {
+                return None;
+            };
+            let value = req_header.headers.get(&name).cloned();
+            vary.push((name, value));
+        }
+    }
+    Some(vary)
+}
+
+impl From<&CoalescedResponse> for HttpResponse {
+    fn from(value: &CoalescedResponse) -> Self {
+        // the cache control of upstream response will replace the default one
+        let headers: Vec<HttpHeader> = value
+            .header
+            .headers
+            .iter()
+            .filter(|(name, _)| !SKIP_HEADERS.contains(name))
+            .map(|(name, value)| (name.clone(), value.clone()))
+            .collect();
+        Self {
+            status: value.header.status,
+            body: value.body.clone(),
+            headers: Some(headers),
+            ..Default::default()
+        }
+    }
+}
+
+/// Coalesce plugin collapses concurrent identical GET requests
+/// into a single upstream request, the response of upstream is
+/// fanned out to all waiting requests even if it's not cacheable,
+/// except the private response(private, no-store or set-cookie).
+pub struct Coalesce {
+    // Request headers which are included in the coalesce key
+    headers: Option<Vec<String>>,
+    // Max time for waiting the response of leader request,
+    // the request will be forwarded to upstream after timeout
+    timeout: Duration,
+    // In-flight upstream calls
+    calls: Arc<Calls>,
+    // Unique identifier for this plugin configuration
+    hash_value: String,
+}
+
+impl TryFrom<&PluginConf> for Coalesce {
+    type Error = Error;
+    fn try_from(value: &PluginConf) -> Result<Self> {
+        let hash_value = get_hash_key(value);
+        let headers = get_str_slice_conf(value, "headers");
+        let timeout = get_str_conf(value, "timeout");
+        let timeout = if timeout.is_empty() {
+            Duration::from_secs(10)
+        } else {
+            humantime::parse_duration(&timeout).map_err(|e| Error::Invalid {
+                category: PluginCategory::Coalesce.to_string(),
+                message: e.to_string(),
+            })?
+        };
+
+        Ok(Self {
+            headers: if headers.is_empty() {
+                None
+            } else {
+                Some(headers)
+            },
+            timeout,
+            calls: Arc::new(Mutex::new(AHashMap::new())),
+            hash_value,
+        })
+    }
+}
+
+impl Coalesce {
+    /// Creates a new Coalesce plugin instance from the provided configuration.
+    pub fn new(params: &PluginConf) -> Result<Self> {
+        debug!(params = params.to_string(), "new coalesce plugin");
+        Self::try_from(params)
+    }
+    /// Returns the coalesce key of request, it's composed of
+    /// host, uri, accept-encoding and the configured request headers.
+    /// The vary headers of response are compared after it's received.
+    fn get_key(&self, session: &Session) -> String {
+        let req_header = session.req_header();
+        let host = pingap_core::get_host(req_header).unwrap_or_default();
+        let mut key = format!(
+            "{host}:{}:{}",
+            req_header.uri,
+            session
+                .get_header_bytes(header::ACCEPT_ENCODING)
+                .to_str_lossy()
+        );
+        if let Some(headers) = &self.headers {
+            for name in headers.iter() {
+                key.push(':');
+                key.push_str(&session.get_header_bytes(name).to_str_lossy());
+            }
+        }
+        key
+    }
+    /// Returns the receiver of in-flight call if exists,
+    /// otherwise registers a new call which request is the leader.
+    fn get_or_register(
+        &self,
+        key: &str,
+    ) -> std::result::Result<CoalescedReceiver, Arc<Call>> {
+        let Ok(mut calls) = self.calls.lock() else {
+            // the map is poisoned, treat the request as leader
+            let (sender, _) = watch::channel(None);
+            return Err(Arc::new(Call {
+                key: key.to_string(),
+                calls: self.calls.clone(),
+                sender,
+                header: Mutex::new(None),
+            }));
+        };
+        if let Some((call, receiver)) = calls.get(key) {
+            if call.strong_count() > 0 {
+                return Ok(receiver.clone());
+            }
+        }
+        let (sender, receiver) = watch::channel(None);
+        let call = Arc::new(Call {
+            key: key.to_string(),
+            calls: self.calls.clone(),
+            sender,
+            header: Mutex::new(None),
+        });
+        calls.insert(key.to_string(), (Arc::downgrade(&call), receiver));
+        Err(call)
+    }
+}
+
+#[async_trait]
+impl Plugin for Coalesce {
+    #[inline]
+    fn hash_key(&self) -> String {
+        self.hash_value.clone()
+    }
+
+    /// Handles the request, the first request of a key is the leader and
+    /// forwarded to upstream, the others wait for its response.
+    #[inline]
+    async fn handle_request(
+        &self,
+        step: PluginStep,
+        session: &mut Session,
+        ctx: &mut Ctx,
+    ) -> pingora::Result<(bool, Option<HttpResponse>)> {
+        if step != PluginStep::Request {
+            return Ok((false, None));
+        }
+        let req_header = session.req_header();
+        // only coalesce anonymous GET requests
+        if req_header.method != Method::GET
+            || req_header.headers.contains_key(header::AUTHORIZATION)
+            || req_header.headers.contains_key(header::COOKIE)
+        {
+            return Ok((false, None));
+        }
+        let key = self.get_key(session);
+        let mut receiver = match self.get_or_register(&key) {
+            Ok(receiver) => receiver,
+            Err(call) => {
+                debug!(key, "coalesce leader request");
+                ctx.coalesce_call = Some(call);
+                return Ok((true, None));
+            },
+        };
+        let result = tokio::time::timeout(
+            self.timeout,
+            receiver.wait_for(|value| value.is_some()),
+        )
+        .await;
+        // the leader fails or timeout, forward the request to upstream
+        let Ok(Ok(value)) = result else {
+            debug!(key, "coalesce wait fail");
+            return Ok((true, None));
+        };
+        let Some(CoalescedResult::Shared(resp)) = value.as_deref() else {
+            debug!(key, "coalesce response is private");
+            return Ok((true, None));
+        };
+        if !resp.is_vary_matched(session) {
+            debug!(key, "coalesce response vary is not matched");
+            return Ok((true, None));
+        }
+        debug!(key, "coalesce response");
+        Ok((true, Some(resp.as_ref().into())))
+    }
+
+    /// Saves the upstream response header of leader request and
+    /// collects its response body.
+    fn handle_upstream_response(
+        &self,
+        step: PluginStep,
+        session: &mut Session,
+        ctx: &mut Ctx,
+        upstream_response: &mut ResponseHeader,
+    ) -> pingora::Result<bool> {
+        if step != PluginStep::UpstreamResponse {
+            return Ok(false);
+        }
+        let Some(call) = ctx
+            .coalesce_call
+            .clone()
+            .and_then(|call| call.downcast::<Call>().ok())
+        else {
+            return Ok(false);
+        };
+        // the response body is modified by other plugin
+        if ctx.modify_upstream_response_body.is_some() {
+            return Ok(false);
+        }
+        let Some(vary) =
+            get_shared_vary(session.req_header(), upstream_response)
+        else {
+            // release the waiting requests, they are forwarded to upstream
+            call.sender
+                .send_replace(Some(Arc::new(CoalescedResult::Private)));
+            call.remove();
+            return Ok(false);
+        };
+        if let Ok(mut header) = call.header.lock() {
+            *header = Some((upstream_response.clone(), vary));
+        }
+        ctx.modify_upstream_response_body =
+            Some(Box::new(CoalesceBody { call }));
+        Ok(true)
+    }
+}
+
+#[ctor]
+fn init() {
+    get_plugin_factory()
+        .register("coalesce", |params| Ok(Arc::new(Coalesce::new(params)?)));
+    get_plugin_factory().register_schema(
+        "coalesce",
+        PluginSchema::new("Coalesce the identical concurrent requests")
+            .steps(&[PluginStep::Request])
+            .property("headers", json!({
+                "type": "array",
+                "items": {
+                    "type": "string"
+                },
+                "description": "Request headers which are appended to the coalescing key, accept-encoding is always included"
+            }))
+            .property("timeout", json!({
+                "type": "string",
+                "description": "Max time to wait for the leader request",
+                "default": "10s"
+            })),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pingap_config::PluginConf;
+    use pingap_core::{Ctx, PluginStep};
+    use pingora::proxy::Session;
+    use pretty_assertions::assert_eq;
+    use tokio_test::io::Builder;
+
+    async fn new_session_with_headers(headers: &[&str]) -> Session {
+        let headers = [&["Host: github.com"], headers].concat().join("\r\n");
+        let input_header =
+            format!("GET /vicanso/pingap?size=1 HTTP/1.1\r\n{headers}\r\n\r\n");
+        let mock_io = Builder::new().read(input_header.as_bytes()).build();
+        let mut session = Session::new_h1(Box::new(mock_io));
+        session.read_request().await.unwrap();
+        session
+    }
+
+    async fn new_session() -> Session {
+        new_session_with_headers(&["Accept-Encoding: gzip", "X-Device: pc"])
+            .await
+    }
+
+    #[test]
+    fn test_coalesce_params() {
+        let coalesce = Coalesce::new(
+            &toml::from_str::<PluginConf>(
+                r###"
+headers = ["Accept-Encoding"]
+timeout = "3s"
+"###,
+            )
+            .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(
+            vec!["Accept-Encoding".to_string()],
+            coalesce.headers.unwrap()
+        );
+        assert_eq!(Duration::from_secs(3), coalesce.timeout);
+
+        let result = Coalesce::new(
+            &toml::from_str::<PluginConf>(
+                r###"
+timeout = "3a"
+"###,
+            )
+            .unwrap(),
+        );
+        assert_eq!(true, result.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_coalesce() {
+        let coalesce = Arc::new(
+            Coalesce::new(
+                &toml::from_str::<PluginConf>(
+                    r###"
+headers = ["X-Device"]
+"###,
+                )
+                .unwrap(),
+            )
+            .unwrap(),
+        );
+        let mut session = new_session().await;
+        assert_eq!(
+            "github.com:/vicanso/pingap?size=1:gzip:pc",
+            coalesce.get_key(&session)
+        );
+
+        // the first request is leader
+        let mut leader_ctx = Ctx::default();
+        let (executed, resp) = coalesce
+            .handle_request(PluginStep::Request, &mut session, &mut leader_ctx)
+            .await
+            .unwrap();
+        assert_eq!(true, executed);
+        assert_eq!(true, resp.is_none());
+        assert_eq!(true, leader_ctx.coalesce_call.is_some());
+
+        // the other request waits for the response of leader
+        let waiter = tokio::spawn({
+            let coalesce = coalesce.clone();
+            async move {
+                let mut session = new_session().await;
+                let mut ctx = Ctx::default();
+                coalesce
+                    .handle_request(PluginStep::Request, &mut session, &mut ctx)
+                    .await
+                    .unwrap()
+            }
+        });
+        // make sure the waiting request is running
+        tokio::time::sleep(Duration::from_millis(10)).await;
+
+        let mut upstream_response = ResponseHeader::build(200, None).unwrap();
+        upstream_response
+            .insert_header("Content-Type", "text/plain")
+            .unwrap();
+        upstream_response
+            .insert_header("Vary", "Accept-Encoding")
+            .unwrap();
+        let executed = coalesce
+            .handle_upstream_response(
+                PluginStep::UpstreamResponse,
+                &mut session,
+                &mut leader_ctx,
+                &mut upstream_response,
+            )
+            .unwrap();
+        assert_eq!(true, executed);
+        let body = leader_ctx
+            .modify_upstream_response_body
+            .as_ref()
+            .unwrap()
+            .handle(Bytes::from_static(b"Hello World!"));
+        assert_eq!(b"Hello World!", body.as_ref());
+
+        let (executed, resp) = waiter.await.unwrap();
+        assert_eq!(true, executed);
+        let resp = resp.unwrap();
+        assert_eq!(200, resp.status.as_u16());
+        assert_eq!(b"Hello World!", resp.body.as_ref());
+        let headers = resp.headers.unwrap();
+        assert_eq!(2, headers.len());
+        assert_eq!("content-type", headers[0].0.as_str());
+
+        // the call is done, new request is leader
+        let mut ctx = Ctx::default();
+        let (_, resp) = coalesce
+            .handle_request(PluginStep::Request, &mut session, &mut ctx)
+            .await
+            .unwrap();
+        assert_eq!(true, resp.is_none());
+        assert_eq!(true, ctx.coalesce_call.is_some());
+
+        // the leader is dropped without response,
+        // the waiting request is forwarded to upstream
+        let waiter = tokio::spawn({
+            let coalesce = coalesce.clone();
+            async move {
+                let mut session = new_session().await;
+                let mut ctx = Ctx::default();
+                let result = coalesce
+                    .handle_request(PluginStep::Request, &mut session, &mut ctx)
+                    .await
+                    .unwrap();
+                (result, ctx.coalesce_call.is_some())
+            }
+        });
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        drop(ctx);
+        let ((executed, resp), is_leader) = waiter.await.unwrap();
+        assert_eq!(true, executed);
+        assert_eq!(true, resp.is_none());
+        assert_eq!(false, is_leader);
+        assert_eq!(true, coalesce.calls.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_coalesce_private() {
+        let coalesce = Arc::new(Coalesce::new(&PluginConf::default()).unwrap());
+
+        // the request with cookie is not coalesced
+        let mut session = new_session_with_headers(&["Cookie: uid=1"]).await;
+        let mut ctx = Ctx::default();
+        let (executed, resp) = coalesce
+            .handle_request(PluginStep::Request, &mut session, &mut ctx)
+            .await
+            .unwrap();
+        assert_eq!(false, executed);
+        assert_eq!(true, resp.is_none());
+        assert_eq!(true, ctx.coalesce_call.is_none());
+
+        // the private response is not shared
+        let mut session = new_session().await;
+        let mut leader_ctx = Ctx::default();
+        coalesce
+            .handle_request(PluginStep::Request, &mut session, &mut leader_ctx)
+            .await
+            .unwrap();
+        let waiter = tokio::spawn({
+            let coalesce = coalesce.clone();
+            async move {
+                let mut session = new_session().await;
+                let mut ctx = Ctx::default();
+                coalesce
+                    .handle_request(PluginStep::Request, &mut session, &mut ctx)
+                    .await
+                    .unwrap()
+            }
+        });
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        let mut upstream_response = ResponseHeader::build(200, None).unwrap();
+        upstream_response
+            .insert_header("Cache-Control", "no-cache, private")
+            .unwrap();
+        let executed = coalesce
+            .handle_upstream_response(
+                PluginStep::UpstreamResponse,
+                &mut session,
+                &mut leader_ctx,
+                &mut upstream_response,
+            )
+            .unwrap();
+        assert_eq!(false, executed);
+        assert_eq!(true, leader_ctx.modify_upstream_response_body.is_none());
+        // the waiting request is released before the leader is done
+        let (executed, resp) = waiter.await.unwrap();
+        assert_eq!(true, executed);
+        assert_eq!(true, resp.is_none());
+        assert_eq!(true, coalesce.calls.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_get_shared_vary() {
+        let session = new_session().await;
+        let req_header = session.req_header();
+
+        let mut resp_header = ResponseHeader::build(200, None).unwrap();
+        resp_header
+            .append_header("Vary", "Accept-Encoding, X-Device")
+            .unwrap();
+        resp_header.append_header("Vary", "Origin").unwrap();
+        let vary = get_shared_vary(req_header, &resp_header).unwrap();
+        assert_eq!(
+            r#"[("accept-encoding", Some("gzip")), ("x-device", Some("pc")), ("origin", None)]"#,
+            format!("{vary:?}")
+        );
+        let resp = CoalescedResponse {
+            header: resp_header.clone(),
+            body: Bytes::new(),
+            vary,
+        };
+        assert_eq!(true, resp.is_vary_matched(&session));
+        let other = new_session_with_headers(&["Accept-Encoding: br"]).await;
+        assert_eq!(false, resp.is_vary_matched(&other));
+
+        for (name, value) in [
+            ("Vary", "*"),
+            ("Cache-Control", "no-store"),
+            ("Cache-Control", "Private"),
+            ("Set-Cookie", "uid=1"),
+        ] {
+            let mut resp_header = ResponseHeader::build(200, None).unwrap();
+            resp_header.insert_header(name, value).unwrap();
+            assert_eq!(
+                true,
+                get_shared_vary(req_header, &resp_header).is_none()
+            );
+        }
+    }
+}
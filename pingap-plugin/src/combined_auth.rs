@@ -14,7 +14,7 @@
 
 use super::{
     get_hash_key, get_int_conf, get_plugin_factory, get_str_conf,
-    get_str_slice_conf, Error,
+    get_str_slice_conf, Error, PluginSchema,
 };
 use ahash::AHashMap;
 use async_trait::async_trait;
@@ -27,6 +27,7 @@ use pingap_core::{
     Ctx, HttpResponse, Plugin, PluginStep, HTTP_HEADER_NO_STORE,
 };
 use pingora::proxy::Session;
+use serde_json::json;
 use sha2::{Digest, Sha256};
 use std::sync::Arc;
 use tracing::debug;
@@ -298,6 +299,42 @@ fn init() {
     get_plugin_factory().register("combined_auth", |params| {
         Ok(Arc::new(CombinedAuth::new(params)?))
     });
+    get_plugin_factory().register_schema(
+        "combined_auth",
+        PluginSchema::new("Authentication of app id with signed timestamp and ip list")
+            .steps(&[PluginStep::Request])
+            .property("authorizations", json!({
+                "type": "array",
+                "description": "Authorizations of apps",
+                "items": {
+                    "type": "object",
+                    "properties": {
+                        "app_id": {
+                            "type": "string",
+                            "description": "Id of app"
+                        },
+                        "secret": {
+                            "type": "string",
+                            "description": "Secret of app"
+                        },
+                        "deviation": {
+                            "type": "integer",
+                            "description": "Allowed deviation of timestamp in seconds"
+                        },
+                        "ip_list": {
+                            "type": "array",
+                            "items": {
+                                "type": "string"
+                            },
+                            "description": "Ip list(or cidr) which are allowed"
+                        }
+                    },
+                    "required": ["app_id"],
+                    "additionalProperties": false
+                }
+            }))
+            .required(&["authorizations"]),
+    );
 }
 
 #[cfg(test)]
@@ -14,6 +14,7 @@
 
 use super::{
     get_bool_conf, get_hash_key, get_int_conf, get_plugin_factory, Error,
+    PluginSchema,
 };
 use async_trait::async_trait;
 use ctor::ctor;
@@ -22,6 +23,7 @@ use pingap_core::{Ctx, HttpResponse, Plugin, PluginStep};
 use pingora::modules::http::compression::ResponseCompression;
 use pingora::protocols::http::compression::Algorithm;
 use pingora::proxy::Session;
+use serde_json::json;
 use std::sync::Arc;
 use tracing::debug;
 
@@ -214,6 +216,33 @@ fn init() {
     get_plugin_factory().register("compression", |params| {
         Ok(Arc::new(Compression::new(params)?))
     });
+    get_plugin_factory().register_schema(
+        "compression",
+        PluginSchema::new("Response compression of gzip, brotli and zstd")
+            .steps(&[PluginStep::EarlyRequest])
+            .property("gzip_level", json!({
+                "type": "integer",
+                "description": "Compression level of gzip, 0 is disabled",
+                "minimum": 0,
+                "maximum": 9
+            }))
+            .property("br_level", json!({
+                "type": "integer",
+                "description": "Compression level of brotli, 0 is disabled",
+                "minimum": 0,
+                "maximum": 11
+            }))
+            .property("zstd_level", json!({
+                "type": "integer",
+                "description": "Compression level of zstd, 0 is disabled",
+                "minimum": 0,
+                "maximum": 22
+            }))
+            .property("decompression", json!({
+                "type": "boolean",
+                "description": "Decompress the response if the encoding is not supported"
+            })),
+    );
 }
 
 #[cfg(test)]
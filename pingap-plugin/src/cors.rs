@@ -14,6 +14,7 @@
 
 use super::{
     get_bool_conf, get_hash_key, get_plugin_factory, get_str_conf, Error,
+    PluginSchema,
 };
 use async_trait::async_trait;
 use ctor::ctor;
@@ -26,6 +27,7 @@ use pingap_core::{
 use pingora::http::ResponseHeader;
 use pingora::proxy::Session;
 use regex::Regex;
+use serde_json::json;
 use std::sync::Arc;
 use std::time::Duration;
 use tracing::debug;
@@ -315,6 +317,43 @@ impl Plugin for Cors {
 fn init() {
     get_plugin_factory()
         .register("cors", |params| Ok(Arc::new(Cors::new(params)?)));
+    get_plugin_factory().register_schema(
+        "cors",
+        PluginSchema::new("Cross-Origin Resource Sharing")
+            .steps(&[PluginStep::Request])
+            .property("path", json!({
+                "type": "string",
+                "description": "Only apply to the request whose path matches the regex"
+            }))
+            .property("allow_origin", json!({
+                "type": "string",
+                "description": "Allowed origin, $http_origin means the origin of request",
+                "default": "*"
+            }))
+            .property("allow_methods", json!({
+                "type": "string",
+                "description": "Allowed methods, separated by comma",
+                "default": "GET, POST, PUT, PATCH, DELETE, OPTIONS"
+            }))
+            .property("allow_headers", json!({
+                "type": "string",
+                "description": "Allowed headers, separated by comma"
+            }))
+            .property("allow_credentials", json!({
+                "type": "boolean",
+                "default": false,
+                "description": "Whether to allow credentials"
+            }))
+            .property("expose_headers", json!({
+                "type": "string",
+                "description": "Exposed headers, separated by comma"
+            }))
+            .property("max_age", json!({
+                "type": "string",
+                "description": "Max age of preflight result",
+                "default": "1h"
+            })),
+    );
 }
 
 #[cfg(test)]
@@ -12,7 +12,9 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use super::{get_hash_key, get_plugin_factory, get_str_conf, Error};
+use super::{
+    get_hash_key, get_plugin_factory, get_str_conf, Error, PluginSchema,
+};
 use async_trait::async_trait;
 use bytes::Bytes;
 use cookie::Cookie;
@@ -26,6 +28,7 @@ use pingap_core::{
 };
 use pingap_util::base64_encode;
 use pingora::proxy::Session;
+use serde_json::json;
 use sha2::{Digest, Sha256};
 use std::sync::Arc;
 use tracing::debug;
@@ -313,6 +316,41 @@ impl Plugin for Csrf {
 fn init() {
     get_plugin_factory()
         .register("csrf", |params| Ok(Arc::new(Csrf::new(params)?)));
+    get_plugin_factory().register_schema(
+        "csrf",
+        PluginSchema::new("Cross-Site Request Forgery protection")
+            .steps(&[PluginStep::Request])
+            .property(
+                "name",
+                json!({
+                    "type": "string",
+                    "description": "Name of csrf token header",
+                    "default": "x-csrf-token"
+                }),
+            )
+            .property(
+                "token_path",
+                json!({
+                    "type": "string",
+                    "description": "Path to get a new csrf token"
+                }),
+            )
+            .property(
+                "key",
+                json!({
+                    "type": "string",
+                    "description": "Key to sign the csrf token"
+                }),
+            )
+            .property(
+                "ttl",
+                json!({
+                    "type": "string",
+                    "description": "Ttl of csrf token"
+                }),
+            )
+            .required(&["token_path", "key"]),
+    );
 }
 
 #[cfg(test)]
@@ -14,7 +14,7 @@
 
 use super::{
     get_bool_conf, get_hash_key, get_plugin_factory, get_step_conf,
-    get_str_conf, get_str_slice_conf, Error,
+    get_str_conf, get_str_slice_conf, Error, PluginSchema,
 };
 use async_trait::async_trait;
 use bytesize::ByteSize;
@@ -29,6 +29,7 @@ use pingap_core::{
 };
 use pingap_core::{Ctx, Plugin, PluginStep};
 use pingora::proxy::Session;
+use serde_json::json;
 use std::fs::Metadata;
 #[cfg(unix)]
 use std::os::unix::fs::MetadataExt;
@@ -535,6 +536,56 @@ impl Plugin for Directory {
 fn init() {
     get_plugin_factory()
         .register("directory", |params| Ok(Arc::new(Directory::new(params)?)));
+    get_plugin_factory().register_schema(
+        "directory",
+        PluginSchema::new("Static file serving and directory listing")
+            .steps(&[PluginStep::Request, PluginStep::ProxyUpstream])
+            .property("path", json!({
+                "type": "string",
+                "description": "Path of static directory"
+            }))
+            .property("index", json!({
+                "type": "string",
+                "description": "Index file of directory",
+                "default": "index.html"
+            }))
+            .property("autoindex", json!({
+                "type": "boolean",
+                "default": false,
+                "description": "List the files if the index file does not exist"
+            }))
+            .property("chunk_size", json!({
+                "type": "string",
+                "description": "Chunk size of file reading",
+                "default": "4096"
+            }))
+            .property("max_age", json!({
+                "type": "string",
+                "description": "Max age of cache-control header"
+            }))
+            .property("private", json!({
+                "type": "boolean",
+                "default": false,
+                "description": "Set the cache-control to private"
+            }))
+            .property("charset", json!({
+                "type": "string",
+                "description": "Charset of content-type header"
+            }))
+            .property("headers", json!({
+                "type": "array",
+                "items": {
+                    "type": "string"
+                },
+                "description": "Response headers in format name:value"
+            }))
+            .property("download", json!({
+                "type": "boolean",
+                "default": false,
+                "description": "Set the content-disposition to attachment"
+            }))
+            .required(&["path"]),
+    );
 }
 
 #[cfg(test)]
@@ -14,6 +14,7 @@
 
 use super::{
     get_hash_key, get_plugin_factory, get_str_conf, get_str_slice_conf, Error,
+    PluginSchema,
 };
 use async_trait::async_trait;
 use bytes::Bytes;
@@ -22,6 +23,7 @@ use http::StatusCode;
 use pingap_config::PluginConf;
 use pingap_core::{Ctx, HttpResponse, Plugin, PluginStep};
 use pingora::proxy::Session;
+use serde_json::json;
 use std::sync::Arc;
 use tracing::debug;
 
@@ -182,6 +184,28 @@ fn init() {
     get_plugin_factory().register("ip_restriction", |params| {
         Ok(Arc::new(IpRestriction::new(params)?))
     });
+    get_plugin_factory().register_schema(
+        "ip_restriction",
+        PluginSchema::new("Ip based access control")
+            .steps(&[PluginStep::Request])
+            .property("ip_list", json!({
+                "type": "array",
+                "items": {
+                    "type": "string"
+                },
+                "description": "Ip list(or cidr) of the restriction"
+            }))
+            .property("type", json!({
+                "type": "string",
+                "description": "Restriction type, the ip list is allowed or denied",
+                "enum": ["allow", "deny"]
+            }))
+            .property("message", json!({
+                "type": "string",
+                "description": "Response message of forbidden request",
+                "default": "Request is forbidden"
+            })),
+    );
 }
 
 #[cfg(test)]
@@ -12,7 +12,9 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use super::{get_hash_key, get_plugin_factory, get_str_conf, Error};
+use super::{
+    get_hash_key, get_plugin_factory, get_str_conf, Error, PluginSchema,
+};
 use async_trait::async_trait;
 use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
 use bytes::Bytes;
@@ -27,6 +29,7 @@ use pingap_core::{
 use pingora::http::ResponseHeader;
 use pingora::proxy::Session;
 use serde::{Deserialize, Serialize};
+use serde_json::json;
 use std::sync::Arc;
 use std::time::Duration;
 use substring::Substring;
@@ -383,6 +386,42 @@ impl ModifyResponseBody for Sign {
 fn init() {
     get_plugin_factory()
         .register("jwt", |params| Ok(Arc::new(JwtAuth::new(params)?)));
+    get_plugin_factory().register_schema(
+        "jwt",
+        PluginSchema::new("Json web token authentication")
+            .steps(&[PluginStep::Request])
+            .property("header", json!({
+                "type": "string",
+                "description": "Header name of jwt token"
+            }))
+            .property("query", json!({
+                "type": "string",
+                "description": "Query name of jwt token"
+            }))
+            .property("cookie", json!({
+                "type": "string",
+                "description": "Cookie name of jwt token"
+            }))
+            .property("secret", json!({
+                "type": "string",
+                "description": "Secret to sign the jwt token"
+            }))
+            .property("auth_path", json!({
+                "type": "string",
+                "description": "Path to sign the response data as jwt token"
+            }))
+            .property("algorithm", json!({
+                "type": "string",
+                "description": "Hmac algorithm of jwt",
+                "enum": ["HS256", "HS512"],
+                "default": "HS256"
+            }))
+            .property("delay", json!({
+                "type": "string",
+                "description": "Delay of the response when authentication fails(e.g. 1s)"
+            }))
+            .required(&["secret"]),
+    );
 }
 
 #[cfg(test)]
@@ -14,7 +14,7 @@
 
 use super::{
     get_bool_conf, get_hash_key, get_plugin_factory, get_str_conf,
-    get_str_slice_conf, Error,
+    get_str_slice_conf, Error, PluginSchema,
 };
 use async_trait::async_trait;
 use bytes::Bytes;
@@ -24,6 +24,7 @@ use humantime::parse_duration;
 use pingap_config::{PluginCategory, PluginConf};
 use pingap_core::{Ctx, HttpResponse, Plugin, PluginStep};
 use pingora::proxy::Session;
+use serde_json::json;
 use std::str::FromStr;
 use std::sync::Arc;
 use std::time::Duration;
@@ -189,6 +190,36 @@ impl KeyAuth {
 fn init() {
     get_plugin_factory()
         .register("key_auth", |params| Ok(Arc::new(KeyAuth::new(params)?)));
+    get_plugin_factory().register_schema(
+        "key_auth",
+        PluginSchema::new("Api key authentication")
+            .steps(&[PluginStep::Request])
+            .property("query", json!({
+                "type": "string",
+                "description": "Query name of api key"
+            }))
+            .property("header", json!({
+                "type": "string",
+                "description": "Header name of api key, it's used if query is empty"
+            }))
+            .property("keys", json!({
+                "type": "array",
+                "items": {
+                    "type": "string"
+                },
+                "description": "Valid api keys"
+            }))
+            .property("hide_credentials", json!({
+                "type": "boolean",
+                "default": false,
+                "description": "Remove the api key before proxying"
+            }))
+            .property("delay", json!({
+                "type": "string",
+                "description": "Delay of the response when authentication fails(e.g. 1s)"
+            }))
+            .required(&["keys"]),
+    );
 }
 
 #[async_trait]
@@ -122,6 +122,7 @@ pub fn get_hash_key(conf: &PluginConf) -> String {
 mod accept_encoding;
 mod basic_auth;
 mod cache;
+mod coalesce;
 mod combined_auth;
 mod compression;
 mod cors;
@@ -142,4 +143,4 @@ mod ua_restriction;
 
 mod plugin;
 
-pub use plugin::get_plugin_factory;
+pub use plugin::{get_plugin_factory, PluginSchema};
@@ -14,7 +14,7 @@
 
 use super::{
     get_hash_key, get_int_conf, get_plugin_factory, get_step_conf,
-    get_str_conf, Error,
+    get_str_conf, Error, PluginSchema,
 };
 use async_trait::async_trait;
 use ctor::ctor;
@@ -23,6 +23,7 @@ use humantime::parse_duration;
 use pingap_config::{PluginCategory, PluginConf};
 use pingap_core::{Ctx, HttpResponse, Inflight, Plugin, PluginStep, Rate};
 use pingora::proxy::Session;
+use serde_json::json;
 use std::sync::Arc;
 use std::time::Duration;
 use tracing::debug;
@@ -308,6 +309,7 @@ impl Plugin for Limiter {
 
         // Try to increment counter
         if let Err(e) = self.incr(session, ctx) {
+            ctx.rate_limited = true;
             // If limit exceeded, return 429 Too Many Requests
             return Ok((
                 true,
@@ -328,6 +330,43 @@ impl Plugin for Limiter {
 fn init() {
     get_plugin_factory()
         .register("limit", |params| Ok(Arc::new(Limiter::new(params)?)));
+    get_plugin_factory().register_schema(
+        "limit",
+        PluginSchema::new("Rate or inflight limit of requests")
+            .steps(&[PluginStep::Request, PluginStep::ProxyUpstream])
+            .property("type", json!({
+                "type": "string",
+                "description": "Limit type, inflight limits the concurrent requests",
+                "enum": ["rate", "inflight"],
+                "default": "rate"
+            }))
+            .property("tag", json!({
+                "type": "string",
+                "description": "Source of limit key",
+                "enum": ["ip", "cookie", "header", "query"],
+                "default": "ip"
+            }))
+            .property("key", json!({
+                "type": "string",
+                "description": "Name of cookie, header or query as the limit key"
+            }))
+            .property("max", json!({
+                "type": "integer",
+                "description": "Max count of requests"
+            }))
+            .property("interval", json!({
+                "type": "string",
+                "description": "Interval of rate limit",
+                "default": "10s"
+            }))
+            .property("weight", json!({
+                "type": "integer",
+                "description": "Weight(0-100) of the previous interval for rate limit",
+                "minimum": 0,
+                "maximum": 100
+            }))
+            .required(&["max"]),
+    );
 }
 
 #[cfg(test)]
@@ -507,18 +546,16 @@ max = 0
         let mock_io = Builder::new().read(input_header.as_bytes()).build();
         let mut session = Session::new_h1(Box::new(mock_io));
         session.read_request().await.unwrap();
+        let mut ctx = Ctx::default();
         let (executed, result) = limiter
-            .handle_request(
-                PluginStep::Request,
-                &mut session,
-                &mut Ctx::default(),
-            )
+            .handle_request(PluginStep::Request, &mut session, &mut ctx)
             .await
             .unwrap();
 
         assert_eq!(true, executed);
         assert_eq!(true, result.is_some());
         assert_eq!(StatusCode::TOO_MANY_REQUESTS, result.unwrap().status);
+        assert_eq!(true, ctx.rate_limited);
 
         let limiter = Limiter::new(
             &toml::from_str::<PluginConf>(
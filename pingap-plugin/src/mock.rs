@@ -14,7 +14,7 @@
 
 use super::{
     get_hash_key, get_int_conf, get_plugin_factory, get_str_conf,
-    get_str_slice_conf, Error,
+    get_str_slice_conf, Error, PluginSchema,
 };
 use async_trait::async_trait;
 use ctor::ctor;
@@ -23,6 +23,7 @@ use humantime::parse_duration;
 use pingap_config::{PluginCategory, PluginConf};
 use pingap_core::{convert_headers, Ctx, HttpResponse, Plugin, PluginStep};
 use pingora::proxy::Session;
+use serde_json::json;
 use std::sync::Arc;
 use std::time::Duration;
 use tokio::time::sleep;
@@ -187,6 +188,35 @@ impl Plugin for MockResponse {
 fn init() {
     get_plugin_factory()
         .register("mock", |params| Ok(Arc::new(MockResponse::new(params)?)));
+    get_plugin_factory().register_schema(
+        "mock",
+        PluginSchema::new("Mock response for testing")
+            .steps(&[PluginStep::Request])
+            .property("path", json!({
+                "type": "string",
+                "description": "Path of mock, all requests are mocked if empty"
+            }))
+            .property("status", json!({
+                "type": "integer",
+                "description": "Status code of response",
+                "default": 200
+            }))
+            .property("headers", json!({
+                "type": "array",
+                "items": {
+                    "type": "string"
+                },
+                "description": "Response headers in format name:value"
+            }))
+            .property("data", json!({
+                "type": "string",
+                "description": "Body of response"
+            }))
+            .property("delay", json!({
+                "type": "string",
+                "description": "Delay of the response(e.g. 1s)"
+            })),
+    );
 }
 
 #[cfg(test)]
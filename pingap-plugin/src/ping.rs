@@ -12,7 +12,9 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use super::{get_hash_key, get_plugin_factory, get_str_conf, Error};
+use super::{
+    get_hash_key, get_plugin_factory, get_str_conf, Error, PluginSchema,
+};
 use async_trait::async_trait;
 use bytes::Bytes;
 use ctor::ctor;
@@ -21,6 +23,7 @@ use once_cell::sync::Lazy;
 use pingap_config::PluginConf;
 use pingap_core::{Ctx, HttpResponse, Plugin, PluginStep};
 use pingora::proxy::Session;
+use serde_json::json;
 use std::sync::Arc;
 use tracing::debug;
 
@@ -103,6 +106,18 @@ impl Plugin for Ping {
 fn init() {
     get_plugin_factory()
         .register("ping", |params| Ok(Arc::new(Ping::new(params)?)));
+    get_plugin_factory().register_schema(
+        "ping",
+        PluginSchema::new("Health check endpoint")
+            .steps(&[PluginStep::Request])
+            .property(
+                "path",
+                json!({
+                    "type": "string",
+                    "description": "Path of ping"
+                }),
+            ),
+    );
 }
 
 #[cfg(test)]
@@ -16,22 +16,123 @@ use super::{get_str_conf, Error};
 use dashmap::DashMap;
 use once_cell::sync::Lazy;
 use pingap_config::PluginConf;
-use pingap_core::Plugin;
+use pingap_core::{Plugin, PluginStep};
+use serde_json::{json, Map, Value};
 use std::sync::Arc;
 
 type Result<T, E = Error> = std::result::Result<T, E>;
 
 type NewPlugin = dyn Fn(&PluginConf) -> Result<Arc<dyn Plugin>> + Send + Sync;
 
+/// Schema of plugin config, it's registered with the plugin creator
+/// and used to generate the json schema of plugin config.
+#[derive(Debug, Default, Clone)]
+pub struct PluginSchema {
+    description: String,
+    steps: Vec<PluginStep>,
+    properties: Map<String, Value>,
+    required: Vec<String>,
+}
+
+impl PluginSchema {
+    pub fn new(description: &str) -> Self {
+        Self {
+            description: description.to_string(),
+            ..Default::default()
+        }
+    }
+    /// Sets the supported steps of plugin, the first one is the default step
+    pub fn steps(mut self, steps: &[PluginStep]) -> Self {
+        self.steps = steps.to_vec();
+        self
+    }
+    /// Adds the property of plugin config, the value is the json schema
+    pub fn property(mut self, name: &str, value: Value) -> Self {
+        self.properties.insert(name.to_string(), value);
+        self
+    }
+    /// Marks the properties as required
+    pub fn required(mut self, names: &[&str]) -> Self {
+        self.required
+            .extend(names.iter().map(|name| name.to_string()));
+        self
+    }
+    /// Returns the json schema of plugin config, the category, step,
+    /// template and params are added and the unknown properties are not allowed.
+    /// The required properties may be set by the template, so they're not
+    /// required if the template is set.
+    pub fn to_json(&self, category: &str) -> Value {
+        let mut properties = Map::new();
+        properties.insert(
+            "category".to_string(),
+            json!({
+                "const": category,
+                "description": "Category of plugin",
+            }),
+        );
+        if let Some(step) = self.steps.first() {
+            let steps: Vec<String> =
+                self.steps.iter().map(|step| step.to_string()).collect();
+            properties.insert(
+                "step".to_string(),
+                json!({
+                    "type": "string",
+                    "enum": steps,
+                    "default": step.to_string(),
+                    "description": "Step of plugin execution",
+                }),
+            );
+        }
+        properties.extend(self.properties.clone());
+        properties.insert(
+            "template".to_string(),
+            json!({
+                "type": "string",
+                "description": "Name of the template(storage of template category) to expand",
+            }),
+        );
+        properties.insert(
+            "params".to_string(),
+            json!({
+                "type": "object",
+                "additionalProperties": { "type": "string" },
+                "description": "Params of the template, they replace the `{{param}}` of template",
+            }),
+        );
+        properties.insert(
+            "remark".to_string(),
+            json!({
+                "type": "string",
+                "description": "Optional description/notes about this plugin",
+            }),
+        );
+        let mut required = vec!["category".to_string()];
+        required.extend(self.required.clone());
+        json!({
+            "title": category,
+            "description": self.description,
+            "type": "object",
+            "properties": properties,
+            "anyOf": [
+                { "required": required },
+                { "required": ["template"] },
+            ],
+            "additionalProperties": false,
+        })
+    }
+}
+
 /// Plugin factory for managing plugin creation and registration
 pub struct PluginFactory {
     plugins: DashMap<String, Arc<NewPlugin>>,
+    schemas: DashMap<String, PluginSchema>,
 }
 
 impl PluginFactory {
     pub fn new() -> Self {
         Self {
             plugins: DashMap::new(),
+            schemas: DashMap::new(),
         }
     }
 
@@ -53,6 +154,29 @@ impl PluginFactory {
         self.plugins.insert(category.to_string(), Arc::new(creator));
     }
 
+    /// Register the config schema of plugin
+    pub fn register_schema(&self, category: &str, schema: PluginSchema) {
+        self.schemas.insert(category.to_string(), schema);
+    }
+
+    /// Returns the json schema of plugin config by category
+    pub fn get_schema(&self, category: &str) -> Option<Value> {
+        self.schemas
+            .get(category)
+            .map(|schema| schema.to_json(category))
+    }
+
+    /// Returns the json schemas of all registered plugins, sorted by category
+    pub fn get_schemas(&self) -> Vec<(String, Value)> {
+        let mut schemas: Vec<(String, Value)> = self
+            .schemas
+            .iter()
+            .map(|item| (item.key().clone(), item.value().to_json(item.key())))
+            .collect();
+        schemas.sort_by(|a, b| a.0.cmp(&b.0));
+        schemas
+    }
+
     /// Create a new plugin instance by name
     pub fn create(&self, conf: &PluginConf) -> Result<Arc<dyn Plugin>> {
         let category = get_str_conf(conf, "category");
@@ -82,3 +206,48 @@ static PLUGIN_FACTORY: Lazy<PluginFactory> = Lazy::new(PluginFactory::new);
 pub fn get_plugin_factory() -> &'static PluginFactory {
     &PLUGIN_FACTORY
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_plugin_schema() {
+        let schema = PluginSchema::new("Mock plugin")
+            .steps(&[PluginStep::Request, PluginStep::ProxyUpstream])
+            .property("path", json!({ "type": "string" }))
+            .required(&["path"])
+            .to_json("mock");
+        assert_eq!("Mock plugin", schema["description"]);
+        assert_eq!("mock", schema["properties"]["category"]["const"]);
+        assert_eq!(
+            json!(["request", "proxy_upstream"]),
+            schema["properties"]["step"]["enum"]
+        );
+        assert_eq!("request", schema["properties"]["step"]["default"]);
+        assert_eq!(
+            json!([
+                { "required": ["category", "path"] },
+                { "required": ["template"] }
+            ]),
+            schema["anyOf"]
+        );
+        assert_eq!("string", schema["properties"]["template"]["type"]);
+        assert_eq!("object", schema["properties"]["params"]["type"]);
+        assert_eq!(false, schema["additionalProperties"]);
+
+        // the schemas are registered with the plugins
+        let factory = get_plugin_factory();
+        assert_eq!(
+            factory.supported_plugins().len(),
+            factory.get_schemas().len()
+        );
+        assert_eq!(
+            "Path of ping",
+            factory.get_schema("ping").unwrap()["properties"]["path"]
+                ["description"]
+        );
+        assert_eq!(true, factory.get_schema("unknown").is_none());
+    }
+}
@@ -14,6 +14,7 @@
 
 use super::{
     get_bool_conf, get_hash_key, get_plugin_factory, get_str_conf, Error,
+    PluginSchema,
 };
 use async_trait::async_trait;
 use ctor::ctor;
@@ -21,6 +22,7 @@ use http::StatusCode;
 use pingap_config::PluginConf;
 use pingap_core::{convert_headers, Ctx, HttpResponse, Plugin, PluginStep};
 use pingora::proxy::Session;
+use serde_json::json;
 use std::sync::Arc;
 use tracing::debug;
 
@@ -181,6 +183,26 @@ impl Plugin for Redirect {
 fn init() {
     get_plugin_factory()
         .register("redirect", |params| Ok(Arc::new(Redirect::new(params)?)));
+    get_plugin_factory().register_schema(
+        "redirect",
+        PluginSchema::new("Redirect to https or the path with prefix")
+            .steps(&[PluginStep::Request])
+            .property(
+                "prefix",
+                json!({
+                    "type": "string",
+                    "description": "Prefix which is added to the redirect path"
+                }),
+            )
+            .property(
+                "http_to_https",
+                json!({
+                    "type": "boolean",
+                    "default": false,
+                    "description": "Redirect http to https"
+                }),
+            ),
+    );
 }
 
 #[cfg(test)]
@@ -14,7 +14,7 @@
 
 use super::{
     get_hash_key, get_int_conf, get_plugin_factory, get_step_conf,
-    get_str_conf, Error,
+    get_str_conf, Error, PluginSchema,
 };
 use async_trait::async_trait;
 use ctor::ctor;
@@ -25,6 +25,7 @@ use pingap_core::{
     Ctx, HttpResponse, Plugin, PluginStep, HTTP_HEADER_NAME_X_REQUEST_ID,
 };
 use pingora::proxy::Session;
+use serde_json::json;
 use std::str::FromStr;
 use std::sync::Arc;
 use tracing::debug;
@@ -220,6 +221,36 @@ impl Plugin for RequestId {
 fn init() {
     get_plugin_factory()
         .register("request_id", |params| Ok(Arc::new(RequestId::new(params)?)));
+    get_plugin_factory().register_schema(
+        "request_id",
+        PluginSchema::new("Request id generation")
+            .steps(&[PluginStep::Request, PluginStep::ProxyUpstream])
+            .property(
+                "header_name",
+                json!({
+                    "type": "string",
+                    "description": "Header name of request id",
+                    "default": "X-Request-Id"
+                }),
+            )
+            .property(
+                "algorithm",
+                json!({
+                    "type": "string",
+                    "description": "Algorithm of request id",
+                    "enum": ["uuid", "nanoid"],
+                    "default": "uuid"
+                }),
+            )
+            .property(
+                "size",
+                json!({
+                    "type": "integer",
+                    "description": "Size of nanoid",
+                    "default": 8
+                }),
+            ),
+    );
 }
 
 #[cfg(test)]
@@ -11,7 +11,9 @@
 // WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
 // See the License for the specific language governing permissions and
 // limitations under the License.
-use super::{get_hash_key, get_plugin_factory, get_str_slice_conf, Error};
+use super::{
+    get_hash_key, get_plugin_factory, get_str_slice_conf, Error, PluginSchema,
+};
 use async_trait::async_trait;
 use ctor::ctor;
 use http::header::HeaderName;
@@ -21,6 +23,7 @@ use pingap_core::{
 };
 use pingora::http::ResponseHeader;
 use pingora::proxy::Session;
+use serde_json::json;
 use std::str::FromStr;
 use std::sync::Arc;
 use tracing::debug;
@@ -294,6 +297,46 @@ fn init() {
     get_plugin_factory().register("response_headers", |params| {
         Ok(Arc::new(ResponseHeaders::new(params)?))
     });
+    get_plugin_factory().register_schema(
+        "response_headers",
+        PluginSchema::new("Modify the response headers")
+            .steps(&[PluginStep::Response])
+            .property("add_headers", json!({
+                "type": "array",
+                "items": {
+                    "type": "string"
+                },
+                "description": "Headers to add in format name:value"
+            }))
+            .property("set_headers", json!({
+                "type": "array",
+                "items": {
+                    "type": "string"
+                },
+                "description": "Headers to set in format name:value"
+            }))
+            .property("remove_headers", json!({
+                "type": "array",
+                "items": {
+                    "type": "string"
+                },
+                "description": "Header names to remove"
+            }))
+            .property("rename_headers", json!({
+                "type": "array",
+                "items": {
+                    "type": "string"
+                },
+                "description": "Headers to rename in format old:new"
+            }))
+            .property("set_headers_not_exists", json!({
+                "type": "array",
+                "items": {
+                    "type": "string"
+                },
+                "description": "Headers to set if not exist in format name:value"
+            })),
+    );
 }
 
 #[cfg(test)]
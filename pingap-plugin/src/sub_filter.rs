@@ -14,6 +14,7 @@
 
 use super::{
     get_hash_key, get_plugin_factory, get_str_conf, get_str_slice_conf, Error,
+    PluginSchema,
 };
 use async_trait::async_trait;
 use bstr::ByteSlice;
@@ -28,6 +29,7 @@ use pingora::http::ResponseHeader;
 use pingora::proxy::Session;
 use regex::bytes::RegexBuilder;
 use regex::Regex;
+use serde_json::json;
 use std::sync::Arc;
 
 type Result<T, E = Error> = std::result::Result<T, E>;
@@ -268,6 +270,22 @@ impl Plugin for SubFilter {
 fn init() {
     get_plugin_factory()
         .register("sub_filter", |params| Ok(Arc::new(SubFilter::new(params)?)));
+    get_plugin_factory().register_schema(
+        "sub_filter",
+        PluginSchema::new("Replace the content of response")
+            .steps(&[PluginStep::Response])
+            .property("path", json!({
+                "type": "string",
+                "description": "Only filter the response of path which matches the regex"
+            }))
+            .property("filters", json!({
+                "type": "array",
+                "items": {
+                    "type": "string"
+                },
+                "description": "Filters in format subs_filter 'pattern' 'replacement' [flags]"
+            })),
+    );
 }
 
 #[cfg(test)]
@@ -14,6 +14,7 @@
 
 use super::{
     get_hash_key, get_plugin_factory, get_str_conf, get_str_slice_conf, Error,
+    PluginSchema,
 };
 use async_trait::async_trait;
 use bytes::Bytes;
@@ -23,6 +24,7 @@ use pingap_config::PluginConf;
 use pingap_core::{Ctx, HttpResponse, Plugin, PluginStep};
 use pingora::proxy::Session;
 use regex::Regex;
+use serde_json::json;
 use std::sync::Arc;
 use tracing::debug;
 
@@ -211,6 +213,28 @@ fn init() {
     get_plugin_factory().register("ua_restriction", |params| {
         Ok(Arc::new(UaRestriction::new(params)?))
     });
+    get_plugin_factory().register_schema(
+        "ua_restriction",
+        PluginSchema::new("User-Agent based access control")
+            .steps(&[PluginStep::Request])
+            .property("ua_list", json!({
+                "type": "array",
+                "items": {
+                    "type": "string"
+                },
+                "description": "User-Agent regex list of the restriction"
+            }))
+            .property("type", json!({
+                "type": "string",
+                "description": "Restriction type, the user agent list is allowed or denied",
+                "enum": ["allow", "deny"]
+            }))
+            .property("message", json!({
+                "type": "string",
+                "description": "Response message of forbidden request",
+                "default": "Request is forbidden"
+            })),
+    );
 }
 
 #[cfg(test)]
@@ -26,9 +26,10 @@ use pingap_core::new_simple_service_task;
 #[cfg(feature = "imageoptim")]
 #[allow(unused_imports)]
 use pingap_imageoptim::ImageOptim;
+use pingap_kubernetes::KubernetesController;
 use pingap_location::try_init_locations;
 #[cfg(feature = "full")]
-use pingap_otel::TracerService;
+use pingap_otel::{MetricsService, TracerService};
 use pingap_performance::new_performance_metrics_log_service;
 use pingap_plugin::get_plugin_factory;
 use pingap_upstream::{new_upstream_health_check_task, try_init_upstreams};
@@ -37,7 +38,7 @@ use pingora::server::configuration::Opt;
 use pingora::services::background::background_service;
 use process::{
     get_admin_addr, get_start_time, new_auto_restart_service,
-    new_observer_service, set_admin_addr,
+    new_kubernetes_controller_service, new_observer_service, set_admin_addr,
 };
 use proxy::{Server, ServerConf};
 use std::collections::HashMap;
@@ -95,7 +96,7 @@ struct Args {
     /// Validate configuration without starting the server
     #[arg(short, long)]
     test: bool,
-    /// Custom log file location
+    /// Custom log file location, syslog:// or http(s):// sink(elasticsearch or loki)
     #[arg(long)]
     log: Option<String>,
     /// Admin server address for management interface
@@ -116,6 +117,24 @@ struct Args {
     /// Output template configuration
     #[arg(long)]
     template: bool,
+    /// List the revisions of configuration with diff
+    #[arg(long)]
+    history: bool,
+    /// Roll back configuration to the revision of version
+    #[arg(long)]
+    rollback: Option<u64>,
+    /// Report the impact of applying the config of path(file, directory
+    /// or storage url) without applying it
+    #[arg(long)]
+    dry_run: Option<String>,
+    /// Output the json schema of configuration,
+    /// or the schema of plugin if the category is set
+    #[arg(long, num_args = 0..=1, default_missing_value = "")]
+    schema: Option<String>,
+    /// Watch the ingresses and httproutes of kubernetes api server
+    /// and apply them as config, the in-cluster api server is used if empty
+    #[arg(long, num_args = 0..=1, default_missing_value = "")]
+    kubernetes: Option<String>,
 }
 
 fn new_server_conf(
@@ -169,6 +188,7 @@ fn get_config(
                     let result =
                         pingap_config::load_config(LoadConfigOptions {
                             replace_include: true,
+                            resolve_secret: true,
                             admin,
                         })
                         .await;
@@ -191,6 +211,53 @@ fn get_config(
     });
 }
 
+/// Lists the revisions of config or rolls back the config to the revision
+fn run_config_history(args: &Args) -> Result<(), Box<dyn Error>> {
+    let rt = tokio::runtime::Runtime::new()?;
+    if let Some(version) = args.rollback {
+        rt.block_on(pingap_config::rollback_config(version, "cli"))?;
+        println!("rollback config to revision {version} success");
+        return Ok(());
+    }
+    for revision in rt.block_on(pingap_config::list_config_revisions())? {
+        let created_at = std::time::UNIX_EPOCH
+            + std::time::Duration::from_secs(revision.created_at);
+        println!(
+            "revision: {}, created_at: {}, author: {}, category: {}, name: {}",
+            revision.version,
+            humantime::format_rfc3339_seconds(created_at),
+            revision.author,
+            revision.category,
+            revision.name.unwrap_or_default()
+        );
+        for line in revision.diff.iter() {
+            println!("  {line}");
+        }
+    }
+    Ok(())
+}
+
+fn run_config_dry_run(
+    path: &str,
+    current: &PingapConf,
+) -> Result<(), Box<dyn Error>> {
+    let rt = tokio::runtime::Runtime::new()?;
+    let candidate = rt.block_on(pingap_config::load_config_from_path(
+        path,
+        LoadConfigOptions {
+            replace_include: true,
+            resolve_secret: true,
+            ..Default::default()
+        },
+    ))?;
+    let impact = process::dry_run_config(current, &candidate);
+    println!("{}", serde_json::to_string_pretty(&impact)?);
+    if let Some(error) = impact.error {
+        return Err(error.into());
+    }
+    Ok(())
+}
+
 fn sync_config(path: String, s: Sender<Result<(), pingap_config::Error>>) {
     std::thread::spawn(move || {
         match tokio::runtime::Runtime::new() {
@@ -318,6 +385,12 @@ fn parse_arguments() -> Args {
     if !args.autoreload && !get_from_env("autoreload").is_empty() {
         args.autoreload = true;
     }
+    if args.kubernetes.is_none() {
+        let kubernetes = get_from_env("kubernetes");
+        if !kubernetes.is_empty() {
+            args.kubernetes = Some(kubernetes);
+        }
+    }
 
     args
 }
@@ -330,6 +403,17 @@ fn run() -> Result<(), Box<dyn Error>> {
         println!("{TEMPLATE_CONFIG}");
         return Ok(());
     }
+    // Handle json schema output request
+    if let Some(category) = &args.schema {
+        let schema = if category.is_empty() {
+            plugin::get_config_schema()
+        } else {
+            plugin::get_plugin_schema(category)
+                .ok_or_else(|| format!("plugin({category}) is not supported"))?
+        };
+        println!("{}", serde_json::to_string_pretty(&schema)?);
+        return Ok(());
+    }
 
     // Set up admin node if specified
     if let Some(admin) = &args.admin {
@@ -341,9 +425,17 @@ fn run() -> Result<(), Box<dyn Error>> {
 
     // Initialize configuration
     pingap_config::try_init_config_storage(&args.conf)?;
+    // the history does not depend on the current config,
+    // so a broken config can also be rolled back
+    if args.history || args.rollback.is_some() {
+        return run_config_history(&args);
+    }
     let (s, r) = crossbeam_channel::bounded(0);
     get_config(args.admin.is_some(), s);
     let conf = r.recv()??;
+    if let Some(path) = &args.dry_run {
+        return run_config_dry_run(path, &conf);
+    }
 
     // Initialize logging system
     let compression_task =
@@ -387,6 +479,11 @@ fn run() -> Result<(), Box<dyn Error>> {
     let auto_restart_check_interval = basic_conf
         .auto_restart_check_interval
         .map_or(Duration::from_secs(90), |item| item);
+    let kubernetes_controller = args
+        .kubernetes
+        .as_deref()
+        .map(KubernetesController::new)
+        .transpose()?;
 
     #[cfg(feature = "perf")]
     info!("Enable feature perf");
@@ -419,6 +516,9 @@ fn run() -> Result<(), Box<dyn Error>> {
         if args.autorestart {
             new_args.push("--autorestart".to_string());
         }
+        if let Some(kubernetes) = &args.kubernetes {
+            new_args.push(format!("--kubernetes={kubernetes}"));
+        }
         cmd.args = new_args;
         process::set_restart_process_command(cmd);
     }
@@ -522,6 +622,14 @@ fn run() -> Result<(), Box<dyn Error>> {
                 TracerService::new(&serve_conf.name, otlp_exporter),
             ));
         }
+        #[cfg(feature = "full")]
+        // add otlp metrics service
+        if let Some(otlp_metrics) = &serve_conf.otlp_metrics {
+            my_server.add_service(background_service(
+                &format!("otlp_metrics:{}", serve_conf.name),
+                MetricsService::new(&serve_conf.name, otlp_metrics),
+            ));
+        }
     }
 
     let mut simple_tasks = vec![
@@ -536,11 +644,7 @@ fn run() -> Result<(), Box<dyn Error>> {
         simple_tasks.push(compression_task);
     }
 
-    let enabled_lets_encrypt = certificates.iter().any(|(_, certificate)| {
-        let acme = certificate.acme.clone().unwrap_or_default();
-        let domains = certificate.domains.clone().unwrap_or_default();
-        !acme.is_empty() && !domains.is_empty()
-    });
+    let enabled_lets_encrypt = proxy::is_lets_encrypt_enabled(&certificates);
 
     if std::env::var("PINGAP_DISABLE_ACME")
         .unwrap_or_default()
@@ -576,16 +680,33 @@ fn run() -> Result<(), Box<dyn Error>> {
     }
 
     for server_conf in server_conf_list.iter() {
-        let listen_80_port = server_conf.addr.ends_with(":80");
-        let mut ps = Server::new(server_conf)?;
-        if enabled_lets_encrypt && listen_80_port {
-            ps.enable_lets_encrypt();
+        // each server can be stopped or replaced by hot reload
+        #[cfg(unix)]
+        {
+            let service = proxy::new_server_service(
+                server_conf,
+                enabled_lets_encrypt,
+                &my_server.configuration,
+            )?;
+            my_server.add_service(service);
         }
-        if let Some(service) = ps.get_prometheus_push_service() {
-            simple_tasks.push(service);
+        // the change of server is applied by restart
+        #[cfg(not(unix))]
+        {
+            let listen_80_port = server_conf.addr.ends_with(":80");
+            let mut ps = Server::new(server_conf)?;
+            if enabled_lets_encrypt && listen_80_port {
+                ps.enable_lets_encrypt();
+            }
+            if let Some(service) = ps.get_prometheus_push_service() {
+                simple_tasks.push(service);
+            }
+            if let Some(service) = ps.take_access_log_compress_service() {
+                simple_tasks.push(service);
+            }
+            let services = ps.run(&my_server.configuration)?;
+            my_server.add_service(services.lb);
         }
-        let services = ps.run(&my_server.configuration)?;
-        my_server.add_service(services.lb);
     }
 
     if args.autorestart || args.autoreload {
@@ -609,6 +730,16 @@ fn run() -> Result<(), Box<dyn Error>> {
         }
     }
 
+    if let Some(controller) = kubernetes_controller {
+        my_server.add_service(background_service(
+            "kubernetes",
+            new_kubernetes_controller_service(
+                auto_restart_check_interval,
+                controller,
+            ),
+        ));
+    }
+
     my_server.add_service(background_service(
         "simple_task",
         new_simple_service_task(
@@ -12,8 +12,14 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use super::{get_hash_key, get_int_conf, get_str_conf, get_str_slice_conf};
-use crate::process::{get_start_time, restart_now};
+use super::{
+    get_config_schema, get_hash_key, get_int_conf, get_plugin_schema,
+    get_str_conf, get_str_slice_conf,
+};
+use crate::process::{self, get_start_time, restart_now};
+use crate::proxy::{
+    get_captured_requests, subscribe_access_records, AccessRecordFilter,
+};
 use async_trait::async_trait;
 use bytes::Bytes;
 use bytes::{BufMut, BytesMut};
@@ -38,21 +44,23 @@ use pingap_config::{
 use pingap_core::{Ctx, HttpResponse, Plugin, PluginStep, TtlLruLimit};
 use pingap_performance::get_process_system_info;
 use pingap_performance::get_processing_accepted;
-use pingap_plugin::{get_plugin_factory, Error};
+use pingap_plugin::{get_plugin_factory, Error, PluginSchema};
 use pingap_upstream::{get_upstream_healthy_status, UpstreamHealthyStatus};
-use pingap_util::base64_decode;
-use pingora::http::RequestHeader;
+use pingap_util::{base64_decode, IpRules};
+use pingora::http::{RequestHeader, ResponseHeader};
 use pingora::proxy::Session;
 use regex::Regex;
 use rust_embed::EmbeddedFile;
 use rust_embed::RustEmbed;
 use serde::{Deserialize, Serialize};
+use serde_json::json;
 use sha2::{Digest, Sha256};
 use std::collections::HashMap;
 use std::io::Write;
 use std::sync::Arc;
 use std::time::Duration;
 use substring::Substring;
+use tokio::sync::broadcast::error::RecvError;
 use tracing::{debug, error};
 use urlencoding::decode;
 
@@ -257,19 +265,18 @@ impl AdminServe {
         {
             return true;
         }
+        self.get_auth_user(req_header).is_some()
+    }
+    /// Returns the user of authorization, it's none if the authorization is invalid
+    fn get_auth_user(&self, req_header: &RequestHeader) -> Option<String> {
         let value =
             pingap_core::get_req_header_value(req_header, "Authorization")
                 .unwrap_or_default();
-        if value.is_empty() {
-            return false;
-        }
-        let Some((token, ts)) = value.split_once(':') else {
-            return false;
-        };
+        let (token, ts) = value.split_once(':')?;
         let offset = pingap_util::now_sec() as i64
             - ts.parse::<i64>().unwrap_or_default();
         if offset.abs() > self.max_age.as_secs() as i64 {
-            return false;
+            return None;
         }
 
         for (user, pass) in self.authorizations.iter() {
@@ -277,10 +284,10 @@ impl AdminServe {
             hasher.update(format!("{user}:{pass}:{ts}").as_bytes());
             let hash256 = hasher.finalize();
             if hash256.encode_hex::<String>() == token {
-                return true;
+                return Some(user.clone());
             }
         }
-        false
+        None
     }
     async fn load_config(
         &self,
@@ -289,6 +296,8 @@ impl AdminServe {
         let conf = pingap_config::load_config(LoadConfigOptions {
             replace_include,
             admin: true,
+            // the secret references should never be shown in plaintext
+            resolve_secret: false,
         })
         .await
         .map_err(|e| {
@@ -342,13 +351,14 @@ impl AdminServe {
         &self,
         category: &str,
         name: &str,
+        author: &str,
     ) -> pingora::Result<HttpResponse> {
         let mut conf = self.load_config(false).await?;
         conf.remove(category, name).map_err(|e| {
             error!(error = e.to_string(), "validate config fail");
             pingap_core::new_internal_error(400, e.to_string())
         })?;
-        save_config(&conf, category, Some(name))
+        save_config(&conf, category, Some(name), author)
             .await
             .map_err(|e| {
                 error!(error = e.to_string(), "save config fail");
@@ -356,12 +366,14 @@ impl AdminServe {
             })?;
         Ok(HttpResponse::no_content())
     }
-    async fn update_config(
+    /// Returns the config which is updated by the request body,
+    /// the config is not saved.
+    async fn new_updated_config(
         &self,
         session: &mut Session,
         category: &str,
         name: &str,
-    ) -> pingora::Result<HttpResponse> {
+    ) -> pingora::Result<PingapConf> {
         if name.is_empty() {
             return Err(pingap_core::new_internal_error(
                 400,
@@ -447,7 +459,29 @@ impl AdminServe {
                 conf.basic = basic_conf;
             },
         };
-        save_config(&conf, category, Some(name))
+        Ok(conf)
+    }
+    async fn update_config(
+        &self,
+        session: &mut Session,
+        category: &str,
+        name: &str,
+        author: &str,
+        dry_run: bool,
+    ) -> pingora::Result<HttpResponse> {
+        let result = self.new_updated_config(session, category, name).await;
+        if dry_run {
+            return match result {
+                Ok(conf) => dry_run_config(&conf),
+                // the invalid config is reported instead of bad request
+                Err(e) if e.etype() == &pingora::ErrorType::HTTPStatus(400) => {
+                    invalid_config_impact(&e)
+                },
+                Err(e) => Err(e),
+            };
+        }
+        let conf = result?;
+        save_config(&conf, category, Some(name), author)
             .await
             .map_err(|e| {
                 error!(error = e.to_string(), "save config fail");
@@ -458,9 +492,19 @@ impl AdminServe {
     async fn import_config(
         &self,
         session: &mut Session,
+        dry_run: bool,
     ) -> pingora::Result<HttpResponse> {
         let buf = get_request_body(session).await?;
-        let conf = PingapConf::new(&buf, false).map_err(|e| {
+        let result = PingapConf::new(&buf, false);
+        if dry_run {
+            return match result {
+                Ok(conf) => dry_run_config(&conf),
+                Err(e) => HttpResponse::try_from_json(
+                    &process::ConfigImpact::new_invalid(e.to_string()),
+                ),
+            };
+        }
+        let conf = result.map_err(|e| {
             error!(error = e.to_string(), "import config fail");
             pingap_core::new_internal_error(400, e.to_string())
         })?;
@@ -476,6 +520,190 @@ impl AdminServe {
     }
 }
 
+/// Reports the impact of applying the candidate config without saving it,
+/// the candidate is resolved in the same way as the running config.
+fn dry_run_config(conf: &PingapConf) -> pingora::Result<HttpResponse> {
+    let data = toml::to_string_pretty(conf)
+        .map_err(|e| pingap_core::new_internal_error(400, e.to_string()))?;
+    // the errors of includes, templates and references are validation errors
+    let mut candidate = match PingapConf::new_resolved(data.as_bytes()) {
+        Ok(candidate) => candidate,
+        Err(e) => {
+            return HttpResponse::try_from_json(
+                &process::ConfigImpact::new_invalid(e.to_string()),
+            );
+        },
+    };
+    // the running config includes the config of kubernetes
+    pingap_kubernetes::get_kubernetes_conf().merge_into(&mut candidate);
+    let impact =
+        process::dry_run_config(get_current_config().as_ref(), &candidate);
+    HttpResponse::try_from_json(&impact)
+}
+
+/// Reports the candidate config which can not be created as invalid
+fn invalid_config_impact(e: &pingora::Error) -> pingora::Result<HttpResponse> {
+    let message = e
+        .context
+        .as_ref()
+        .map(|item| item.to_string())
+        .unwrap_or_else(|| e.to_string());
+    HttpResponse::try_from_json(&process::ConfigImpact::new_invalid(message))
+}
+
+/// Default duration of tailing access records
+const DEFAULT_TAIL_DURATION: Duration = Duration::from_secs(60);
+/// Max duration of tailing access records
+const MAX_TAIL_DURATION: Duration = Duration::from_secs(30 * 60);
+/// Interval of keepalive comment for idle event stream
+const TAIL_KEEPALIVE_INTERVAL: Duration = Duration::from_secs(15);
+
+/// Gets the decoded query value of request
+fn get_decoded_query_value(
+    req_header: &RequestHeader,
+    name: &str,
+) -> Option<String> {
+    pingap_core::get_query_value(req_header, name)
+        .map(|value| decode(value).unwrap_or_default().to_string())
+        .filter(|value| !value.is_empty())
+}
+
+/// Streams the access records as server-sent events,
+/// the records can be filtered by location, status, path regex and client ip.
+/// e.g. `/tail?location=api&status=5xx&path=^/users&client_ip=10.0.0.0/8&duration=5m`
+async fn tail_access_records(
+    session: &mut Session,
+    ctx: &mut Ctx,
+) -> pingora::Result<HttpResponse> {
+    let req_header = session.req_header();
+    let path =
+        if let Some(value) = get_decoded_query_value(req_header, "path") {
+            Some(Regex::new(&value).map_err(|e| {
+                pingap_core::new_internal_error(400, e.to_string())
+            })?)
+        } else {
+            None
+        };
+    let filter = AccessRecordFilter {
+        location: get_decoded_query_value(req_header, "location"),
+        status: get_decoded_query_value(req_header, "status"),
+        path,
+        client_ip: get_decoded_query_value(req_header, "client_ip").map(
+            |value| {
+                IpRules::new(
+                    &value.split(',').map(|item| item.to_string()).collect(),
+                )
+            },
+        ),
+    };
+    let duration = get_decoded_query_value(req_header, "duration")
+        .and_then(|value| parse_duration(&value).ok())
+        .unwrap_or(DEFAULT_TAIL_DURATION)
+        .min(MAX_TAIL_DURATION);
+
+    let mut rx = subscribe_access_records();
+    let mut resp = ResponseHeader::build(StatusCode::OK, Some(4))?;
+    resp.insert_header(header::CONTENT_TYPE, "text/event-stream")?;
+    resp.insert_header(header::CACHE_CONTROL, "no-cache")?;
+    let chunked = pingap_core::HTTP_HEADER_TRANSFER_CHUNKED.clone();
+    resp.insert_header(chunked.0, chunked.1)?;
+    session.write_response_header(Box::new(resp), false).await?;
+    ctx.status = Some(StatusCode::OK);
+
+    let deadline = tokio::time::Instant::now() + duration;
+    loop {
+        let timeout = TAIL_KEEPALIVE_INTERVAL.min(
+            deadline.saturating_duration_since(tokio::time::Instant::now()),
+        );
+        if timeout.is_zero() {
+            break;
+        }
+        let data = match tokio::time::timeout(timeout, rx.recv()).await {
+            Ok(Ok(record)) => {
+                if !filter.matches(&record) {
+                    continue;
+                }
+                let data =
+                    serde_json::to_string(record.as_ref()).map_err(|e| {
+                        pingap_core::new_internal_error(500, e.to_string())
+                    })?;
+                format!("data: {data}\n\n")
+            },
+            // the oldest records are dropped because the watcher is too slow
+            Ok(Err(RecvError::Lagged(count))) => {
+                format!("event: lagged\ndata: {count}\n\n")
+            },
+            Ok(Err(RecvError::Closed)) => break,
+            Err(_) => ": keepalive\n\n".to_string(),
+        };
+        // the client is disconnected if write fails
+        session
+            .write_response_body(Some(Bytes::from(data)), false)
+            .await?;
+    }
+    session.write_response_body(None, true).await?;
+    session.finish_body().await?;
+
+    // the response has been sent
+    Ok(HttpResponse {
+        status: StatusCode::from_u16(999).unwrap_or_default(),
+        ..Default::default()
+    })
+}
+
+/// Summary of config revision, the data of config is omitted
+#[derive(Serialize)]
+struct ConfigRevisionSummary {
+    version: u64,
+    created_at: u64,
+    author: String,
+    category: String,
+    name: Option<String>,
+}
+
+/// Handles the config revisions:
+/// - `GET /revisions`: list the revisions
+/// - `GET /revisions/{version}`: get the revision with diff and config
+/// - `POST /revisions/{version}/rollback`: roll back the config
+async fn handle_config_revisions(
+    method: &Method,
+    params: &[String],
+    author: &str,
+) -> std::result::Result<HttpResponse, pingap_config::Error> {
+    let version = params.get(2).map(|item| item.as_str()).unwrap_or_default();
+    if version.is_empty() {
+        let summaries: Vec<ConfigRevisionSummary> =
+            pingap_config::list_config_revisions()
+                .await?
+                .into_iter()
+                .map(|item| ConfigRevisionSummary {
+                    version: item.version,
+                    created_at: item.created_at,
+                    author: item.author,
+                    category: item.category,
+                    name: item.name,
+                })
+                .collect();
+        return Ok(HttpResponse::try_from_json(&summaries)
+            .unwrap_or(HttpResponse::unknown_error("Json serde fail".into())));
+    }
+    let version =
+        version
+            .parse::<u64>()
+            .map_err(|e| pingap_config::Error::Invalid {
+                message: format!("version is invalid, {e}"),
+            })?;
+    if method == Method::POST
+        && params.get(3).map(|item| item.as_str()) == Some("rollback")
+    {
+        pingap_config::rollback_config(version, author).await?;
+        return Ok(HttpResponse::no_content());
+    }
+    let revision = pingap_config::get_config_revision(version).await?;
+    Ok(HttpResponse::try_from_json(&revision)
+        .unwrap_or(HttpResponse::unknown_error("Json serde fail".into())))
+}
+
 fn get_method_path(session: &Session) -> (Method, String) {
     let req_header = session.req_header();
     let method = req_header.method.clone();
@@ -486,7 +714,7 @@ fn get_method_path(session: &Session) -> (Method, String) {
 async fn handle_request_admin(
     plugin: &AdminServe,
     session: &mut Session,
-    _ctx: &mut Ctx,
+    ctx: &mut Ctx,
 ) -> pingora::Result<Option<HttpResponse>> {
     let ip = pingap_core::get_client_ip(session);
     if !plugin.ip_fail_limit.validate(&ip) {
@@ -537,22 +765,34 @@ async fn handle_request_admin(
     if params.len() >= 3 {
         category = &params[2];
     }
+    // the admin user who changes the config
+    let author = plugin
+        .get_auth_user(session.req_header())
+        .unwrap_or_default();
     let resp = if path.starts_with("/configs") {
+        // only report the impact of change, nothing is saved
+        let dry_run =
+            pingap_core::get_query_value(session.req_header(), "dry_run")
+                == Some("true");
         match method {
             Method::POST => {
                 if category == "import" {
-                    plugin.import_config(session).await
+                    plugin.import_config(session, dry_run).await
                 } else if params.len() < 4 {
                     Err(pingora::Error::new_str("Url is invalid(no name)"))
                 } else {
-                    plugin.update_config(session, category, &params[3]).await
+                    plugin
+                        .update_config(
+                            session, category, &params[3], &author, dry_run,
+                        )
+                        .await
                 }
             },
             Method::DELETE => {
                 if params.len() < 4 {
                     Err(pingora::Error::new_str("Url is invalid(no name)"))
                 } else {
-                    plugin.remove_config(category, &params[3]).await
+                    plugin.remove_config(category, &params[3], &author).await
                 }
             },
             _ => plugin.get_config(category).await,
@@ -637,6 +877,45 @@ async fn handle_request_admin(
         .map_err(|e| pingap_core::new_internal_error(400, e.to_string()))?;
         HttpResponse::try_from_json(&AesResp { value })
             .unwrap_or(HttpResponse::unknown_error("Json serde fail".into()))
+    } else if path.starts_with("/revisions") {
+        handle_config_revisions(&method, &params, &author)
+            .await
+            .unwrap_or_else(|err| {
+                HttpResponse::try_from_json_status(
+                    &ErrorResponse {
+                        message: err.to_string(),
+                    },
+                    StatusCode::BAD_REQUEST,
+                )
+                .unwrap_or(HttpResponse::unknown_error(
+                    "Json serde fail".into(),
+                ))
+            })
+    } else if path.starts_with("/schemas") {
+        // the schema of whole config, or the schema of plugin category
+        let schema = if category.is_empty() {
+            Some(get_config_schema())
+        } else {
+            get_plugin_schema(category)
+        };
+        if let Some(schema) = schema {
+            HttpResponse::try_from_json(&schema).unwrap_or(
+                HttpResponse::unknown_error("Json serde fail".into()),
+            )
+        } else {
+            HttpResponse::try_from_json_status(
+                &ErrorResponse {
+                    message: format!("plugin({category}) is not supported"),
+                },
+                StatusCode::NOT_FOUND,
+            )
+            .unwrap_or(HttpResponse::unknown_error("Json serde fail".into()))
+        }
+    } else if path == "/captures" {
+        HttpResponse::try_from_json(&get_captured_requests())
+            .unwrap_or(HttpResponse::unknown_error("Json serde fail".into()))
+    } else if path == "/tail" {
+        tail_access_records(session, ctx).await?
     } else if path == "/certificates" {
         let mut infos = HashMap::new();
         for (name, info) in get_certificate_info_list() {
@@ -668,7 +947,7 @@ impl Plugin for AdminServe {
         &self,
         step: PluginStep,
         session: &mut Session,
-        _ctx: &mut Ctx,
+        ctx: &mut Ctx,
     ) -> pingora::Result<(bool, Option<HttpResponse>)> {
         if self.plugin_step != step {
             return Ok((false, None));
@@ -676,7 +955,7 @@ impl Plugin for AdminServe {
         if !session.req_header().uri.path().starts_with(&self.path) {
             return Ok((false, None));
         }
-        let resp = handle_request_admin(self, session, _ctx).await?;
+        let resp = handle_request_admin(self, session, ctx).await?;
         Ok((true, resp))
     }
 }
@@ -685,11 +964,51 @@ impl Plugin for AdminServe {
 fn init() {
     get_plugin_factory()
         .register("admin", |params| Ok(Arc::new(AdminServe::new(params)?)));
+    get_plugin_factory().register_schema(
+        "admin",
+        PluginSchema::new("Admin web interface of pingap")
+            .steps(&[PluginStep::Request])
+            .property(
+                "path",
+                json!({
+                    "type": "string",
+                    "description": "Path prefix of admin"
+                }),
+            )
+            .property(
+                "authorizations",
+                json!({
+                    "type": "array",
+                    "items": {
+                        "type": "string"
+                    },
+                    "description": "Base64 encoded credentials of user:password"
+                }),
+            )
+            .property(
+                "max_age",
+                json!({
+                    "type": "string",
+                    "description": "Max age of the login session",
+                    "default": "2d"
+                }),
+            )
+            .property(
+                "ip_fail_limit",
+                json!({
+                    "type": "integer",
+                    "description": "Max failed login count of ip",
+                    "default": 10
+                }),
+            ),
+    );
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{AdminAsset, AdminServe, EmbeddedStaticFile};
+    use super::{
+        invalid_config_impact, AdminAsset, AdminServe, EmbeddedStaticFile,
+    };
     use pingap_config::PluginConf;
     use pingap_core::HttpResponse;
     use pretty_assertions::assert_eq;
@@ -759,4 +1078,18 @@ mod tests {
             EmbeddedStaticFile(None, Duration::from_secs(60)).into();
         assert_eq!(404, resp.status.as_u16())
     }
+
+    #[test]
+    fn test_invalid_config_impact() {
+        let e = pingap_core::new_internal_error(
+            400,
+            "invalid type: string \"1\", expected u32".to_string(),
+        );
+        let resp = invalid_config_impact(&e).unwrap();
+        assert_eq!(200, resp.status.as_u16());
+        assert_eq!(
+            r#"{"valid":false,"error":"invalid type: string \"1\", expected u32","diff":[],"hot_reload":[],"restart":[],"plugin_errors":[]}"#,
+            std::str::from_utf8(&resp.body).unwrap()
+        );
+    }
 }
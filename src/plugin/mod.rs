@@ -213,6 +213,10 @@ type Plugins = AHashMap<String, Arc<dyn Plugin>>;
 static PLUGINS: Lazy<ArcSwap<Plugins>> =
     Lazy::new(|| ArcSwap::from_pointee(AHashMap::new()));
 
+/// Categories of all active plugins, it's used for tracing
+static PLUGIN_CATEGORIES: Lazy<ArcSwap<AHashMap<String, String>>> =
+    Lazy::new(|| ArcSwap::from_pointee(AHashMap::new()));
+
 /// Parses plugin configurations and instantiates plugin instances.
 ///
 /// # Arguments
@@ -301,6 +305,16 @@ pub fn try_init_plugins(
     }
 
     plugin_configs.extend(get_builtin_proxy_plugins());
+    let categories = plugin_configs
+        .iter()
+        .map(|(name, conf)| {
+            let category = conf
+                .get("category")
+                .and_then(|value| value.as_str())
+                .unwrap_or_default();
+            (name.to_string(), category.to_string())
+        })
+        .collect();
 
     let mut updated_plugins = vec![];
     let mut plugins = AHashMap::new();
@@ -336,6 +350,7 @@ pub fn try_init_plugins(
     plugins.extend(new_plugins);
     errors.extend(new_errors);
     PLUGINS.store(Arc::new(plugins));
+    PLUGIN_CATEGORIES.store(Arc::new(categories));
     let error = if !errors.is_empty() {
         let error = errors
             .iter()
@@ -355,6 +370,30 @@ pub fn get_plugin(name: &str) -> Option<Arc<dyn Plugin>> {
     PLUGINS.load().get(name).cloned()
 }
 
+/// Returns the json schema of the whole config with the schemas of
+/// all registered plugins.
+pub fn get_config_schema() -> serde_json::Value {
+    pingap_config::get_config_schema(&get_plugin_factory().get_schemas())
+}
+
+/// Returns the json schema of the plugin config of category,
+/// it's None if the plugin is not registered.
+pub fn get_plugin_schema(category: &str) -> Option<serde_json::Value> {
+    get_plugin_factory()
+        .get_schema(category)
+        .map(|value| pingap_config::get_plugin_schema(category, &value))
+}
+
+/// Returns the category of plugin, e.g. `limit`, `cache`
+#[cfg(feature = "full")]
+pub fn get_plugin_category(name: &str) -> String {
+    PLUGIN_CATEGORIES
+        .load()
+        .get(name)
+        .cloned()
+        .unwrap_or_default()
+}
+
 /// Helper functions for accessing plugin configuration values
 pub(crate) fn get_str_conf(value: &PluginConf, key: &str) -> String {
     if let Some(value) = value.get(key) {
@@ -14,17 +14,23 @@
 
 use super::{get_hash_key, get_step_conf, get_str_conf, Plugin};
 use crate::process::get_start_time;
+use crate::proxy::{get_captured_requests, CapturedRequests};
 use async_trait::async_trait;
 use bytes::Bytes;
 use ctor::ctor;
 use pingap_config::{PluginCategory, PluginConf};
 use pingap_core::{get_hostname, Ctx, HttpResponse, PluginStep};
 use pingap_location::get_locations_stats;
+use pingap_logger::{
+    get_access_log_writer_stats, get_http_log_sink_stats, AccessLogWriterStats,
+    HttpLogSinkStats,
+};
 use pingap_performance::{get_process_system_info, get_processing_accepted};
-use pingap_plugin::{get_plugin_factory, Error};
+use pingap_plugin::{get_plugin_factory, Error, PluginSchema};
 use pingap_upstream::{get_upstream_healthy_status, UpstreamHealthyStatus};
 use pingora::proxy::Session;
 use serde::Serialize;
+use serde_json::json;
 use std::collections::HashMap;
 use std::sync::Arc;
 use std::time::Duration;
@@ -75,6 +81,9 @@ struct ServerStats {
 
     upstream_healthy_status: HashMap<String, UpstreamHealthyStatus>, // Upstream healthy status
     locations_stats: HashMap<String, (i32, u64)>, // Locations stats
+    captured_requests: HashMap<String, CapturedRequests>, // The slowest and failed requests of servers
+    log_sinks: Vec<HttpLogSinkStats>, // Sent and dropped lines of http log sinks
+    access_log_writers: Vec<AccessLogWriterStats>, // Dropped lines of access log writers
 }
 
 /// Stats plugin that exposes server metrics and statistics via an HTTP endpoint
@@ -194,6 +203,9 @@ impl Plugin for Stats {
             tcp6_count: info.tcp6_count,
             upstream_healthy_status: get_upstream_healthy_status(),
             locations_stats: get_locations_stats(),
+            captured_requests: get_captured_requests(),
+            log_sinks: get_http_log_sink_stats(),
+            access_log_writers: get_access_log_writer_stats(),
         })
         .unwrap_or_else(|e| {
             HttpResponse::unknown_error(Bytes::from(e.to_string()))
@@ -206,6 +218,18 @@ impl Plugin for Stats {
 fn init() {
     get_plugin_factory()
         .register("stats", |params| Ok(Arc::new(Stats::new(params)?)));
+    get_plugin_factory().register_schema(
+        "stats",
+        PluginSchema::new("Performance statistics of pingap")
+            .steps(&[PluginStep::Request, PluginStep::ProxyUpstream])
+            .property(
+                "path",
+                json!({
+                    "type": "string",
+                    "description": "Path of statistics"
+                }),
+            ),
+    );
 }
 
 #[cfg(test)]
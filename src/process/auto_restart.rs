@@ -16,6 +16,9 @@ use super::{restart, LOG_CATEGORY};
 use crate::webhook::{get_webhook_sender, send_notification};
 use crate::{plugin, proxy};
 use async_trait::async_trait;
+use once_cell::sync::Lazy;
+#[cfg(unix)]
+use pingap_config::CATEGORY_SERVER;
 use pingap_config::{
     get_config_storage, get_current_config, load_config, set_current_config,
     LoadConfigOptions, PingapConf, CATEGORY_CERTIFICATE, CATEGORY_LOCATION,
@@ -30,13 +33,20 @@ use pingora::server::ShutdownWatch;
 use pingora::services::background::BackgroundService;
 use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
 use std::time::Duration;
+use tokio::sync::Mutex;
 use tokio::time::interval;
 use tracing::{debug, error, info};
 
+/// Lock of config diff and update, it's called by the auto restart service
+/// and the kubernetes controller, so the config should be diffed and applied
+/// one at a time, otherwise the same change may be applied twice.
+static DIFF_UPDATE_LOCK: Lazy<Mutex<()>> = Lazy::new(|| Mutex::new(()));
+
 /// Compares configurations and handles updates through hot reload or full restart
 ///
 /// This function:
-/// 1. Loads and validates the new configuration
+/// 1. Loads and validates the new configuration, the config generated by
+///    kubernetes controller is merged into it
 /// 2. Compares it with current config to find differences
 /// 3. Attempts hot reload for supported changes:
 ///    - Server locations
@@ -44,17 +54,22 @@ use tracing::{debug, error, info};
 ///    - Location definitions
 ///    - Plugin configurations
 ///    - Certificates (except ACME/Let's Encrypt)
+///    - Servers, they are added, removed or replaced without restart
+///      (except the admin server and the change of otlp settings)
 /// 4. Sends notifications for successful updates
 /// 5. If hot_reload_only=false and there are non-hot-reloadable changes,
 ///    triggers a full server restart
 async fn diff_and_update_config(
     hot_reload_only: bool,
 ) -> Result<(), Box<dyn std::error::Error>> {
-    let new_config = load_config(LoadConfigOptions {
+    let _guard = DIFF_UPDATE_LOCK.lock().await;
+    let mut new_config = load_config(LoadConfigOptions {
         replace_include: true,
+        resolve_secret: true,
         ..Default::default()
     })
     .await?;
+    pingap_kubernetes::get_kubernetes_conf().merge_into(&mut new_config);
     new_config.validate()?;
     let current_config: PingapConf = get_current_config().as_ref().clone();
 
@@ -81,6 +96,8 @@ async fn diff_and_update_config(
         let mut should_reload_location = false;
         let mut should_reload_plugin = false;
         let mut should_reload_certificate = false;
+        #[cfg(unix)]
+        let mut should_reload_server = false;
 
         // update the values which can be hot reload
         // set server locations
@@ -119,6 +136,10 @@ async fn diff_and_update_config(
                 CATEGORY_LOCATION => should_reload_location = true,
                 CATEGORY_UPSTREAM => should_reload_upstream = true,
                 CATEGORY_PLUGIN => should_reload_plugin = true,
+                // the server is only hot reloaded on unix,
+                // otherwise it's applied by restart
+                #[cfg(unix)]
+                CATEGORY_SERVER => should_reload_server = true,
                 CATEGORY_CERTIFICATE => {
                     if !exists_acme {
                         should_reload_certificate = true;
@@ -273,6 +294,52 @@ async fn diff_and_update_config(
                 },
             };
         }
+
+        // the other servers keep running, so their connection pools
+        // and caches are not dropped
+        #[cfg(unix)]
+        if should_reload_server {
+            // the locations of new servers should be initialized first
+            if let Err(e) = proxy::try_init_server_locations(
+                &new_config.servers,
+                &new_config.locations,
+            ) {
+                error!(
+                    category = LOG_CATEGORY,
+                    error = %e,
+                    "init server location fail"
+                );
+            }
+            let (updated_servers, error) = proxy::try_update_servers(
+                &hot_reload_config.servers,
+                &new_config,
+            )
+            .await;
+            for name in updated_servers.iter() {
+                if let Some(server) = new_config.servers.get(name) {
+                    hot_reload_config
+                        .servers
+                        .insert(name.to_string(), server.clone());
+                } else {
+                    hot_reload_config.servers.remove(name);
+                }
+            }
+            if !updated_servers.is_empty() {
+                info!(category = LOG_CATEGORY, "reload server success");
+                send_notification(NotificationData {
+                    category: "reload_config".to_string(),
+                    level: NotificationLevel::Info,
+                    message: format_message("Server", updated_servers),
+                    ..Default::default()
+                })
+                .await;
+            }
+            if !error.is_empty() {
+                reload_fail_messages
+                    .push(format!("server reload fail: {error}"));
+                error!(category = LOG_CATEGORY, error, "reload server fail");
+            }
+        }
     }
 
     let reload_fail_message = reload_fail_messages.join(";");
@@ -496,7 +563,7 @@ impl BackgroundService for ConfigObserverService {
 
 /// Helper function to run the config diff and update process
 /// Logs any errors that occur during the update
-async fn run_diff_and_update_config(hot_reload_only: bool) {
+pub(super) async fn run_diff_and_update_config(hot_reload_only: bool) {
     if let Err(e) = diff_and_update_config(hot_reload_only).await {
         error!(
             category = LOG_CATEGORY,
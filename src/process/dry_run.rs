@@ -0,0 +1,211 @@
+// Copyright 2024-2025 Tree xie.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::plugin;
+#[cfg(unix)]
+use crate::proxy;
+use pingap_config::{
+    PingapConf, PluginCategory, CATEGORY_CERTIFICATE, CATEGORY_LOCATION,
+    CATEGORY_PLUGIN, CATEGORY_SERVER, CATEGORY_UPSTREAM,
+};
+use serde::Serialize;
+
+/// Impact report of applying the candidate config, nothing is applied
+#[derive(Debug, Default, Serialize)]
+pub struct ConfigImpact {
+    /// Whether the candidate config passes the validation
+    pub valid: bool,
+    /// Validation error of the candidate config
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// Diff from the current config to the candidate config
+    pub diff: Vec<String>,
+    /// Categories which will be applied by hot reload
+    pub hot_reload: Vec<String>,
+    /// Categories which require restart
+    pub restart: Vec<String>,
+    /// Servers which require restart, e.g. the change of otlp settings
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub restart_servers: Vec<String>,
+    /// Errors of creating the added or modified plugins
+    pub plugin_errors: Vec<String>,
+    /// Added or modified plugins which are not created, their constructors
+    /// change the global state(e.g. the backend of cache)
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub unchecked_plugins: Vec<String>,
+}
+
+impl ConfigImpact {
+    /// Creates the impact of candidate config which can not be loaded
+    pub fn new_invalid(error: String) -> Self {
+        Self {
+            valid: false,
+            error: Some(error),
+            ..Default::default()
+        }
+    }
+}
+
+/// Returns whether creating the plugin changes the global state,
+/// e.g. the cache plugin initializes the cache backend and eviction manager.
+fn has_global_state(conf: &pingap_config::PluginConf) -> bool {
+    conf.get("category").and_then(|v| v.as_str())
+        == Some(PluginCategory::Cache.to_string().as_str())
+}
+
+/// Returns the errors of creating the plugins which are added or modified
+/// and the names of the unchecked plugins with global state.
+/// The created plugins are dropped and the running plugins are not changed.
+fn get_plugin_errors(
+    current: &PingapConf,
+    candidate: &PingapConf,
+) -> (Vec<String>, Vec<String>) {
+    let mut configs = vec![];
+    let mut unchecked = vec![];
+    for (name, conf) in candidate.plugins.iter() {
+        if current.plugins.get(name) == Some(conf) {
+            continue;
+        }
+        if has_global_state(conf) {
+            unchecked.push(name.to_string());
+        } else {
+            configs.push((name.to_string(), conf.clone()));
+        }
+    }
+    unchecked.sort();
+    let (_, errors) = plugin::parse_plugins(configs);
+    (errors.iter().map(|e| e.to_string()).collect(), unchecked)
+}
+
+/// Reports what applying the candidate config will do without applying it,
+/// both configs should be resolved as the running config.
+/// The categories are classified in the same way as the hot reload:
+/// - location, upstream and plugin are hot reloaded
+/// - certificate is hot reloaded if no acme certificate exists
+/// - server is hot reloaded except the admin server and otlp settings
+/// - the others(basic and storage) require restart
+pub fn dry_run_config(
+    current: &PingapConf,
+    candidate: &PingapConf,
+) -> ConfigImpact {
+    let mut impact = ConfigImpact {
+        valid: true,
+        ..Default::default()
+    };
+    if let Err(e) = candidate.validate() {
+        impact.valid = false;
+        impact.error = Some(e.to_string());
+    }
+    let (mut categories, diff) = current.diff(candidate);
+    impact.diff = diff;
+    categories.sort();
+    categories.dedup();
+
+    let exists_acme = candidate
+        .certificates
+        .values()
+        .any(|item| item.acme.is_some());
+    for category in categories {
+        let hot_reload = match category.as_str() {
+            CATEGORY_LOCATION | CATEGORY_UPSTREAM | CATEGORY_PLUGIN => true,
+            CATEGORY_CERTIFICATE => !exists_acme,
+            #[cfg(unix)]
+            CATEGORY_SERVER => {
+                impact.restart_servers = proxy::get_restart_required_servers(
+                    &current.servers,
+                    &candidate.servers,
+                );
+                impact.restart_servers.is_empty()
+            },
+            // the server is only hot reloaded on unix
+            #[cfg(not(unix))]
+            CATEGORY_SERVER => false,
+            _ => false,
+        };
+        if hot_reload {
+            impact.hot_reload.push(category);
+        } else {
+            impact.restart.push(category);
+        }
+    }
+    (impact.plugin_errors, impact.unchecked_plugins) =
+        get_plugin_errors(current, candidate);
+    impact
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_dry_run_config() {
+        let current = PingapConf::new(
+            r#"
+[upstreams.charts]
+addrs = ["127.0.0.1:5000"]
+
+[locations.lo]
+upstream = "charts"
+
+[servers.test]
+addr = "127.0.0.1:6188"
+locations = ["lo"]
+"#
+            .as_bytes(),
+            false,
+        )
+        .unwrap();
+        let impact = dry_run_config(&current, &current);
+        assert_eq!(true, impact.valid);
+        assert_eq!(true, impact.diff.is_empty());
+        assert_eq!(true, impact.hot_reload.is_empty());
+
+        let mut candidate = current.clone();
+        candidate.basic.name = Some("pingap".to_string());
+        candidate.upstreams.get_mut("charts").unwrap().addrs =
+            vec!["127.0.0.1:5001".to_string()];
+        candidate.servers.get_mut("test").unwrap().otlp_exporter =
+            Some("http://127.0.0.1:4317".to_string());
+        let mut plugin = pingap_config::PluginConf::new();
+        plugin.insert("category".to_string(), "unknown".into());
+        candidate.plugins.insert("test".to_string(), plugin);
+        let impact = dry_run_config(&current, &candidate);
+        assert_eq!(true, impact.valid);
+        assert_eq!(vec!["plugin", "upstream"], impact.hot_reload);
+        assert_eq!(vec!["basic", "server"], impact.restart);
+        assert_eq!(vec!["test"], impact.restart_servers);
+        assert_eq!(1, impact.plugin_errors.len());
+
+        // the location references an unknown upstream
+        let mut candidate = current.clone();
+        candidate.locations.get_mut("lo").unwrap().upstream =
+            Some("unknown".to_string());
+        let impact = dry_run_config(&current, &candidate);
+        assert_eq!(false, impact.valid);
+        assert_eq!(true, impact.error.is_some());
+        assert_eq!(vec!["location"], impact.hot_reload);
+
+        // the cache plugin is not created
+        let mut candidate = current.clone();
+        let mut plugin = pingap_config::PluginConf::new();
+        plugin.insert("category".to_string(), "cache".into());
+        plugin.insert("directory".to_string(), "/not/exists/cache".into());
+        candidate.plugins.insert("cache".to_string(), plugin);
+        let impact = dry_run_config(&current, &candidate);
+        assert_eq!(true, impact.plugin_errors.is_empty());
+        assert_eq!(vec!["cache"], impact.unchecked_plugins);
+        assert_eq!(false, std::path::Path::new("/not/exists/cache").exists());
+    }
+}
@@ -0,0 +1,115 @@
+// Copyright 2024-2025 Tree xie.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use super::auto_restart::run_diff_and_update_config;
+use super::LOG_CATEGORY;
+use async_trait::async_trait;
+use pingap_kubernetes::{set_kubernetes_conf, KubernetesController};
+use pingora::server::ShutdownWatch;
+use pingora::services::background::BackgroundService;
+use std::time::Duration;
+use tokio::sync::Mutex;
+use tokio::time::interval;
+use tracing::{error, info};
+
+/// Delay before syncing after the resources are changed,
+/// so the changes in a short time(e.g. rolling update) are synced once
+const SYNC_DELAY: Duration = Duration::from_secs(1);
+const MIN_DELAY: Duration = Duration::from_millis(500);
+const MAX_DELAY: Duration = Duration::from_secs(60);
+
+static KUBERNETES_NAME: &str = "kubernetesController";
+
+/// KubernetesControllerService watches the kubernetes resources
+///
+/// This service:
+/// 1. Syncs the ingresses, httproutes and endpoints on start and periodically
+/// 2. Watches the resources and syncs when they are changed
+/// 3. Applies the generated config by hot reload
+pub struct KubernetesControllerService {
+    /// How often to sync all resources
+    interval: Duration,
+    controller: Mutex<KubernetesController>,
+}
+
+pub fn new_kubernetes_controller_service(
+    interval: Duration,
+    controller: KubernetesController,
+) -> KubernetesControllerService {
+    KubernetesControllerService {
+        interval,
+        controller: Mutex::new(controller),
+    }
+}
+
+#[async_trait]
+impl BackgroundService for KubernetesControllerService {
+    async fn start(&self, mut shutdown: ShutdownWatch) {
+        let period_human: humantime::Duration = self.interval.into();
+        info!(
+            category = LOG_CATEGORY,
+            name = KUBERNETES_NAME,
+            interval = period_human.to_string(),
+            "background service is running",
+        );
+        let mut controller = self.controller.lock().await;
+        let mut period = interval(self.interval);
+        let mut delay = MIN_DELAY;
+        loop {
+            let should_sync = tokio::select! {
+                _ = shutdown.changed() => {
+                    break;
+                }
+                _ = period.tick() => true,
+                result = controller.watch() => {
+                    match result {
+                        Ok(updated) => {
+                            delay = MIN_DELAY;
+                            if updated {
+                                tokio::time::sleep(SYNC_DELAY).await;
+                            }
+                            updated
+                        },
+                        Err(e) => {
+                            error!(
+                                category = LOG_CATEGORY,
+                                error = %e,
+                                "watch kubernetes resources fail"
+                            );
+                            tokio::time::sleep(delay).await;
+                            delay = (delay * 2).min(MAX_DELAY);
+                            false
+                        },
+                    }
+                }
+            };
+            if !should_sync {
+                continue;
+            }
+            match controller.sync().await {
+                Ok(conf) => {
+                    set_kubernetes_conf(conf);
+                    run_diff_and_update_config(true).await;
+                },
+                Err(e) => {
+                    error!(
+                        category = LOG_CATEGORY,
+                        error = %e,
+                        "sync kubernetes resources fail"
+                    );
+                },
+            }
+        }
+    }
+}
@@ -1,7 +1,11 @@
 mod auto_restart;
 mod common;
+mod dry_run;
+mod kubernetes;
 
 pub const LOG_CATEGORY: &str = "process";
 
 pub use auto_restart::*;
 pub use common::*;
+pub use dry_run::*;
+pub use kubernetes::*;
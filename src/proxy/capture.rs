@@ -0,0 +1,427 @@
+// Copyright 2024-2025 Tree xie.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use arc_swap::ArcSwap;
+use once_cell::sync::Lazy;
+use pingap_core::Ctx;
+use pingora::proxy::Session;
+use serde::Serialize;
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+/// Value of sensitive headers and query params in captured request
+const REDACTED: &str = "***";
+
+/// Names of headers and query params which are always redacted
+const DEFAULT_REDACTED_NAMES: [&str; 5] = [
+    "authorization",
+    "proxy-authorization",
+    "cookie",
+    "token",
+    "access_token",
+];
+
+/// Redacts the values of query params in the redacted list
+fn redact_uri(uri: &http::Uri, redacted: &[String]) -> String {
+    let uri = uri.to_string();
+    let Some((path, query)) = uri.split_once('?') else {
+        return uri;
+    };
+    let query = query
+        .split('&')
+        .map(|item| {
+            let name = item.split('=').next().unwrap_or_default();
+            if redacted.contains(&name.to_lowercase()) {
+                format!("{name}={REDACTED}")
+            } else {
+                item.to_string()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("&");
+    format!("{path}?{query}")
+}
+
+/// Timings of captured request in milliseconds
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct CapturedTimings {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub upstream_connect: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub upstream_tcp_connect: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub upstream_tls_handshake: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub upstream_processing: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub upstream_response: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cache_lookup: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cache_lock: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub plugins: Option<Vec<(String, u32)>>,
+}
+
+/// Request captured for incident investigation
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct CapturedRequest {
+    /// Finished time of the request(unix timestamp in milliseconds)
+    pub time: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
+    pub client_ip: String,
+    pub method: String,
+    /// Request uri, the values of sensitive query params are redacted
+    pub uri: String,
+    pub location: String,
+    pub status: u16,
+    /// Latency of the request in milliseconds
+    pub latency: u64,
+    /// Request headers, the sensitive values are redacted
+    pub headers: Vec<(String, String)>,
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub upstream: String,
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub upstream_address: String,
+    pub timings: CapturedTimings,
+    /// Error of proxy, e.g. upstream connect fail
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl CapturedRequest {
+    fn new(
+        session: &Session,
+        ctx: &Ctx,
+        redacted: &[String],
+        error: Option<String>,
+    ) -> Self {
+        let req_header = session.req_header();
+        let headers = req_header
+            .headers
+            .iter()
+            .map(|(name, value)| {
+                // header name is lowercase
+                let value = if redacted.iter().any(|item| item == name.as_str())
+                {
+                    REDACTED.to_string()
+                } else {
+                    value.to_str().unwrap_or_default().to_string()
+                };
+                (name.to_string(), value)
+            })
+            .collect();
+        let now = pingap_util::now_ms();
+        Self {
+            time: now,
+            request_id: ctx.request_id.clone(),
+            client_ip: ctx
+                .client_ip
+                .clone()
+                .unwrap_or_else(|| pingap_core::get_client_ip(session)),
+            method: req_header.method.to_string(),
+            uri: redact_uri(&req_header.uri, redacted),
+            location: ctx.location.clone(),
+            status: ctx.status.map(|s| s.as_u16()).unwrap_or_default(),
+            latency: now.saturating_sub(ctx.created_at),
+            headers,
+            upstream: ctx.upstream.clone(),
+            upstream_address: ctx.upstream_address.clone(),
+            timings: CapturedTimings {
+                upstream_connect: ctx.upstream_connect_time,
+                upstream_tcp_connect: ctx.upstream_tcp_connect_time,
+                upstream_tls_handshake: ctx.upstream_tls_handshake_time,
+                upstream_processing: ctx.upstream_processing_time,
+                upstream_response: ctx.upstream_response_time,
+                cache_lookup: ctx.cache_lookup_time,
+                cache_lock: ctx.cache_lock_time,
+                plugins: ctx.plugin_processing_times.clone(),
+            },
+            error,
+        }
+    }
+}
+
+/// Snapshot of captured requests of server
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct CapturedRequests {
+    /// The slowest requests, sorted by latency in descending order
+    pub slowest: Vec<CapturedRequest>,
+    /// The most recent failed requests, the newest is first
+    pub failed: Vec<CapturedRequest>,
+}
+
+/// Bounded store of the slowest and the most recent failed requests
+pub struct RequestCapture {
+    size: usize,
+    /// Lowercase names of headers and query params to redact
+    redacted: Vec<String>,
+    /// Min latency of slowest requests when the store is full,
+    /// the faster request is ignored without lock.
+    min_slow_latency: AtomicU64,
+    slowest: Mutex<Vec<CapturedRequest>>,
+    failed: Mutex<VecDeque<CapturedRequest>>,
+}
+
+impl RequestCapture {
+    /// Creates a new request capture, it keeps `size` requests for each category.
+    /// The `redacted` names are added to the default redacted headers and query params.
+    pub fn new(size: usize, redacted: &[String]) -> Self {
+        let mut names: Vec<String> = DEFAULT_REDACTED_NAMES
+            .iter()
+            .map(|item| item.to_string())
+            .collect();
+        for item in redacted {
+            let item = item.trim().to_lowercase();
+            if !item.is_empty() && !names.contains(&item) {
+                names.push(item);
+            }
+        }
+        Self {
+            size,
+            redacted: names,
+            min_slow_latency: AtomicU64::new(0),
+            slowest: Mutex::new(Vec::with_capacity(size)),
+            failed: Mutex::new(VecDeque::with_capacity(size)),
+        }
+    }
+
+    /// Captures the request if it's one of the slowest or it fails.
+    /// A request fails if it has a proxy error or the status is 5xx.
+    pub fn capture(
+        &self,
+        session: &Session,
+        ctx: &Ctx,
+        error: Option<&pingora::Error>,
+    ) {
+        let latency = pingap_util::now_ms().saturating_sub(ctx.created_at);
+        let status = ctx.status.map(|s| s.as_u16()).unwrap_or_default();
+        let failed = error.is_some() || status >= 500;
+        let slow = latency > self.min_slow_latency.load(Ordering::Relaxed);
+        if !failed && !slow {
+            return;
+        }
+        let record = CapturedRequest::new(
+            session,
+            ctx,
+            &self.redacted,
+            error.map(|e| e.to_string()),
+        );
+        if slow {
+            self.add_slow(record.clone());
+        }
+        if failed {
+            self.add_failed(record);
+        }
+    }
+
+    fn add_slow(&self, record: CapturedRequest) {
+        let Ok(mut slowest) = self.slowest.lock() else {
+            return;
+        };
+        if slowest.len() >= self.size {
+            // the latency of last one is the smallest
+            if slowest
+                .last()
+                .map(|item| item.latency >= record.latency)
+                .unwrap_or_default()
+            {
+                return;
+            }
+            slowest.pop();
+        }
+        let index =
+            slowest.partition_point(|item| item.latency >= record.latency);
+        slowest.insert(index, record);
+        if slowest.len() >= self.size {
+            let min = slowest.last().map(|item| item.latency).unwrap_or(0);
+            self.min_slow_latency.store(min, Ordering::Relaxed);
+        }
+    }
+
+    fn add_failed(&self, record: CapturedRequest) {
+        let Ok(mut failed) = self.failed.lock() else {
+            return;
+        };
+        if failed.len() >= self.size {
+            failed.pop_back();
+        }
+        failed.push_front(record);
+    }
+
+    /// Returns the snapshot of captured requests
+    pub fn snapshot(&self) -> CapturedRequests {
+        CapturedRequests {
+            slowest: self
+                .slowest
+                .lock()
+                .map(|item| item.clone())
+                .unwrap_or_default(),
+            failed: self
+                .failed
+                .lock()
+                .map(|item| item.iter().cloned().collect())
+                .unwrap_or_default(),
+        }
+    }
+}
+
+type RequestCaptures = HashMap<String, Arc<RequestCapture>>;
+static REQUEST_CAPTURE_MAP: Lazy<ArcSwap<RequestCaptures>> =
+    Lazy::new(|| ArcSwap::from_pointee(HashMap::new()));
+
+/// Creates the request capture of server and registers it for querying
+pub fn new_request_capture(
+    name: &str,
+    size: usize,
+    redacted: &[String],
+) -> Arc<RequestCapture> {
+    let capture = Arc::new(RequestCapture::new(size, redacted));
+    let mut m: RequestCaptures = HashMap::new();
+    for (key, value) in REQUEST_CAPTURE_MAP.load().iter() {
+        m.insert(key.to_string(), value.clone());
+    }
+    m.insert(name.to_string(), capture.clone());
+    REQUEST_CAPTURE_MAP.store(Arc::new(m));
+    capture
+}
+
+/// Removes the request capture of server, it's called when the server
+/// is removed or the request capture is disabled by hot reload
+pub fn remove_request_capture(name: &str) {
+    if !REQUEST_CAPTURE_MAP.load().contains_key(name) {
+        return;
+    }
+    let mut m: RequestCaptures = HashMap::new();
+    for (key, value) in REQUEST_CAPTURE_MAP.load().iter() {
+        if key != name {
+            m.insert(key.to_string(), value.clone());
+        }
+    }
+    REQUEST_CAPTURE_MAP.store(Arc::new(m));
+}
+
+/// Returns the captured requests of all servers
+pub fn get_captured_requests() -> HashMap<String, CapturedRequests> {
+    REQUEST_CAPTURE_MAP
+        .load()
+        .iter()
+        .map(|(name, capture)| (name.to_string(), capture.snapshot()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use http::StatusCode;
+    use pretty_assertions::assert_eq;
+    use tokio_test::io::Builder;
+
+    #[tokio::test]
+    async fn test_request_capture() {
+        let headers = ["Host: github.com", "Cookie: uid=abc"].join("\r\n");
+        let input_header =
+            format!("GET /vicanso/pingap?size=1 HTTP/1.1\r\n{headers}\r\n\r\n");
+        let mock_io = Builder::new().read(input_header.as_bytes()).build();
+        let mut session = Session::new_h1(Box::new(mock_io));
+        session.read_request().await.unwrap();
+
+        let capture = RequestCapture::new(2, &[]);
+        let now = pingap_util::now_ms();
+        for latency in [10, 30, 20, 5] {
+            capture.capture(
+                &session,
+                &Ctx {
+                    created_at: now - latency,
+                    status: Some(StatusCode::OK),
+                    ..Default::default()
+                },
+                None,
+            );
+        }
+        capture.capture(
+            &session,
+            &Ctx {
+                created_at: now,
+                status: Some(StatusCode::BAD_GATEWAY),
+                ..Default::default()
+            },
+            None,
+        );
+
+        let result = capture.snapshot();
+        assert_eq!(2, result.slowest.len());
+        assert_eq!(true, result.slowest[0].latency >= 30);
+        assert_eq!(true, result.slowest[1].latency >= 20);
+        assert_eq!(1, result.failed.len());
+        assert_eq!(502, result.failed[0].status);
+        assert_eq!("/vicanso/pingap?size=1", result.failed[0].uri);
+        assert_eq!(
+            r#"[("host", "github.com"), ("cookie", "***")]"#,
+            format!("{:?}", result.failed[0].headers)
+        );
+    }
+
+    #[test]
+    fn test_remove_request_capture() {
+        new_request_capture("capture-remove-a", 10, &[]);
+        new_request_capture("capture-remove-b", 10, &[]);
+        remove_request_capture("capture-remove-a");
+        let captures = get_captured_requests();
+        assert_eq!(false, captures.contains_key("capture-remove-a"));
+        assert_eq!(true, captures.contains_key("capture-remove-b"));
+    }
+
+    #[tokio::test]
+    async fn test_request_capture_redacted() {
+        let headers = [
+            "Host: github.com",
+            "Authorization: Bearer abc",
+            "X-Api-Key: 123",
+            "X-Device: ios",
+        ]
+        .join("\r\n");
+        let input_header = format!(
+            "GET /vicanso/pingap?token=abc&size=1&Key=123 HTTP/1.1\r\n{headers}\r\n\r\n"
+        );
+        let mock_io = Builder::new().read(input_header.as_bytes()).build();
+        let mut session = Session::new_h1(Box::new(mock_io));
+        session.read_request().await.unwrap();
+
+        let capture = RequestCapture::new(
+            1,
+            &["X-Api-Key".to_string(), "key".to_string()],
+        );
+        capture.capture(
+            &session,
+            &Ctx {
+                created_at: pingap_util::now_ms(),
+                status: Some(StatusCode::INTERNAL_SERVER_ERROR),
+                ..Default::default()
+            },
+            None,
+        );
+        let result = capture.snapshot();
+        assert_eq!(
+            "/vicanso/pingap?token=***&size=1&Key=***",
+            result.failed[0].uri
+        );
+        assert_eq!(
+            r#"[("host", "github.com"), ("authorization", "***"), ("x-api-key", "***"), ("x-device", "ios")]"#,
+            format!("{:?}", result.failed[0].headers)
+        );
+    }
+}
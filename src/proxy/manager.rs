@@ -0,0 +1,501 @@
+// Copyright 2024-2025 Tree xie.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use super::{
+    is_lets_encrypt_enabled, parse_from_conf, remove_request_capture, Error,
+    Server, ServerConf, LOG_CATEGORY,
+};
+use async_trait::async_trait;
+use nix::fcntl::{fcntl, FcntlArg};
+use once_cell::sync::{Lazy, OnceCell};
+use pingap_config::PingapConf;
+use pingap_core::{new_simple_service_task, SimpleServiceTaskFuture};
+use pingora::proxy::HttpProxy;
+use pingora::server::configuration;
+use pingora::server::{Fds, ListenFds, ShutdownWatch};
+use pingora::services::background::BackgroundService;
+use pingora::services::listening::Service;
+use pingora::services::Service as ServiceTrait;
+use pingora_runtime::Runtime;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::watch;
+use tracing::{error, info};
+
+type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// Default grace period of pingora, the requests of the removed server
+/// are still processed in this period.
+const DEFAULT_GRACE_PERIOD: Duration = Duration::from_secs(5 * 60);
+
+/// Listening context of pingora, it's captured when the first server is started
+/// and used to start the servers which are added by hot reload.
+struct ListenContext {
+    fds: Option<ListenFds>,
+    shutdown: ShutdownWatch,
+    listeners_per_fd: usize,
+}
+
+static LISTEN_CONTEXT: OnceCell<ListenContext> = OnceCell::new();
+
+static SERVER_CONFIGURATION: OnceCell<Arc<configuration::ServerConf>> =
+    OnceCell::new();
+
+/// Running server which can be stopped without affecting the other servers
+struct RunningServer {
+    addrs: Vec<String>,
+    admin: bool,
+    stop: Arc<watch::Sender<bool>>,
+}
+
+static RUNNING_SERVERS: Lazy<Mutex<HashMap<String, RunningServer>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+fn split_addrs(addr: &str) -> Vec<String> {
+    addr.split(',').map(|item| item.to_string()).collect()
+}
+
+fn new_error(category: &str, message: String) -> Error {
+    Error::Common {
+        category: category.to_string(),
+        message,
+    }
+}
+
+/// Listening service of server, it's stopped by the shutdown of pingora
+/// or when the server is removed or replaced by hot reload.
+pub struct ServerService {
+    name: String,
+    lb: Service<HttpProxy<Server>>,
+    /// Tasks of server, e.g. prometheus push and access log compression
+    tasks: Vec<(String, SimpleServiceTaskFuture)>,
+    stop: watch::Receiver<bool>,
+}
+
+#[async_trait]
+impl ServiceTrait for ServerService {
+    async fn start_service(
+        &mut self,
+        #[cfg(unix)] fds: Option<ListenFds>,
+        mut shutdown: ShutdownWatch,
+        listeners_per_fd: usize,
+    ) {
+        let _ = LISTEN_CONTEXT.get_or_init(|| ListenContext {
+            fds: fds.clone(),
+            shutdown: shutdown.clone(),
+            listeners_per_fd,
+        });
+        let (tx, rx) = watch::channel(false);
+        // keep the sender until the service is stopped,
+        // otherwise the receivers are closed
+        let tx = Arc::new(tx);
+        let sender = tx.clone();
+        let mut stop = self.stop.clone();
+        tokio::spawn(async move {
+            tokio::select! {
+                _ = shutdown.wait_for(|value| *value) => {},
+                _ = stop.wait_for(|value| *value) => {},
+            }
+            let _ = sender.send(true);
+        });
+        if !self.tasks.is_empty() {
+            let task = new_simple_service_task(
+                &format!("server:{}", self.name),
+                Duration::from_secs(60),
+                std::mem::take(&mut self.tasks),
+            );
+            let rx = rx.clone();
+            tokio::spawn(async move {
+                task.start(rx).await;
+            });
+        }
+        self.lb.start_service(fds, rx, listeners_per_fd).await;
+        drop(tx);
+        info!(
+            category = LOG_CATEGORY,
+            name = self.name,
+            "server is stopped"
+        );
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn threads(&self) -> Option<usize> {
+        self.lb.threads
+    }
+}
+
+/// Creates the listening service of server and registers it as running server,
+/// so it can be removed or replaced by hot reload.
+pub fn new_server_service(
+    conf: &ServerConf,
+    enabled_lets_encrypt: bool,
+    configuration: &Arc<configuration::ServerConf>,
+) -> Result<ServerService> {
+    let _ = SERVER_CONFIGURATION.get_or_init(|| configuration.clone());
+    let mut ps = Server::new(conf)?;
+    if enabled_lets_encrypt && conf.addr.ends_with(":80") {
+        ps.enable_lets_encrypt();
+    }
+    let mut tasks = vec![];
+    if let Some(task) = ps.get_prometheus_push_service() {
+        tasks.push(task);
+    }
+    if let Some(task) = ps.take_access_log_compress_service() {
+        tasks.push(task);
+    }
+    let services = ps.run(configuration)?;
+    let (tx, rx) = watch::channel(false);
+    let running_server = RunningServer {
+        addrs: split_addrs(&conf.addr),
+        admin: conf.admin,
+        stop: Arc::new(tx),
+    };
+    if let Ok(mut servers) = RUNNING_SERVERS.lock() {
+        servers.insert(conf.name.clone(), running_server);
+    }
+    Ok(ServerService {
+        name: conf.name.clone(),
+        lb: services.lb,
+        tasks,
+        stop: rx,
+    })
+}
+
+/// Removes the listening sockets of addresses from the fd table,
+/// so they are not passed to the new process when upgrading.
+fn remove_listen_fds(table: &mut Fds, addrs: &[String]) {
+    let (binds, fds) = table.serialize();
+    let (binds, fds): (Vec<_>, Vec<_>) = binds
+        .into_iter()
+        .zip(fds)
+        .filter(|(bind, _)| !addrs.contains(bind))
+        .unzip();
+    let mut new_table = Fds::new();
+    new_table.deserialize(binds, fds);
+    *table = new_table;
+}
+
+fn take_running_server(name: &str) -> Option<RunningServer> {
+    RUNNING_SERVERS
+        .lock()
+        .ok()
+        .and_then(|mut servers| servers.remove(name))
+}
+
+fn restore_running_server(name: &str, server: Option<RunningServer>) {
+    if let (Some(server), Ok(mut servers)) = (server, RUNNING_SERVERS.lock()) {
+        servers.insert(name.to_string(), server);
+    }
+}
+
+/// Stops the running server, the listening sockets which are not used
+/// by the new server are removed from the fd table.
+async fn stop_running_server(server: RunningServer, keep_addrs: &[String]) {
+    let _ = server.stop.send(true);
+    let Some(fds) = LISTEN_CONTEXT.get().and_then(|ctx| ctx.fds.as_ref())
+    else {
+        return;
+    };
+    let addrs: Vec<String> = server
+        .addrs
+        .into_iter()
+        .filter(|addr| !keep_addrs.contains(addr))
+        .collect();
+    remove_listen_fds(&mut *fds.lock().await, &addrs);
+}
+
+/// Starts the server which is added or modified, the listening sockets of the
+/// current server are duplicated for the new server, so the new connections
+/// are accepted without interruption. The current server is stopped after
+/// the new server is started, and its requests are processed gracefully.
+async fn start_server(
+    conf: &ServerConf,
+    enabled_lets_encrypt: bool,
+) -> Result<()> {
+    let (Some(ctx), Some(configuration)) =
+        (LISTEN_CONTEXT.get(), SERVER_CONFIGURATION.get())
+    else {
+        return Err(new_error(
+            "server",
+            "listening context is not initialized".to_string(),
+        ));
+    };
+    let name = &conf.name;
+    let current = take_running_server(name);
+    let current_addrs = current
+        .as_ref()
+        .map(|item| item.addrs.clone())
+        .unwrap_or_default();
+    let addrs = split_addrs(&conf.addr);
+    let result = async {
+        // check the new addresses are available before the server is started
+        for addr in addrs.iter().filter(|addr| !current_addrs.contains(addr)) {
+            std::net::TcpListener::bind(addr)
+                .map_err(|e| new_error("listen", format!("{addr}, {e}")))?;
+        }
+        let shared_addrs: Vec<&String> = addrs
+            .iter()
+            .filter(|addr| current_addrs.contains(addr))
+            .collect();
+        if shared_addrs.is_empty() {
+            return Ok(());
+        }
+        let Some(fds) = &ctx.fds else {
+            return Err(new_error("listen", "fd table not found".to_string()));
+        };
+        let mut table = fds.lock().await;
+        for addr in shared_addrs {
+            let Some(fd) = table.get(addr).copied() else {
+                return Err(new_error(
+                    "listen",
+                    format!("{addr}, listening socket not found"),
+                ));
+            };
+            let new_fd = fcntl(fd, FcntlArg::F_DUPFD_CLOEXEC(0))
+                .map_err(|e| new_error("listen", format!("{addr}, {e}")))?;
+            table.add(addr.to_string(), new_fd);
+        }
+        Ok(())
+    }
+    .await
+    .and_then(|_| {
+        new_server_service(conf, enabled_lets_encrypt, configuration)
+    });
+    let mut service = match result {
+        Ok(service) => service,
+        Err(e) => {
+            restore_running_server(name, current);
+            return Err(e);
+        },
+    };
+
+    let threads = service.threads().unwrap_or(configuration.threads).max(1);
+    let grace_period = configuration
+        .grace_period_seconds
+        .map(Duration::from_secs)
+        .unwrap_or(DEFAULT_GRACE_PERIOD);
+    let fds = ctx.fds.clone();
+    let shutdown = ctx.shutdown.clone();
+    let listeners_per_fd = ctx.listeners_per_fd;
+    let runtime_name = name.clone();
+    std::thread::spawn(move || {
+        let runtime = Runtime::new_steal(threads, &runtime_name);
+        runtime.get_handle().block_on(async {
+            service.start_service(fds, shutdown, listeners_per_fd).await;
+            // wait for the processing requests of the stopped server
+            tokio::time::sleep(grace_period).await;
+        });
+        runtime.shutdown_timeout(Duration::from_secs(1));
+    });
+
+    if let Some(current) = current {
+        stop_running_server(current, &addrs).await;
+    }
+    info!(
+        category = LOG_CATEGORY,
+        name,
+        addr = conf.addr,
+        "server is started by hot reload"
+    );
+    Ok(())
+}
+
+/// Returns the names of servers which are added, removed or modified,
+/// the change of locations is ignored because it's reloaded separately.
+fn get_changed_servers(
+    current: &HashMap<String, pingap_config::ServerConf>,
+    new: &HashMap<String, pingap_config::ServerConf>,
+) -> Vec<String> {
+    let to_string = |conf: &pingap_config::ServerConf| {
+        let mut conf = conf.clone();
+        conf.locations = None;
+        toml::to_string(&conf).unwrap_or_default()
+    };
+    let mut names: Vec<String> = current
+        .keys()
+        .filter(|name| !new.contains_key(*name))
+        .cloned()
+        .collect();
+    for (name, conf) in new.iter() {
+        let changed = current
+            .get(name)
+            .map(|item| to_string(item) != to_string(conf))
+            .unwrap_or(true);
+        if changed {
+            names.push(name.to_string());
+        }
+    }
+    names.sort();
+    names
+}
+
+/// Whether the change of server can be applied without restart,
+/// the otlp services of server are only created at startup.
+fn is_hot_reloadable(
+    current: Option<&pingap_config::ServerConf>,
+    new: Option<&pingap_config::ServerConf>,
+) -> bool {
+    let otlp = |conf: Option<&pingap_config::ServerConf>| {
+        conf.map(|item| (item.otlp_exporter.clone(), item.otlp_metrics.clone()))
+            .unwrap_or_default()
+    };
+    otlp(current) == otlp(new)
+}
+
+fn is_admin_server(name: &str) -> bool {
+    RUNNING_SERVERS
+        .lock()
+        .map(|servers| {
+            servers.get(name).map(|item| item.admin).unwrap_or_default()
+        })
+        .unwrap_or_default()
+}
+
+/// Whether the change of server can be applied without restart,
+/// the admin server is created from the command line arguments.
+fn is_server_hot_reloadable(
+    name: &str,
+    current: &HashMap<String, pingap_config::ServerConf>,
+    new: &HashMap<String, pingap_config::ServerConf>,
+) -> bool {
+    is_hot_reloadable(current.get(name), new.get(name))
+        && !is_admin_server(name)
+}
+
+/// Returns the names of changed servers which can only be applied by restart
+pub fn get_restart_required_servers(
+    current: &HashMap<String, pingap_config::ServerConf>,
+    new: &HashMap<String, pingap_config::ServerConf>,
+) -> Vec<String> {
+    get_changed_servers(current, new)
+        .into_iter()
+        .filter(|name| !is_server_hot_reloadable(name, current, new))
+        .collect()
+}
+
+/// Adds, removes or replaces the servers whose config is changed without
+/// restart, the other servers keep running, so the connection pools and
+/// caches are not dropped. The servers which can't be updated are left
+/// for restart, e.g. the admin server and the change of otlp settings.
+/// Returns the names of updated servers and the error message.
+pub async fn try_update_servers(
+    current: &HashMap<String, pingap_config::ServerConf>,
+    conf: &PingapConf,
+) -> (Vec<String>, String) {
+    let names = get_changed_servers(current, &conf.servers);
+    if names.is_empty() || LISTEN_CONTEXT.get().is_none() {
+        return (vec![], "".to_string());
+    }
+    let enabled_lets_encrypt = is_lets_encrypt_enabled(&conf.certificates);
+    let mut server_conf_list: HashMap<String, ServerConf> =
+        parse_from_conf(conf.clone())
+            .into_iter()
+            .map(|item| (item.name.clone(), item))
+            .collect();
+
+    let mut updated_servers = vec![];
+    let mut errors = vec![];
+    // stop the removed servers first, so their addresses can be reused
+    let (removed, modified): (Vec<String>, Vec<String>) = names
+        .into_iter()
+        .partition(|name| !conf.servers.contains_key(name));
+    for name in removed.into_iter().chain(modified) {
+        if !is_server_hot_reloadable(&name, current, &conf.servers) {
+            continue;
+        }
+        let Some(server_conf) = server_conf_list.remove(&name) else {
+            if let Some(server) = take_running_server(&name) {
+                stop_running_server(server, &[]).await;
+                remove_request_capture(&name);
+                info!(category = LOG_CATEGORY, name, "server is removed");
+            }
+            updated_servers.push(name);
+            continue;
+        };
+        match start_server(&server_conf, enabled_lets_encrypt).await {
+            Ok(()) => updated_servers.push(name),
+            Err(e) => {
+                error!(
+                    category = LOG_CATEGORY,
+                    error = %e,
+                    name,
+                    "start server fail"
+                );
+                errors.push(format!("{name}: {e}"));
+            },
+        }
+    }
+    (updated_servers, errors.join(";"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_get_changed_servers() {
+        let new_server = |addr: &str| pingap_config::ServerConf {
+            addr: addr.to_string(),
+            ..Default::default()
+        };
+        let mut current = HashMap::new();
+        current.insert("a".to_string(), new_server("127.0.0.1:6188"));
+        current.insert("b".to_string(), new_server("127.0.0.1:6189"));
+        current.insert("c".to_string(), new_server("127.0.0.1:6190"));
+
+        let mut new = current.clone();
+        new.remove("a");
+        // only locations are modified
+        new.get_mut("b").unwrap().locations = Some(vec!["lo".to_string()]);
+        new.get_mut("c").unwrap().access_log = Some("tiny".to_string());
+        new.insert("d".to_string(), new_server("127.0.0.1:6191"));
+        assert_eq!(
+            vec!["a".to_string(), "c".to_string(), "d".to_string()],
+            get_changed_servers(&current, &new)
+        );
+        assert_eq!(true, get_changed_servers(&current, &current).is_empty());
+    }
+
+    #[test]
+    fn test_is_hot_reloadable() {
+        let conf = pingap_config::ServerConf {
+            addr: "127.0.0.1:6188".to_string(),
+            ..Default::default()
+        };
+        let otlp_conf = pingap_config::ServerConf {
+            otlp_exporter: Some("http://127.0.0.1:4317".to_string()),
+            ..conf.clone()
+        };
+        assert_eq!(true, is_hot_reloadable(Some(&conf), None));
+        assert_eq!(true, is_hot_reloadable(None, Some(&conf)));
+        assert_eq!(true, is_hot_reloadable(Some(&otlp_conf), Some(&otlp_conf)));
+        assert_eq!(false, is_hot_reloadable(Some(&conf), Some(&otlp_conf)));
+        assert_eq!(false, is_hot_reloadable(None, Some(&otlp_conf)));
+    }
+
+    #[test]
+    fn test_remove_listen_fds() {
+        let mut table = Fds::new();
+        table.add("127.0.0.1:6188".to_string(), 10);
+        table.add("127.0.0.1:6189".to_string(), 11);
+        remove_listen_fds(&mut table, &["127.0.0.1:6188".to_string()]);
+        assert_eq!(None, table.get("127.0.0.1:6188"));
+        assert_eq!(Some(&11), table.get("127.0.0.1:6189"));
+    }
+}
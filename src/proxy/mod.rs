@@ -12,12 +12,29 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+mod capture;
+// the listening sockets are shared by the fd table of unix
+#[cfg(unix)]
+mod manager;
 mod server;
 mod server_conf;
+mod tail;
 
 pub static LOG_CATEGORY: &str = "proxy";
 
 // TODO remove this
+pub use capture::{
+    get_captured_requests, new_request_capture, remove_request_capture,
+    CapturedRequests, RequestCapture,
+};
+#[cfg(unix)]
+pub use manager::{
+    get_restart_required_servers, new_server_service, try_update_servers,
+};
 #[allow(unused_imports)]
 pub use server::*;
 pub use server_conf::{parse_from_conf, ServerConf};
+pub use tail::{
+    is_access_record_watched, publish_access_record, subscribe_access_records,
+    AccessRecord, AccessRecordFilter,
+};
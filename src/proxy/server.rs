@@ -12,7 +12,13 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use super::{ServerConf, LOG_CATEGORY};
+use super::{
+    is_access_record_watched, new_request_capture, publish_access_record,
+    remove_request_capture, AccessRecord, RequestCapture, ServerConf,
+    LOG_CATEGORY,
+};
+#[cfg(feature = "full")]
+use crate::plugin::get_plugin_category;
 use crate::plugin::{get_plugin, ADMIN_SERVER_PLUGIN};
 use ahash::AHashMap;
 use arc_swap::ArcSwap;
@@ -28,16 +34,20 @@ use pingap_config::get_config_storage;
 use pingap_core::OtelTracer;
 use pingap_core::SimpleServiceTaskFuture;
 use pingap_core::{convert_header_value, convert_headers, HttpHeader};
-use pingap_core::{get_cache_key, CompressionStat, Ctx, PluginStep};
+use pingap_core::{
+    get_cache_key, CompressionStat, Ctx, PluginStep, ServerTimingMetrics,
+};
 use pingap_core::{HttpResponse, HTTP_HEADER_NAME_X_REQUEST_ID};
 use pingap_location::{get_location, Location};
-use pingap_logger::Parser;
+use pingap_logger::{
+    new_access_log_writer, AccessLogFilter, AccessLogWriter, Parser,
+};
 #[cfg(feature = "full")]
 use pingap_otel::HeaderExtractor;
 #[cfg(feature = "full")]
 use pingap_otel::{
-    global,
-    trace::{Span, SpanKind, Tracer},
+    global::{self, BoxedSpan},
+    trace::{Span, SpanKind, Status, Tracer},
     KeyValue,
 };
 use pingap_performance::{accept_request, end_request};
@@ -52,7 +62,8 @@ use pingora::cache::cache_control::DirectiveValue;
 use pingora::cache::cache_control::InterpretCacheControl;
 use pingora::cache::filters::resp_cacheable;
 use pingora::cache::{
-    CacheKey, CacheMetaDefaults, NoCacheReason, RespCacheable,
+    CacheKey, CacheMeta, CacheMetaDefaults, ForcedInvalidationKind,
+    NoCacheReason, RespCacheable,
 };
 use pingora::http::{RequestHeader, ResponseHeader};
 use pingora::listeners::TcpSocketOptions;
@@ -93,6 +104,18 @@ type ServerLocations = AHashMap<String, Arc<Vec<String>>>;
 static SERVER_LOCATIONS_MAP: Lazy<ArcSwap<ServerLocations>> =
     Lazy::new(|| ArcSwap::from_pointee(AHashMap::new()));
 
+/// Whether the let's encrypt certificate is configured,
+/// the server listens on port 80 handles the acme challenge if it's true.
+pub fn is_lets_encrypt_enabled(
+    certificates: &HashMap<String, pingap_config::CertificateConf>,
+) -> bool {
+    certificates.iter().any(|(_, certificate)| {
+        let acme = certificate.acme.clone().unwrap_or_default();
+        let domains = certificate.domains.clone().unwrap_or_default();
+        !acme.is_empty() && !domains.is_empty()
+    })
+}
+
 /// Initializes server locations with their associated configurations.
 /// - Orders locations by weight to determine processing priority
 /// - Updates only modified server configurations
@@ -162,6 +185,15 @@ pub struct Server {
     /// Optional parser for customizing access log format and output
     log_parser: Option<Parser>,
 
+    /// Conditions and sampling of access log, None means logging all requests
+    access_log_filter: Option<AccessLogFilter>,
+
+    /// Dedicated writer of access log, None means writing to the application log
+    access_log_writer: Option<AccessLogWriter>,
+
+    /// Compression service of access log files, it's taken when the server is started
+    access_log_compress_service: Option<(String, SimpleServiceTaskFuture)>,
+
     /// HTML/JSON template used for rendering error responses
     error_template: String,
 
@@ -207,11 +239,24 @@ pub struct Server {
     #[cfg(feature = "full")]
     enabled_otel: bool,
 
+    /// Whether OpenTelemetry metrics exporting is enabled
+    #[cfg(feature = "full")]
+    enabled_otel_metrics: bool,
+
     /// List of enabled modules (e.g. "grpc-web")
     modules: Option<Vec<String>>,
 
     /// Whether to enable server-timing header
     enable_server_timing: bool,
+
+    /// Store of the slowest and the most recent failed requests
+    request_capture: Option<Arc<RequestCapture>>,
+
+    /// Metric groups of server-timing header
+    server_timing_metrics: ServerTimingMetrics,
+
+    /// Client ip rules allowed to receive server-timing header
+    server_timing_trusted_ips: Option<pingap_util::IpRules>,
 }
 
 pub struct ServerServices {
@@ -242,6 +287,29 @@ impl Server {
         if !access_log.is_empty() {
             p = Some(Parser::from(access_log.as_str()));
         }
+        let access_log_filter = AccessLogFilter::new(
+            conf.access_log_min_status,
+            conf.access_log_min_latency,
+            conf.access_log_exclude_path.as_deref(),
+            conf.access_log_sample_ratio,
+        )
+        .map_err(|e| Error::Common {
+            category: "access_log".to_string(),
+            message: e.to_string(),
+        })?;
+        let mut access_log_writer = None;
+        let mut access_log_compress_service = None;
+        let access_log_output =
+            conf.access_log_output.clone().unwrap_or_default();
+        if p.is_some() && !access_log_output.is_empty() {
+            let (writer, task) = new_access_log_writer(&access_log_output)
+                .map_err(|e| Error::Common {
+                    category: "access_log".to_string(),
+                    message: e.to_string(),
+                })?;
+            access_log_writer = Some(writer);
+            access_log_compress_service = task;
+        }
         let tcp_socket_options =
             if conf.tcp_fastopen.is_some() || conf.tcp_keepalive.is_some() {
                 let mut opts = TcpSocketOptions::default();
@@ -270,6 +338,9 @@ impl Server {
             processing: AtomicI32::new(0),
             addr: conf.addr.clone(),
             log_parser: p,
+            access_log_filter,
+            access_log_writer,
+            access_log_compress_service,
             error_template: conf.error_template.clone(),
             tls_cipher_list: conf.tls_cipher_list.clone(),
             tls_ciphersuites: conf.tls_ciphersuites.clone(),
@@ -284,10 +355,31 @@ impl Server {
             #[cfg(feature = "full")]
             enabled_otel: conf.otlp_exporter.is_some(),
             #[cfg(feature = "full")]
+            enabled_otel_metrics: conf.otlp_metrics.is_some(),
+            #[cfg(feature = "full")]
             prometheus_metrics,
             #[cfg(feature = "full")]
             prometheus,
             enable_server_timing: conf.enable_server_timing,
+            request_capture: match conf.request_capture_size {
+                Some(size) if size > 0 => Some(new_request_capture(
+                    &conf.name,
+                    size,
+                    &conf.request_capture_redacted,
+                )),
+                // the capture of previous config is removed by hot reload
+                _ => {
+                    remove_request_capture(&conf.name);
+                    None
+                },
+            },
+            server_timing_metrics: ServerTimingMetrics::new(
+                &conf.server_timing_metrics.clone().unwrap_or_default(),
+            ),
+            server_timing_trusted_ips: conf
+                .server_timing_trusted_ips
+                .as_ref()
+                .map(pingap_util::IpRules::new),
             modules: conf.modules.clone(),
         };
         Ok(s)
@@ -297,6 +389,13 @@ impl Server {
     pub fn enable_lets_encrypt(&mut self) {
         self.lets_encrypt_enabled = true;
     }
+    /// Takes the compression service of access log files if it's configured.
+    /// Returns a tuple of (service name, service future).
+    pub fn take_access_log_compress_service(
+        &mut self,
+    ) -> Option<(String, SimpleServiceTaskFuture)> {
+        self.access_log_compress_service.take()
+    }
     /// Get the prometheus push service configuration if enabled.
     /// Returns a tuple of (metrics endpoint, service future) if push mode is configured.
     pub fn get_prometheus_push_service(
@@ -332,6 +431,30 @@ impl Server {
         }
     }
 
+    /// Get the OpenTelemetry metrics of server,
+    /// it's None if otlp metrics is not configured or the exporter is not started.
+    #[cfg(feature = "full")]
+    #[inline]
+    fn get_otel_metrics(&self) -> Option<Arc<pingap_otel::OtelMetrics>> {
+        if !self.enabled_otel_metrics {
+            return None;
+        }
+        pingap_otel::get_metrics(&self.name)
+    }
+
+    /// Whether the client is allowed to receive server-timing header,
+    /// all clients are allowed if trusted ips are not configured.
+    /// The remote address is used because x-forwarded-for can be forged by client.
+    fn is_server_timing_trusted(&self, ctx: &Ctx) -> bool {
+        let Some(ip_rules) = &self.server_timing_trusted_ips else {
+            return true;
+        };
+        let Some(remote_addr) = &ctx.remote_addr else {
+            return false;
+        };
+        ip_rules.is_match(remote_addr).unwrap_or_default()
+    }
+
     /// Starts the server and sets up TCP/TLS listening endpoints.
     /// - Configures listeners for each address
     /// - Sets up TLS if enabled
@@ -585,8 +708,19 @@ impl Server {
         for name in plugins.iter() {
             if let Some(plugin) = get_plugin(name) {
                 let now = Instant::now();
-                let (executed, result) =
-                    plugin.handle_request(step, session, ctx).await?;
+                #[cfg(feature = "full")]
+                let span = new_plugin_span(ctx, name, step);
+                let result = plugin.handle_request(step, session, ctx).await;
+                #[cfg(feature = "full")]
+                end_plugin_span(
+                    span,
+                    match &result {
+                        Ok((_, Some(_))) => Ok("responded"),
+                        Ok((executed, _)) => Ok(get_plugin_outcome(*executed)),
+                        Err(e) => Err(e.to_string()),
+                    },
+                );
+                let (executed, result) = result?;
                 if executed {
                     let elapsed = now.elapsed().as_millis() as u32;
                     debug!(
@@ -603,6 +737,7 @@ impl Server {
                     // ignore http response status >= 900
                     if resp.status.as_u16() < 900 {
                         ctx.status = Some(resp.status);
+                        ctx.responded_plugin = Some(name.to_string());
                         resp.send(session).await?;
                     }
                     return Ok(true);
@@ -631,9 +766,20 @@ impl Server {
         for name in plugins.iter() {
             if let Some(plugin) = get_plugin(name) {
                 let now = Instant::now();
-                let executed = plugin
+                #[cfg(feature = "full")]
+                let span = new_plugin_span(ctx, name, step);
+                let result = plugin
                     .handle_response(step, session, ctx, upstream_response)
-                    .await?;
+                    .await;
+                #[cfg(feature = "full")]
+                end_plugin_span(
+                    span,
+                    match &result {
+                        Ok(executed) => Ok(get_plugin_outcome(*executed)),
+                        Err(e) => Err(e.to_string()),
+                    },
+                );
+                let executed = result?;
                 if executed {
                     let elapsed = now.elapsed().as_millis() as u32;
                     debug!(
@@ -669,12 +815,23 @@ impl Server {
         for name in plugins.iter() {
             if let Some(plugin) = get_plugin(name) {
                 let now = Instant::now();
-                let executed = plugin.handle_upstream_response(
+                #[cfg(feature = "full")]
+                let span = new_plugin_span(ctx, name, step);
+                let result = plugin.handle_upstream_response(
                     step,
                     session,
                     ctx,
                     upstream_response,
-                )?;
+                );
+                #[cfg(feature = "full")]
+                end_plugin_span(
+                    span,
+                    match &result {
+                        Ok(executed) => Ok(get_plugin_outcome(*executed)),
+                        Err(e) => Err(e.to_string()),
+                    },
+                );
+                let executed = result?;
                 if executed {
                     let elapsed = now.elapsed().as_millis() as u32;
                     debug!(
@@ -693,6 +850,57 @@ impl Server {
     }
 }
 
+/// Returns the outcome of plugin execution
+#[cfg(feature = "full")]
+#[inline]
+fn get_plugin_outcome(executed: bool) -> &'static str {
+    if executed {
+        "executed"
+    } else {
+        "skipped"
+    }
+}
+
+/// Creates a span of plugin execution if open telemetry is enabled
+#[cfg(feature = "full")]
+#[inline]
+fn new_plugin_span(
+    ctx: &Ctx,
+    name: &str,
+    step: PluginStep,
+) -> Option<BoxedSpan> {
+    let tracer = ctx.otel_tracer.as_ref()?;
+    let mut span = tracer.new_internal_span(&format!("plugin.{name}"));
+    span.set_attributes([
+        KeyValue::new("plugin.name", name.to_string()),
+        KeyValue::new("plugin.category", get_plugin_category(name)),
+        KeyValue::new("plugin.step", step.to_string()),
+    ]);
+    Some(span)
+}
+
+/// Ends the span of plugin execution with the outcome or error
+#[cfg(feature = "full")]
+#[inline]
+fn end_plugin_span(
+    span: Option<BoxedSpan>,
+    outcome: std::result::Result<&'static str, String>,
+) {
+    let Some(mut span) = span else {
+        return;
+    };
+    match outcome {
+        Ok(outcome) => {
+            span.set_attribute(KeyValue::new("plugin.outcome", outcome));
+        },
+        Err(message) => {
+            span.set_attribute(KeyValue::new("plugin.outcome", "error"));
+            span.set_status(Status::error(message));
+        },
+    }
+    span.end();
+}
+
 #[inline]
 fn get_upstream_with_variables(
     upstream: &str,
@@ -845,6 +1053,10 @@ impl ProxyHttp for Server {
         if let Some(prom) = &self.prometheus {
             prom.before(&ctx.location);
         }
+        #[cfg(feature = "full")]
+        if let Some(metrics) = self.get_otel_metrics() {
+            metrics.before(&ctx.location);
+        }
 
         if let Some(location) = &current_location {
             location.validate_content_length(header).map_err(|e| {
@@ -1035,6 +1247,8 @@ impl ProxyHttp for Server {
                         "upstream.connected",
                         ctx.upstream_connected.unwrap_or_default() as i64,
                     ));
+                    ctx.upstream_connect_span =
+                        Some(tracer.new_child_span("upstream.connect", &span));
                     ctx.upstream_span = Some(span);
                 }
                 let peer =
@@ -1103,6 +1317,30 @@ impl ProxyHttp for Server {
         ctx.upstream_processing_time =
             pingap_util::get_latency(&ctx.upstream_processing_time);
 
+        #[cfg(feature = "full")]
+        if let Some(mut span) = ctx.upstream_connect_span.take() {
+            span.set_attributes([
+                KeyValue::new("upstream.reused", reused),
+                KeyValue::new(
+                    "upstream.tcp_connect_time",
+                    ctx.upstream_tcp_connect_time.unwrap_or_default() as i64,
+                ),
+                KeyValue::new(
+                    "upstream.tls_handshake_time",
+                    ctx.upstream_tls_handshake_time.unwrap_or_default() as i64,
+                ),
+            ]);
+            span.end();
+        }
+        #[cfg(feature = "full")]
+        if let (Some(tracer), Some(upstream_span)) =
+            (&ctx.otel_tracer, &ctx.upstream_span)
+        {
+            // wait for the response header of upstream
+            ctx.upstream_response_span =
+                Some(tracer.new_child_span("upstream.response", upstream_span));
+        }
+
         Ok(())
     }
     /// Filters upstream request before sending.
@@ -1163,6 +1401,12 @@ impl ProxyHttp for Server {
             session.req_header().method.as_ref(),
             &session.req_header().uri,
         );
+        #[cfg(feature = "full")]
+        if let Some(tracer) = &ctx.otel_tracer {
+            // the cache lookup is started after the key is generated
+            ctx.cache_lookup_span =
+                Some(tracer.new_internal_span("cache.lookup"));
+        }
         debug!(
             category = LOG_CATEGORY,
             key = format!("{key:?}"),
@@ -1171,6 +1415,42 @@ impl ProxyHttp for Server {
         Ok(key)
     }
 
+    /// Called when the cache lookup is missed.
+    fn cache_miss(&self, session: &mut Session, ctx: &mut Self::CTX) {
+        #[cfg(feature = "full")]
+        if let Some(mut span) = ctx.cache_lookup_span.take() {
+            span.set_attribute(KeyValue::new("cache.hit", false));
+            span.end();
+        }
+        #[cfg(not(feature = "full"))]
+        let _ = ctx;
+        session.cache.cache_miss();
+    }
+
+    /// Called after the cache lookup is hit (fresh or stale).
+    async fn cache_hit_filter(
+        &self,
+        _session: &Session,
+        _meta: &CacheMeta,
+        is_fresh: bool,
+        ctx: &mut Self::CTX,
+    ) -> pingora::Result<Option<ForcedInvalidationKind>>
+    where
+        Self::CTX: Send + Sync,
+    {
+        #[cfg(feature = "full")]
+        if let Some(mut span) = ctx.cache_lookup_span.take() {
+            span.set_attributes([
+                KeyValue::new("cache.hit", true),
+                KeyValue::new("cache.fresh", is_fresh),
+            ]);
+            span.end();
+        }
+        #[cfg(not(feature = "full"))]
+        let _ = (is_fresh, ctx);
+        Ok(None)
+    }
+
     /// Determines if and how responses should be cached.
     /// Checks:
     /// - Cache-Control headers
@@ -1224,12 +1504,17 @@ impl ProxyHttp for Server {
             }
         }
 
-        Ok(resp_cacheable(
-            cc.as_ref(),
-            resp.clone(),
-            false,
-            &META_DEFAULTS,
-        ))
+        let cacheable =
+            resp_cacheable(cc.as_ref(), resp.clone(), false, &META_DEFAULTS);
+        #[cfg(feature = "full")]
+        if let (Some(tracer), RespCacheable::Cacheable(_)) =
+            (&ctx.otel_tracer, &cacheable)
+        {
+            // the response body is written to cache until end of stream
+            ctx.cache_write_span =
+                Some(tracer.new_internal_span("cache.write"));
+        }
+        Ok(cacheable)
     }
 
     async fn response_filter(
@@ -1246,6 +1531,7 @@ impl ProxyHttp for Server {
         if session.cache.enabled() {
             // ignore insert header error
             let cache_status = session.cache.phase().as_str();
+            ctx.cache_status = Some(cache_status);
             let _ =
                 upstream_response.insert_header("X-Cache-Status", cache_status);
             #[cfg(feature = "full")]
@@ -1298,9 +1584,11 @@ impl ProxyHttp for Server {
             .await?;
         }
 
-        if self.enable_server_timing {
-            let _ = upstream_response
-                .insert_header("Server-Timing", ctx.generate_server_timing());
+        if self.enable_server_timing && self.is_server_timing_trusted(ctx) {
+            let _ = upstream_response.insert_header(
+                "Server-Timing",
+                ctx.generate_server_timing(&self.server_timing_metrics),
+            );
         }
 
         Ok(())
@@ -1314,6 +1602,15 @@ impl ProxyHttp for Server {
     ) -> pingora::Result<()> {
         debug!(category = LOG_CATEGORY, "--> upstream response filter");
         defer!(debug!(category = LOG_CATEGORY, "<-- upstream response filter"););
+        ctx.upstream_status = Some(upstream_response.status);
+        #[cfg(feature = "full")]
+        if let Some(mut span) = ctx.upstream_response_span.take() {
+            span.set_attribute(KeyValue::new(
+                "http.status_code",
+                upstream_response.status.as_u16() as i64,
+            ));
+            span.end();
+        }
         if let Some(location) = get_location(&ctx.location) {
             self.handle_upstream_response_plugin(
                 PluginStep::UpstreamResponse,
@@ -1420,6 +1717,10 @@ impl ProxyHttp for Server {
                 span.end();
                 ctx.upstream_span = None;
             }
+            #[cfg(feature = "full")]
+            if let Some(mut span) = ctx.cache_write_span.take() {
+                span.end();
+            }
         }
         Ok(())
     }
@@ -1579,7 +1880,7 @@ impl ProxyHttp for Server {
     async fn logging(
         &self,
         session: &mut Session,
-        _e: Option<&pingora::Error>,
+        e: Option<&pingora::Error>,
         ctx: &mut Self::CTX,
     ) where
         Self::CTX: Send + Sync,
@@ -1603,8 +1904,26 @@ impl ProxyHttp for Server {
         }
         #[cfg(feature = "full")]
         // enable open telemetry and proxy upstream fail
-        if let Some(ref mut span) = ctx.upstream_span.as_mut() {
-            span.end();
+        {
+            for mut span in [
+                ctx.upstream_connect_span.take(),
+                ctx.upstream_response_span.take(),
+                ctx.cache_lookup_span.take(),
+                ctx.cache_write_span.take(),
+            ]
+            .into_iter()
+            .flatten()
+            {
+                // the span is not ended by its phase, e.g. the cache lookup
+                // is skipped, it's an error only if the request fails
+                if let Some(e) = e {
+                    span.set_status(Status::error(e.to_string()));
+                }
+                span.end();
+            }
+            if let Some(ref mut span) = ctx.upstream_span.as_mut() {
+                span.end();
+            }
         }
 
         if let Some(c) =
@@ -1624,6 +1943,10 @@ impl ProxyHttp for Server {
         if let Some(prom) = &self.prometheus {
             prom.after(session, ctx);
         }
+        #[cfg(feature = "full")]
+        if let Some(metrics) = self.get_otel_metrics() {
+            metrics.after(session, ctx);
+        }
 
         #[cfg(feature = "full")]
         // open telemetry
@@ -1655,8 +1978,34 @@ impl ProxyHttp for Server {
             tracer.http_request_span.end()
         }
 
+        if let Some(capture) = &self.request_capture {
+            capture.capture(session, ctx, e);
+        }
+
+        // only create the access record when someone is watching
+        if is_access_record_watched() {
+            publish_access_record(AccessRecord::new(&self.name, session, ctx));
+        }
+
         if let Some(p) = &self.log_parser {
-            info!("{}", p.format(session, ctx));
+            if let Some(filter) = &self.access_log_filter {
+                let status = ctx.status.map(|s| s.as_u16()).unwrap_or_default();
+                let latency =
+                    pingap_util::now_ms().saturating_sub(ctx.created_at);
+                if !filter.should_log(
+                    session.req_header().uri.path(),
+                    status,
+                    latency,
+                ) {
+                    return;
+                }
+            }
+            let line = p.format(session, ctx);
+            if let Some(writer) = &self.access_log_writer {
+                writer.write(&line);
+            } else {
+                info!("{line}");
+            }
         }
     }
 }
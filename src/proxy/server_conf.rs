@@ -13,6 +13,7 @@
 // limitations under the License.
 
 // use pingap_config::PingapConf;
+use pingap_config::PluginCategory;
 use pingora::protocols::l4::ext::TcpKeepalive;
 use std::fmt;
 #[cfg(target_os = "linux")]
@@ -37,6 +38,20 @@ pub struct ServerConf {
     // None means access logging is disabled
     pub access_log: Option<String>,
 
+    // Dedicated output of access log, supports "stdout", "stderr", "syslog://..."
+    // or file path. None means access log is written to the application log
+    pub access_log_output: Option<String>,
+
+    // Conditions of access log, the error requests are always logged.
+    // Only log the successful requests whose status >= min status
+    // or latency >= min latency, the path matches the exclude regex is skipped
+    pub access_log_min_status: Option<u16>,
+    pub access_log_min_latency: Option<Duration>,
+    pub access_log_exclude_path: Option<String>,
+
+    // Ratio of successful requests to log, None means all
+    pub access_log_sample_ratio: Option<f64>,
+
     // List of location route identifiers that this server will handle
     pub locations: Vec<String>,
 
@@ -88,12 +103,31 @@ pub struct ServerConf {
     // Used for distributed tracing support
     pub otlp_exporter: Option<String>,
 
+    // OpenTelemetry metrics exporter configuration string
+    // Metrics are exported to the collector periodically
+    pub otlp_metrics: Option<String>,
+
     // List of enabled module names for this server instance
     // Allows for dynamic functionality extension
     pub modules: Option<Vec<String>>,
 
     // Whether to enable server-timing header
     pub enable_server_timing: bool,
+
+    // Number of the slowest and the most recent failed requests to capture
+    // None means request capture is disabled
+    pub request_capture_size: Option<usize>,
+
+    // Names of request headers and query params redacted in captured requests,
+    // including the header or query of key auth plugins
+    pub request_capture_redacted: Vec<String>,
+
+    // Metric groups of server-timing header, empty means all metrics
+    pub server_timing_metrics: Option<Vec<String>>,
+
+    // Client ip list allowed to receive server-timing header
+    // None means the header is sent to all clients
+    pub server_timing_trusted_ips: Option<Vec<String>>,
 }
 
 impl fmt::Display for ServerConf {
@@ -103,6 +137,21 @@ impl fmt::Display for ServerConf {
         write!(f, "admin: {}, ", self.admin)?;
         write!(f, "addr: {}, ", self.addr)?;
         write!(f, "access_log: {:?}, ", self.access_log)?;
+        if let Some(ref output) = self.access_log_output {
+            write!(f, "access_log_output: {output}, ")?;
+        }
+        if let Some(min_status) = self.access_log_min_status {
+            write!(f, "access_log_min_status: {min_status}, ")?;
+        }
+        if let Some(min_latency) = self.access_log_min_latency {
+            write!(f, "access_log_min_latency: {min_latency:?}, ")?;
+        }
+        if let Some(ref exclude_path) = self.access_log_exclude_path {
+            write!(f, "access_log_exclude_path: {exclude_path}, ")?;
+        }
+        if let Some(ratio) = self.access_log_sample_ratio {
+            write!(f, "access_log_sample_ratio: {ratio}, ")?;
+        }
         write!(f, "locations: {:?}, ", self.locations)?;
         if let Some(ref ciphers) = self.tls_cipher_list {
             write!(f, "tls_cipher_list: {}, ", ciphers)?;
@@ -127,10 +176,29 @@ impl fmt::Display for ServerConf {
         if let Some(ref exporter) = self.otlp_exporter {
             write!(f, "otlp_exporter: {}, ", exporter)?;
         }
+        if let Some(ref metrics) = self.otlp_metrics {
+            write!(f, "otlp_metrics: {}, ", metrics)?;
+        }
         if let Some(ref modules) = self.modules {
             write!(f, "modules: {:?}, ", modules)?;
         }
         write!(f, "enable_server_timing: {}, ", self.enable_server_timing)?;
+        if let Some(size) = self.request_capture_size {
+            write!(f, "request_capture_size: {size}, ")?;
+        }
+        if !self.request_capture_redacted.is_empty() {
+            write!(
+                f,
+                "request_capture_redacted: {:?}, ",
+                self.request_capture_redacted
+            )?;
+        }
+        if let Some(ref metrics) = self.server_timing_metrics {
+            write!(f, "server_timing_metrics: {:?}, ", metrics)?;
+        }
+        if let Some(ref ips) = self.server_timing_trusted_ips {
+            write!(f, "server_timing_trusted_ips: {:?}, ", ips)?;
+        }
         write!(f, "error_template: {} }}", self.error_template)?;
         Ok(())
     }
@@ -148,8 +216,28 @@ pub fn parse_from_conf(conf: pingap_config::PingapConf) -> Vec<ServerConf> {
     }
     // Sort locations by weight in descending order for priority routing
     locations.sort_by_key(|b| std::cmp::Reverse(b.1.get_weight()));
+    // The api key of key auth plugin should not be captured
+    let mut key_auth_names = vec![];
+    for plugin in conf.plugins.values() {
+        if plugin.get("category").and_then(|v| v.as_str())
+            != Some(PluginCategory::KeyAuth.to_string().as_str())
+        {
+            continue;
+        }
+        for key in ["header", "query"] {
+            if let Some(value) = plugin.get(key).and_then(|v| v.as_str()) {
+                if !value.is_empty() {
+                    key_auth_names.push(value.to_string());
+                }
+            }
+        }
+    }
     let mut servers = vec![];
     for (name, item) in conf.servers {
+        let mut request_capture_redacted =
+            item.request_capture_redacted.clone().unwrap_or_default();
+        request_capture_redacted.extend(key_auth_names.iter().cloned());
+
         // Set up error template, using default if none specified
         let mut error_template =
             conf.basic.error_template.clone().unwrap_or_default();
@@ -183,6 +271,11 @@ pub fn parse_from_conf(conf: pingap_config::PingapConf) -> Vec<ServerConf> {
             tls_max_version: item.tls_max_version.clone(),
             addr: item.addr,
             access_log: item.access_log,
+            access_log_output: item.access_log_output,
+            access_log_min_status: item.access_log_min_status,
+            access_log_min_latency: item.access_log_min_latency,
+            access_log_exclude_path: item.access_log_exclude_path,
+            access_log_sample_ratio: item.access_log_sample_ratio,
             locations: item.locations.unwrap_or_default(),
             threads: item.threads,
             global_certificates: item.global_certificates.unwrap_or_default(),
@@ -191,8 +284,13 @@ pub fn parse_from_conf(conf: pingap_config::PingapConf) -> Vec<ServerConf> {
             tcp_fastopen: item.tcp_fastopen,
             prometheus_metrics: item.prometheus_metrics,
             otlp_exporter: item.otlp_exporter.clone(),
+            otlp_metrics: item.otlp_metrics.clone(),
             modules: item.modules.clone(),
             enable_server_timing: item.enable_server_timing.unwrap_or_default(),
+            request_capture_size: item.request_capture_size,
+            request_capture_redacted,
+            server_timing_metrics: item.server_timing_metrics.clone(),
+            server_timing_trusted_ips: item.server_timing_trusted_ips.clone(),
             error_template,
         });
     }
@@ -202,7 +300,7 @@ pub fn parse_from_conf(conf: pingap_config::PingapConf) -> Vec<ServerConf> {
 
 #[cfg(test)]
 mod tests {
-    use super::ServerConf;
+    use super::{parse_from_conf, ServerConf};
     use pingora::protocols::l4::ext::TcpKeepalive;
     use pretty_assertions::assert_eq;
     use std::time::Duration;
@@ -240,4 +338,28 @@ mod tests {
             conf.to_string()
         );
     }
+
+    #[test]
+    fn test_parse_request_capture_redacted() {
+        let conf = pingap_config::PingapConf::new(
+            r#"
+[servers.web]
+addr = "127.0.0.1:3000"
+request_capture_redacted = ["X-Device"]
+
+[plugins.api-key]
+category = "key_auth"
+header = "X-Api-Key"
+keys = ["123"]
+"#
+            .as_bytes(),
+            false,
+        )
+        .unwrap();
+        let servers = parse_from_conf(conf);
+        assert_eq!(
+            vec!["X-Device".to_string(), "X-Api-Key".to_string()],
+            servers[0].request_capture_redacted
+        );
+    }
 }
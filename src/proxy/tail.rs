@@ -0,0 +1,216 @@
+// Copyright 2024-2025 Tree xie.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use once_cell::sync::Lazy;
+use pingap_core::Ctx;
+use pingap_util::IpRules;
+use pingora::proxy::Session;
+use regex::Regex;
+use serde::Serialize;
+use std::sync::Arc;
+use tokio::sync::broadcast;
+
+/// Capacity of the access record ring buffer,
+/// the oldest records are dropped if the watcher is too slow.
+const ACCESS_RECORD_CAPACITY: usize = 1024;
+
+/// Access record of a finished request for live inspection
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct AccessRecord {
+    /// Name of the server
+    pub server: String,
+    /// Finished time of the request(unix timestamp in milliseconds)
+    pub time: u64,
+    /// Request id if the request id plugin is used
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
+    /// Client ip of the request
+    pub client_ip: String,
+    /// Http method
+    pub method: String,
+    /// Host of the request
+    pub host: String,
+    /// Path of the request
+    pub path: String,
+    /// Query of the request
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub query: String,
+    /// Location matched by the request
+    pub location: String,
+    /// Response status code
+    pub status: u16,
+    /// Latency of the request in milliseconds
+    pub latency: u64,
+    /// Upstream of the location
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub upstream: String,
+    /// Backend address of the upstream
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub upstream_address: String,
+    /// Size of the request body in bytes
+    pub payload_size: usize,
+    /// Size of the response body sent in bytes
+    pub sent: usize,
+}
+
+impl AccessRecord {
+    /// Creates a new access record from the session and context of request
+    pub fn new(server: &str, session: &Session, ctx: &Ctx) -> Self {
+        let req_header = session.req_header();
+        let client_ip = ctx
+            .client_ip
+            .clone()
+            .unwrap_or_else(|| pingap_core::get_client_ip(session));
+        let now = pingap_util::now_ms();
+        Self {
+            server: server.to_string(),
+            time: now,
+            request_id: ctx.request_id.clone(),
+            client_ip,
+            method: req_header.method.to_string(),
+            host: pingap_core::get_host(req_header)
+                .unwrap_or_default()
+                .to_string(),
+            path: req_header.uri.path().to_string(),
+            query: req_header.uri.query().unwrap_or_default().to_string(),
+            location: ctx.location.clone(),
+            status: ctx.status.map(|s| s.as_u16()).unwrap_or_default(),
+            latency: now.saturating_sub(ctx.created_at),
+            upstream: ctx.upstream.clone(),
+            upstream_address: ctx.upstream_address.clone(),
+            payload_size: ctx.payload_size,
+            sent: session.body_bytes_sent(),
+        }
+    }
+}
+
+static ACCESS_RECORD_SENDER: Lazy<broadcast::Sender<Arc<AccessRecord>>> =
+    Lazy::new(|| broadcast::channel(ACCESS_RECORD_CAPACITY).0);
+
+/// Whether someone is watching the access records,
+/// the record should only be created when it returns true.
+#[inline]
+pub fn is_access_record_watched() -> bool {
+    ACCESS_RECORD_SENDER.receiver_count() > 0
+}
+
+/// Publishes the access record to all watchers
+pub fn publish_access_record(record: AccessRecord) {
+    // send fails only if there is no receiver
+    let _ = ACCESS_RECORD_SENDER.send(Arc::new(record));
+}
+
+/// Subscribes the access records which are published after now
+pub fn subscribe_access_records() -> broadcast::Receiver<Arc<AccessRecord>> {
+    ACCESS_RECORD_SENDER.subscribe()
+}
+
+/// Filter of access records, all conditions should be matched
+#[derive(Default)]
+pub struct AccessRecordFilter {
+    /// Location of the request
+    pub location: Option<String>,
+    /// Status code(e.g. 404) or class of status code(e.g. 5xx)
+    pub status: Option<String>,
+    /// Regex of the request path
+    pub path: Option<Regex>,
+    /// Ip list or cidr of the client
+    pub client_ip: Option<IpRules>,
+}
+
+impl AccessRecordFilter {
+    /// Returns true if the record matches all conditions of filter
+    pub fn matches(&self, record: &AccessRecord) -> bool {
+        if let Some(location) = &self.location {
+            if location != &record.location {
+                return false;
+            }
+        }
+        if let Some(status) = &self.status {
+            let code = record.status.to_string();
+            let matched = if let Some(prefix) = status.strip_suffix("xx") {
+                code.starts_with(prefix)
+            } else {
+                &code == status
+            };
+            if !matched {
+                return false;
+            }
+        }
+        if let Some(path) = &self.path {
+            if !path.is_match(&record.path) {
+                return false;
+            }
+        }
+        if let Some(client_ip) = &self.client_ip {
+            if !client_ip.is_match(&record.client_ip).unwrap_or_default() {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_access_record_filter() {
+        let record = AccessRecord {
+            client_ip: "192.168.1.10".to_string(),
+            path: "/api/users".to_string(),
+            location: "api".to_string(),
+            status: 502,
+            ..Default::default()
+        };
+        assert_eq!(true, AccessRecordFilter::default().matches(&record));
+
+        let filter = AccessRecordFilter {
+            location: Some("api".to_string()),
+            status: Some("5xx".to_string()),
+            path: Some(Regex::new("^/api").unwrap()),
+            client_ip: Some(IpRules::new(&vec!["192.168.1.0/24".to_string()])),
+        };
+        assert_eq!(true, filter.matches(&record));
+
+        let filter = AccessRecordFilter {
+            status: Some("404".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(false, filter.matches(&record));
+
+        let filter = AccessRecordFilter {
+            client_ip: Some(IpRules::new(&vec!["10.0.0.1".to_string()])),
+            ..Default::default()
+        };
+        assert_eq!(false, filter.matches(&record));
+    }
+
+    #[test]
+    fn test_publish_access_record() {
+        assert_eq!(false, is_access_record_watched());
+        let mut rx = subscribe_access_records();
+        assert_eq!(true, is_access_record_watched());
+        publish_access_record(AccessRecord {
+            path: "/ping".to_string(),
+            ..Default::default()
+        });
+        let record = rx.try_recv().unwrap();
+        assert_eq!("/ping", record.path);
+        drop(rx);
+        assert_eq!(false, is_access_record_watched());
+    }
+}
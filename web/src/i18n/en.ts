@@ -122,6 +122,9 @@ export default {
     cacheMaxSize: "Cache Max Size",
     cacheMaxSizePlaceholder:
       "Input max size of cache(e.g. 100mb), it's only for memory cache",
+    cacheDiskMaxSize: "Cache Disk Max Size",
+    cacheDiskMaxSizePlaceholder:
+      "Input max disk size of file cache(e.g. 10gb), empty means unlimited",
     upgradeSock: "Upgrade Sock For Daemon",
     upgradeSockPlaceholder: "Input upgrade unix sock for daemon",
     user: "User For Daemon",
@@ -156,8 +159,27 @@ export default {
     globalCertificates: "Using Global Certificates",
     accessLog: "Access Log Format",
     accessLogPlaceholder: "Input the format layout for access",
+    accessLogOutput: "Access Log Output",
+    accessLogOutputPlaceholder: "Input the output of access log, e.g. stdout, syslog://, http(s):// or file path",
+    accessLogMinStatus: "Access Log Min Status",
+    accessLogMinStatusPlaceholder: "Only log the successful requests whose status >= the value",
+    accessLogMinLatency: "Access Log Min Latency",
+    accessLogMinLatencyPlaceholder: "Only log the successful requests whose latency >= the value, e.g. 1s",
+    accessLogExcludePath: "Access Log Exclude Path",
+    accessLogExcludePathPlaceholder: "Input the regex of path which is not logged",
+    accessLogSampleRatio: "Access Log Sample Ratio",
+    accessLogSampleRatioPlaceholder: "Input the ratio of successful requests to log, between 0 and 1",
     enabledH2: "Enable Http2(h2c)",
     enabledServerTiming: "Enable Server Timing",
+    requestCaptureSize: "Request Capture Size",
+    requestCaptureSizePlaceholder: "Input the count of slowest and failed requests to capture",
+    requestCaptureRedacted: "Request Capture Redacted",
+    requestCaptureRedactedPlaceholder:
+      "Input the header or query names to redact in captured requests",
+    serverTimingMetrics: "Server Timing Metrics",
+    serverTimingMetricsPlaceholder: "Select the metrics of server timing, empty means all",
+    serverTimingTrustedIps: "Server Timing Trusted Ips",
+    serverTimingTrustedIpsPlaceholder: "Input the ip or cidr allowed to receive server timing",
     modules: "Http Modules",
     modulesPlaceholder: "Select http modules for server",
     tlsCipherList: "Tls Cipher List",
@@ -180,6 +202,8 @@ export default {
       "Input the pull path of push gateway for prometheus",
     otlpExporter: "Otlp Exporter",
     otlpExporterPlaceholder: "Input the exporter for opentelemetry",
+    otlpMetrics: "Otlp Metrics",
+    otlpMetricsPlaceholder: "Input the metrics exporter for opentelemetry",
     remark: "Remark",
   },
   location: {
@@ -117,6 +117,8 @@ export default {
     cacheDirectoryPlaceholder: "输入文件缓存目录(如/opt/cache)",
     cacheMaxSize: "缓存空间最大限制",
     cacheMaxSizePlaceholder: "输入最大缓存空间限制(如100mb), 仅对内存缓存有效",
+    cacheDiskMaxSize: "文件缓存磁盘最大限制",
+    cacheDiskMaxSizePlaceholder: "输入文件缓存的磁盘空间限制(如10gb), 为空则不限制",
     upgradeSock: "更新配置使用的sock",
     upgradeSockPlaceholder: "输入后台服务更新配置使用的sock",
     user: "启用后台服务的用户",
@@ -150,8 +152,26 @@ export default {
     globalCertificates: "使用全局证书",
     accessLog: "访问日志格式化",
     accessLogPlaceholder: "输入日志格式化模板",
+    accessLogOutput: "访问日志输出",
+    accessLogOutputPlaceholder: "输入访问日志的输出，如stdout、syslog://、http(s)://或文件路径",
+    accessLogMinStatus: "访问日志最小状态码",
+    accessLogMinStatusPlaceholder: "仅记录状态码大于等于该值的成功请求",
+    accessLogMinLatency: "访问日志最小耗时",
+    accessLogMinLatencyPlaceholder: "仅记录耗时大于等于该值的成功请求，如1s",
+    accessLogExcludePath: "访问日志排除路径",
+    accessLogExcludePathPlaceholder: "输入不记录日志的路径正则",
+    accessLogSampleRatio: "访问日志采样率",
+    accessLogSampleRatioPlaceholder: "输入成功请求的日志采样率，范围0到1",
     enabledH2: "启用http2(h2c)",
     enabledServerTiming: "启用Server Timing",
+    requestCaptureSize: "请求捕获数量",
+    requestCaptureSizePlaceholder: "输入保留的最慢及失败请求数量",
+    requestCaptureRedacted: "请求捕获脱敏字段",
+    requestCaptureRedactedPlaceholder: "输入捕获请求中需脱敏的请求头或查询参数名称",
+    serverTimingMetrics: "Server Timing指标",
+    serverTimingMetricsPlaceholder: "选择Server Timing的指标，为空则全部",
+    serverTimingTrustedIps: "Server Timing可信IP",
+    serverTimingTrustedIpsPlaceholder: "输入允许获取Server Timing的IP或网段",
     modules: "Http模块",
     modulesPlaceholder: "选择要使用的http模块",
     tlsCipherList: "tls密码套件列表",
@@ -173,6 +193,8 @@ export default {
       "输入pull模式的路径或者完整的push模式的网关地址",
     otlpExporter: "Otlp Exporter",
     otlpExporterPlaceholder: "输入opentelemetry的导出链接",
+    otlpMetrics: "Otlp Metrics",
+    otlpMetricsPlaceholder: "输入opentelemetry指标的导出链接",
     remark: "备注",
   },
   location: {
@@ -143,6 +143,14 @@ export default function Basic() {
       span: 3,
       category: ExFormItemCategory.TEXT,
     },
+    {
+      name: "cache_disk_max_size",
+      label: basicI18n("cacheDiskMaxSize"),
+      placeholder: basicI18n("cacheDiskMaxSizePlaceholder"),
+      defaultValue: basic.cache_disk_max_size,
+      span: 3,
+      category: ExFormItemCategory.TEXT,
+    },
     {
       name: "upgrade_sock",
       label: basicI18n("upgradeSock"),
@@ -249,6 +257,7 @@ export default function Basic() {
     graceful_shutdown_timeout: newZodDuration().optional(),
     auto_restart_check_interval: newZodDuration().optional(),
     cache_max_size: newZodBytes().optional(),
+    cache_disk_max_size: newZodBytes().optional(),
   });
   return (
     <div className="grow overflow-auto p-4">
@@ -126,6 +126,46 @@ export default function Servers() {
       span: 6,
       category: ExFormItemCategory.TEXT,
     },
+    {
+      name: "access_log_output",
+      label: serverI18n("accessLogOutput"),
+      placeholder: serverI18n("accessLogOutputPlaceholder"),
+      defaultValue: serverConfig.access_log_output,
+      span: 6,
+      category: ExFormItemCategory.TEXT,
+    },
+    {
+      name: "access_log_min_status",
+      label: serverI18n("accessLogMinStatus"),
+      placeholder: serverI18n("accessLogMinStatusPlaceholder"),
+      defaultValue: serverConfig.access_log_min_status,
+      span: 3,
+      category: ExFormItemCategory.NUMBER,
+    },
+    {
+      name: "access_log_min_latency",
+      label: serverI18n("accessLogMinLatency"),
+      placeholder: serverI18n("accessLogMinLatencyPlaceholder"),
+      defaultValue: serverConfig.access_log_min_latency,
+      span: 3,
+      category: ExFormItemCategory.TEXT,
+    },
+    {
+      name: "access_log_exclude_path",
+      label: serverI18n("accessLogExcludePath"),
+      placeholder: serverI18n("accessLogExcludePathPlaceholder"),
+      defaultValue: serverConfig.access_log_exclude_path,
+      span: 3,
+      category: ExFormItemCategory.TEXT,
+    },
+    {
+      name: "access_log_sample_ratio",
+      label: serverI18n("accessLogSampleRatio"),
+      placeholder: serverI18n("accessLogSampleRatioPlaceholder"),
+      defaultValue: serverConfig.access_log_sample_ratio,
+      span: 3,
+      category: ExFormItemCategory.NUMBER,
+    },
     {
       name: "enabled_h2",
       label: serverI18n("enabledH2"),
@@ -144,6 +184,42 @@ export default function Servers() {
       category: ExFormItemCategory.RADIOS,
       options: newBooleanOptions(),
     },
+    {
+      name: "request_capture_size",
+      label: serverI18n("requestCaptureSize"),
+      placeholder: serverI18n("requestCaptureSizePlaceholder"),
+      defaultValue: serverConfig.request_capture_size,
+      span: 3,
+      category: ExFormItemCategory.NUMBER,
+    },
+    {
+      name: "request_capture_redacted",
+      label: serverI18n("requestCaptureRedacted"),
+      placeholder: serverI18n("requestCaptureRedactedPlaceholder"),
+      defaultValue: serverConfig.request_capture_redacted,
+      span: 3,
+      category: ExFormItemCategory.TEXTS,
+    },
+    {
+      name: "server_timing_metrics",
+      label: serverI18n("serverTimingMetrics"),
+      placeholder: serverI18n("serverTimingMetricsPlaceholder"),
+      defaultValue: serverConfig.server_timing_metrics,
+      span: 3,
+      category: ExFormItemCategory.MULTI_SELECT,
+      options: newStringOptions(
+        ["upstream", "cache", "plugin", "total"],
+        false,
+      ),
+    },
+    {
+      name: "server_timing_trusted_ips",
+      label: serverI18n("serverTimingTrustedIps"),
+      placeholder: serverI18n("serverTimingTrustedIpsPlaceholder"),
+      defaultValue: serverConfig.server_timing_trusted_ips,
+      span: 3,
+      category: ExFormItemCategory.TEXTS,
+    },
     {
       name: "modules",
       label: serverI18n("modules"),
@@ -248,6 +324,14 @@ export default function Servers() {
         span: 6,
         category: ExFormItemCategory.TEXT,
       },
+      {
+        name: "otlp_metrics",
+        label: serverI18n("otlpMetrics"),
+        placeholder: serverI18n("otlpMetricsPlaceholder"),
+        defaultValue: serverConfig.otlp_metrics,
+        span: 6,
+        category: ExFormItemCategory.TEXT,
+      },
     );
   }
   items.push({
@@ -275,6 +359,7 @@ export default function Servers() {
     addr: z.string().min(1),
     tcp_idle: newZodDuration().optional(),
     tcp_interval: newZodDuration().optional(),
+    access_log_min_latency: newZodDuration().optional(),
   });
 
   const onRemove = async () => {
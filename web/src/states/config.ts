@@ -84,6 +84,11 @@ export function getLocationWeight(location: Location) {
 export interface Server {
   addr: string;
   access_log?: string;
+  access_log_output?: string;
+  access_log_min_status?: number;
+  access_log_min_latency?: string;
+  access_log_exclude_path?: string;
+  access_log_sample_ratio?: number;
   locations?: string[];
   threads?: number;
   tls_cert?: string;
@@ -92,6 +97,10 @@ export interface Server {
   certificate_file?: string;
   enabled_h2?: boolean;
   enable_server_timing?: boolean;
+  request_capture_size?: number;
+  request_capture_redacted?: string[];
+  server_timing_metrics?: string[];
+  server_timing_trusted_ips?: string[];
   global_certificates?: boolean;
   tls_cipher_list?: string;
   tls_ciphersuites?: string;
@@ -103,6 +112,7 @@ export interface Server {
   tcp_fastopen?: number;
   prometheus_metrics?: string;
   otlp_exporter?: string;
+  otlp_metrics?: string;
   includes?: string[];
   modules?: string[];
   remark?: string;
@@ -146,6 +156,7 @@ interface Basic {
   log_level?: string;
   auto_restart_check_interval?: string;
   cache_max_size?: number;
+  cache_disk_max_size?: number;
   cache_directory?: string;
   sentry?: string;
   pyroscope?: string;